@@ -0,0 +1,190 @@
+//! Benchmarks for the hot paths of a transfer: hashing, listing parsing and buffered writes.
+//!
+//! Run with `cargo bench`; pass a filter to run a subset, e.g. `cargo bench -- hash`.
+//! Each case is warmed up, then sampled for a fixed time; the report shows the fastest,
+//! median and slowest sample and the throughput at the median.
+
+use app_lib::bench_support::{
+    hash_file, parse_blob_list, parse_list_objects, ChecksumAlgorithm, ChecksumHasher, TransferTuning,
+};
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+const MIB: usize = 1024 * 1024;
+
+const WARM_UP: Duration = Duration::from_millis(500);
+const MEASUREMENT: Duration = Duration::from_secs(3);
+const MIN_SAMPLES: usize = 10;
+
+/// Size of the content hashed and written per iteration
+const PAYLOAD_SIZE: usize = 64 * MIB;
+
+/// Size of the chunks a network stream typically delivers
+const NETWORK_CHUNK: usize = 64 * 1024;
+
+/// Objects in the generated bucket listings
+const LISTING_ENTRIES: usize = 1000;
+
+struct Bencher {
+    filter: Option<String>,
+}
+
+impl Bencher {
+    fn from_args() -> Bencher {
+        // cargo passes `--bench`; the first other argument is the filter
+        let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+        Bencher { filter }
+    }
+
+    fn bench<F: FnMut()>(&self, name: &str, bytes: u64, mut routine: F) {
+        if self.filter.as_deref().is_some_and(|filter| !name.contains(filter)) {
+            return;
+        }
+
+        let warm_up = Instant::now();
+        while warm_up.elapsed() < WARM_UP {
+            routine();
+        }
+
+        let mut samples = Vec::new();
+        let measurement = Instant::now();
+        while measurement.elapsed() < MEASUREMENT || samples.len() < MIN_SAMPLES {
+            let start = Instant::now();
+            routine();
+            samples.push(start.elapsed());
+        }
+        samples.sort();
+
+        let median = samples[samples.len() / 2];
+        let throughput = bytes as f64 / median.as_secs_f64() / MIB as f64;
+        println!(
+            "{:<44} time: [{:>10.3?} {:>10.3?} {:>10.3?}]  thrpt: {:>9.1} MiB/s  ({} samples)",
+            name,
+            samples[0],
+            median,
+            samples[samples.len() - 1],
+            throughput,
+            samples.len()
+        );
+    }
+}
+
+fn payload() -> Vec<u8> {
+    (0..PAYLOAD_SIZE).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+fn scratch_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join("bids-collector-bench");
+    std::fs::create_dir_all(&dir).expect("create scratch directory");
+    dir
+}
+
+fn tunings() -> Vec<TransferTuning> {
+    let mut tunings = TransferTuning::presets();
+    tunings.push(TransferTuning::performance());
+    tunings
+}
+
+fn bench_hashing(bencher: &Bencher, runtime: &tokio::runtime::Runtime) {
+    let content = payload();
+
+    for (name, algorithm) in [("sha256", ChecksumAlgorithm::Sha256), ("md5", ChecksumAlgorithm::Md5)] {
+        bencher.bench(&format!("hash/{}/memory", name), content.len() as u64, || {
+            let mut hasher = ChecksumHasher::new(algorithm);
+            for chunk in content.chunks(NETWORK_CHUNK) {
+                hasher.update(chunk);
+            }
+            black_box(hasher.finalize_hex());
+        });
+    }
+
+    let path = scratch_dir().join("hash-input.bin");
+    std::fs::write(&path, &content).expect("write hash input");
+    let path_str = path.to_string_lossy().to_string();
+    for tuning in tunings() {
+        bencher.bench(&format!("hash/sha256/file/{}", tuning.profile), content.len() as u64, || {
+            black_box(runtime.block_on(hash_file(&path_str, tuning.chunk_size)).expect("hash file"));
+        });
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+fn s3_listing() -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?><ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><Name>bids</Name><IsTruncated>false</IsTruncated>"#,
+    );
+    for i in 0..LISTING_ENTRIES {
+        xml.push_str(&format!(
+            "<Contents><Key>ds000001/sub-{:03}/anat/sub-{:03}_T1w.nii.gz</Key><LastModified>2024-05-01T12:00:00.000Z</LastModified><ETag>&quot;{:032x}&quot;</ETag><Size>{}</Size><StorageClass>STANDARD</StorageClass></Contents>",
+            i, i, i, 1_000_000 + i
+        ));
+    }
+    xml.push_str("</ListBucketResult>");
+    xml
+}
+
+fn azure_listing() -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="utf-8"?><EnumerationResults ContainerName="bids"><Blobs>"#,
+    );
+    for i in 0..LISTING_ENTRIES {
+        xml.push_str(&format!(
+            "<Blob><Name>ds000001/sub-{:03}/anat/sub-{:03}_T1w.nii.gz</Name><Properties><Content-Length>{}</Content-Length><BlobType>BlockBlob</BlobType></Properties></Blob>",
+            i, i, 1_000_000 + i
+        ));
+    }
+    xml.push_str("</Blobs><NextMarker /></EnumerationResults>");
+    xml
+}
+
+fn bench_parsing(bencher: &Bencher) {
+    let s3 = s3_listing();
+    bencher.bench(&format!("parse/s3-list-objects/{}", LISTING_ENTRIES), s3.len() as u64, || {
+        black_box(parse_list_objects(black_box(&s3)));
+    });
+
+    let azure = azure_listing();
+    bencher.bench(&format!("parse/azure-list-blobs/{}", LISTING_ENTRIES), azure.len() as u64, || {
+        black_box(parse_blob_list(black_box(&azure)));
+    });
+}
+
+/// Write the payload the way a download does: network-sized chunks through the tuned buffer
+async fn stream_copy(path: &Path, content: &[u8], tuning: &TransferTuning) {
+    let file = tokio::fs::File::create(path).await.expect("create copy target");
+    let mut file = BufWriter::with_capacity(tuning.write_buffer_size, file);
+    let mut unflushed = 0u64;
+
+    for chunk in content.chunks(NETWORK_CHUNK) {
+        file.write_all(chunk).await.expect("write chunk");
+        unflushed += chunk.len() as u64;
+        if tuning.should_flush(unflushed) {
+            file.flush().await.expect("flush");
+            unflushed = 0;
+        }
+    }
+    file.flush().await.expect("flush");
+}
+
+fn bench_streaming_copy(bencher: &Bencher, runtime: &tokio::runtime::Runtime) {
+    let content = payload();
+    let path = scratch_dir().join("copy-output.bin");
+
+    for tuning in tunings() {
+        bencher.bench(&format!("copy/stream/{}", tuning.profile), content.len() as u64, || {
+            runtime.block_on(stream_copy(&path, &content, &tuning));
+        });
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+fn main() {
+    let bencher = Bencher::from_args();
+    let runtime = tokio::runtime::Runtime::new().expect("start tokio runtime");
+
+    bench_hashing(&bencher, &runtime);
+    bench_parsing(&bencher);
+    bench_streaming_copy(&bencher, &runtime);
+}
@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::fs;
+
+use crate::path_guard;
+
+/// Directory under the dataset root holding one audit log per task
+pub const AUDIT_LOG_DIR: &str = ".bids-collector/anonymization";
+
+/// Sidecar fields removed unless the task names its own: acquisition dates and times,
+/// the site, and the scanner's serial numbers
+const DEFAULT_JSON_FIELDS: &[&str] = &[
+    "AcquisitionDateTime",
+    "AcquisitionDate",
+    "AcquisitionTime",
+    "InstitutionName",
+    "InstitutionAddress",
+    "InstitutionalDepartmentName",
+    "StationName",
+    "DeviceSerialNumber",
+    "PatientName",
+    "PatientID",
+    "PatientBirthDate",
+];
+
+/// participants.tsv columns removed unless the task names its own, compared case-insensitively
+const DEFAULT_PARTICIPANT_COLUMNS: &[&str] = &[
+    "name",
+    "birth_date",
+    "birthdate",
+    "date_of_birth",
+    "dob",
+    "patient_id",
+    "mrn",
+    "address",
+    "email",
+    "phone",
+];
+
+/// A file with identifying data taken out
+pub struct Scrubbed {
+    pub content: Vec<u8>,
+    /// Sidecar fields or participants.tsv columns that were removed
+    pub removed: Vec<String>,
+}
+
+/// Removes identifying fields from JSON sidecars and identifying columns from participants.tsv
+/// as files are collected (`anonymize`). Scrubbed files no longer match the provider's sizes,
+/// so sync mode collects them again.
+#[derive(Debug, Clone)]
+pub struct Anonymizer {
+    json_fields: Vec<String>,
+    participant_columns: Vec<String>,
+}
+
+impl Anonymizer {
+    /// Task field `anonymize`: `true` for the default fields and columns, or
+    /// `{ "jsonFields": [...], "participantColumns": [...] }` to choose them
+    pub fn from_task(task: &Value) -> Result<Option<Anonymizer>, String> {
+        let defaults = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        match task.get("anonymize") {
+            None | Some(Value::Null) | Some(Value::Bool(false)) => Ok(None),
+            Some(Value::Bool(true)) => Ok(Some(Anonymizer {
+                json_fields: defaults(DEFAULT_JSON_FIELDS),
+                participant_columns: defaults(DEFAULT_PARTICIPANT_COLUMNS),
+            })),
+            Some(Value::Object(options)) => Ok(Some(Anonymizer {
+                json_fields: names(options, "jsonFields")?.unwrap_or_else(|| defaults(DEFAULT_JSON_FIELDS)),
+                participant_columns: names(options, "participantColumns")?.unwrap_or_else(|| defaults(DEFAULT_PARTICIPANT_COLUMNS)),
+            })),
+            Some(_) => Err("Invalid anonymize: expected true or an object".to_string()),
+        }
+    }
+
+    fn applies_to(path: &str) -> bool {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        !path.starts_with(".bids-collector/")
+            && (file_name.to_lowercase().ends_with(".json") || file_name == "participants.tsv")
+    }
+
+    /// Scrubbed content of a collected file and what was removed from it, or None when the
+    /// file has nothing to remove
+    pub fn scrub(&self, path: &str, content: &[u8]) -> Result<Option<Scrubbed>, String> {
+        if !Anonymizer::applies_to(path) {
+            return Ok(None);
+        }
+        if path.ends_with(".tsv") {
+            return Ok(self.scrub_participants(content));
+        }
+
+        let Value::Object(mut document) = serde_json::from_slice::<Value>(content)
+            .map_err(|e| format!("Invalid JSON: {}", e))? else {
+            return Ok(None);
+        };
+        let removed: Vec<String> = self.json_fields.iter()
+            .filter(|field| document.remove(field.as_str()).is_some())
+            .cloned()
+            .collect();
+        if removed.is_empty() {
+            return Ok(None);
+        }
+        let content = serde_json::to_vec_pretty(&Value::Object(document))
+            .map_err(|e| format!("Failed to serialize {}: {}", path, e))?;
+        Ok(Some(Scrubbed { content, removed }))
+    }
+
+    fn scrub_participants(&self, content: &[u8]) -> Option<Scrubbed> {
+        let text = String::from_utf8_lossy(content);
+        let mut lines = text.lines();
+        let header: Vec<&str> = lines.next()?.split('\t').collect();
+        let drop: Vec<bool> = header.iter()
+            .map(|column| self.participant_columns.iter().any(|c| c.eq_ignore_ascii_case(column.trim())))
+            .collect();
+        if !drop.contains(&true) {
+            return None;
+        }
+
+        let keep = |line: &str| line.split('\t')
+            .enumerate()
+            .filter(|(i, _)| !drop.get(*i).copied().unwrap_or(false))
+            .map(|(_, cell)| cell)
+            .collect::<Vec<_>>()
+            .join("\t");
+        let mut scrubbed: Vec<String> = vec![keep(&header.join("\t"))];
+        scrubbed.extend(lines.map(keep));
+        let removed = header.iter()
+            .zip(&drop)
+            .filter(|(_, dropped)| **dropped)
+            .map(|(column, _)| column.trim().to_string())
+            .collect();
+        Some(Scrubbed { content: (scrubbed.join("\n") + "\n").into_bytes(), removed })
+    }
+
+    /// Scrub a file collected to local storage in place, returning what was removed
+    pub async fn scrub_file(&self, path: &str, disk_path: &str) -> Result<Option<Vec<String>>, String> {
+        if !Anonymizer::applies_to(path) {
+            return Ok(None);
+        }
+        let content = fs::read(disk_path).await
+            .map_err(|e| format!("Failed to read {}: {}", disk_path, e))?;
+        let Some(scrubbed) = self.scrub(path, &content)? else {
+            return Ok(None);
+        };
+        path_guard::check(disk_path)?;
+        fs::write(disk_path, scrubbed.content).await
+            .map_err(|e| format!("Failed to write {}: {}", disk_path, e))?;
+        Ok(Some(scrubbed.removed))
+    }
+}
+
+fn names(options: &Map<String, Value>, field: &str) -> Result<Option<Vec<String>>, String> {
+    match options.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Array(items)) => items.iter()
+            .map(|item| item.as_str()
+                .map(|s| s.trim().to_string())
+                .ok_or_else(|| format!("Invalid entry in anonymize.{}: expected a string", field)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(_) => Err(format!("Invalid anonymize.{}: expected an array of strings", field)),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub path: String,
+    /// Sidecar fields or participants.tsv columns taken out
+    pub removed: Vec<String>,
+    /// Why the file was left as collected, when it could not be scrubbed
+    pub error: Option<String>,
+}
+
+/// What the anonymization pass of a task changed, written next to the dataset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLog {
+    pub task_id: String,
+    pub created_at: String,
+    pub files: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    pub fn new(task_id: &str) -> AuditLog {
+        AuditLog {
+            task_id: task_id.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            files: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Files that were changed, not counting those that could not be scrubbed
+    pub fn scrubbed_files(&self) -> u32 {
+        self.files.iter().filter(|f| f.error.is_none()).count() as u32
+    }
+
+    /// Note the outcome of scrubbing one file; unchanged files are not listed
+    pub fn record(&mut self, path: &str, outcome: Result<Option<Vec<String>>, String>) {
+        let (removed, error) = match outcome {
+            Ok(None) => return,
+            Ok(Some(removed)) => (removed, None),
+            Err(e) => {
+                log::warn!("Could not anonymize {}, leaving it as collected: {}", path, e);
+                (Vec::new(), Some(e))
+            }
+        };
+        self.files.push(AuditEntry { path: path.to_string(), removed, error });
+    }
+
+    /// Key or path of the log relative to the dataset root
+    pub fn relative_path(&self) -> String {
+        format!("{}/{}.json", AUDIT_LOG_DIR, self.task_id)
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>, String> {
+        serde_json::to_vec_pretty(self).map_err(|e| format!("Failed to serialize anonymization log: {}", e))
+    }
+
+    /// Write the log next to the dataset, returning its path
+    pub async fn write_local(&self, dest_dir: &str) -> Result<String, String> {
+        let dir = format!("{}/{}", dest_dir, AUDIT_LOG_DIR);
+        path_guard::check(&dir)?;
+        fs::create_dir_all(&dir).await
+            .map_err(|e| format!("Failed to create directory {}: {}", dir, e))?;
+
+        let path = format!("{}/{}", dest_dir, self.relative_path());
+        fs::write(&path, self.to_json()?).await
+            .map_err(|e| format!("Failed to write anonymization log {}: {}", path, e))?;
+        log::info!("Anonymized {} files, audit log written to {}", self.scrubbed_files(), path);
+        Ok(path)
+    }
+}
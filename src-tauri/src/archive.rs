@@ -0,0 +1,286 @@
+use std::cell::Cell;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use zip::write::SimpleFileOptions;
+use zip::CompressionMethod;
+
+use crate::{path_guard, safe_path};
+
+/// Files that are already compressed and are stored in zips as-is
+const COMPRESSED_EXTENSIONS: &[&str] = &[".gz", ".zip", ".bz2", ".xz", ".zst"];
+
+/// Archive formats that can be unpacked into a dataset directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn detect(path: &str) -> Option<ArchiveFormat> {
+        let lower = path.to_lowercase();
+        if lower.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if lower.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// How far an extraction got, reported after every entry
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExtractProgress {
+    pub entries: usize,
+    /// Bytes written to the destination
+    pub bytes_written: u64,
+    /// Bytes of the archive consumed so far, out of `archive_size`
+    pub archive_read: u64,
+    pub archive_size: u64,
+}
+
+/// Whether a listing is a dataset shipped as archives: nothing but archives and loose
+/// top-level files, and no dataset_description.json outside them
+pub fn is_archived_dataset<'a>(paths: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut has_archive = false;
+    for path in paths {
+        if path == "dataset_description.json" || path.ends_with("/dataset_description.json") {
+            return false;
+        }
+        has_archive |= ArchiveFormat::detect(path).is_some();
+    }
+    has_archive
+}
+
+/// Unpack an archive into `dest_dir`, returning the extracted file paths relative to `dest_dir`.
+/// Entries with absolute paths or `..` components are rejected. An archive holding a single
+/// directory with a dataset_description.json (such as "ds000001-1.0.0/") is unpacked without
+/// that directory, so the dataset lands directly in `dest_dir`.
+pub async fn extract_archive(
+    archive_path: &str,
+    dest_dir: &str,
+    mut on_progress: impl FnMut(ExtractProgress) + Send + 'static,
+) -> Result<Vec<String>, String> {
+    let format = ArchiveFormat::detect(archive_path)
+        .ok_or_else(|| format!("Unsupported archive format: {}", archive_path))?;
+    let archive_path = archive_path.to_string();
+    let dest_dir = PathBuf::from(dest_dir);
+
+    log::info!("Extracting {} into {}", archive_path, dest_dir.display());
+
+    tokio::task::spawn_blocking(move || {
+        let file = File::open(&archive_path).map_err(|e| format!("Failed to open {}: {}", archive_path, e))?;
+        let archive_size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let extracted = match format {
+            ArchiveFormat::Zip => extract_zip(file, Path::new(&archive_path), &dest_dir, archive_size, &mut on_progress)?,
+            ArchiveFormat::Tar => extract_tar(file, &dest_dir, archive_size, &mut on_progress)?,
+            ArchiveFormat::TarGz => extract_tar_gz(file, &dest_dir, archive_size, &mut on_progress)?,
+        };
+        Ok(hoist_dataset_root(&dest_dir, extracted))
+    })
+    .await
+    .map_err(|e| format!("Archive extraction task failed: {}", e))?
+}
+
+/// Counts the compressed bytes a decoder consumes
+struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: io::Read> io::Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.set(self.count.get() + read as u64);
+        Ok(read)
+    }
+}
+
+fn extract_tar_gz(
+    file: File,
+    dest_dir: &Path,
+    archive_size: u64,
+    on_progress: &mut dyn FnMut(ExtractProgress),
+) -> Result<Vec<String>, String> {
+    let count = Rc::new(Cell::new(0));
+    let reader = CountingReader { inner: file, count: count.clone() };
+    extract_tar_from(flate2::read::GzDecoder::new(reader), dest_dir, &mut |mut progress| {
+        progress.archive_read = count.get();
+        progress.archive_size = archive_size;
+        on_progress(progress);
+    })
+}
+
+fn extract_tar(
+    file: File,
+    dest_dir: &Path,
+    archive_size: u64,
+    on_progress: &mut dyn FnMut(ExtractProgress),
+) -> Result<Vec<String>, String> {
+    let count = Rc::new(Cell::new(0));
+    let reader = CountingReader { inner: file, count: count.clone() };
+    extract_tar_from(reader, dest_dir, &mut |mut progress| {
+        progress.archive_read = count.get();
+        progress.archive_size = archive_size;
+        on_progress(progress);
+    })
+}
+
+fn extract_zip(
+    file: File,
+    archive_path: &Path,
+    dest_dir: &Path,
+    archive_size: u64,
+    on_progress: &mut dyn FnMut(ExtractProgress),
+) -> Result<Vec<String>, String> {
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Invalid zip archive {}: {}", archive_path.display(), e))?;
+
+    let mut extracted = Vec::new();
+    let mut progress = ExtractProgress { archive_size, ..ExtractProgress::default() };
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)
+            .map_err(|e| format!("Failed to read zip entry: {}", e))?;
+        progress.archive_read += entry.compressed_size();
+
+        let (relative, target) = entry_paths(dest_dir, entry.name())?;
+        path_guard::check(&target)?;
+
+        if entry.is_dir() {
+            std::fs::create_dir_all(&target)
+                .map_err(|e| format!("Failed to create directory {}: {}", target.display(), e))?;
+            continue;
+        }
+        // Links are not part of BIDS datasets
+        if entry.is_symlink() {
+            log::info!("Skipping link in archive: {}", entry.name());
+            continue;
+        }
+
+        progress.bytes_written += write_entry(&mut entry, &target)?;
+        progress.entries += 1;
+        extracted.push(relative);
+        on_progress(progress);
+    }
+
+    Ok(extracted)
+}
+
+fn extract_tar_from<R: io::Read>(
+    reader: R,
+    dest_dir: &Path,
+    on_progress: &mut dyn FnMut(ExtractProgress),
+) -> Result<Vec<String>, String> {
+    let mut archive = tar::Archive::new(reader);
+    let mut extracted = Vec::new();
+    let mut progress = ExtractProgress::default();
+
+    let entries = archive.entries()
+        .map_err(|e| format!("Failed to read tar archive: {}", e))?;
+
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let entry_path = entry.path()
+            .map_err(|e| format!("Invalid path in tar archive: {}", e))?
+            .into_owned();
+
+        let (relative, target) = entry_paths(dest_dir, &entry_path.to_string_lossy())?;
+        path_guard::check(&target)?;
+
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => {
+                std::fs::create_dir_all(&target)
+                    .map_err(|e| format!("Failed to create directory {}: {}", target.display(), e))?;
+            }
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                progress.bytes_written += write_entry(&mut entry, &target)?;
+                progress.entries += 1;
+                extracted.push(relative);
+                on_progress(progress);
+            }
+            // Links and special files are not part of BIDS datasets
+            other => log::info!("Skipping {:?} entry in archive: {}", other, entry_path.display()),
+        }
+    }
+
+    Ok(extracted)
+}
+
+fn write_entry<R: io::Read>(entry: &mut R, target: &Path) -> Result<u64, String> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+
+    let mut output = File::create(target)
+        .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    io::copy(entry, &mut output)
+        .map_err(|e| format!("Failed to extract {}: {}", target.display(), e))
+}
+
+/// Relative path of an archive entry and where it is written, see `safe_path::join`
+fn entry_paths(dest_dir: &Path, name: &str) -> Result<(String, PathBuf), String> {
+    let relative = safe_path::relative(name).map_err(|e| format!("{} in archive", e))?;
+    let target = safe_path::join(&dest_dir.to_string_lossy(), &relative)?;
+    Ok((relative, PathBuf::from(target)))
+}
+
+/// Move the contents of an archive's single wrapping directory up into `dest_dir` when that
+/// directory is a BIDS dataset. Nothing is moved if any name would collide.
+fn hoist_dataset_root(dest_dir: &Path, extracted: Vec<String>) -> Vec<String> {
+    let Some(root) = extracted.first().and_then(|path| path.split_once('/')).map(|(root, _)| format!("{}/", root)) else {
+        return extracted;
+    };
+    if !extracted.iter().all(|path| path.starts_with(&root))
+        || !extracted.iter().any(|path| path[root.len()..] == *"dataset_description.json")
+    {
+        return extracted;
+    }
+
+    let root_dir = dest_dir.join(root.trim_end_matches('/'));
+    let Ok(children) = std::fs::read_dir(&root_dir).map(|entries| entries.flatten().map(|e| e.file_name()).collect::<Vec<_>>()) else {
+        return extracted;
+    };
+    if let Some(collision) = children.iter().find(|name| dest_dir.join(name).exists()) {
+        log::info!("Keeping {} as extracted: {} already exists in the destination", root, collision.to_string_lossy());
+        return extracted;
+    }
+
+    for name in &children {
+        if let Err(e) = std::fs::rename(root_dir.join(name), dest_dir.join(name)) {
+            log::warn!("Failed to move {} out of {}: {}", name.to_string_lossy(), root, e);
+            return extracted;
+        }
+    }
+    let _ = std::fs::remove_dir(&root_dir);
+
+    log::info!("Unpacked the dataset from {} into the destination root", root);
+    extracted.into_iter().map(|path| path[root.len()..].to_string()).collect()
+}
+
+/// Zip entry options for a file: deflated unless already compressed, zip64 when large
+pub fn zip_file_options(name: &str, size: u64) -> SimpleFileOptions {
+    let lower = name.to_lowercase();
+    let method = if COMPRESSED_EXTENSIONS.iter().any(|ext| lower.ends_with(ext)) {
+        CompressionMethod::Stored
+    } else {
+        CompressionMethod::Deflated
+    };
+    SimpleFileOptions::default()
+        .compression_method(method)
+        .large_file(size >= u32::MAX as u64)
+}
+
+pub fn to_slash_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,478 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{Listener, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task::JoinSet;
+
+use crate::credentials;
+use crate::error::{CollectorError, ErrorKind};
+use crate::task_queue::TaskQueueState;
+use crate::DownloadState;
+
+/// File in the app data directory holding the API settings, without the token
+pub const SETTINGS_FILE: &str = "automation.json";
+
+/// Keychain entry holding the API token
+const TOKEN_CREDENTIALS_ID: &str = "automation-api";
+
+const DEFAULT_PORT: u16 = 47821;
+
+/// Events forwarded to `/v1/events` subscribers, as emitted to the webview
+const FORWARDED_EVENTS: &[&str] = &[
+    "download_progress",
+    "download-selection",
+    "download-completed",
+    "download_completed",
+    "dataset-metadata-ready",
+    "dataset-validated",
+    "archive-extract-progress",
+    "archive-export-progress",
+    "desktop-notification",
+];
+
+/// Events kept for subscribers that fall behind before the oldest are dropped
+const EVENT_BUFFER: usize = 256;
+
+/// Connections served at once; more are turned away
+const MAX_CONNECTIONS: usize = 32;
+
+const MAX_HEADER_LINES: usize = 64;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Comment sent to event subscribers when nothing happened, so idle connections stay open
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationSettings {
+    /// Off by default; the API only listens on 127.0.0.1
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Bearer token clients send; kept in the keychain, reported but never read from the file
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+impl Default for AutomationSettings {
+    fn default() -> Self {
+        AutomationSettings {
+            enabled: false,
+            port: DEFAULT_PORT,
+            token: None,
+        }
+    }
+}
+
+/// An event emitted to the webview, with its JSON payload
+#[derive(Debug, Clone)]
+struct ApiEvent {
+    name: &'static str,
+    payload: String,
+}
+
+/// Body of `POST /v1/tasks`: the arguments of `start_download_task`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnqueueRequest {
+    #[serde(default)]
+    task_id: Option<String>,
+    task_data: Value,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    token: Option<String>,
+    body: Vec<u8>,
+}
+
+fn load_token() -> Result<Option<String>, String> {
+    credentials::load_app_secret(TOKEN_CREDENTIALS_ID)
+}
+
+fn store_token(token: &str) -> Result<(), String> {
+    credentials::store_app_secret(TOKEN_CREDENTIALS_ID, token)
+}
+
+fn generate_token() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+/// Compare without returning early, so the time taken does not reveal the matching prefix
+fn token_matches(given: Option<&str>, expected: &str) -> bool {
+    let Some(given) = given else {
+        return false;
+    };
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// The opt-in localhost API that lets pipeline tools enqueue tasks, read their progress and
+/// follow the progress events of the webview. Every request needs the token as
+/// `Authorization: Bearer <token>`.
+///
+/// - `GET /v1/tasks`: progress of every task
+/// - `GET /v1/tasks/<id>`: progress of one task
+/// - `POST /v1/tasks`: enqueue `{"taskId"?, "taskData"}` like `start_download_task`
+/// - `POST /v1/tasks/<id>/cancel`: cancel a task like `cancel_download_task`
+/// - `GET /v1/events`: server-sent events, named as emitted to the webview
+pub struct AutomationServer {
+    app_handle: tauri::AppHandle,
+    settings_path: PathBuf,
+    settings: Mutex<AutomationSettings>,
+    events: broadcast::Sender<ApiEvent>,
+    server: Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
+}
+
+pub type AutomationState = Arc<AutomationServer>;
+
+impl AutomationServer {
+    /// Load the settings and start forwarding webview events; the API itself listens once
+    /// `restart` is called with the API enabled
+    pub fn open(app_handle: &tauri::AppHandle) -> Result<AutomationServer, String> {
+        let settings_path = settings_path(app_handle)?;
+        let settings = read_settings(&settings_path)?;
+
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        for name in FORWARDED_EVENTS {
+            let events = events.clone();
+            app_handle.listen_any(*name, move |event| {
+                // Nobody subscribed is not an error
+                let _ = events.send(ApiEvent {
+                    name,
+                    payload: event.payload().to_string(),
+                });
+            });
+        }
+
+        Ok(AutomationServer {
+            app_handle: app_handle.clone(),
+            settings_path,
+            settings: Mutex::new(settings),
+            events,
+            server: Mutex::new(None),
+        })
+    }
+
+    /// Serve the API even when it is switched off in the settings, as the daemon does so a
+    /// desktop app can hand it tasks
+    pub fn enable(&self) {
+        self.settings.lock().unwrap().enabled = true;
+    }
+
+    /// The settings with the token, generating one the first time the API is enabled
+    pub fn settings(&self) -> Result<AutomationSettings, String> {
+        let mut settings = self.settings.lock().unwrap().clone();
+        settings.token = match load_token()? {
+            Some(token) => Some(token),
+            None if settings.enabled => {
+                let token = generate_token();
+                store_token(&token)?;
+                Some(token)
+            }
+            None => None,
+        };
+        Ok(settings)
+    }
+
+    fn save(&self, settings: &AutomationSettings) -> Result<(), String> {
+        crate::path_guard::check(&self.settings_path)?;
+        if let Some(parent) = self.settings_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        let settings = AutomationSettings { token: None, ..settings.clone() };
+        let content = serde_json::to_vec_pretty(&settings)
+            .map_err(|e| format!("Failed to serialize automation settings: {}", e))?;
+        std::fs::write(&self.settings_path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.settings_path.display(), e))
+    }
+
+    /// Stop the API, closing every connection, and listen again when it is enabled
+    pub fn restart(&self) -> Result<(), String> {
+        if let Some(server) = self.server.lock().unwrap().take() {
+            server.abort();
+        }
+        let settings = self.settings()?;
+        let Some(token) = settings.token.filter(|_| settings.enabled) else {
+            return Ok(());
+        };
+
+        // Bound here so a taken port is reported to the caller
+        let listener = std::net::TcpListener::bind(("127.0.0.1", settings.port))
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|e| format!("Failed to listen on 127.0.0.1:{}: {}", settings.port, e))?;
+        log::info!("Automation API listening on 127.0.0.1:{}", settings.port);
+
+        let app_handle = self.app_handle.clone();
+        let events = self.events.clone();
+        let server = tauri::async_runtime::spawn(async move {
+            if let Err(e) = serve(listener, app_handle, events, token).await {
+                log::info!("Automation API stopped: {}", e);
+            }
+        });
+        *self.server.lock().unwrap() = Some(server);
+        Ok(())
+    }
+}
+
+fn settings_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app_handle.path().app_data_dir()
+        .map(|dir| dir.join(SETTINGS_FILE))
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))
+}
+
+fn read_settings(path: &std::path::Path) -> Result<AutomationSettings, String> {
+    match std::fs::read(path) {
+        Ok(content) => serde_json::from_slice(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(AutomationSettings::default()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// The saved settings with the token, as another process of the same user reads them to reach
+/// the API; no token is generated
+pub fn saved_settings(app_handle: &tauri::AppHandle) -> Result<AutomationSettings, String> {
+    let mut settings = read_settings(&settings_path(app_handle)?)?;
+    settings.token = load_token()?;
+    Ok(settings)
+}
+
+/// Accept connections until the server is aborted; dropping `connections` then closes the
+/// open ones, including event subscriptions
+async fn serve(
+    listener: std::net::TcpListener,
+    app_handle: tauri::AppHandle,
+    events: broadcast::Sender<ApiEvent>,
+    token: String,
+) -> Result<(), String> {
+    let listener = TcpListener::from_std(listener).map_err(|e| e.to_string())?;
+    let token = Arc::new(token);
+    let mut connections = JoinSet::new();
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (mut stream, _) = accepted.map_err(|e| e.to_string())?;
+                if connections.len() >= MAX_CONNECTIONS {
+                    let error = CollectorError::new(ErrorKind::RateLimited, "Too many connections");
+                    let _ = respond(&mut stream, 503, &error).await;
+                    continue;
+                }
+                let (app_handle, events, token) = (app_handle.clone(), events.clone(), token.clone());
+                connections.spawn(async move {
+                    if let Err(e) = handle(stream, &app_handle, &events, &token).await {
+                        log::warn!("Automation API request failed: {}", e);
+                    }
+                });
+            }
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+        }
+    }
+}
+
+async fn read_request(reader: &mut BufReader<TcpStream>) -> Result<Request, String> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.map_err(|e| e.to_string())?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err("Malformed request line".to_string());
+    };
+    let method = method.to_string();
+    let path = target.split('?').next().unwrap_or_default().to_string();
+
+    let mut token = None;
+    let mut content_length = 0;
+    for _ in 0..MAX_HEADER_LINES {
+        line.clear();
+        reader.read_line(&mut line).await.map_err(|e| e.to_string())?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).await.map_err(|e| e.to_string())?;
+            return Ok(Request { method, path, token, body });
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(format!("Malformed header {}", header));
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("authorization") {
+            token = value.strip_prefix("Bearer ").map(|t| t.trim().to_string());
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().map_err(|_| format!("Invalid Content-Length {}", value))?;
+            if content_length > MAX_BODY_BYTES {
+                return Err(format!("Request body of {} bytes is too large", content_length));
+            }
+        }
+    }
+    Err("Too many headers".to_string())
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        409 => "Conflict",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+fn error_status(error: &CollectorError) -> u16 {
+    match error.kind {
+        ErrorKind::InvalidInput => 400,
+        ErrorKind::Authentication => 401,
+        ErrorKind::NotFound => 404,
+        ErrorKind::Conflict => 409,
+        _ => 500,
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: u16, body: &impl Serialize) -> Result<(), String> {
+    let body = serde_json::to_vec(body).map_err(|e| e.to_string())?;
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status, reason_phrase(status), body.len()
+    );
+    stream.write_all(head.as_bytes()).await.map_err(|e| e.to_string())?;
+    stream.write_all(&body).await.map_err(|e| e.to_string())
+}
+
+async fn handle(
+    stream: TcpStream,
+    app_handle: &tauri::AppHandle,
+    events: &broadcast::Sender<ApiEvent>,
+    token: &str,
+) -> Result<(), String> {
+    let mut reader = BufReader::new(stream);
+    let request = tokio::time::timeout(READ_TIMEOUT, read_request(&mut reader)).await
+        .unwrap_or_else(|_| Err("Timed out reading the request".to_string()));
+    let mut stream = reader.into_inner();
+    let request = match request {
+        Ok(request) => request,
+        Err(e) => return respond(&mut stream, 400, &CollectorError::new(ErrorKind::InvalidInput, e)).await,
+    };
+
+    if !token_matches(request.token.as_deref(), token) {
+        let error = CollectorError::new(ErrorKind::Authentication, "Missing or wrong API token");
+        return respond(&mut stream, 401, &error).await;
+    }
+
+    // Task IDs are percent-encoded in the path; segments are decoded after splitting
+    let segments: Vec<String> = request.path.trim_matches('/')
+        .split('/')
+        .map(|segment| percent_encoding::percent_decode_str(segment).decode_utf8_lossy().into_owned())
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["v1", "events"]) => return stream_events(stream, events.subscribe()).await,
+        ("GET", ["v1", "tasks"]) => {
+            let downloads = app_handle.state::<DownloadState>();
+            let tasks: Vec<_> = downloads.lock().unwrap().values().cloned().collect();
+            serde_json::to_value(tasks).map(|tasks| (200, tasks)).map_err(|e| CollectorError::from(e.to_string()))
+        }
+        ("GET", ["v1", "tasks", task_id]) => {
+            let downloads = app_handle.state::<DownloadState>();
+            let progress = downloads.lock().unwrap().get(*task_id).cloned();
+            match progress {
+                Some(progress) => Ok((200, serde_json::json!(progress))),
+                None => Err(CollectorError::new(ErrorKind::NotFound, format!("Task {} not found", task_id))),
+            }
+        }
+        ("POST", ["v1", "tasks"]) => enqueue(app_handle, &request.body).map(|task_id| (202, serde_json::json!({ "taskId": task_id }))),
+        ("POST", ["v1", "tasks", task_id, "cancel"]) => {
+            crate::cancel_task(task_id, app_handle);
+            Ok((200, serde_json::json!({ "taskId": task_id })))
+        }
+        _ => Err(CollectorError::new(ErrorKind::NotFound, format!("No route for {} {}", request.method, request.path))),
+    };
+
+    match result {
+        Ok((status, body)) => respond(&mut stream, status, &body).await,
+        Err(error) => respond(&mut stream, error_status(&error), &error).await,
+    }
+}
+
+/// Queue the task in a `POST /v1/tasks` body, returning its ID
+pub fn enqueue(app_handle: &tauri::AppHandle, body: &[u8]) -> Result<String, CollectorError> {
+    let request: EnqueueRequest = serde_json::from_slice(body)
+        .map_err(|e| CollectorError::new(ErrorKind::InvalidInput, format!("Invalid task request: {}", e)))?;
+    let task_id = request.task_id.unwrap_or_else(|| format!("api-{}", &generate_token()[..12]));
+
+    let state = app_handle.state::<DownloadState>().inner().clone();
+    if state.lock().unwrap().contains_key(&task_id) {
+        return Err(CollectorError::new(ErrorKind::Conflict, format!("Task {} was already started", task_id)));
+    }
+    let queue = app_handle.state::<TaskQueueState>().inner().clone();
+    crate::queue_download_task(task_id.clone(), request.task_data, state, queue, app_handle.clone())
+        .map_err(|e| CollectorError::new(ErrorKind::InvalidInput, e))?;
+    log::info!("Automation API queued task {}", task_id);
+    Ok(task_id)
+}
+
+/// Send events as they are emitted until the client disconnects
+async fn stream_events(mut stream: TcpStream, mut events: broadcast::Receiver<ApiEvent>) -> Result<(), String> {
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n";
+    stream.write_all(head.as_bytes()).await.map_err(|e| e.to_string())?;
+
+    loop {
+        let message = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => format!("event: {}\ndata: {}\n\n", event.name, event.payload),
+                Err(broadcast::error::RecvError::Lagged(missed)) => format!(": {} events dropped\n\n", missed),
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            _ = tokio::time::sleep(KEEP_ALIVE_INTERVAL) => ": keep-alive\n\n".to_string(),
+        };
+        if stream.write_all(message.as_bytes()).await.is_err() {
+            // The client went away
+            return Ok(());
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_automation_settings(state: tauri::State<'_, AutomationState>) -> Result<AutomationSettings, CollectorError> {
+    Ok(state.settings()?)
+}
+
+/// Save the settings and restart the API with them
+#[tauri::command]
+pub async fn update_automation_settings(
+    settings: AutomationSettings,
+    state: tauri::State<'_, AutomationState>,
+) -> Result<AutomationSettings, CollectorError> {
+    if settings.port < 1024 {
+        return Err(format!("Port must be 1024 or higher, got {}", settings.port).into());
+    }
+    state.save(&settings)?;
+    *state.settings.lock().unwrap() = settings;
+    state.restart()?;
+    log::info!("Automation API settings updated");
+    Ok(state.settings()?)
+}
+
+/// Replace the API token; clients using the old one are disconnected
+#[tauri::command]
+pub async fn regenerate_automation_token(state: tauri::State<'_, AutomationState>) -> Result<AutomationSettings, CollectorError> {
+    store_token(&generate_token())?;
+    state.restart()?;
+    log::info!("Automation API token regenerated");
+    Ok(state.settings()?)
+}
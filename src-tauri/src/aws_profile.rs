@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Credentials (and region) of a named profile in the shared AWS configuration files
+#[derive(Debug, Clone, Default)]
+pub struct ProfileCredentials {
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+    pub region: Option<String>,
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// `AWS_SHARED_CREDENTIALS_FILE` / `AWS_CONFIG_FILE`, or the file in `~/.aws`
+fn shared_file(env_var: &str, name: &str) -> Option<PathBuf> {
+    std::env::var_os(env_var)
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".aws").join(name)))
+}
+
+/// Section name -> key/value pairs of an INI file
+fn parse_ini(content: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let section = section.trim().to_string();
+            sections.entry(section.clone()).or_default();
+            current = Some(section);
+        } else if let (Some(section), Some((key, value))) = (&current, line.split_once('=')) {
+            sections
+                .get_mut(section)
+                .unwrap()
+                .insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    sections
+}
+
+fn read_section(path: Option<PathBuf>, section: &str) -> Option<HashMap<String, String>> {
+    let content = std::fs::read_to_string(path?).ok()?;
+    parse_ini(&content).remove(section)
+}
+
+/// Load a profile from `~/.aws/credentials`, with the region from `~/.aws/config`.
+/// Fails when neither file has the profile or it has no key pair.
+pub fn load(profile: &str) -> Result<ProfileCredentials, String> {
+    let credentials = read_section(shared_file("AWS_SHARED_CREDENTIALS_FILE", "credentials"), profile);
+
+    // The config file names non-default profiles "profile <name>" and may hold keys too
+    let config_section = if profile == "default" { profile.to_string() } else { format!("profile {}", profile) };
+    let config = read_section(shared_file("AWS_CONFIG_FILE", "config"), &config_section);
+
+    if credentials.is_none() && config.is_none() {
+        return Err(format!("AWS profile {} not found in ~/.aws/credentials or ~/.aws/config", profile));
+    }
+
+    let value = |key: &str| {
+        credentials.as_ref().and_then(|s| s.get(key))
+            .or_else(|| config.as_ref().and_then(|s| s.get(key)))
+            .cloned()
+    };
+
+    let loaded = ProfileCredentials {
+        access_key_id: value("aws_access_key_id"),
+        secret_access_key: value("aws_secret_access_key"),
+        session_token: value("aws_session_token"),
+        region: config.as_ref().and_then(|s| s.get("region")).cloned(),
+    };
+
+    if loaded.access_key_id.is_none() || loaded.secret_access_key.is_none() {
+        return Err(format!(
+            "AWS profile {} has no aws_access_key_id/aws_secret_access_key (SSO and credential_process profiles are not supported)",
+            profile
+        ));
+    }
+
+    log::info!("Using credentials from AWS profile {}", profile);
+    Ok(loaded)
+}
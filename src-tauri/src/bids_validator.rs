@@ -0,0 +1,399 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::error::CollectorError;
+
+/// Where the report of an automatic validation is written, relative to the dataset root
+pub const REPORT_PATH: &str = ".bids-collector/validation.json";
+
+/// Top-level directories BIDS leaves to their own conventions
+const UNCHECKED_DIRS: &[&str] = &["derivatives", "sourcedata", "code", "stimuli", "phenotype"];
+
+/// Datatype directories defined by the specification
+const DATATYPES: &[&str] = &[
+    "anat", "func", "dwi", "fmap", "perf", "pet", "meg", "eeg", "ieeg", "beh", "micr", "nirs", "motion", "mrs",
+];
+
+/// Imaging datatypes whose NIfTI files are described by JSON sidecars
+const SIDECAR_DATATYPES: &[&str] = &["anat", "func", "dwi", "fmap", "perf", "pet"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub code: String,
+    pub message: String,
+    /// Path relative to the dataset root, when the issue concerns one file
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    pub path: String,
+    pub valid: bool,
+    pub subjects: Vec<String>,
+    pub files_checked: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<ValidationIssue>,
+    pub validated_at: String,
+}
+
+/// A BIDS filename split into its entities, suffix and extension
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BidsName {
+    entities: Vec<(String, String)>,
+    suffix: String,
+    extension: String,
+}
+
+impl BidsName {
+    fn parse(file_name: &str) -> Option<BidsName> {
+        static ENTITY: OnceLock<Regex> = OnceLock::new();
+        let entity = ENTITY.get_or_init(|| Regex::new(r"^([a-zA-Z0-9]+)-([a-zA-Z0-9]+)$").unwrap());
+
+        let (stem, extension) = match file_name.find('.') {
+            Some(dot) => (&file_name[..dot], &file_name[dot..]),
+            None => (file_name, ""),
+        };
+
+        let mut parts: Vec<&str> = stem.split('_').collect();
+        let suffix = parts.pop()?;
+        if suffix.is_empty() || !suffix.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        let entities = parts
+            .iter()
+            .map(|part| {
+                entity.captures(part).map(|c| (c[1].to_string(), c[2].to_string()))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(BidsName {
+            entities,
+            suffix: suffix.to_string(),
+            extension: extension.to_string(),
+        })
+    }
+
+    fn entity(&self, key: &str) -> Option<&str> {
+        self.entities.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// Whether every entity of `self` also appears, with the same label, in `other`
+    fn applies_to(&self, other: &BidsName) -> bool {
+        self.suffix == other.suffix
+            && self.entities.iter().all(|(k, v)| other.entity(k) == Some(v.as_str()))
+    }
+}
+
+struct Validator {
+    issues: Vec<ValidationIssue>,
+}
+
+impl Validator {
+    fn error(&mut self, code: &str, message: String, file: Option<&str>) {
+        self.push(Severity::Error, code, message, file);
+    }
+
+    fn warning(&mut self, code: &str, message: String, file: Option<&str>) {
+        self.push(Severity::Warning, code, message, file);
+    }
+
+    fn push(&mut self, severity: Severity, code: &str, message: String, file: Option<&str>) {
+        self.issues.push(ValidationIssue {
+            severity,
+            code: code.to_string(),
+            message,
+            file: file.map(|f| f.to_string()),
+        });
+    }
+}
+
+/// Check a dataset on disk against the BIDS structural rules
+pub fn validate(root: &Path) -> Result<ValidationReport, String> {
+    if !root.is_dir() {
+        return Err(format!("Dataset directory not found: {}", root.display()));
+    }
+
+    let mut files = Vec::new();
+    collect_files(root, "", &mut files);
+    files.sort();
+
+    let mut validator = Validator { issues: Vec::new() };
+
+    check_description(root, &mut validator);
+
+    let subjects: BTreeSet<String> = std::fs::read_dir(root)
+        .map_err(|e| format!("Failed to read {}: {}", root.display(), e))?
+        .flatten()
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .filter_map(|e| e.file_name().to_str().and_then(|n| n.strip_prefix("sub-")).map(|s| s.to_string()))
+        .collect();
+
+    if subjects.is_empty() {
+        validator.error("NO_SUBJECTS", "No sub-<label> directories found".to_string(), None);
+    }
+
+    check_participants(root, &subjects, &mut validator);
+
+    let mut names = HashMap::new();
+    for file in &files {
+        if let Some(name) = check_file_name(file, &mut validator) {
+            names.insert(file.as_str(), name);
+        }
+    }
+
+    check_sidecars(&files, &names, &mut validator);
+
+    let errors = validator.issues.iter().filter(|i| i.severity == Severity::Error).count();
+    Ok(ValidationReport {
+        path: root.display().to_string(),
+        valid: errors == 0,
+        subjects: subjects.into_iter().collect(),
+        files_checked: files.len(),
+        errors,
+        warnings: validator.issues.len() - errors,
+        issues: validator.issues,
+        validated_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Relative paths of all files in the dataset, skipping hidden and unchecked directories
+fn collect_files(dir: &Path, prefix: &str, files: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Some(name) = entry.file_name().to_str().map(|n| n.to_string()) else {
+            continue;
+        };
+        if name.starts_with('.') || (prefix.is_empty() && UNCHECKED_DIRS.contains(&name.as_str())) {
+            continue;
+        }
+
+        let relative = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+        match entry.file_type() {
+            Ok(t) if t.is_dir() => collect_files(&entry.path(), &relative, files),
+            Ok(t) if t.is_file() => files.push(relative),
+            _ => {}
+        }
+    }
+}
+
+fn check_description(root: &Path, validator: &mut Validator) {
+    let file = "dataset_description.json";
+    let content = match std::fs::read(root.join(file)) {
+        Ok(content) => content,
+        Err(_) => {
+            validator.error("MISSING_DATASET_DESCRIPTION", "dataset_description.json is missing".to_string(), None);
+            return;
+        }
+    };
+
+    let description: serde_json::Value = match serde_json::from_slice(&content) {
+        Ok(value) => value,
+        Err(e) => {
+            validator.error("INVALID_JSON", format!("dataset_description.json is not valid JSON: {}", e), Some(file));
+            return;
+        }
+    };
+
+    for field in ["Name", "BIDSVersion"] {
+        if description.get(field).and_then(|v| v.as_str()).map(|s| s.trim().is_empty()).unwrap_or(true) {
+            validator.error("MISSING_REQUIRED_FIELD", format!("dataset_description.json has no {}", field), Some(file));
+        }
+    }
+}
+
+/// participants.tsv must list exactly the subjects that have directories
+fn check_participants(root: &Path, subjects: &BTreeSet<String>, validator: &mut Validator) {
+    let file = "participants.tsv";
+    let content = match std::fs::read_to_string(root.join(file)) {
+        Ok(content) => content,
+        Err(_) => {
+            if !subjects.is_empty() {
+                validator.warning("MISSING_PARTICIPANTS", "participants.tsv is missing".to_string(), None);
+            }
+            return;
+        }
+    };
+
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+    let header: Vec<&str> = lines.next().unwrap_or("").split('\t').collect();
+    if header.first().map(|h| h.trim()) != Some("participant_id") {
+        validator.error("PARTICIPANT_ID_COLUMN", "The first column of participants.tsv must be participant_id".to_string(), Some(file));
+        return;
+    }
+
+    let mut listed = BTreeSet::new();
+    for (row, line) in lines.enumerate() {
+        let columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() != header.len() {
+            validator.error(
+                "TSV_COLUMN_COUNT",
+                format!("Row {} has {} columns, the header has {}", row + 2, columns.len(), header.len()),
+                Some(file),
+            );
+        }
+
+        let id = columns[0].trim();
+        match id.strip_prefix("sub-") {
+            Some(label) => {
+                if !listed.insert(label.to_string()) {
+                    validator.error("DUPLICATE_PARTICIPANT", format!("{} is listed more than once", id), Some(file));
+                }
+            }
+            None => validator.error("PARTICIPANT_ID_FORMAT", format!("participant_id {} does not start with sub-", id), Some(file)),
+        }
+    }
+
+    for missing in subjects.difference(&listed) {
+        validator.error(
+            "PARTICIPANT_ID_MISMATCH",
+            format!("sub-{} has a directory but is not listed in participants.tsv", missing),
+            Some(file),
+        );
+    }
+    for extra in listed.difference(subjects) {
+        validator.warning(
+            "PARTICIPANT_WITHOUT_DATA",
+            format!("sub-{} is listed in participants.tsv but has no directory", extra),
+            Some(file),
+        );
+    }
+}
+
+/// Check a file below a subject directory against the entity conventions
+fn check_file_name(file: &str, validator: &mut Validator) -> Option<BidsName> {
+    let components: Vec<&str> = file.split('/').collect();
+    let file_name = *components.last()?;
+
+    // Top-level files: only the sidecars used for inheritance are parsed
+    if components.len() == 1 {
+        return BidsName::parse(file_name).filter(|n| n.extension == ".json" && !n.entities.is_empty());
+    }
+
+    let subject = components[0].strip_prefix("sub-")?;
+
+    let Some(name) = BidsName::parse(file_name) else {
+        validator.error("INVALID_FILENAME", format!("{} does not follow the key-value_suffix.extension convention", file_name), Some(file));
+        return None;
+    };
+
+    if name.entities.first().map(|(k, _)| k.as_str()) != Some("sub") || name.entity("sub") != Some(subject) {
+        validator.error("SUBJECT_MISMATCH", format!("{} does not start with sub-{}", file_name, subject), Some(file));
+    }
+
+    let session = components.get(1).and_then(|c| c.strip_prefix("ses-")).filter(|_| components.len() > 2);
+    if let Some(session) = session {
+        if name.entity("ses") != Some(session) {
+            validator.error("SESSION_MISMATCH", format!("{} is in ses-{} but does not carry ses-{}", file_name, session, session), Some(file));
+        }
+    }
+
+    let depth = if session.is_some() { 2 } else { 1 };
+    if components.len() > depth + 1 {
+        let datatype = components[depth];
+        if !DATATYPES.contains(&datatype) {
+            validator.warning("UNKNOWN_DATATYPE", format!("{} is not a BIDS datatype directory", datatype), Some(file));
+        }
+        if components.len() > depth + 2 && datatype != "micr" {
+            validator.warning("NESTED_DATATYPE", "Data files are nested below the datatype directory".to_string(), Some(file));
+        }
+    }
+
+    Some(name)
+}
+
+/// NIfTI files need a JSON sidecar (found by inheritance); DWI also needs bval/bvec
+fn check_sidecars(files: &[String], names: &HashMap<&str, BidsName>, validator: &mut Validator) {
+    let parent = |file: &str| file.rsplit_once('/').map(|(dir, _)| dir.to_string()).unwrap_or_default();
+
+    let has_inherited = |file: &str, name: &BidsName, extension: &str| {
+        let dir = parent(file);
+        names.iter().any(|(other, other_name)| {
+            let other_dir = parent(other);
+            other_name.extension == extension
+                && (other_dir.is_empty() || dir == other_dir || dir.starts_with(&format!("{}/", other_dir)))
+                && other_name.applies_to(name)
+        })
+    };
+
+    for file in files {
+        let Some(name) = names.get(file.as_str()) else {
+            continue;
+        };
+        if name.extension != ".nii" && name.extension != ".nii.gz" {
+            continue;
+        }
+
+        let datatype = file.rsplit('/').nth(1).unwrap_or("");
+        if !SIDECAR_DATATYPES.contains(&datatype) {
+            continue;
+        }
+
+        if !has_inherited(file, name, ".json") {
+            let message = format!("No JSON sidecar describes {}", file);
+            if datatype == "func" && name.suffix == "bold" {
+                // RepetitionTime and TaskName are required for BOLD runs
+                validator.error("MISSING_SIDECAR", message, Some(file));
+            } else {
+                validator.warning("MISSING_SIDECAR", message, Some(file));
+            }
+        }
+
+        if datatype == "dwi" && name.suffix == "dwi" {
+            for extension in [".bval", ".bvec"] {
+                if !has_inherited(file, name, extension) {
+                    validator.error("MISSING_DWI_GRADIENTS", format!("No {} file for {}", extension, file), Some(file));
+                }
+            }
+        }
+    }
+}
+
+/// Validate in the background and store the report next to the dataset
+pub async fn validate_and_write(dest_dir: &str) -> Result<ValidationReport, String> {
+    let root = dest_dir.to_string();
+    let report = tokio::task::spawn_blocking(move || validate(Path::new(&root)))
+        .await
+        .map_err(|e| format!("Validation task failed: {}", e))??;
+
+    let path = Path::new(dest_dir).join(REPORT_PATH);
+    crate::path_guard::check(&path)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_vec_pretty(&report)
+        .map_err(|e| format!("Failed to serialize validation report: {}", e))?;
+    tokio::fs::write(&path, content).await
+        .map_err(|e| format!("Failed to write validation report {}: {}", path.display(), e))?;
+
+    log::warn!(
+        "BIDS validation of {}: {} errors, {} warnings",
+        dest_dir, report.errors, report.warnings
+    );
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn validate_dataset(path: String) -> Result<ValidationReport, CollectorError> {
+    tokio::task::spawn_blocking(move || validate(Path::new(&path)))
+        .await
+        .map_err(|e| format!("Validation task failed: {}", e))?
+        .map_err(CollectorError::from)
+}
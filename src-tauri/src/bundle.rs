@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::catalog::CatalogState;
+use crate::error::CollectorError;
+use crate::filters::SELECTION_FIELDS;
+
+const BUNDLE_VERSION: u32 = 1;
+
+/// Everything needed to assemble the same data collection again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionBundle {
+    pub bundle_version: u32,
+    pub created_at: String,
+    pub generated_by: String,
+    pub datasets: Vec<BundleDataset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleDataset {
+    pub provider: String,
+    pub identifier: String,
+    pub version: Option<String>,
+    pub doi: Option<String>,
+    /// Selection fields as they were set on the original task
+    pub selection: Value,
+    pub file_count: u64,
+    pub total_size: u64,
+    /// Compare against the checksum root of a re-collected copy to confirm it is identical
+    pub checksum_root: String,
+}
+
+/// Describe the given catalog entries as one collection bundle for a publication
+#[tauri::command]
+pub async fn export_collection_bundle(
+    dataset_ids: Vec<String>,
+    catalog: tauri::State<'_, CatalogState>,
+) -> Result<CollectionBundle, CollectorError> {
+    if dataset_ids.is_empty() {
+        return Err("No datasets selected for the collection bundle".into());
+    }
+
+    let catalog = catalog.lock().unwrap();
+    let mut datasets = Vec::with_capacity(dataset_ids.len());
+
+    for id in &dataset_ids {
+        let entry = catalog.get(id)?
+            .ok_or_else(|| format!("Dataset {} is not in the catalog; only completed collections can be exported", id))?;
+
+        datasets.push(BundleDataset {
+            provider: entry.provider,
+            identifier: entry.identifier,
+            version: entry.version,
+            doi: entry.doi,
+            selection: entry.selection,
+            file_count: entry.file_count,
+            total_size: entry.total_size,
+            checksum_root: entry.checksum_root,
+        });
+    }
+
+    log::info!("Exported collection bundle with {} datasets", datasets.len());
+
+    Ok(CollectionBundle {
+        bundle_version: BUNDLE_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        generated_by: format!("bids-collector {}", env!("CARGO_PKG_VERSION")),
+        datasets,
+    })
+}
+
+/// Turn a bundle back into task drafts the frontend can hand to `start_download_task`
+#[tauri::command]
+pub async fn import_collection_bundle(bundle: CollectionBundle) -> Result<Vec<Value>, CollectorError> {
+    if bundle.bundle_version > BUNDLE_VERSION {
+        return Err(format!(
+            "Collection bundle version {} is newer than supported version {}",
+            bundle.bundle_version, BUNDLE_VERSION
+        ).into());
+    }
+
+    let tasks = bundle.datasets
+        .iter()
+        .map(|dataset| {
+            let mut task = json!({
+                "datasetProvider": dataset.provider,
+                "downloadPath": dataset.identifier,
+                "datasetVersion": dataset.version,
+                "datasetDoi": dataset.doi,
+                "expectedChecksumRoot": dataset.checksum_root,
+            });
+
+            for field in SELECTION_FIELDS {
+                if let Some(value) = dataset.selection.get(field) {
+                    task[field] = value.clone();
+                }
+            }
+            task
+        })
+        .collect();
+
+    log::info!("Imported collection bundle with {} datasets", bundle.datasets.len());
+    Ok(tasks)
+}
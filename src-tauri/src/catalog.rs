@@ -0,0 +1,375 @@
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::library::LocalDataset;
+use crate::statistics::TransferRecord;
+
+/// File name of the catalog database inside the app data directory
+pub const CATALOG_FILE: &str = "catalog.sqlite3";
+
+/// One completed collection, as recorded in the catalog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogEntry {
+    /// Task ID of the collection
+    pub id: String,
+    pub provider: String,
+    /// Provider identifier as given in the task's `downloadPath`
+    pub identifier: String,
+    pub version: Option<String>,
+    pub doi: Option<String>,
+    pub storage_type: String,
+    /// Local directory, or `s3://bucket/prefix`
+    pub location: String,
+    /// Selection fields of the task (include/exclude patterns, subjects, ...)
+    pub selection: serde_json::Value,
+    pub file_count: u64,
+    pub total_size: u64,
+    /// SHA-256 over the sorted manifest, see `Manifest::checksum_root`
+    pub checksum_root: String,
+    pub collected_at: String,
+}
+
+/// Persistent record of every dataset collected by this installation
+pub struct Catalog {
+    conn: Connection,
+    path: PathBuf,
+}
+
+pub type CatalogState = Arc<Mutex<Catalog>>;
+
+impl Catalog {
+    pub fn open(path: &Path) -> Result<Catalog, String> {
+        crate::path_guard::check(path)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open catalog {}: {}", path.display(), e))?;
+
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS datasets (
+                id TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                identifier TEXT NOT NULL,
+                version TEXT,
+                doi TEXT,
+                storage_type TEXT NOT NULL,
+                location TEXT NOT NULL,
+                selection TEXT NOT NULL,
+                file_count INTEGER NOT NULL,
+                total_size INTEGER NOT NULL,
+                checksum_root TEXT NOT NULL,
+                collected_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS library (
+                path TEXT PRIMARY KEY,
+                storage_location_id TEXT NOT NULL,
+                name TEXT,
+                accession TEXT,
+                size INTEGER NOT NULL,
+                file_count INTEGER NOT NULL,
+                last_sync TEXT,
+                scanned_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS content_cache (
+                path TEXT PRIMARY KEY,
+                sha256 TEXT NOT NULL,
+                size INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS content_cache_sha256 ON content_cache (sha256);
+            CREATE TABLE IF NOT EXISTS transfers (
+                task_id TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                provider TEXT,
+                dataset TEXT,
+                status TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                finished_at TEXT NOT NULL,
+                bytes INTEGER NOT NULL,
+                files INTEGER NOT NULL,
+                duration_seconds REAL NOT NULL,
+                average_speed REAL NOT NULL,
+                retries INTEGER NOT NULL,
+                error TEXT
+            );
+            CREATE INDEX IF NOT EXISTS transfers_finished_at ON transfers (finished_at);",
+        )
+        .map_err(|e| format!("Failed to initialize catalog: {}", e))?;
+
+        log::info!("Opened catalog at {}", path.display());
+        Ok(Catalog { conn, path: path.to_path_buf() })
+    }
+
+    /// Datasets found by the last library scan
+    pub fn library(&self) -> Result<Vec<LocalDataset>, String> {
+        let mut statement = self.conn
+            .prepare("SELECT * FROM library ORDER BY path")
+            .map_err(|e| format!("Failed to read library: {}", e))?;
+        let datasets = statement
+            .query_map([], dataset_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read library: {}", e))?;
+        Ok(datasets)
+    }
+
+    /// Replace the library with the result of a scan
+    pub fn replace_library(&mut self, datasets: &[LocalDataset]) -> Result<(), String> {
+        let transaction = self.conn
+            .transaction()
+            .map_err(|e| format!("Failed to update library: {}", e))?;
+        transaction
+            .execute("DELETE FROM library", [])
+            .map_err(|e| format!("Failed to update library: {}", e))?;
+        for dataset in datasets {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO library (
+                        path, storage_location_id, name, accession, size, file_count, last_sync, scanned_at
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        dataset.path,
+                        dataset.storage_location_id,
+                        dataset.name,
+                        dataset.accession,
+                        dataset.size as i64,
+                        dataset.file_count as i64,
+                        dataset.last_sync,
+                        dataset.scanned_at,
+                    ],
+                )
+                .map_err(|e| format!("Failed to record {} in library: {}", dataset.path, e))?;
+        }
+        transaction
+            .commit()
+            .map_err(|e| format!("Failed to update library: {}", e))
+    }
+
+    /// Local files known to hold the content with this SHA-256 and size
+    pub fn cached_content(&self, sha256: &str, size: u64) -> Result<Vec<String>, String> {
+        let mut statement = self.conn
+            .prepare("SELECT path FROM content_cache WHERE sha256 = ?1 AND size = ?2")
+            .map_err(|e| format!("Failed to read content cache: {}", e))?;
+        let paths = statement
+            .query_map(params![sha256, size as i64], |row| row.get(0))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<String>>>())
+            .map_err(|e| format!("Failed to read content cache: {}", e))?;
+        Ok(paths)
+    }
+
+    pub fn cache_content(&self, path: &str, sha256: &str, size: u64) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO content_cache (path, sha256, size) VALUES (?1, ?2, ?3)",
+                params![path, sha256, size as i64],
+            )
+            .map_err(|e| format!("Failed to record {} in content cache: {}", path, e))?;
+        Ok(())
+    }
+
+    pub fn forget_content(&self, path: &str) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM content_cache WHERE path = ?1", params![path])
+            .map_err(|e| format!("Failed to remove {} from content cache: {}", path, e))?;
+        Ok(())
+    }
+
+    /// Fails when SQLite finds the database damaged
+    pub fn check_integrity(&self) -> Result<(), String> {
+        integrity_check(&self.conn).map_err(|e| format!("Catalog {} is damaged: {}", self.path.display(), e))
+    }
+
+    /// Write a consistent copy of the database to `path`, which must not exist yet
+    pub fn backup_to(&self, path: &Path) -> Result<(), String> {
+        crate::path_guard::check(path)?;
+        self.conn
+            .execute("VACUUM INTO ?1", params![path.to_string_lossy()])
+            .map_err(|e| format!("Failed to back up catalog to {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    /// Replace the database with a backup written by `backup_to`
+    pub fn restore_from(&mut self, backup: &Path) -> Result<(), String> {
+        verify_backup(backup)?;
+        crate::path_guard::check(&self.path)?;
+
+        // Close the current connection before its file is replaced
+        let placeholder = Connection::open_in_memory()
+            .map_err(|e| format!("Failed to open temporary catalog: {}", e))?;
+        std::mem::replace(&mut self.conn, placeholder)
+            .close()
+            .map_err(|(_, e)| format!("Failed to close catalog: {}", e))?;
+
+        for suffix in ["-journal", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", self.path.display(), suffix));
+        }
+        std::fs::copy(backup, &self.path)
+            .map_err(|e| format!("Failed to copy {} to {}: {}", backup.display(), self.path.display(), e))?;
+
+        *self = Catalog::open(&self.path)?;
+        Ok(())
+    }
+
+    /// Insert or replace the entry for a collection
+    pub fn record(&self, entry: &CatalogEntry) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO datasets (
+                    id, provider, identifier, version, doi, storage_type, location,
+                    selection, file_count, total_size, checksum_root, collected_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    entry.id,
+                    entry.provider,
+                    entry.identifier,
+                    entry.version,
+                    entry.doi,
+                    entry.storage_type,
+                    entry.location,
+                    entry.selection.to_string(),
+                    entry.file_count as i64,
+                    entry.total_size as i64,
+                    entry.checksum_root,
+                    entry.collected_at,
+                ],
+            )
+            .map_err(|e| format!("Failed to record {} in catalog: {}", entry.id, e))?;
+
+        Ok(())
+    }
+
+    /// Every recorded collection, oldest first
+    pub fn list(&self) -> Result<Vec<CatalogEntry>, String> {
+        let mut statement = self.conn
+            .prepare("SELECT * FROM datasets ORDER BY collected_at")
+            .map_err(|e| format!("Failed to read catalog: {}", e))?;
+        let entries = statement
+            .query_map([], entry_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read catalog: {}", e))?;
+        Ok(entries)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<CatalogEntry>, String> {
+        self.conn
+            .query_row("SELECT * FROM datasets WHERE id = ?1", params![id], entry_from_row)
+            .optional()
+            .map_err(|e| format!("Failed to read {} from catalog: {}", id, e))
+    }
+
+    /// Insert or replace the statistics of a finished task
+    pub fn record_transfer(&self, record: &TransferRecord) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO transfers (
+                    task_id, kind, provider, dataset, status, started_at, finished_at,
+                    bytes, files, duration_seconds, average_speed, retries, error
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    record.task_id,
+                    record.kind,
+                    record.provider,
+                    record.dataset,
+                    record.status,
+                    record.started_at,
+                    record.finished_at,
+                    record.bytes as i64,
+                    record.files as i64,
+                    record.duration_seconds,
+                    record.average_speed,
+                    record.retries as i64,
+                    record.error,
+                ],
+            )
+            .map_err(|e| format!("Failed to record statistics of {}: {}", record.task_id, e))?;
+        Ok(())
+    }
+
+    /// Tasks that finished at or after `from` and before `to` (RFC 3339), oldest first
+    pub fn transfers(&self, from: &str, to: &str) -> Result<Vec<TransferRecord>, String> {
+        let mut statement = self.conn
+            .prepare("SELECT * FROM transfers WHERE finished_at >= ?1 AND finished_at < ?2 ORDER BY finished_at")
+            .map_err(|e| format!("Failed to read transfer statistics: {}", e))?;
+        let records = statement
+            .query_map(params![from, to], transfer_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read transfer statistics: {}", e))?;
+        Ok(records)
+    }
+}
+
+/// Check that `path` is an intact catalog; returns the number of datasets it records
+pub fn verify_backup(path: &Path) -> Result<u64, String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open backup {}: {}", path.display(), e))?;
+    integrity_check(&conn).map_err(|e| format!("Backup {} is damaged: {}", path.display(), e))?;
+
+    conn.query_row("SELECT COUNT(*) FROM datasets", [], |row| row.get::<_, i64>(0))
+        .map(|count| count as u64)
+        .map_err(|e| format!("{} is not a catalog backup: {}", path.display(), e))
+}
+
+fn integrity_check(conn: &Connection) -> Result<(), String> {
+    let result: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if result == "ok" {
+        Ok(())
+    } else {
+        Err(result)
+    }
+}
+
+fn dataset_from_row(row: &Row) -> rusqlite::Result<LocalDataset> {
+    Ok(LocalDataset {
+        path: row.get("path")?,
+        storage_location_id: row.get("storage_location_id")?,
+        name: row.get("name")?,
+        accession: row.get("accession")?,
+        size: row.get::<_, i64>("size")? as u64,
+        file_count: row.get::<_, i64>("file_count")? as u64,
+        last_sync: row.get("last_sync")?,
+        scanned_at: row.get("scanned_at")?,
+    })
+}
+
+fn transfer_from_row(row: &Row) -> rusqlite::Result<TransferRecord> {
+    Ok(TransferRecord {
+        task_id: row.get("task_id")?,
+        kind: row.get("kind")?,
+        provider: row.get("provider")?,
+        dataset: row.get("dataset")?,
+        status: row.get("status")?,
+        started_at: row.get("started_at")?,
+        finished_at: row.get("finished_at")?,
+        bytes: row.get::<_, i64>("bytes")? as u64,
+        files: row.get::<_, i64>("files")? as u64,
+        duration_seconds: row.get("duration_seconds")?,
+        average_speed: row.get("average_speed")?,
+        retries: row.get::<_, i64>("retries")? as u64,
+        error: row.get("error")?,
+    })
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<CatalogEntry> {
+    let selection: String = row.get("selection")?;
+
+    Ok(CatalogEntry {
+        id: row.get("id")?,
+        provider: row.get("provider")?,
+        identifier: row.get("identifier")?,
+        version: row.get("version")?,
+        doi: row.get("doi")?,
+        storage_type: row.get("storage_type")?,
+        location: row.get("location")?,
+        selection: serde_json::from_str(&selection).unwrap_or(serde_json::Value::Null),
+        file_count: row.get::<_, i64>("file_count")? as u64,
+        total_size: row.get::<_, i64>("total_size")? as u64,
+        checksum_root: row.get("checksum_root")?,
+        collected_at: row.get("collected_at")?,
+    })
+}
@@ -0,0 +1,343 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::catalog::{self, Catalog, CatalogState};
+use crate::error::CollectorError;
+use crate::{credentials, path_guard, storage};
+
+/// File in the app data directory holding the backup settings
+pub const SETTINGS_FILE: &str = "catalog_backup.json";
+
+/// Directory in the app data directory the backups rotate in
+const BACKUP_DIR: &str = "catalog-backups";
+
+const BACKUP_PREFIX: &str = "catalog-";
+const BACKUP_EXTENSION: &str = ".sqlite3";
+
+/// How often the background loop checks whether a backup is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSettings {
+    pub enabled: bool,
+    pub interval_hours: u32,
+    /// Backups kept in the app data directory; older ones are deleted
+    pub keep: usize,
+    /// Saved storage location every backup is also uploaded to
+    #[serde(default)]
+    pub storage_location_id: Option<String>,
+    #[serde(default = "default_remote_prefix")]
+    pub remote_prefix: String,
+}
+
+fn default_remote_prefix() -> String {
+    "bids-collector/catalog-backups".to_string()
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        BackupSettings {
+            enabled: true,
+            interval_hours: 24,
+            keep: 7,
+            storage_location_id: None,
+            remote_prefix: default_remote_prefix(),
+        }
+    }
+}
+
+impl BackupSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.interval_hours == 0 {
+            return Err("Backup interval must be at least one hour".to_string());
+        }
+        if self.keep == 0 {
+            return Err("At least one catalog backup must be kept".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogBackup {
+    /// File name inside the backup directory, as passed to `restore_catalog_backup`
+    pub file: String,
+    pub size: u64,
+    pub created_at: String,
+}
+
+impl CatalogBackup {
+    /// Name carries a label after the timestamp
+    fn is_labelled(&self) -> bool {
+        self.file
+            .trim_start_matches(BACKUP_PREFIX)
+            .trim_end_matches(BACKUP_EXTENSION)
+            .contains('-')
+    }
+}
+
+/// Takes rotating snapshots of the catalog and restores them
+pub struct BackupManager {
+    catalog_path: PathBuf,
+    backup_dir: PathBuf,
+    settings_path: PathBuf,
+    settings: Mutex<BackupSettings>,
+}
+
+pub type CatalogBackupState = Arc<BackupManager>;
+
+impl BackupManager {
+    /// Load the settings from the app data directory, falling back to the defaults
+    pub fn open(app_handle: &tauri::AppHandle) -> Result<BackupManager, String> {
+        let app_data_dir = app_handle.path().app_data_dir()
+            .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+        let settings_path = app_data_dir.join(SETTINGS_FILE);
+
+        let settings = match std::fs::read(&settings_path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", settings_path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BackupSettings::default(),
+            Err(e) => return Err(format!("Failed to read {}: {}", settings_path.display(), e)),
+        };
+
+        Ok(BackupManager {
+            catalog_path: app_data_dir.join(catalog::CATALOG_FILE),
+            backup_dir: app_data_dir.join(BACKUP_DIR),
+            settings_path,
+            settings: Mutex::new(settings),
+        })
+    }
+
+    pub fn settings(&self) -> BackupSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    fn save(&self, settings: BackupSettings) -> Result<(), String> {
+        path_guard::check(&self.settings_path)?;
+        if let Some(parent) = self.settings_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        let content = serde_json::to_vec_pretty(&settings)
+            .map_err(|e| format!("Failed to serialize backup settings: {}", e))?;
+        std::fs::write(&self.settings_path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.settings_path.display(), e))?;
+
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+
+    /// Open the catalog. A damaged database is moved aside and replaced with the newest
+    /// intact backup, so the collection history survives a corrupted file.
+    pub fn open_catalog(&self) -> Result<Catalog, String> {
+        let error = match Catalog::open(&self.catalog_path).and_then(|c| c.check_integrity().map(|_| c)) {
+            Ok(catalog) => return Ok(catalog),
+            Err(e) => e,
+        };
+        log::warn!("{}", error);
+
+        let Some(backup) = self.list()?.into_iter().find(|b| catalog::verify_backup(&self.backup_dir.join(&b.file)).is_ok()) else {
+            return Err(format!("{}; no intact backup to restore from", error));
+        };
+
+        let damaged = self.catalog_path.with_extension(format!("damaged-{}", chrono::Utc::now().format("%Y%m%dT%H%M%SZ")));
+        path_guard::check(&damaged)?;
+        std::fs::rename(&self.catalog_path, &damaged)
+            .map_err(|e| format!("Failed to move damaged catalog to {}: {}", damaged.display(), e))?;
+        std::fs::copy(self.backup_dir.join(&backup.file), &self.catalog_path)
+            .map_err(|e| format!("Failed to restore catalog backup {}: {}", backup.file, e))?;
+
+        log::info!("Restored catalog from backup {} (damaged copy kept at {})", backup.file, damaged.display());
+        Catalog::open(&self.catalog_path)
+    }
+
+    /// Backups in the backup directory, newest first
+    pub fn list(&self) -> Result<Vec<CatalogBackup>, String> {
+        let entries = match std::fs::read_dir(&self.backup_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read {}: {}", self.backup_dir.display(), e)),
+        };
+
+        let mut backups = Vec::new();
+        for entry in entries.flatten() {
+            let file = entry.file_name().to_string_lossy().to_string();
+            if !file.starts_with(BACKUP_PREFIX) || !file.ends_with(BACKUP_EXTENSION) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let created_at = metadata.modified()
+                .map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339())
+                .unwrap_or_default();
+            backups.push(CatalogBackup { file, size: metadata.len(), created_at });
+        }
+
+        // File names start with the UTC timestamp, so they sort chronologically
+        backups.sort_by(|a, b| b.file.cmp(&a.file));
+        Ok(backups)
+    }
+
+    /// Snapshot the catalog into the backup directory and delete backups beyond `keep`
+    pub fn create(&self, catalog: &CatalogState, label: Option<&str>) -> Result<CatalogBackup, String> {
+        path_guard::check(&self.backup_dir)?;
+        std::fs::create_dir_all(&self.backup_dir)
+            .map_err(|e| format!("Failed to create directory {}: {}", self.backup_dir.display(), e))?;
+
+        let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+        let file = match label {
+            Some(label) => format!("{}{}-{}{}", BACKUP_PREFIX, timestamp, label, BACKUP_EXTENSION),
+            None => format!("{}{}{}", BACKUP_PREFIX, timestamp, BACKUP_EXTENSION),
+        };
+        let path = self.backup_dir.join(&file);
+        catalog.lock().unwrap().backup_to(&path)?;
+
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        log::info!("Backed up catalog to {} ({})", file, crate::formatting::bytes(size));
+        self.rotate()?;
+
+        Ok(CatalogBackup { file, size, created_at: chrono::Utc::now().to_rfc3339() })
+    }
+
+    /// Labelled backups, such as the ones taken before a restore, are kept until removed by hand
+    fn rotate(&self) -> Result<(), String> {
+        let keep = self.settings().keep;
+        let scheduled = self.list()?.into_iter().filter(|backup| !backup.is_labelled());
+        for old in scheduled.skip(keep) {
+            let path = self.backup_dir.join(&old.file);
+            path_guard::check(&path)?;
+            match std::fs::remove_file(&path) {
+                Ok(()) => log::info!("Removed old catalog backup {}", old.file),
+                Err(e) => log::warn!("Failed to remove old catalog backup {}: {}", old.file, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Upload a backup to the configured storage location, if there is one
+    pub async fn upload(&self, backup: &CatalogBackup, app_handle: &tauri::AppHandle) -> Result<(), String> {
+        let settings = self.settings();
+        let Some(location_id) = settings.storage_location_id else {
+            return Ok(());
+        };
+
+        let location = credentials::saved_location(app_handle, &location_id)?;
+        let storage = storage::from_storage_location(&location)?;
+        let content = tokio::fs::read(self.backup_dir.join(&backup.file)).await
+            .map_err(|e| format!("Failed to read catalog backup {}: {}", backup.file, e))?;
+
+        let key = format!("{}/{}", settings.remote_prefix.trim_end_matches('/'), backup.file);
+        storage.put(&key, &content).await
+            .map_err(|e| format!("Failed to upload catalog backup to {}: {}", storage.display_name(), e))?;
+        log::info!("Uploaded catalog backup to {}", storage.location(&key));
+        Ok(())
+    }
+
+    /// Whether the newest backup is older than the configured interval
+    fn is_due(&self) -> Result<bool, String> {
+        let settings = self.settings();
+        if !settings.enabled {
+            return Ok(false);
+        }
+        let Some(newest) = self.list()?.into_iter().next() else {
+            return Ok(true);
+        };
+        let Ok(created_at) = chrono::DateTime::parse_from_rfc3339(&newest.created_at) else {
+            return Ok(true);
+        };
+        Ok(chrono::Utc::now().signed_duration_since(created_at) >= chrono::Duration::hours(settings.interval_hours as i64))
+    }
+
+    /// Resolve a backup given by file name (from `list`) or as an absolute path
+    fn backup_path(&self, file: &str) -> Result<PathBuf, String> {
+        let path = Path::new(file);
+        if path.is_absolute() {
+            return Ok(path.to_path_buf());
+        }
+        if path.components().count() != 1 {
+            return Err(format!("{} is not a catalog backup file name", file));
+        }
+        Ok(self.backup_dir.join(path))
+    }
+}
+
+async fn run_scheduled_backup(manager: &BackupManager, app_handle: &tauri::AppHandle) -> Result<(), String> {
+    if !manager.is_due()? {
+        return Ok(());
+    }
+    let backup = manager.create(&app_handle.state::<CatalogState>(), None)?;
+    manager.upload(&backup, app_handle).await
+}
+
+/// Back up the catalog whenever the interval has passed, for the lifetime of the app
+pub fn spawn(app_handle: tauri::AppHandle, manager: CatalogBackupState) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = run_scheduled_backup(&manager, &app_handle).await {
+                log::warn!("Scheduled catalog backup failed: {}", e);
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn list_catalog_backups(state: tauri::State<'_, CatalogBackupState>) -> Result<Vec<CatalogBackup>, CollectorError> {
+    Ok(state.list()?)
+}
+
+/// Back up the catalog now, uploading the backup when a storage location is configured
+#[tauri::command]
+pub async fn create_catalog_backup(
+    state: tauri::State<'_, CatalogBackupState>,
+    catalog: tauri::State<'_, CatalogState>,
+    app_handle: tauri::AppHandle,
+) -> Result<CatalogBackup, CollectorError> {
+    let backup = state.create(catalog.inner(), None)?;
+    state.upload(&backup, &app_handle).await?;
+    Ok(backup)
+}
+
+/// Replace the catalog with a backup. The current catalog is backed up first, so a restore
+/// can itself be undone.
+#[tauri::command]
+pub async fn restore_catalog_backup(
+    file: String,
+    state: tauri::State<'_, CatalogBackupState>,
+    catalog: tauri::State<'_, CatalogState>,
+) -> Result<u64, CollectorError> {
+    let path = state.backup_path(&file)?;
+    let datasets = catalog::verify_backup(&path)?;
+
+    if let Err(e) = state.create(catalog.inner(), Some("pre-restore")) {
+        log::warn!("Could not back up the current catalog before restoring: {}", e);
+    }
+    catalog.lock().unwrap().restore_from(&path)?;
+
+    log::info!("Restored catalog from {} ({} datasets)", path.display(), datasets);
+    Ok(datasets)
+}
+
+#[tauri::command]
+pub async fn get_catalog_backup_settings(state: tauri::State<'_, CatalogBackupState>) -> Result<BackupSettings, CollectorError> {
+    Ok(state.settings())
+}
+
+#[tauri::command]
+pub async fn update_catalog_backup_settings(
+    settings: BackupSettings,
+    state: tauri::State<'_, CatalogBackupState>,
+) -> Result<BackupSettings, CollectorError> {
+    settings.validate()?;
+    state.save(settings)?;
+    state.rotate()?;
+
+    log::info!("Catalog backup settings updated");
+    Ok(state.settings())
+}
@@ -0,0 +1,116 @@
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Md5,
+}
+
+/// A checksum published by a dataset provider
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checksum {
+    pub algorithm: ChecksumAlgorithm,
+    pub value: String,
+}
+
+impl Checksum {
+    pub fn sha256(value: &str) -> Checksum {
+        Checksum {
+            algorithm: ChecksumAlgorithm::Sha256,
+            value: value.to_lowercase(),
+        }
+    }
+
+    pub fn md5(value: &str) -> Checksum {
+        Checksum {
+            algorithm: ChecksumAlgorithm::Md5,
+            value: value.to_lowercase(),
+        }
+    }
+
+    /// Parse the "algorithm:value" form used by Zenodo and others (e.g. "md5:9e10...")
+    pub fn parse(prefixed: &str) -> Option<Checksum> {
+        let (algorithm, value) = prefixed.split_once(':')?;
+        match algorithm.to_lowercase().replace('-', "").as_str() {
+            "sha256" => Some(Checksum::sha256(value)),
+            "md5" => Some(Checksum::md5(value)),
+            _ => None,
+        }
+    }
+
+    /// Parse a git-annex key such as "SHA256E-s1024--9f86d0...e5.nii.gz", as OpenNeuro
+    /// reports for annexed files. Keys of other backends (or git blob ids) give None.
+    pub fn from_annex_key(key: &str) -> Option<Checksum> {
+        let (backend, rest) = key.split_once('-')?;
+        let (_, name) = rest.split_once("--")?;
+        let (algorithm, length) = match backend {
+            "SHA256" | "SHA256E" => (ChecksumAlgorithm::Sha256, 64),
+            "MD5" | "MD5E" => (ChecksumAlgorithm::Md5, 32),
+            _ => return None,
+        };
+        // The E backends keep the file extension after the digest
+        let digest = name.get(..length).filter(|d| d.bytes().all(|b| b.is_ascii_hexdigit()))?;
+        Some(Checksum {
+            algorithm,
+            value: digest.to_lowercase(),
+        })
+    }
+
+    /// Compare against data that is fully in memory
+    pub fn verify_bytes(&self, content: &[u8]) -> Result<(), String> {
+        let mut hasher = ChecksumHasher::new(self.algorithm);
+        hasher.update(content);
+        self.verify_hex(&hasher.finalize_hex())
+    }
+
+    /// Compare against a digest computed while streaming
+    pub fn verify_hex(&self, actual: &str) -> Result<(), String> {
+        if actual.eq_ignore_ascii_case(&self.value) {
+            Ok(())
+        } else {
+            Err(format!("Checksum mismatch: expected {} {}, got {}", self, self.value, actual))
+        }
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.algorithm {
+            ChecksumAlgorithm::Sha256 => write!(f, "SHA-256"),
+            ChecksumAlgorithm::Md5 => write!(f, "MD5"),
+        }
+    }
+}
+
+/// Incremental hasher for any supported algorithm
+pub enum ChecksumHasher {
+    Sha256(Sha256),
+    Md5(Md5),
+}
+
+impl ChecksumHasher {
+    pub fn new(algorithm: ChecksumAlgorithm) -> ChecksumHasher {
+        match algorithm {
+            ChecksumAlgorithm::Sha256 => ChecksumHasher::Sha256(Sha256::new()),
+            ChecksumAlgorithm::Md5 => ChecksumHasher::Md5(Md5::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            ChecksumHasher::Sha256(hasher) => hasher.update(data),
+            ChecksumHasher::Md5(hasher) => hasher.update(data),
+        }
+    }
+
+    pub fn finalize_hex(self) -> String {
+        match self {
+            ChecksumHasher::Sha256(hasher) => hex::encode(hasher.finalize()),
+            ChecksumHasher::Md5(hasher) => hex::encode(hasher.finalize()),
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use serde_json::{json, Value};
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::error::ErrorKind;
+use crate::formatting;
+use crate::providers;
+use crate::settings::SettingsState;
+use crate::task_queue::TaskQueueState;
+use crate::{DownloadProgress, DownloadState};
+
+/// How often progress is reported while the collection runs
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Exit statuses of `collect`, so scripts can tell a retry apart from a configuration problem
+pub const EXIT_OK: i32 = 0;
+/// The collection failed for a reason not covered below
+pub const EXIT_FAILED: i32 = 1;
+/// Invalid arguments or task options
+pub const EXIT_USAGE: i32 = 2;
+/// Network trouble, a timeout or rate limiting; trying again later may succeed
+pub const EXIT_RETRYABLE: i32 = 3;
+/// Not enough disk space or storage quota
+pub const EXIT_NO_SPACE: i32 = 4;
+/// Credentials were rejected or a path is not writable
+pub const EXIT_DENIED: i32 = 5;
+pub const EXIT_CANCELLED: i32 = 130;
+
+pub const USAGE: &str = "Usage: bids-collector collect <dataset> [options]
+
+Collect one dataset without opening a window, then exit.
+
+Options:
+  --provider <id>      Dataset provider (default: openneuro)
+  --dest <dir>         Local directory to collect into (default: the default download
+                       directory from the app settings)
+  --storage <target>   Saved storage location ID, or s3://bucket[/prefix] of a saved
+                       S3-compatible location, to upload into instead
+  --include <glob>     Only collect matching files; may be repeated
+  --subject <label>    Only collect this subject; may be repeated
+  --metadata-only      Only collect sidecars, TSV tables and other metadata files
+  --task-id <id>       Task ID (default: cli-<timestamp>)
+  --json               Print progress as JSON lines instead of text
+
+Exit status: 0 collected, 1 failed, 2 invalid arguments, 3 network error (retry later),
+4 out of disk space or quota, 5 access denied, 130 cancelled.
+";
+
+/// Arguments of `bids-collector collect`
+#[derive(Debug, Clone)]
+pub struct CollectArgs {
+    pub dataset: String,
+    pub provider: String,
+    pub dest: Option<String>,
+    pub storage: Option<String>,
+    pub include: Vec<String>,
+    pub subjects: Vec<String>,
+    pub metadata_only: bool,
+    pub task_id: String,
+    pub json: bool,
+}
+
+impl CollectArgs {
+    /// Parse the arguments following `collect`
+    pub fn parse(args: &[String]) -> Result<CollectArgs, String> {
+        let mut parsed = CollectArgs {
+            dataset: String::new(),
+            provider: "openneuro".to_string(),
+            dest: None,
+            storage: None,
+            include: Vec::new(),
+            subjects: Vec::new(),
+            metadata_only: false,
+            task_id: format!("cli-{}", chrono::Utc::now().format("%Y%m%dT%H%M%S")),
+            json: false,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--provider" => parsed.provider = value()?,
+                "--dest" => parsed.dest = Some(value()?),
+                "--storage" => parsed.storage = Some(value()?),
+                "--include" => parsed.include.push(value()?),
+                "--subject" => parsed.subjects.push(value()?),
+                "--metadata-only" => parsed.metadata_only = true,
+                "--task-id" => parsed.task_id = value()?,
+                "--json" => parsed.json = true,
+                other if other.starts_with("--") => return Err(format!("Unknown option {}", other)),
+                dataset if parsed.dataset.is_empty() => parsed.dataset = dataset.to_string(),
+                extra => return Err(format!("Unexpected argument {}", extra)),
+            }
+        }
+
+        if parsed.dataset.is_empty() {
+            return Err("No dataset given".to_string());
+        }
+        if parsed.dest.is_some() && parsed.storage.is_some() {
+            return Err("Give either --dest or --storage, not both".to_string());
+        }
+        Ok(parsed)
+    }
+
+    /// Storage location named by `--storage`: a saved location ID, or the saved S3-compatible
+    /// location of the bucket in an s3:// URL with its path set to the URL's prefix
+    fn remote_location(&self, app_handle: &tauri::AppHandle, storage: &str) -> Result<Value, String> {
+        let Some(rest) = storage.strip_prefix("s3://") else {
+            return crate::credentials::saved_location(app_handle, storage);
+        };
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        let mut location = crate::credentials::saved_locations(app_handle)?
+            .into_iter()
+            .find(|location| {
+                location.get("type").and_then(|v| v.as_str()) == Some("s3-compatible")
+                    && location.get("bucketName").and_then(|v| v.as_str()) == Some(bucket)
+            })
+            .ok_or_else(|| format!("No saved S3-compatible storage location for bucket {}", bucket))?;
+        location["path"] = json!(prefix.trim_matches('/'));
+        Ok(location)
+    }
+
+    /// Task payload in the shape the frontend sends
+    fn task_data(&self, app_handle: &tauri::AppHandle) -> Result<Value, String> {
+        providers::registry().get(&self.provider)?;
+
+        let storage_location = match (&self.dest, &self.storage) {
+            (Some(dest), _) => {
+                let dest = std::env::current_dir()
+                    .map_err(|e| format!("Failed to resolve the current directory: {}", e))?
+                    .join(dest);
+                let dest = dest.to_string_lossy().to_string();
+                // Naming the destination on the command line registers it, as saving a location does
+                crate::path_guard::add_root(&dest)?;
+                json!({ "id": "cli", "type": "local", "path": dest })
+            }
+            (None, Some(storage)) => self.remote_location(app_handle, storage)?,
+            (None, None) => {
+                let dest = app_handle.state::<SettingsState>().default_download_directory()
+                    .ok_or("Give --dest or --storage, or set a default download directory in the app settings")?;
+                json!({ "id": "cli", "type": "local", "path": dest })
+            }
+        };
+
+        Ok(json!({
+            "task": {
+                "datasetProvider": self.provider,
+                "downloadPath": self.dataset,
+                "includePatterns": self.include,
+                "subjects": self.subjects,
+                "metadataOnly": self.metadata_only,
+            },
+            "storageLocations": [storage_location],
+        }))
+    }
+}
+
+/// Exit status for a task that stopped
+fn exit_code(progress: Option<&DownloadProgress>) -> i32 {
+    let Some(progress) = progress else {
+        return EXIT_FAILED;
+    };
+    match progress.status.as_str() {
+        "completed" => EXIT_OK,
+        "cancelled" => EXIT_CANCELLED,
+        _ => match progress.error.as_ref().map(|error| error.kind) {
+            Some(ErrorKind::InvalidInput | ErrorKind::NotFound) => EXIT_USAGE,
+            Some(ErrorKind::Timeout | ErrorKind::Network | ErrorKind::RateLimited) => EXIT_RETRYABLE,
+            Some(ErrorKind::DiskFull | ErrorKind::QuotaExceeded) => EXIT_NO_SPACE,
+            Some(ErrorKind::Authentication | ErrorKind::PermissionDenied) => EXIT_DENIED,
+            _ => EXIT_FAILED,
+        },
+    }
+}
+
+/// One progress line: the task's progress as JSON, or a short text summary
+fn report_line(progress: &DownloadProgress, json: bool) -> String {
+    if json {
+        return serde_json::to_string(progress).unwrap_or_default();
+    }
+
+    let format = formatting::current();
+    let mut line = format!(
+        "{}: {} {}% ({} of {} files, {} of {})",
+        progress.task_id,
+        progress.status,
+        format.number(progress.progress, 1),
+        format.count(progress.completed_files.unwrap_or(0) as u64),
+        format.count(progress.total_files.unwrap_or(0) as u64),
+        format.bytes(progress.downloaded_size),
+        format.bytes(progress.total_size),
+    );
+    if progress.speed > 0.0 {
+        line.push_str(&format!(" at {}", format.rate(progress.speed)));
+    }
+    if let Some(reason) = &progress.pause_reason {
+        line.push_str(&format!(" - {}", reason));
+    }
+    if let Some(error) = &progress.error_message {
+        line.push_str(&format!(" - {}", error));
+    }
+    line
+}
+
+/// Run the collection and report its progress until it stops; returns the exit status
+async fn collect(app_handle: &tauri::AppHandle, args: CollectArgs) -> i32 {
+    let state = app_handle.state::<DownloadState>().inner().clone();
+    let queue = app_handle.state::<TaskQueueState>().inner().clone();
+
+    let task_data = match args.task_data(app_handle) {
+        Ok(task_data) => task_data,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_USAGE;
+        }
+    };
+    // While the daemon holds the engine its copy of the task runs, and setup mirrors its
+    // progress into the state reported below
+    let queued = match crate::daemon::daemon_client(app_handle) {
+        Some(daemon) => daemon.enqueue(&args.task_id, &task_data).await
+            .map(|()| tokio::spawn(wait_for_daemon(state.clone(), args.task_id.clone())))
+            .map_err(|e| e.to_string()),
+        None => crate::queue_download_task(args.task_id.clone(), task_data, state.clone(), queue, app_handle.clone()),
+    };
+    let mut task = match queued {
+        Ok(task) => task,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_USAGE;
+        }
+    };
+
+    let progress = || state.lock().unwrap().get(&args.task_id).cloned();
+    let mut reported = String::new();
+    loop {
+        tokio::select! {
+            _ = &mut task => break,
+            _ = tokio::time::sleep(REPORT_INTERVAL) => {}
+        }
+        if let Some(line) = progress().map(|p| report_line(&p, args.json)).filter(|line| *line != reported) {
+            println!("{}", line);
+            reported = line;
+        }
+    }
+
+    let progress = progress();
+    if let Some(line) = progress.as_ref().map(|p| report_line(p, args.json)).filter(|line| *line != reported) {
+        println!("{}", line);
+    }
+    exit_code(progress.as_ref())
+}
+
+/// Wait until the daemon's task, as mirrored into `state`, has stopped
+async fn wait_for_daemon(state: DownloadState, task_id: String) {
+    loop {
+        tokio::time::sleep(REPORT_INTERVAL).await;
+        let status = state.lock().unwrap().get(&task_id).map(|progress| progress.status.clone());
+        if matches!(status.as_deref(), Some("completed" | "failed" | "cancelled")) {
+            return;
+        }
+    }
+}
+
+/// Start the collection given on the command line and exit the app with its outcome
+pub fn start(app_handle: &tauri::AppHandle, args: CollectArgs) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let code = collect(&app_handle, args).await;
+        app_handle.exit(code);
+    });
+}
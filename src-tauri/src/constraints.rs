@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use crate::download_window::TimeWindow;
+use crate::environment::Environment;
+use crate::scheduler::{WaitKind, WaitReason};
+
+/// Conditions a task only transfers under, from the task's `constraints` field, e.g.
+/// `{"acPower": true, "unmetered": true, "window": {"start": "22:00", "end": "06:00"}}`.
+/// They apply on top of the global download window.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskConstraints {
+    #[serde(default)]
+    pub ac_power: bool,
+    #[serde(default)]
+    pub unmetered: bool,
+    #[serde(default)]
+    pub window: Option<TimeWindow>,
+}
+
+impl TaskConstraints {
+    pub fn from_task(task: &serde_json::Value) -> Result<TaskConstraints, String> {
+        let Some(value) = task.get("constraints").filter(|v| !v.is_null()) else {
+            return Ok(TaskConstraints::default());
+        };
+        let constraints: TaskConstraints = serde_json::from_value(value.clone())
+            .map_err(|e| format!("Invalid task constraints: {}", e))?;
+        if let Some(window) = &constraints.window {
+            window.bounds()?;
+        }
+        Ok(constraints)
+    }
+
+    /// Why the task cannot transfer right now, or None when every constraint is met.
+    /// Conditions the OS cannot report do not hold a task back.
+    pub fn unmet(&self, environment: &Environment) -> Option<WaitReason> {
+        if self.ac_power && environment.on_ac_power == Some(false) {
+            return Some(WaitReason::new(WaitKind::AcPower, "Waiting for AC power"));
+        }
+        if self.unmetered && environment.metered_network == Some(true) {
+            return Some(WaitReason::new(WaitKind::UnmeteredNetwork, "Waiting for an unmetered network"));
+        }
+        match &self.window {
+            Some(window) if !window.is_open() => Some(WaitReason::new(
+                WaitKind::TaskWindow,
+                format!("Waiting for {}-{}", window.start, window.end),
+            )),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,57 @@
+/// Used for anything not in `CONTENT_TYPES`
+const DEFAULT: &str = "application/octet-stream";
+
+/// Content types by file suffix, longest suffixes first so `.nii.gz` wins over `.gz`
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    // Imaging data: NIfTI has no registered type, the compressed form is served as gzip
+    (".nii.gz", "application/gzip"),
+    (".tsv.gz", "application/gzip"),
+    (".nii", "application/x-nifti"),
+    (".gz", "application/gzip"),
+    // Sidecars, tables and gradient files
+    (".json", "application/json"),
+    (".jsonld", "application/ld+json"),
+    (".tsv", "text/tab-separated-values; charset=utf-8"),
+    (".csv", "text/csv; charset=utf-8"),
+    (".bval", "text/plain; charset=utf-8"),
+    (".bvec", "text/plain; charset=utf-8"),
+    (".txt", "text/plain; charset=utf-8"),
+    (".md", "text/markdown; charset=utf-8"),
+    (".rst", "text/x-rst; charset=utf-8"),
+    (".html", "text/html; charset=utf-8"),
+    (".xml", "application/xml"),
+    (".yaml", "application/yaml"),
+    (".yml", "application/yaml"),
+    // Containers used by MEG, EEG and microscopy datasets
+    (".nwb", "application/x-hdf5"),
+    (".h5", "application/x-hdf5"),
+    (".zip", "application/zip"),
+    (".tar", "application/x-tar"),
+    (".pdf", "application/pdf"),
+    // Stimuli, figures and photos
+    (".png", "image/png"),
+    (".jpg", "image/jpeg"),
+    (".jpeg", "image/jpeg"),
+    (".tif", "image/tiff"),
+    (".tiff", "image/tiff"),
+    (".svg", "image/svg+xml"),
+    (".mp4", "video/mp4"),
+    (".wav", "audio/wav"),
+];
+
+/// Top-level BIDS files without an extension, all plain text
+const TEXT_FILENAMES: &[&str] = &["README", "CHANGES", "LICENSE"];
+
+/// Content type of a collected file by its name, as sent with uploads so sidecars and
+/// tables open in browsers and downstream tools
+pub fn of(path: &str) -> &'static str {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    if TEXT_FILENAMES.iter().any(|name| file_name.eq_ignore_ascii_case(name)) {
+        return "text/plain; charset=utf-8";
+    }
+    let lower = file_name.to_lowercase();
+    CONTENT_TYPES.iter()
+        .find(|(suffix, _)| lower.ends_with(suffix))
+        .map(|(_, content_type)| *content_type)
+        .unwrap_or(DEFAULT)
+}
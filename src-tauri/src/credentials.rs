@@ -0,0 +1,413 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::Manager;
+
+use crate::error::CollectorError;
+
+/// Keychain service under which storage credentials are stored
+const KEYCHAIN_SERVICE: &str = "bids-collector-desktop";
+
+/// Keychain service for the app's own secrets (API token, proxy and SMTP passwords), so no
+/// storage location id can name one of them
+const APP_SECRETS_SERVICE: &str = "bids-collector-desktop.app";
+
+/// Entry that is looked up, never stored, to find out whether the keychain can be read
+const KEYCHAIN_PROBE: &str = "keychain-probe";
+
+/// File in the app data directory holding storage locations without their secrets
+pub const LOCATIONS_FILE: &str = "storage_locations.json";
+
+/// Storage location fields that are kept in the keychain, never on disk or in logs
+pub const SECRET_FIELDS: &[&str] = &[
+    "accessKeyId",
+    "secretAccessKey",
+    "sessionToken",
+    "password",
+    "passphrase",
+    "accountKey",
+    "sasToken",
+    "serviceAccountJson",
+];
+
+/// Fields that decide which host a location's requests go to and as whom. A payload naming a
+/// saved location's `id` only gets that location's keychain secrets when these match.
+const CONNECTION_FIELDS: &[&str] = &[
+    "type",
+    "endpoint",
+    "region",
+    "bucketName",
+    "host",
+    "port",
+    "username",
+    "privateKeyPath",
+    "accountName",
+    "containerName",
+    "serviceAccountPath",
+    "awsProfile",
+    "addressingStyle",
+];
+
+/// `LOCATIONS_FILE` in the app data directory, set by `init`
+static LOCATIONS_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Secrets of one storage location, stored as a single keychain entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredCredentials {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_key_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_access_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
+    /// SFTP password and private key passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase: Option<String>,
+    /// Azure storage account key or SAS token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sas_token: Option<String>,
+    /// Google Cloud service account key file contents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_account_json: Option<String>,
+}
+
+impl StoredCredentials {
+    fn from_location(location: &Value) -> StoredCredentials {
+        let field = |name: &str| location.get(name).and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(|s| s.to_string());
+        StoredCredentials {
+            access_key_id: field("accessKeyId"),
+            secret_access_key: field("secretAccessKey"),
+            session_token: field("sessionToken"),
+            password: field("password"),
+            passphrase: field("passphrase"),
+            account_key: field("accountKey"),
+            sas_token: field("sasToken"),
+            service_account_json: field("serviceAccountJson"),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.access_key_id.is_none()
+            && self.secret_access_key.is_none()
+            && self.session_token.is_none()
+            && self.password.is_none()
+            && self.passphrase.is_none()
+            && self.account_key.is_none()
+            && self.sas_token.is_none()
+            && self.service_account_json.is_none()
+    }
+}
+
+fn entry(location_id: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, location_id)
+        .map_err(|e| format!("Failed to open keychain entry for {}: {}", location_id, e))
+}
+
+/// Credentials stored for a storage location, if any
+pub fn load(location_id: &str) -> Result<Option<StoredCredentials>, String> {
+    match entry(location_id)?.get_password() {
+        Ok(secret) => serde_json::from_str(&secret)
+            .map(Some)
+            .map_err(|e| format!("Corrupt keychain entry for {}: {}", location_id, e)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read credentials for {} from the keychain: {}", location_id, e)),
+    }
+}
+
+pub fn store(location_id: &str, credentials: &StoredCredentials) -> Result<(), String> {
+    let secret = serde_json::to_string(credentials)
+        .map_err(|e| format!("Failed to serialize credentials: {}", e))?;
+    entry(location_id)?.set_password(&secret)
+        .map_err(|e| format!("Failed to store credentials for {} in the keychain: {}", location_id, e))
+}
+
+pub fn forget(location_id: &str) -> Result<(), String> {
+    match entry(location_id)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to remove credentials for {} from the keychain: {}", location_id, e)),
+    }
+}
+
+fn app_secret_entry(name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(APP_SECRETS_SERVICE, name)
+        .map_err(|e| format!("Failed to open keychain entry for {}: {}", name, e))
+}
+
+/// One of the app's own secrets. Earlier versions kept them among the storage credentials;
+/// such an entry is moved over the first time it is read, unless a saved storage location
+/// uses the same id.
+pub fn load_app_secret(name: &str) -> Result<Option<String>, String> {
+    match app_secret_entry(name)?.get_password() {
+        Ok(secret) => return Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(format!("Failed to read {} from the keychain: {}", name, e)),
+    }
+
+    if is_saved_location(name)? {
+        return Ok(None);
+    }
+    let Some(secret) = load(name)?.and_then(|c| c.password) else {
+        return Ok(None);
+    };
+    store_app_secret(name, &secret)?;
+    forget(name)?;
+    log::info!("Moved {} out of the storage credentials in the keychain", name);
+    Ok(Some(secret))
+}
+
+pub fn store_app_secret(name: &str, secret: &str) -> Result<(), String> {
+    app_secret_entry(name)?.set_password(secret)
+        .map_err(|e| format!("Failed to store {} in the keychain: {}", name, e))
+}
+
+pub fn forget_app_secret(name: &str) -> Result<(), String> {
+    match app_secret_entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(format!("Failed to remove {} from the keychain: {}", name, e)),
+    }
+    if is_saved_location(name)? {
+        return Ok(());
+    }
+    forget(name)
+}
+
+/// Fail unless this process can read the keychain. Without a login session the macOS login
+/// keychain is locked and Linux has no Secret Service to ask.
+pub fn check_keychain() -> Result<(), String> {
+    match app_secret_entry(KEYCHAIN_PROBE)?.get_password() {
+        Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("The keychain cannot be read: {}", e)),
+    }
+}
+
+fn is_saved_location(location_id: &str) -> Result<bool, String> {
+    let path = LOCATIONS_PATH.get().ok_or("Credentials are not initialized")?;
+    Ok(read_locations(path)?.iter().any(|l| l.get("id").and_then(|v| v.as_str()) == Some(location_id)))
+}
+
+/// Copy of a JSON value with every secret field masked, for logging
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let v = if SECRET_FIELDS.contains(&k.as_str()) && !v.is_null() {
+                        Value::String("***".to_string())
+                    } else {
+                        redact(v)
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Copy of a JSON value with every secret field removed, for persisting task payloads
+pub fn strip_secrets(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(k, _)| !SECRET_FIELDS.contains(&k.as_str()))
+                .map(|(k, v)| (k.clone(), strip_secrets(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(strip_secrets).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Look up a secret field of a storage location: the payload wins, then the keychain
+/// entry of the location's `id`. Keychain secrets are only handed out for a payload that
+/// connects exactly like the saved location, so a payload cannot borrow them for another host.
+pub fn secret_field(location: &Value, name: &str) -> Result<Option<String>, String> {
+    if let Some(value) = location.get(name).and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+        return Ok(Some(value.to_string()));
+    }
+
+    let Some(location_id) = location.get("id").and_then(|v| v.as_str()) else {
+        return Ok(None);
+    };
+    let credentials = match load(location_id) {
+        Ok(Some(credentials)) => credentials,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            log::warn!("{}", e);
+            return Ok(None);
+        }
+    };
+    check_saved_connection(location, location_id)?;
+
+    Ok(match name {
+        "accessKeyId" => credentials.access_key_id,
+        "secretAccessKey" => credentials.secret_access_key,
+        "sessionToken" => credentials.session_token,
+        "password" => credentials.password,
+        "passphrase" => credentials.passphrase,
+        "accountKey" => credentials.account_key,
+        "sasToken" => credentials.sas_token,
+        "serviceAccountJson" => credentials.service_account_json,
+        _ => None,
+    })
+}
+
+/// Fail unless `location` is the saved location `location_id` in every `CONNECTION_FIELDS`
+/// entry; absent, null and empty values count as the same
+fn check_saved_connection(location: &Value, location_id: &str) -> Result<(), String> {
+    let path = LOCATIONS_PATH.get().ok_or("Credentials are not initialized")?;
+    let saved = read_locations(path)?
+        .into_iter()
+        .find(|l| l.get("id").and_then(|v| v.as_str()) == Some(location_id))
+        .ok_or_else(|| format!("Keychain credentials of {} are only used for its saved storage location", location_id))?;
+
+    let value = |location: &Value, field: &str| match location.get(field) {
+        None | Some(Value::Null) => None,
+        Some(Value::String(s)) if s.is_empty() => None,
+        Some(value) => Some(value.clone()),
+    };
+    let changed: Vec<&str> = CONNECTION_FIELDS.iter()
+        .filter(|field| value(location, field) != value(&saved, field))
+        .copied()
+        .collect();
+    if !changed.is_empty() {
+        log::warn!("Withheld keychain credentials of {} from a payload with different {}", location_id, changed.join(", "));
+        return Err(format!(
+            "Storage location {} differs from the saved one ({}); save it before using its stored credentials",
+            location_id,
+            changed.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Remember where saved locations are kept, so keychain lookups can check payloads against them
+pub fn init(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let _ = LOCATIONS_PATH.set(locations_path(app_handle)?);
+    Ok(())
+}
+
+fn locations_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app_handle.path().app_data_dir()
+        .map(|dir| dir.join(LOCATIONS_FILE))
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))
+}
+
+fn read_locations(path: &Path) -> Result<Vec<Value>, String> {
+    match std::fs::read(path) {
+        Ok(content) => serde_json::from_slice(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+fn write_locations(path: &Path, locations: &[Value]) -> Result<(), String> {
+    crate::path_guard::check(path)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_vec_pretty(locations)
+        .map_err(|e| format!("Failed to serialize storage locations: {}", e))?;
+    std::fs::write(path, content)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// All saved storage locations, without their secrets
+pub fn saved_locations(app_handle: &tauri::AppHandle) -> Result<Vec<Value>, String> {
+    read_locations(&locations_path(app_handle)?)
+}
+
+/// A saved storage location by ID, without its secrets; `from_storage_location` of the
+/// backend fetches them from the keychain
+pub fn saved_location(app_handle: &tauri::AppHandle, location_id: &str) -> Result<Value, String> {
+    read_locations(&locations_path(app_handle)?)?
+        .into_iter()
+        .find(|l| l.get("id").and_then(|v| v.as_str()) == Some(location_id))
+        .ok_or_else(|| format!("No saved storage location with id {}", location_id))
+}
+
+/// Location without its secrets, flagged with whether the keychain holds credentials for it
+fn without_secrets(location: &Value, has_credentials: bool) -> Value {
+    let mut location = location.clone();
+    if let Some(map) = location.as_object_mut() {
+        for field in SECRET_FIELDS {
+            map.remove(*field);
+        }
+        map.insert("hasCredentials".to_string(), Value::Bool(has_credentials));
+    }
+    location
+}
+
+/// Save a storage location. Its secrets go to the OS keychain under the location ID;
+/// everything else is stored in the app data directory. Returns the location without secrets.
+#[tauri::command]
+pub async fn save_storage_credentials(storage_location: Value, app_handle: tauri::AppHandle) -> Result<Value, CollectorError> {
+    let location_id = storage_location.get("id")
+        .and_then(|v| v.as_str())
+        .ok_or("Storage location has no id")?
+        .to_string();
+
+    // A saved local location is a destination the user chose, so the backend may write to it
+    if storage_location.get("type").and_then(|v| v.as_str()) == Some("local") {
+        if let Some(path) = storage_location.get("path").and_then(|v| v.as_str()) {
+            crate::path_guard::add_root(path)?;
+        }
+    }
+
+    let credentials = StoredCredentials::from_location(&storage_location);
+    if !credentials.is_empty() {
+        store(&location_id, &credentials)?;
+    }
+    let has_credentials = !credentials.is_empty() || load(&location_id)?.is_some();
+
+    let path = locations_path(&app_handle)?;
+    let mut locations = read_locations(&path)?;
+    let saved = without_secrets(&storage_location, has_credentials);
+    match locations.iter_mut().find(|l| l.get("id").and_then(|v| v.as_str()) == Some(location_id.as_str())) {
+        Some(existing) => *existing = saved.clone(),
+        None => locations.push(saved.clone()),
+    }
+    write_locations(&path, &locations)?;
+
+    log::info!("Saved storage location {} (credentials in keychain: {})", location_id, has_credentials);
+    Ok(saved)
+}
+
+/// Storage locations saved with `save_storage_credentials`, without their secrets
+#[tauri::command]
+pub async fn get_storage_locations(app_handle: tauri::AppHandle) -> Result<Vec<Value>, CollectorError> {
+    let locations = read_locations(&locations_path(&app_handle)?)?;
+    Ok(locations
+        .iter()
+        .map(|location| {
+            let has_credentials = location.get("id")
+                .and_then(|v| v.as_str())
+                .map(|id| matches!(load(id), Ok(Some(_))))
+                .unwrap_or(false);
+            without_secrets(location, has_credentials)
+        })
+        .collect())
+}
+
+/// Remove a saved storage location and its keychain entry
+#[tauri::command]
+pub async fn delete_storage_location(location_id: String, app_handle: tauri::AppHandle) -> Result<(), CollectorError> {
+    forget(&location_id)?;
+
+    let path = locations_path(&app_handle)?;
+    let mut locations = read_locations(&path)?;
+    locations.retain(|l| l.get("id").and_then(|v| v.as_str()) != Some(location_id.as_str()));
+    write_locations(&path, &locations)?;
+
+    log::info!("Removed storage location {}", location_id);
+    Ok(())
+}
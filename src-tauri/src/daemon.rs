@@ -0,0 +1,497 @@
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::{Pid, System};
+use tauri::{Emitter, Manager, RunEvent};
+
+use crate::error::{CollectorError, ErrorKind};
+use crate::{DownloadProgress, DownloadState};
+
+/// File in the app data directory describing the running daemon
+const STATUS_FILE: &str = "daemon.json";
+
+/// File in the app data directory naming the process that runs tasks
+const ENGINE_LOCK_FILE: &str = "engine.lock";
+
+/// How often a process controlling the daemon refreshes the daemon's tasks
+const MIRROR_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Wait before reconnecting to the daemon's event stream
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Name of the installed service
+const SERVICE_NAME: &str = "bids-collector";
+
+/// How the process was started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Desktop,
+    /// `--daemon`: no webview; the queue, download window and catalog backups keep running
+    /// until the service is stopped
+    Daemon,
+    /// `--print-service`: print a per-user service definition for this OS and exit
+    PrintService,
+    /// `collect <dataset> ...`: collect one dataset without windows and exit with its outcome
+    Collect,
+}
+
+impl Mode {
+    pub fn from_args() -> Mode {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.first().is_some_and(|arg| arg == "collect") {
+            Mode::Collect
+        } else if args.iter().any(|arg| arg == "--print-service") {
+            Mode::PrintService
+        } else if args.iter().any(|arg| arg == "--daemon") {
+            Mode::Daemon
+        } else {
+            Mode::Desktop
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Desktop => "desktop",
+            Mode::Daemon => "daemon",
+            Mode::PrintService => "print-service",
+            Mode::Collect => "collect",
+        }
+    }
+}
+
+/// Holder of the engine lock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EngineRecord {
+    pid: u32,
+    mode: String,
+    started_at: String,
+}
+
+/// Which process resumes and runs tasks. Only the holder of the engine lock does, so the
+/// desktop app, the daemon and command-line collections never write the same files; a desktop
+/// app or collection started while the daemon runs hands its tasks to the daemon instead.
+pub enum Engine {
+    /// This process holds the lock
+    Local,
+    /// The daemon holds it and takes tasks through its automation API
+    Daemon(DaemonClient),
+}
+
+pub type EngineState = Arc<Engine>;
+
+/// The daemon to hand tasks to, when this process does not run them itself
+pub fn daemon_client(app_handle: &tauri::AppHandle) -> Option<DaemonClient> {
+    match app_handle.state::<EngineState>().as_ref() {
+        Engine::Local => None,
+        Engine::Daemon(client) => Some(client.clone()),
+    }
+}
+
+/// Fail for work the daemon cannot be handed, while it runs the tasks
+pub fn ensure_local_engine(app_handle: &tauri::AppHandle) -> Result<(), CollectorError> {
+    match app_handle.state::<EngineState>().as_ref() {
+        Engine::Local => Ok(()),
+        Engine::Daemon(_) => Err(CollectorError::new(
+            ErrorKind::Conflict,
+            "The background service runs the tasks; this can only be started while it is stopped",
+        )),
+    }
+}
+
+fn app_data_file(app_handle: &tauri::AppHandle, name: &str) -> Result<PathBuf, String> {
+    app_handle.path().app_data_dir()
+        .map(|dir| dir.join(name))
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))
+}
+
+fn is_running(pid: u32) -> bool {
+    pid != std::process::id() && System::new().refresh_process(Pid::from_u32(pid))
+}
+
+/// Take the engine lock, or find out who holds it. A desktop app or collection finds the daemon
+/// to control; any other holder that is still running is an error. Locks of processes that are
+/// gone are taken over.
+pub fn lock_engine(app_handle: &tauri::AppHandle, mode: Mode) -> Result<Engine, String> {
+    let path = app_data_file(app_handle, ENGINE_LOCK_FILE)?;
+    crate::path_guard::check(&path)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+    let record = EngineRecord {
+        pid: std::process::id(),
+        mode: mode.name().to_string(),
+        started_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let content = serde_json::to_vec_pretty(&record)
+        .map_err(|e| format!("Failed to serialize engine lock: {}", e))?;
+
+    // The record is written in full first and then linked into place, which fails if the lock
+    // exists; nobody can read a lock that is taken but still empty
+    let pending = path.with_extension(format!("lock.{}", record.pid));
+    std::fs::write(&pending, &content)
+        .map_err(|e| format!("Failed to write {}: {}", pending.display(), e))?;
+    let locked = take_lock(app_handle, mode, &path, &pending);
+    let _ = std::fs::remove_file(&pending);
+    let engine = locked?;
+    if matches!(engine, Engine::Local) {
+        log::info!("Running tasks in this process (pid {})", record.pid);
+    }
+    Ok(engine)
+}
+
+/// Link the written record `pending` in as the lock at `path`, see `lock_engine`
+fn take_lock(app_handle: &tauri::AppHandle, mode: Mode, path: &Path, pending: &Path) -> Result<Engine, String> {
+    // A stale lock is removed once, then taken like a free one
+    for _ in 0..2 {
+        match std::fs::hard_link(pending, path) {
+            Ok(()) => return Ok(Engine::Local),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                let content = std::fs::read(path).unwrap_or_default();
+                let holder = serde_json::from_slice::<EngineRecord>(&content).ok()
+                    .filter(|holder| is_running(holder.pid));
+                match holder {
+                    Some(holder) if holder.mode == Mode::Daemon.name() && matches!(mode, Mode::Desktop | Mode::Collect) => {
+                        log::info!("The daemon (pid {}) runs the tasks; handing them to it", holder.pid);
+                        return Ok(Engine::Daemon(DaemonClient::connect(app_handle)?));
+                    }
+                    Some(holder) => {
+                        return Err(format!(
+                            "BIDS Collector already runs tasks in another process ({}, pid {}); stop it first",
+                            holder.mode, holder.pid
+                        ));
+                    }
+                    // Only the lock that was found stale, not one another process just took
+                    None if std::fs::read(path).unwrap_or_default() == content => {
+                        log::info!("Taking over the engine lock of a process that is gone");
+                        let _ = std::fs::remove_file(path);
+                    }
+                    None => {}
+                }
+            }
+            Err(e) => return Err(format!("Failed to create {}: {}", path.display(), e)),
+        }
+    }
+    Err(format!("Failed to take the engine lock {}", path.display()))
+}
+
+fn release_engine(app_handle: &tauri::AppHandle) {
+    let Ok(path) = app_data_file(app_handle, ENGINE_LOCK_FILE) else {
+        return;
+    };
+    let held = std::fs::read(&path).ok()
+        .and_then(|content| serde_json::from_slice::<EngineRecord>(&content).ok())
+        .is_some_and(|holder| holder.pid == std::process::id());
+    if held {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Client of the daemon's automation API, used by a desktop app or collection that hands its
+/// tasks to the daemon
+#[derive(Clone)]
+pub struct DaemonClient {
+    base_url: String,
+    token: String,
+    client: reqwest::Client,
+}
+
+impl DaemonClient {
+    /// Port and token of the daemon's API, from the automation settings both processes share
+    fn connect(app_handle: &tauri::AppHandle) -> Result<DaemonClient, String> {
+        let settings = crate::automation::saved_settings(app_handle)?;
+        let token = settings.token.ok_or("The daemon's API token is not in the keychain")?;
+        // Never through a configured proxy: the daemon only listens on this machine
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(DaemonClient {
+            base_url: format!("http://127.0.0.1:{}/v1", settings.port),
+            token,
+            client,
+        })
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, CollectorError> {
+        let response = request.bearer_auth(&self.token).send().await
+            .map_err(|e| CollectorError::new(ErrorKind::Network, format!("The daemon is not reachable: {}", e)))?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let status = response.status();
+        Err(response.json::<CollectorError>().await
+            .unwrap_or_else(|_| CollectorError::new(ErrorKind::Other, format!("The daemon answered HTTP {}", status))))
+    }
+
+    pub async fn tasks(&self) -> Result<Vec<DownloadProgress>, CollectorError> {
+        let response = self.send(self.client.get(format!("{}/tasks", self.base_url))).await?;
+        response.json().await
+            .map_err(|e| CollectorError::new(ErrorKind::Other, format!("Invalid task list from the daemon: {}", e)))
+    }
+
+    /// Queue a download task in the daemon, like `start_download_task`
+    pub async fn enqueue(&self, task_id: &str, task_data: &Value) -> Result<(), CollectorError> {
+        let body = serde_json::json!({ "taskId": task_id, "taskData": task_data });
+        self.send(self.client.post(format!("{}/tasks", self.base_url)).json(&body)).await?;
+        Ok(())
+    }
+
+    pub async fn cancel(&self, task_id: &str) -> Result<(), CollectorError> {
+        let url = format!("{}/tasks/{}/cancel", self.base_url, utf8_percent_encode(task_id));
+        self.send(self.client.post(url)).await?;
+        Ok(())
+    }
+
+    /// Emit the daemon's events in this process until its event stream ends
+    async fn relay_events(&self, app_handle: &tauri::AppHandle) -> Result<(), CollectorError> {
+        let response = self.send(self.client.get(format!("{}/events", self.base_url))).await?;
+        let mut stream = response.bytes_stream();
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut event: Option<String> = None;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| CollectorError::new(ErrorKind::Network, e.to_string()))?;
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_end();
+                if let Some(name) = line.strip_prefix("event: ") {
+                    event = Some(name.to_string());
+                } else if let (Some(data), Some(name)) = (line.strip_prefix("data: "), event.take()) {
+                    let payload: Value = serde_json::from_str(data).unwrap_or(Value::Null);
+                    let _ = app_handle.emit(&name, payload);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Path segment encoding for task IDs in API URLs
+fn utf8_percent_encode(segment: &str) -> String {
+    percent_encoding::utf8_percent_encode(segment, percent_encoding::NON_ALPHANUMERIC).to_string()
+}
+
+/// Show the daemon's tasks in this process: its progress records replace the local ones, so the
+/// progress commands, tray and taskbar report them, and its events reach the webview as if the
+/// tasks ran here
+pub fn spawn_control(app_handle: tauri::AppHandle, client: DaemonClient) {
+    let mirror = (app_handle.clone(), client.clone());
+    tauri::async_runtime::spawn(async move {
+        let (app_handle, client) = mirror;
+        loop {
+            match client.tasks().await {
+                Ok(tasks) => {
+                    let state = app_handle.state::<DownloadState>();
+                    let mut downloads = state.lock().unwrap();
+                    downloads.clear();
+                    downloads.extend(tasks.into_iter().map(|task| (task.task_id.clone(), task)));
+                }
+                Err(e) => log::debug!("Failed to refresh the daemon's tasks: {}", e),
+            }
+            tokio::time::sleep(MIRROR_INTERVAL).await;
+        }
+    });
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = client.relay_events(&app_handle).await {
+                log::debug!("Daemon event stream closed: {}", e);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DaemonRecord {
+    pid: u32,
+    started_at: String,
+    version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonStatus {
+    pub running: bool,
+    pub pid: Option<u32>,
+    pub started_at: Option<String>,
+    pub version: Option<String>,
+}
+
+fn status_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app_data_file(app_handle, STATUS_FILE)
+}
+
+fn read_record(app_handle: &tauri::AppHandle) -> Option<DaemonRecord> {
+    let content = std::fs::read(status_path(app_handle).ok()?).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+/// Announce this process as the daemon, so desktop instances can find it
+pub fn start(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    if let DaemonStatus { running: true, pid: Some(pid), .. } = status(app_handle) {
+        return Err(format!("A daemon is already running (pid {})", pid));
+    }
+    // Storage credentials and the API token live in the keychain, so running without it would
+    // only fail task by task
+    crate::credentials::check_keychain().map_err(|e| format!(
+        "{}. The daemon uses the keychain of the user it runs as, which is only unlocked while \
+         that user is logged in; start it from a login session",
+        e
+    ))?;
+
+    let path = status_path(app_handle)?;
+    crate::path_guard::check(&path)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+    let record = DaemonRecord {
+        pid: std::process::id(),
+        started_at: chrono::Utc::now().to_rfc3339(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    };
+    let content = serde_json::to_vec_pretty(&record)
+        .map_err(|e| format!("Failed to serialize daemon status: {}", e))?;
+    std::fs::write(&path, content)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    log::info!("Running as daemon (pid {})", record.pid);
+    Ok(())
+}
+
+fn stop(app_handle: &tauri::AppHandle) {
+    if read_record(app_handle).is_some_and(|record| record.pid == std::process::id()) {
+        if let Ok(path) = status_path(app_handle) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    log::info!("Daemon stopped");
+}
+
+/// Whether a daemon is running, from its status file and the process table
+pub fn status(app_handle: &tauri::AppHandle) -> DaemonStatus {
+    let Some(record) = read_record(app_handle) else {
+        return DaemonStatus { running: false, pid: None, started_at: None, version: None };
+    };
+
+    // A daemon that crashed leaves its status file behind
+    let running = is_running(record.pid);
+
+    DaemonStatus {
+        running,
+        pid: Some(record.pid),
+        started_at: Some(record.started_at),
+        version: Some(record.version),
+    }
+}
+
+/// Keep the daemon and command-line collections alive without windows, and release the
+/// engine lock and the daemon's status file on exit
+pub fn handle_run_event(mode: Mode, app_handle: &tauri::AppHandle, event: RunEvent) {
+    match event {
+        // No code means the last window closed rather than an explicit exit
+        RunEvent::ExitRequested { code: None, api, .. } if mode != Mode::Desktop => api.prevent_exit(),
+        RunEvent::Exit => {
+            release_engine(app_handle);
+            if mode == Mode::Daemon {
+                stop(app_handle);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Service definition that runs this executable in daemon mode for the current user, whose
+/// keychain entries and app data directory the daemon needs: a systemd user unit on Linux, a
+/// launchd daemon with `UserName` on macOS, and a scheduled task started at boot under the
+/// user's account on Windows.
+///
+/// The macOS login keychain and the Linux Secret Service are only unlocked while the user is
+/// logged in, so there the daemon refuses to start before the user logs in (see `start`) and
+/// the service manager retries it. Windows stores the account password with the task, which
+/// lets the Credential Manager be read at boot.
+pub fn service_definition() -> Result<String, String> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to resolve the executable path: {}", e))?;
+    let exe = exe.display();
+    let user = std::env::var("USER").or_else(|_| std::env::var("USERNAME"))
+        .map_err(|_| "Failed to determine the current user".to_string())?;
+
+    if cfg!(target_os = "windows") {
+        let account = match std::env::var("USERDOMAIN") {
+            Ok(domain) => format!("{}\\{}", domain, user),
+            Err(_) => user,
+        };
+        // /RP * asks for the password, so the task also runs while nobody is logged in
+        Ok(format!(
+            "schtasks /Create /TN \"{name}\" /SC ONSTART /RU \"{account}\" /RP * /RL LIMITED /TR \"\\\"{exe}\\\" --daemon\"\n",
+            name = SERVICE_NAME,
+            account = account,
+            exe = exe
+        ))
+    } else if cfg!(target_os = "macos") {
+        Ok(format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<!-- Save as /Library/LaunchDaemons/org.{name}.daemon.plist and load it with
+     sudo launchctl bootstrap system /Library/LaunchDaemons/org.{name}.daemon.plist
+     The daemon reads credentials from the login keychain of {user}, so it only starts once
+     {user} has logged in; until then it exits and launchd tries again -->
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>org.{name}.daemon</string>
+    <key>UserName</key>
+    <string>{user}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{exe}</string>
+        <string>--daemon</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <true/>
+</dict>
+</plist>
+"#,
+            name = SERVICE_NAME,
+            user = user,
+            exe = exe
+        ))
+    } else {
+        Ok(format!(
+            "# Save as ~/.config/systemd/user/{name}.service, then run
+#   systemctl --user enable --now {name}
+#   loginctl enable-linger {user}    # keep it running while {user} is logged out
+# The daemon reads credentials from the keyring, so it only starts once {user} has logged in
+# and the keyring is unlocked; until then it exits and systemd tries again
+[Unit]
+Description=BIDS Collector daemon
+
+[Service]
+ExecStart=\"{exe}\" --daemon
+Restart=on-failure
+RestartSec=30
+
+[Install]
+WantedBy=default.target
+",
+            name = SERVICE_NAME,
+            user = user,
+            exe = exe
+        ))
+    }
+}
+
+#[tauri::command]
+pub async fn get_daemon_status(app_handle: tauri::AppHandle) -> Result<DaemonStatus, CollectorError> {
+    Ok(status(&app_handle))
+}
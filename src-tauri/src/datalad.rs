@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tokio::fs;
+
+use crate::manifest::{Manifest, ManifestEntry};
+use crate::path_guard;
+
+/// Records for `datalad addurls`, relative to the dataset root
+pub const ADDURLS_PATH: &str = ".bids-collector/datalad/addurls.json";
+
+/// Written to new repositories: SHA256E keys, and BIDS metadata kept in git rather than the
+/// annex, like DataLad's text2git configuration
+const GITATTRIBUTES: &str = "\
+* annex.backend=SHA256E
+**/.git* annex.largefiles=nothing
+*.json annex.largefiles=nothing
+*.tsv annex.largefiles=nothing
+*.bval annex.largefiles=nothing
+*.bvec annex.largefiles=nothing
+README* annex.largefiles=nothing
+CHANGES annex.largefiles=nothing
+LICENSE annex.largefiles=nothing
+.bids-collector/** annex.largefiles=nothing
+";
+
+/// git-annex keeps at most this many extensions of this length in SHA256E keys
+const MAX_EXTENSIONS: usize = 2;
+const MAX_EXTENSION_LENGTH: usize = 4;
+
+/// A collected file and where its content can be fetched again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddurlsRecord {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    pub url: String,
+    /// git-annex SHA256E key of the content
+    pub key: String,
+}
+
+/// Makes a collected dataset usable from DataLad (`datalad`): a manifest that
+/// `datalad addurls --key '{key}' .bids-collector/datalad/addurls.json '{url}' '{path}'`
+/// takes as is, and for local datasets optionally a git repository whose annex already
+/// knows each file's source URL, so `datalad save` and `datalad get` work without
+/// crawling the provider again.
+#[derive(Debug, Clone, Copy)]
+pub struct DataladExport {
+    init_repository: bool,
+}
+
+impl DataladExport {
+    /// Task field `datalad`: `true` for the manifest, or `{ "initRepository": true }` to also
+    /// turn a local dataset into a git-annex repository
+    pub fn from_task(task: &Value) -> Result<Option<DataladExport>, String> {
+        match task.get("datalad") {
+            None | Some(Value::Null) | Some(Value::Bool(false)) => Ok(None),
+            Some(Value::Bool(true)) => Ok(Some(DataladExport { init_repository: false })),
+            Some(Value::Object(options)) => match options.get("initRepository") {
+                None | Some(Value::Null) => Ok(Some(DataladExport { init_repository: false })),
+                Some(Value::Bool(init_repository)) => Ok(Some(DataladExport { init_repository: *init_repository })),
+                Some(_) => Err("Invalid datalad.initRepository: expected a boolean".to_string()),
+            },
+            Some(_) => Err("Invalid datalad: expected true or an object".to_string()),
+        }
+    }
+
+    pub fn init_repository(&self) -> bool {
+        self.init_repository
+    }
+
+    /// Records for the files stored exactly as their source URL serves them. `rewritten`
+    /// files (anonymized, or a description with provenance added) are left out, as are files
+    /// extracted from an archive, which share the archive's URL.
+    pub fn records(manifest: &Manifest, rewritten: &HashSet<&str>) -> Vec<AddurlsRecord> {
+        let mut url_uses: HashMap<&str, usize> = HashMap::new();
+        for url in manifest.files.iter().filter_map(|entry| entry.source_url.as_deref()) {
+            *url_uses.entry(url).or_default() += 1;
+        }
+
+        manifest.files.iter()
+            .filter(|entry| !entry.path.starts_with(".bids-collector/") && !rewritten.contains(entry.path.as_str()))
+            .filter_map(|entry| {
+                let url = entry.source_url.as_deref().filter(|url| url_uses.get(url) == Some(&1))?;
+                Some(AddurlsRecord {
+                    path: entry.path.clone(),
+                    size: entry.size,
+                    sha256: entry.sha256.clone(),
+                    url: url.to_string(),
+                    key: annex_key(entry),
+                })
+            })
+            .collect()
+    }
+
+    pub fn to_json(records: &[AddurlsRecord]) -> Result<Vec<u8>, String> {
+        serde_json::to_vec_pretty(records).map_err(|e| format!("Failed to serialize DataLad manifest: {}", e))
+    }
+
+    /// Write the manifest into a locally collected dataset and set up the repository when
+    /// asked to, returning the manifest's path. Without git or git-annex installed, only
+    /// the manifest is written.
+    pub async fn write_local(&self, dest_dir: &str, records: Vec<AddurlsRecord>) -> Result<String, String> {
+        let path = format!("{}/{}", dest_dir, ADDURLS_PATH);
+        path_guard::check(&path)?;
+        if let Some(parent) = Path::new(&path).parent() {
+            fs::create_dir_all(parent).await
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        fs::write(&path, DataladExport::to_json(&records)?).await
+            .map_err(|e| format!("Failed to write DataLad manifest {}: {}", path, e))?;
+        log::info!("Wrote DataLad manifest with {} files to {}", records.len(), path);
+
+        if self.init_repository {
+            let dir = dest_dir.to_string();
+            let result = tokio::task::spawn_blocking(move || init_repository(Path::new(&dir), &records))
+                .await
+                .map_err(|e| format!("Repository setup failed: {}", e))
+                .and_then(|result| result);
+            if let Err(e) = result {
+                log::warn!("{} was not set up as a DataLad dataset: {}", dest_dir, e);
+            }
+        }
+        Ok(path)
+    }
+}
+
+/// git-annex SHA256E key: size and hash plus the file's extensions (`.nii.gz`)
+fn annex_key(entry: &ManifestEntry) -> String {
+    let file_name = entry.path.rsplit('/').next().unwrap_or(&entry.path);
+    let extensions = file_name.split_once('.').map(|(_, extensions)| extensions).unwrap_or("");
+    let mut extensions: Vec<&str> = extensions.rsplit('.')
+        .take_while(|extension| extension.len() <= MAX_EXTENSION_LENGTH)
+        .filter(|extension| !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric()))
+        .take(MAX_EXTENSIONS)
+        .collect();
+    extensions.reverse();
+    let suffix: String = extensions.iter().map(|extension| format!(".{}", extension)).collect();
+    format!("SHA256E-s{}--{}{}", entry.size, entry.sha256.to_lowercase(), suffix)
+}
+
+/// Turn `dir` into a git repository with an annex that knows where every recorded file
+/// comes from. Existing repositories and `.gitattributes` are kept; nothing is committed.
+fn init_repository(dir: &Path, records: &[AddurlsRecord]) -> Result<(), String> {
+    let attributes = dir.join(".gitattributes");
+    if !attributes.exists() {
+        path_guard::check(&attributes)?;
+        std::fs::write(&attributes, GITATTRIBUTES)
+            .map_err(|e| format!("Failed to write {}: {}", attributes.display(), e))?;
+    }
+    if !dir.join(".git").exists() {
+        git(dir, &["init", "--quiet"], None)?;
+    }
+    git(dir, &["annex", "init", "bids-collector"], None)
+        .map_err(|e| format!("git-annex is not available: {}", e))?;
+
+    let batch: String = records.iter().map(|record| format!("{} {}\n", record.key, record.url)).collect();
+    git(dir, &["annex", "registerurl", "--batch"], Some(&batch))?;
+    log::info!("Registered {} source URLs in the annex of {}", records.len(), dir.display());
+    Ok(())
+}
+
+/// Run git in `dir` without a console window, feeding it `input`
+fn git(dir: &Path, args: &[&str], input: Option<&str>) -> Result<(), String> {
+    let mut command = Command::new("git");
+    command.args(args)
+        .current_dir(dir)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let mut child = command.spawn()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())
+            .map_err(|e| format!("Failed to write to git {}: {}", args.join(" "), e))?;
+    }
+    let output = child.wait_with_output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+use crate::error::CollectorError;
+use crate::path_guard;
+use crate::safe_path;
+use crate::s3_client::{self, S3ConnectionConfig};
+
+/// Minimum time between two `dataset-delete-progress` events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What `plan_dataset_deletion` found, and the token `delete_dataset` needs to remove it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletionPlan {
+    pub location: String,
+    pub file_count: usize,
+    pub total_size: u64,
+    /// Only valid while the dataset holds exactly these files
+    pub confirmation_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedDeletion {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletionResult {
+    pub location: String,
+    pub deleted_files: usize,
+    pub deleted_bytes: u64,
+    pub failed: Vec<FailedDeletion>,
+}
+
+/// Payload of the `dataset-delete-progress` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletionProgress {
+    pub location: String,
+    pub file_count: usize,
+    pub files_done: usize,
+    pub total_size: u64,
+    pub bytes_done: u64,
+}
+
+/// Where a dataset to be deleted lives
+enum Target {
+    Local(PathBuf),
+    S3 { config: Box<S3ConnectionConfig>, prefix: String },
+}
+
+impl Target {
+    /// The dataset directory or key prefix `prefix` below a storage location. The prefix
+    /// must name something inside the location, never the location itself.
+    fn resolve(storage_location: &Value, prefix: &str) -> Result<Target, String> {
+        let relative = safe_path::relative(prefix)
+            .map_err(|_| format!("Refusing to delete {:?}: the prefix must be a path inside the storage location", prefix))?;
+
+        match storage_location.get("type").and_then(|t| t.as_str()) {
+            Some("local") => {
+                let root = storage_location.get("path")
+                    .and_then(|p| p.as_str())
+                    .ok_or("No storage path specified")?;
+                let dir = path_guard::check(Path::new(root).join(&relative))?;
+                Ok(Target::Local(dir))
+            }
+            Some("s3-compatible") => {
+                let config = S3ConnectionConfig::from_storage_location(storage_location)?;
+                Ok(Target::S3 { config: Box::new(config), prefix: format!("{}/", relative) })
+            }
+            Some(other) => Err(format!("Deleting datasets from {} storage is not supported", other)),
+            None => Err("No storage type specified".to_string()),
+        }
+    }
+
+    fn location(&self) -> String {
+        match self {
+            Target::Local(dir) => dir.display().to_string(),
+            Target::S3 { config, prefix } => format!("s3://{}/{}", config.bucket_name, prefix),
+        }
+    }
+
+    /// Every file of the dataset with its size: paths for local storage, keys for S3
+    async fn list(&self) -> Result<Vec<(String, u64)>, String> {
+        match self {
+            Target::Local(dir) => {
+                let dir = dir.clone();
+                tokio::task::spawn_blocking(move || list_local(&dir))
+                    .await
+                    .map_err(|e| format!("Listing failed: {}", e))?
+            }
+            Target::S3 { config, prefix } => Ok(s3_client::list_objects(config, prefix).await?
+                .into_iter()
+                .map(|object| (object.key, object.size))
+                .collect()),
+        }
+    }
+}
+
+/// Files below `dir`, symlinks included as files so their targets are left alone
+fn list_local(dir: &Path) -> Result<Vec<(String, u64)>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = std::fs::read_dir(&current)
+            .map_err(|e| format!("Failed to read {}: {}", current.display(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read {}: {}", current.display(), e))?;
+            let metadata = entry.path().symlink_metadata()
+                .map_err(|e| format!("Failed to read {}: {}", entry.path().display(), e))?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                files.push((entry.path().to_string_lossy().to_string(), metadata.len()));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Token binding a confirmation to the location and the files it held when planned
+fn confirmation_token(location: &str, files: &[(String, u64)]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(location.as_bytes());
+    for (path, size) in files {
+        hasher.update(format!("\n{}\t{}", path, size).as_bytes());
+    }
+    hex::encode(hasher.finalize())[..16].to_string()
+}
+
+struct Progress<'a> {
+    app_handle: &'a tauri::AppHandle,
+    report: DeletionProgress,
+    last_emit: Option<Instant>,
+}
+
+impl Progress<'_> {
+    fn advance(&mut self, files: usize, bytes: u64) {
+        self.report.files_done += files;
+        self.report.bytes_done += bytes;
+        if self.last_emit.map_or(true, |at| at.elapsed() >= PROGRESS_INTERVAL) || self.report.files_done == self.report.file_count {
+            self.last_emit = Some(Instant::now());
+            let _ = self.app_handle.emit("dataset-delete-progress", &self.report);
+        }
+    }
+}
+
+/// List what `delete_dataset` would remove at `prefix` below a local or S3-compatible storage
+/// location, and issue the confirmation token it requires
+#[tauri::command]
+pub async fn plan_dataset_deletion(storage_location: Value, prefix: String) -> Result<DeletionPlan, CollectorError> {
+    let target = Target::resolve(&storage_location, &prefix)?;
+    let location = target.location();
+    let files = target.list().await?;
+    if files.is_empty() {
+        return Err(format!("Nothing to delete at {}", location).into());
+    }
+
+    Ok(DeletionPlan {
+        confirmation_token: confirmation_token(&location, &files),
+        file_count: files.len(),
+        total_size: files.iter().map(|(_, size)| size).sum(),
+        location,
+    })
+}
+
+/// Delete a dataset planned with `plan_dataset_deletion`: the local directory with everything
+/// in it, or every object under the prefix in batched DeleteObjects requests. The token must
+/// match the files still there, so a changed dataset has to be planned again. Files that
+/// cannot be deleted are reported without stopping the rest.
+#[tauri::command]
+pub async fn delete_dataset(
+    storage_location: Value,
+    prefix: String,
+    confirmation_token: String,
+    app_handle: tauri::AppHandle,
+) -> Result<DeletionResult, CollectorError> {
+    let target = Target::resolve(&storage_location, &prefix)?;
+    let location = target.location();
+    let files = target.list().await?;
+    if self::confirmation_token(&location, &files) != confirmation_token {
+        return Err(format!(
+            "The confirmation token does not match the files at {}; plan the deletion again",
+            location
+        ).into());
+    }
+    log::info!("Deleting {} files from {}", files.len(), location);
+
+    let mut progress = Progress {
+        app_handle: &app_handle,
+        report: DeletionProgress {
+            location: location.clone(),
+            file_count: files.len(),
+            files_done: 0,
+            total_size: files.iter().map(|(_, size)| size).sum(),
+            bytes_done: 0,
+        },
+        last_emit: None,
+    };
+    let mut failed = Vec::new();
+
+    match &target {
+        Target::Local(dir) => {
+            for (path, size) in &files {
+                match tokio::fs::remove_file(path).await {
+                    Ok(()) => progress.advance(1, *size),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => progress.advance(1, *size),
+                    Err(e) => failed.push(FailedDeletion { path: path.clone(), error: e.to_string() }),
+                }
+            }
+            // Only empty directories are left unless some files could not be deleted
+            if failed.is_empty() {
+                if let Err(e) = tokio::fs::remove_dir_all(dir).await {
+                    failed.push(FailedDeletion { path: dir.display().to_string(), error: e.to_string() });
+                }
+            }
+        }
+        Target::S3 { config, .. } => {
+            for batch in files.chunks(s3_client::DELETE_BATCH_SIZE) {
+                let keys: Vec<String> = batch.iter().map(|(key, _)| key.clone()).collect();
+                let errors = match s3_client::delete_objects(config, &keys).await {
+                    Ok(errors) => errors,
+                    Err(e) => keys.into_iter().map(|key| (key, e.clone())).collect(),
+                };
+                let deleted: Vec<_> = batch.iter().filter(|(key, _)| !errors.iter().any(|(failed, _)| failed == key)).collect();
+                progress.advance(deleted.len(), deleted.iter().map(|(_, size)| size).sum());
+                failed.extend(errors.into_iter().map(|(path, error)| FailedDeletion { path, error }));
+            }
+        }
+    }
+
+    log::info!(
+        "Deleted {} of {} files from {}",
+        progress.report.files_done, files.len(), location
+    );
+    if !failed.is_empty() {
+        log::warn!("{} files could not be deleted from {}", failed.len(), location);
+    }
+    Ok(DeletionResult {
+        location,
+        deleted_files: progress.report.files_done,
+        deleted_bytes: progress.report.bytes_done,
+        failed,
+    })
+}
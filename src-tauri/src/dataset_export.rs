@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use zip::ZipWriter;
+
+use crate::archive::{self, ArchiveFormat};
+use crate::error::CollectorError;
+use crate::path_guard;
+
+/// Minimum time between two progress events of one export
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Cancellation flags of running exports, by dataset path
+pub type ExportState = Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>;
+
+/// A finished archive export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveExport {
+    pub path: String,
+    pub output_path: String,
+    pub file_count: usize,
+    /// Size of the packaged files before compression
+    pub total_size: u64,
+    pub archive_size: u64,
+}
+
+/// Payload of the `archive-export-progress` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProgress {
+    pub path: String,
+    pub output_path: String,
+    pub file_count: usize,
+    pub files_done: usize,
+    pub total_size: u64,
+    pub bytes_done: u64,
+    pub current_file: Option<String>,
+}
+
+struct Progress {
+    app_handle: tauri::AppHandle,
+    report: ExportProgress,
+    cancelled: Arc<AtomicBool>,
+    last_emit: Instant,
+}
+
+impl Progress {
+    fn start_file(&mut self, name: &str) {
+        self.report.current_file = Some(name.to_string());
+        self.emit(true);
+    }
+
+    fn finish_file(&mut self) {
+        self.report.files_done += 1;
+    }
+
+    fn emit(&mut self, force: bool) {
+        if force || self.last_emit.elapsed() >= PROGRESS_INTERVAL {
+            self.last_emit = Instant::now();
+            let _ = self.app_handle.emit("archive-export-progress", &self.report);
+        }
+    }
+}
+
+/// Counts the bytes read from a file into the archive and stops the export when cancelled
+struct ProgressReader<'a, R> {
+    inner: R,
+    progress: &'a mut Progress,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.progress.cancelled.load(Ordering::SeqCst) {
+            return Err(io::Error::other("Export cancelled"));
+        }
+        let read = self.inner.read(buf)?;
+        self.progress.report.bytes_done += read as u64;
+        self.progress.emit(false);
+        Ok(read)
+    }
+}
+
+/// Every file below `dir` with its name in the archive, skipping `exclude`
+fn collect_files(dir: &Path, name: &str, exclude: &Path, files: &mut Vec<(PathBuf, String, u64)>) -> Result<(), String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let entry_name = format!("{}/{}", name, entry.file_name().to_string_lossy());
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            collect_files(&path, &entry_name, exclude, files)?;
+        } else if metadata.is_file() && path != exclude {
+            files.push((path, entry_name, metadata.len()));
+        }
+    }
+    Ok(())
+}
+
+fn write_tar<W: Write>(writer: W, files: &[(PathBuf, String, u64)], progress: &mut Progress) -> Result<W, String> {
+    let mut builder = tar::Builder::new(writer);
+    for (source, name, _) in files {
+        progress.start_file(name);
+        let input = File::open(source)
+            .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+        let metadata = input.metadata()
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata(&metadata);
+        builder.append_data(&mut header, name, ProgressReader { inner: input, progress: &mut *progress })
+            .map_err(|e| format!("Failed to add {} to the archive: {}", name, e))?;
+        progress.finish_file();
+    }
+    builder.into_inner().map_err(|e| format!("Failed to finish the archive: {}", e))
+}
+
+fn write_zip(output: File, files: &[(PathBuf, String, u64)], progress: &mut Progress) -> Result<(), String> {
+    let mut zip = ZipWriter::new(BufWriter::new(output));
+    for (source, name, size) in files {
+        progress.start_file(name);
+        let input = File::open(source)
+            .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+
+        zip.start_file(name.as_str(), archive::zip_file_options(name, *size))
+            .map_err(|e| format!("Failed to add {} to the archive: {}", name, e))?;
+        io::copy(&mut ProgressReader { inner: input, progress: &mut *progress }, &mut zip)
+            .map_err(|e| format!("Failed to add {} to the archive: {}", name, e))?;
+        progress.finish_file();
+    }
+    zip.finish()
+        .and_then(|mut writer| writer.flush().map_err(Into::into))
+        .map_err(|e| format!("Failed to finish the archive: {}", e))
+}
+
+fn write_archive(format: ArchiveFormat, output: &Path, files: &[(PathBuf, String, u64)], progress: &mut Progress) -> Result<(), String> {
+    let file = File::create(output)
+        .map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let flush = |result: io::Result<BufWriter<File>>| result
+        .and_then(|mut writer| writer.flush())
+        .map_err(|e| format!("Failed to finish the archive: {}", e));
+
+    match format {
+        ArchiveFormat::Zip => write_zip(file, files, progress),
+        ArchiveFormat::Tar => flush(Ok(write_tar(BufWriter::new(file), files, progress)?)),
+        ArchiveFormat::TarGz => {
+            let encoder = flate2::write::GzEncoder::new(BufWriter::new(file), flate2::Compression::default());
+            flush(write_tar(encoder, files, progress)?.finish())
+        }
+    }
+}
+
+/// Package a local dataset directory into a `.tar.gz`, `.tar` or `.zip` archive whose entries
+/// sit below the dataset's directory name. Progress is reported through
+/// `archive-export-progress` events; `cancel_dataset_archive_export` stops the export and
+/// removes the partial archive. The archive is written next to the dataset unless
+/// `output_path` is given, and an existing file is never overwritten.
+#[tauri::command]
+pub async fn export_dataset_archive(
+    path: String,
+    format: String,
+    output_path: Option<String>,
+    state: tauri::State<'_, ExportState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ArchiveExport, CollectorError> {
+    let dataset_dir = PathBuf::from(path.trim_end_matches(['/', '\\']));
+    let extension = format.trim_start_matches('.').to_lowercase();
+    let archive_format = ArchiveFormat::detect(&format!("dataset.{}", extension))
+        .ok_or_else(|| format!("Unsupported archive format: {} (expected tar.gz, tar or zip)", format))?;
+    if !dataset_dir.join("dataset_description.json").is_file() {
+        return Err(format!("{} is not a BIDS dataset (no dataset_description.json)", dataset_dir.display()).into());
+    }
+    let dir_name = dataset_dir.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| format!("Invalid dataset path: {}", path))?;
+
+    let output_path = output_path.unwrap_or_else(|| format!("{}.{}", dataset_dir.display(), extension));
+    let output = PathBuf::from(&output_path);
+    path_guard::check(&output)?;
+    if output.exists() {
+        return Err(format!("{} already exists", output_path).into());
+    }
+    let partial = PathBuf::from(format!("{}.part", output_path));
+    path_guard::check(&partial)?;
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    {
+        let mut exports = state.lock().unwrap();
+        if exports.contains_key(&path) {
+            return Err(format!("{} is already being exported", path).into());
+        }
+        exports.insert(path.clone(), cancelled.clone());
+    }
+
+    log::info!("Exporting {} to {}", dataset_dir.display(), output_path);
+    let report = ExportProgress {
+        path: path.clone(),
+        output_path: output_path.clone(),
+        file_count: 0,
+        files_done: 0,
+        total_size: 0,
+        bytes_done: 0,
+        current_file: None,
+    };
+    let result = tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        collect_files(&dataset_dir, &dir_name, &partial, &mut files)?;
+        files.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let mut progress = Progress { app_handle, report, cancelled, last_emit: Instant::now() };
+        progress.report.file_count = files.len();
+        progress.report.total_size = files.iter().map(|(_, _, size)| size).sum();
+
+        let written = write_archive(archive_format, &partial, &files, &mut progress)
+            .and_then(|()| std::fs::rename(&partial, &output)
+                .map_err(|e| format!("Failed to move the archive to {}: {}", output.display(), e)));
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&partial);
+            return Err(if progress.cancelled.load(Ordering::SeqCst) { "Export cancelled".to_string() } else { e });
+        }
+
+        progress.report.current_file = None;
+        progress.emit(true);
+        let archive_size = std::fs::metadata(&output).map(|m| m.len()).unwrap_or(0);
+        Ok((progress.report, archive_size))
+    })
+    .await
+    .map_err(|e| format!("Archive export task failed: {}", e));
+    state.lock().unwrap().remove(&path);
+
+    let (report, archive_size) = result??;
+    log::info!("Exported {} files of {} into {} ({} bytes)", report.file_count, path, output_path, archive_size);
+    Ok(ArchiveExport {
+        path,
+        output_path,
+        file_count: report.file_count,
+        total_size: report.total_size,
+        archive_size,
+    })
+}
+
+/// Stop a running archive export; returns false when the dataset is not being exported
+#[tauri::command]
+pub async fn cancel_dataset_archive_export(path: String, state: tauri::State<'_, ExportState>) -> Result<bool, CollectorError> {
+    match state.lock().unwrap().get(&path) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::SeqCst);
+            log::info!("Cancelling archive export of {}", path);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
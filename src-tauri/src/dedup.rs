@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::catalog::{CatalogEntry, CatalogState};
+use crate::checksum::ChecksumAlgorithm;
+use crate::error::CollectorError;
+use crate::journal::load_manifest;
+use crate::manifest::{self, Manifest};
+use crate::providers::RemoteFile;
+use crate::{credentials, storage};
+
+/// Chunk size used to re-hash files before they are linked
+const HASH_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Files of a task that were hardlinked to content collected earlier (`dedup`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DedupStats {
+    pub linked_files: u32,
+    pub saved_bytes: u64,
+}
+
+impl DedupStats {
+    fn record(&mut self, size: u64) {
+        self.linked_files += 1;
+        self.saved_bytes += size;
+    }
+}
+
+/// A file from the content cache that still holds this content, other than `exclude`.
+/// Entries whose file was removed or changed size are dropped from the cache.
+fn find_cached(catalog: &CatalogState, sha256: &str, size: u64, exclude: &str) -> Option<String> {
+    let catalog = catalog.lock().unwrap();
+    let candidates = match catalog.cached_content(sha256, size) {
+        Ok(candidates) => candidates,
+        Err(e) => {
+            log::warn!("{}", e);
+            return None;
+        }
+    };
+
+    for path in candidates.into_iter().filter(|path| path != exclude) {
+        match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() && metadata.len() == size => return Some(path),
+            _ => {
+                let _ = catalog.forget_content(&path);
+            }
+        }
+    }
+    None
+}
+
+/// Replace `dest_path` with a hardlink to `source`. The link is made next to the destination
+/// first, so a failed link (e.g. across file systems) leaves the destination untouched.
+fn hard_link_over(source: &str, dest_path: &str) -> Result<(), String> {
+    crate::path_guard::check(dest_path)?;
+    let temp_path = format!("{}.dedup", dest_path);
+    let _ = std::fs::remove_file(&temp_path);
+
+    std::fs::hard_link(source, &temp_path)
+        .map_err(|e| format!("Failed to link {} to {}: {}", dest_path, source, e))?;
+    std::fs::rename(&temp_path, dest_path).map_err(|e| {
+        let _ = std::fs::remove_file(&temp_path);
+        format!("Failed to replace {} with a link: {}", dest_path, e)
+    })
+}
+
+fn remember(catalog: &CatalogState, path: &str, sha256: &str, size: u64) {
+    if let Err(e) = catalog.lock().unwrap().cache_content(path, sha256, size) {
+        log::warn!("{}", e);
+    }
+}
+
+/// Content cache of one task; linking is only attempted when the task asks for `dedup`
+pub struct Deduplicator {
+    catalog: CatalogState,
+    pub stats: DedupStats,
+}
+
+impl Deduplicator {
+    pub fn new(catalog: CatalogState) -> Deduplicator {
+        Deduplicator {
+            catalog,
+            stats: DedupStats::default(),
+        }
+    }
+
+    /// Link a file whose publisher-reported SHA-256 is already in the cache instead of
+    /// downloading it. Returns the SHA-256 when the file was linked.
+    pub fn link_published(&mut self, file_info: &RemoteFile, dest_path: &str) -> Option<String> {
+        let checksum = file_info.checksum.as_ref().filter(|c| c.algorithm == ChecksumAlgorithm::Sha256)?;
+        let source = find_cached(&self.catalog, &checksum.value, file_info.size, dest_path)?;
+
+        match hard_link_over(&source, dest_path) {
+            Ok(()) => {
+                log::info!("Linked {} to identical {}", dest_path, source);
+                self.stats.record(file_info.size);
+                remember(&self.catalog, dest_path, &checksum.value, file_info.size);
+                Some(checksum.value.clone())
+            }
+            Err(e) => {
+                log::warn!("{}; downloading instead", e);
+                None
+            }
+        }
+    }
+
+    /// Replace a freshly downloaded file with a link when identical content was collected
+    /// before; otherwise add it to the cache. Returns whether the file was linked.
+    pub fn link_downloaded(&mut self, dest_path: &str, sha256: &str, size: u64) -> bool {
+        let linked = match find_cached(&self.catalog, sha256, size, dest_path) {
+            Some(source) => match hard_link_over(&source, dest_path) {
+                Ok(()) => {
+                    log::info!("Replaced {} with a link to identical {}", dest_path, source);
+                    self.stats.record(size);
+                    true
+                }
+                Err(e) => {
+                    log::warn!("{}; keeping the downloaded copy", e);
+                    false
+                }
+            },
+            None => false,
+        };
+        remember(&self.catalog, dest_path, sha256, size);
+        linked
+    }
+
+    /// Add a file that was already at the destination to the cache
+    pub fn remember_existing(&self, dest_path: &str, sha256: &str, size: u64) {
+        remember(&self.catalog, dest_path, sha256, size);
+    }
+}
+
+/// One stored copy of a duplicated file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateCopy {
+    /// Local dataset directory or storage URI of the collection
+    pub dataset: String,
+    pub path: String,
+    pub local: bool,
+}
+
+/// Identical content stored more than once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub sha256: String,
+    pub size: u64,
+    pub copies: Vec<DuplicateCopy>,
+    /// Space freed by keeping a single copy; local copies that are already hardlinked count once
+    pub reclaimable: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateReport {
+    pub datasets_scanned: usize,
+    /// Collections whose manifest could not be read, with the reason
+    pub skipped: Vec<String>,
+    /// Largest savings first
+    pub groups: Vec<DuplicateGroup>,
+    pub reclaimable_bytes: u64,
+    /// Copies replaced by hardlinks when `hardlink` was requested
+    pub linked_files: u32,
+    pub linked_bytes: u64,
+}
+
+/// Device and inode of a local file, so copies that already share storage count once
+#[cfg(unix)]
+fn file_id(path: &str) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|metadata| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_path: &str) -> Option<(u64, u64)> {
+    None
+}
+
+/// Saved storage location a remote collection was written to, matched by its storage URI
+fn location_of(entry: &CatalogEntry, locations: &[Value]) -> Option<Value> {
+    locations.iter()
+        .filter(|location| location.get("type").and_then(|t| t.as_str()) == Some(entry.storage_type.as_str()))
+        .find(|location| {
+            storage::from_storage_location(location)
+                .is_ok_and(|storage| storage.location(&entry.identifier) == entry.location)
+        })
+        .cloned()
+}
+
+fn copy_path(copy: &DuplicateCopy) -> String {
+    format!("{}/{}", copy.dataset, copy.path)
+}
+
+/// Copies that are physically distinct; hardlinked local copies count once
+fn distinct_copies(copies: &[DuplicateCopy]) -> usize {
+    let mut seen = HashSet::new();
+    copies.iter()
+        .filter(|copy| !copy.local || file_id(&copy_path(copy)).map_or(true, |id| seen.insert(id)))
+        .count()
+}
+
+/// Replace the local copies of a group with hardlinks to the first one, after checking each
+/// still holds the recorded content. Returns the number of copies linked.
+async fn link_group(group: &DuplicateGroup) -> u32 {
+    let mut local = group.copies.iter().filter(|copy| copy.local).map(copy_path);
+    let Some(keeper) = local.next() else {
+        return 0;
+    };
+    let matches = |result: Result<(u64, String), String>| {
+        result.is_ok_and(|(size, sha256)| size == group.size && sha256 == group.sha256)
+    };
+    if !matches(manifest::hash_file(&keeper, HASH_CHUNK_SIZE).await) {
+        log::info!("{} no longer matches its manifest, not linking its duplicates", keeper);
+        return 0;
+    }
+
+    let mut linked = 0;
+    for path in local {
+        if file_id(&path).is_some() && file_id(&path) == file_id(&keeper) {
+            continue;
+        }
+        if !matches(manifest::hash_file(&path, HASH_CHUNK_SIZE).await) {
+            log::info!("{} no longer matches its manifest, leaving it alone", path);
+            continue;
+        }
+        match hard_link_over(&keeper, &path) {
+            Ok(()) => linked += 1,
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+    linked
+}
+
+/// Find files stored more than once across the cataloged collections, using the SHA-256
+/// recorded in their manifests. With `hardlink`, local duplicates are replaced by hardlinks to
+/// one copy where they share a file system; remote copies are only reported.
+#[tauri::command]
+pub async fn find_duplicate_data(
+    hardlink: Option<bool>,
+    catalog: tauri::State<'_, CatalogState>,
+    app_handle: tauri::AppHandle,
+) -> Result<DuplicateReport, CollectorError> {
+    let entries = catalog.lock().unwrap().list()?;
+    let locations = credentials::saved_locations(&app_handle)?;
+
+    // Later collections into the same destination supersede earlier ones
+    let latest: BTreeMap<&str, &CatalogEntry> = entries.iter().map(|entry| (entry.location.as_str(), entry)).collect();
+
+    let mut copies: HashMap<(String, u64), Vec<DuplicateCopy>> = HashMap::new();
+    let mut skipped = Vec::new();
+    let mut datasets_scanned = 0;
+    for entry in latest.values() {
+        let local = entry.storage_type == "local";
+        let location = if local { None } else { location_of(entry, &locations) };
+        let manifest: Manifest = match load_manifest(entry, location.as_ref()).await {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                skipped.push(format!("{}: {}", entry.location, e));
+                continue;
+            }
+        };
+        datasets_scanned += 1;
+
+        for file in manifest.files {
+            copies.entry((file.sha256, file.size)).or_default().push(DuplicateCopy {
+                dataset: entry.location.clone(),
+                path: file.path,
+                local,
+            });
+        }
+    }
+
+    let mut groups: Vec<DuplicateGroup> = copies.into_iter()
+        .filter(|(_, copies)| copies.len() > 1)
+        .map(|((sha256, size), copies)| DuplicateGroup {
+            reclaimable: size * (distinct_copies(&copies).saturating_sub(1) as u64),
+            sha256,
+            size,
+            copies,
+        })
+        .collect();
+    groups.sort_by(|a, b| b.reclaimable.cmp(&a.reclaimable).then_with(|| a.sha256.cmp(&b.sha256)));
+
+    let mut linked_files = 0;
+    let mut linked_bytes = 0;
+    if hardlink.unwrap_or(false) {
+        for group in groups.iter_mut().filter(|group| group.reclaimable > 0) {
+            let linked = link_group(group).await;
+            linked_files += linked;
+            linked_bytes += group.size * linked as u64;
+            group.reclaimable = group.size * (distinct_copies(&group.copies).saturating_sub(1) as u64);
+        }
+    }
+
+    let reclaimable_bytes = groups.iter().map(|group| group.reclaimable).sum();
+    log::info!(
+        "Found {} duplicated files across {} datasets; {} bytes reclaimable, {} files linked",
+        groups.len(), datasets_scanned, reclaimable_bytes, linked_files
+    );
+    Ok(DuplicateReport {
+        datasets_scanned,
+        skipped,
+        groups,
+        reclaimable_bytes,
+        linked_files,
+        linked_bytes,
+    })
+}
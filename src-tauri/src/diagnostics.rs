@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::archive;
+use crate::environment::EnvironmentState;
+use crate::error::CollectorError;
+use crate::logging;
+use crate::network::NetworkState;
+use crate::path_guard;
+use crate::task_queue::TaskQueueState;
+use crate::DownloadState;
+
+/// Logs modified longer ago than this are left out of the bundle
+const RECENT_LOGS: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Newest logs are added until they reach this size
+const MAX_LOG_BYTES: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsBundle {
+    pub path: String,
+    pub files: Vec<String>,
+    pub size: u64,
+}
+
+/// Log files changed within `RECENT_LOGS`, newest first and capped at `MAX_LOG_BYTES`
+fn recent_logs(log_dir: &Path) -> Vec<(PathBuf, u64)> {
+    let Ok(entries) = std::fs::read_dir(log_dir) else {
+        return Vec::new();
+    };
+    let now = SystemTime::now();
+    let mut logs: Vec<(PathBuf, SystemTime, u64)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "log"))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().ok()?;
+            let recent = now.duration_since(modified).unwrap_or_default() <= RECENT_LOGS;
+            recent.then(|| (entry.path(), modified, metadata.len()))
+        })
+        .collect();
+    logs.sort_by_key(|(_, modified, _)| std::cmp::Reverse(*modified));
+
+    let mut total = 0;
+    logs.into_iter()
+        .take_while(|(_, _, size)| {
+            total += size;
+            total <= MAX_LOG_BYTES
+        })
+        .map(|(path, _, size)| (path, size))
+        .collect()
+}
+
+/// Proxy URL without any user and password written into it
+fn redact_proxy(proxy_url: &str) -> String {
+    match url::Url::parse(proxy_url) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.to_string()
+        }
+        Err(_) => "<unparseable>".to_string(),
+    }
+}
+
+fn write_bundle(output: &Path, logs: &[(PathBuf, u64)], documents: &[(&str, Value)]) -> Result<Vec<String>, String> {
+    path_guard::check(output)?;
+    let file = File::create(output)
+        .map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let mut names = Vec::new();
+
+    for (name, document) in documents {
+        let content = serde_json::to_vec_pretty(document)
+            .map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+        zip.start_file(*name, SimpleFileOptions::default())
+            .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+        io::Write::write_all(&mut zip, &content)
+            .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+        names.push(name.to_string());
+    }
+
+    for (source, size) in logs {
+        let Some(file_name) = source.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let name = format!("logs/{}", file_name);
+        // The logger may be writing to it; whatever is there when it is opened goes in
+        let mut input = File::open(source)
+            .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+        zip.start_file(name.as_str(), archive::zip_file_options(&name, *size))
+            .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+        io::copy(&mut input, &mut zip)
+            .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+        names.push(name);
+    }
+
+    zip.finish().map_err(|e| format!("Failed to finish {}: {}", output.display(), e))?;
+    Ok(names)
+}
+
+/// Zip the logs of the last week with the state of every task, the queue and the settings
+/// that shape transfers, for attaching to a support request. Credentials are left out.
+#[tauri::command]
+pub async fn export_diagnostics_bundle(
+    output_path: String,
+    app_handle: tauri::AppHandle,
+    downloads: tauri::State<'_, DownloadState>,
+    queue: tauri::State<'_, TaskQueueState>,
+    environment: tauri::State<'_, EnvironmentState>,
+    network: tauri::State<'_, NetworkState>,
+) -> Result<DiagnosticsBundle, CollectorError> {
+    let logs = recent_logs(&logging::log_dir(&app_handle)?);
+
+    let mut tasks: Vec<_> = downloads.lock().unwrap().values().cloned().collect();
+    tasks.sort_by_key(|task| task.task_id.clone());
+    let mut network_settings = network.settings();
+    network_settings.proxy_url = network_settings.proxy_url.as_deref().map(redact_proxy);
+
+    let documents = [
+        ("app.json", json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "createdAt": chrono::Utc::now().to_rfc3339(),
+            "logFilter": std::env::var(logging::FILTER_ENV).ok(),
+        })),
+        ("tasks.json", json!(tasks)),
+        ("queue.json", json!(queue.snapshot())),
+        ("environment.json", json!(*environment.lock().unwrap())),
+        ("network.json", json!(network_settings)),
+    ];
+
+    let output = PathBuf::from(&output_path);
+    let files = tokio::task::spawn_blocking(move || write_bundle(&output, &logs, &documents))
+        .await
+        .map_err(|e| format!("Diagnostics export failed: {}", e))??;
+    let size = std::fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0);
+
+    log::info!("Exported diagnostics bundle with {} files to {}", files.len(), output_path);
+    Ok(DiagnosticsBundle { path: output_path, files, size })
+}
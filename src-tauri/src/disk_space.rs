@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use sysinfo::Disks;
+
+use crate::formatting;
+
+/// Returned when the destination volume cannot hold the selected files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsufficientSpace {
+    pub path: String,
+    pub required_bytes: u64,
+    pub available_bytes: u64,
+    pub missing_bytes: u64,
+}
+
+impl fmt::Display for InsufficientSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Not enough free disk space at {}: the download needs {} but only {} is available ({} more required)",
+            self.path,
+            formatting::bytes(self.required_bytes),
+            formatting::bytes(self.available_bytes),
+            formatting::bytes(self.missing_bytes)
+        )
+    }
+}
+
+/// Check that the volume holding `dest_dir` has at least `required_bytes` free.
+/// If the volume cannot be determined the check is skipped rather than blocking the download.
+pub fn check_available_space(dest_dir: &str, required_bytes: u64) -> Result<(), InsufficientSpace> {
+    let available_bytes = match available_space(Path::new(dest_dir)) {
+        Some(available) => available,
+        None => {
+            log::warn!("Could not determine free space for {}, skipping pre-flight check", dest_dir);
+            return Ok(());
+        }
+    };
+
+    log::info!("Pre-flight space check for {}: need {} bytes, {} bytes available", dest_dir, required_bytes, available_bytes);
+
+    if available_bytes >= required_bytes {
+        return Ok(());
+    }
+
+    Err(InsufficientSpace {
+        path: dest_dir.to_string(),
+        required_bytes,
+        available_bytes,
+        missing_bytes: required_bytes - available_bytes,
+    })
+}
+
+/// Free space of the volume that contains `path`, matched by the longest mount point prefix
+pub fn available_space(path: &Path) -> Option<u64> {
+    let resolved = existing_ancestor(path)?;
+    let disks = Disks::new_with_refreshed_list();
+
+    disks
+        .list()
+        .iter()
+        .filter(|disk| resolved.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+/// The destination may not exist yet, so walk up to the closest existing directory
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find_map(|ancestor| std::fs::canonicalize(ancestor).ok())
+        .or_else(|| std::env::current_dir().ok())
+}
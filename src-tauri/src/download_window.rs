@@ -0,0 +1,167 @@
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::error::CollectorError;
+use crate::task_queue::TaskQueueState;
+
+/// File in the app data directory holding the download window
+pub const SETTINGS_FILE: &str = "download_window.json";
+
+/// How often the window is compared with the clock
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+const TIME_FORMAT: &str = "%H:%M";
+
+/// Hours of the day (local time), e.g. off-peak 22:00-06:00. A window whose start is after
+/// its end wraps past midnight.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeWindow {
+    /// "HH:MM"
+    pub start: String,
+    /// "HH:MM"
+    pub end: String,
+}
+
+impl TimeWindow {
+    pub fn bounds(&self) -> Result<(NaiveTime, NaiveTime), String> {
+        let parse = |value: &str| NaiveTime::parse_from_str(value, TIME_FORMAT)
+            .map_err(|_| format!("Invalid time {}, expected HH:MM", value));
+        Ok((parse(&self.start)?, parse(&self.end)?))
+    }
+
+    pub fn is_open_at(&self, now: NaiveTime) -> bool {
+        match self.bounds() {
+            Ok((start, end)) if start < end => start <= now && now < end,
+            Ok((start, end)) if start > end => now >= start || now < end,
+            // Equal bounds cover the whole day; a broken file does not stop downloads
+            _ => true,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open_at(chrono::Local::now().time())
+    }
+}
+
+/// Window during which download tasks may transfer at all
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadWindow {
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub window: TimeWindow,
+}
+
+impl Default for DownloadWindow {
+    fn default() -> Self {
+        DownloadWindow {
+            enabled: false,
+            window: TimeWindow {
+                start: "22:00".to_string(),
+                end: "06:00".to_string(),
+            },
+        }
+    }
+}
+
+impl DownloadWindow {
+    /// Whether tasks may transfer now; always true when the window is disabled
+    pub fn is_open(&self) -> bool {
+        !self.enabled || self.window.is_open()
+    }
+}
+
+/// The configured window, persisted in the app data directory
+pub struct WindowSchedule {
+    settings_path: PathBuf,
+    window: Mutex<DownloadWindow>,
+}
+
+pub type WindowState = Arc<WindowSchedule>;
+
+impl WindowSchedule {
+    /// Load the window from the app data directory, falling back to the default (disabled)
+    pub fn open(app_handle: &tauri::AppHandle) -> Result<WindowSchedule, String> {
+        let settings_path = app_handle.path().app_data_dir()
+            .map(|dir| dir.join(SETTINGS_FILE))
+            .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+
+        let window = match std::fs::read(&settings_path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", settings_path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => DownloadWindow::default(),
+            Err(e) => return Err(format!("Failed to read {}: {}", settings_path.display(), e)),
+        };
+
+        Ok(WindowSchedule {
+            settings_path,
+            window: Mutex::new(window),
+        })
+    }
+
+    pub fn window(&self) -> DownloadWindow {
+        self.window.lock().unwrap().clone()
+    }
+
+    fn save(&self, window: DownloadWindow) -> Result<(), String> {
+        crate::path_guard::check(&self.settings_path)?;
+        if let Some(parent) = self.settings_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        let content = serde_json::to_vec_pretty(&window)
+            .map_err(|e| format!("Failed to serialize download window: {}", e))?;
+        std::fs::write(&self.settings_path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.settings_path.display(), e))?;
+
+        *self.window.lock().unwrap() = window;
+        Ok(())
+    }
+
+    /// Open or close the task queue according to the window at the current time
+    pub fn apply(&self, queue: &TaskQueueState) {
+        queue.set_window_open(self.window().is_open());
+    }
+}
+
+/// Start queued tasks when the window opens and pause running ones when it closes,
+/// for the lifetime of the app
+pub fn spawn(schedule: WindowState, queue: TaskQueueState) {
+    schedule.apply(&queue);
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            schedule.apply(&queue);
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_download_window(state: tauri::State<'_, WindowState>) -> Result<DownloadWindow, CollectorError> {
+    Ok(state.window())
+}
+
+/// Replace the download window; takes effect immediately
+#[tauri::command]
+pub async fn update_download_window(
+    window: DownloadWindow,
+    state: tauri::State<'_, WindowState>,
+    queue: tauri::State<'_, TaskQueueState>,
+) -> Result<DownloadWindow, CollectorError> {
+    window.window.bounds()?;
+    state.save(window)?;
+    state.apply(queue.inner());
+
+    let window = state.window();
+    if window.enabled {
+        log::info!("Downloads run between {} and {}", window.window.start, window.window.end);
+    } else {
+        log::info!("Download window disabled");
+    }
+    Ok(window)
+}
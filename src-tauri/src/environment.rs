@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::error::CollectorError;
+use crate::task_queue::TaskQueueState;
+
+/// How often power and network state are probed
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Power and network conditions task constraints are evaluated against.
+/// None means the OS could not tell; constraints on an unknown condition are treated as met.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Environment {
+    pub on_ac_power: Option<bool>,
+    pub metered_network: Option<bool>,
+}
+
+pub type EnvironmentState = Arc<Mutex<Environment>>;
+
+impl Environment {
+    pub fn probe() -> Environment {
+        Environment {
+            on_ac_power: on_ac_power(),
+            metered_network: metered_network(),
+        }
+    }
+}
+
+/// Output of a command run without a console window, if it succeeded
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let mut command = Command::new(program);
+    command.args(args);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let output = command.output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn on_ac_power() -> Option<bool> {
+    let read = |path: std::path::PathBuf| std::fs::read_to_string(path).map(|s| s.trim().to_string()).ok();
+    let mut mains = Vec::new();
+    let mut discharging = false;
+    for supply in std::fs::read_dir("/sys/class/power_supply").ok()?.flatten() {
+        let path = supply.path();
+        match read(path.join("type")).as_deref() {
+            Some("Mains") => mains.push(read(path.join("online")).as_deref() == Some("1")),
+            Some("Battery") => discharging |= read(path.join("status")).as_deref() == Some("Discharging"),
+            _ => {}
+        }
+    }
+
+    if !mains.is_empty() {
+        Some(mains.contains(&true))
+    } else {
+        // Machines without a battery report no power supplies at all
+        Some(!discharging)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn on_ac_power() -> Option<bool> {
+    let output = command_output("pmset", &["-g", "batt"])?;
+    let source = output.lines().next()?;
+    if source.contains("AC Power") {
+        Some(true)
+    } else if source.contains("Battery Power") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn on_ac_power() -> Option<bool> {
+    let output = command_output("powershell", &[
+        "-NoProfile",
+        "-Command",
+        "Add-Type -AssemblyName System.Windows.Forms; [System.Windows.Forms.SystemInformation]::PowerStatus.PowerLineStatus",
+    ])?;
+    match output.trim() {
+        "Online" => Some(true),
+        "Offline" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn on_ac_power() -> Option<bool> {
+    None
+}
+
+/// NetworkManager's metered flag of the connected devices
+#[cfg(target_os = "linux")]
+fn metered_network() -> Option<bool> {
+    let output = command_output("nmcli", &["-t", "-f", "GENERAL.STATE,GENERAL.METERED", "device", "show"])?;
+    let mut connected = false;
+    let mut metered = None;
+    for line in output.lines() {
+        if let Some(state) = line.strip_prefix("GENERAL.STATE:") {
+            connected = state.contains("(connected)");
+        } else if let Some(value) = line.strip_prefix("GENERAL.METERED:") {
+            if connected {
+                let device_metered = value.starts_with("yes");
+                metered = Some(metered.unwrap_or(false) || device_metered);
+            }
+        }
+    }
+    metered
+}
+
+#[cfg(target_os = "windows")]
+fn metered_network() -> Option<bool> {
+    let output = command_output("powershell", &[
+        "-NoProfile",
+        "-Command",
+        "[Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime]::GetInternetConnectionProfile().GetConnectionCost().NetworkCostType",
+    ])?;
+    match output.trim() {
+        "Unrestricted" => Some(false),
+        "Fixed" | "Variable" => Some(true),
+        _ => None,
+    }
+}
+
+/// macOS has no command-line view of whether a network is expensive
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn metered_network() -> Option<bool> {
+    None
+}
+
+/// Probe power and network state for the lifetime of the app, waking waiting tasks on every
+/// probe so time-based constraints are re-evaluated too
+pub fn spawn(environment: EnvironmentState, queue: TaskQueueState) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Ok(probed) = tokio::task::spawn_blocking(Environment::probe).await {
+                let mut current = environment.lock().unwrap();
+                if *current != probed {
+                    log::info!("Environment changed: AC power {:?}, metered network {:?}", probed.on_ac_power, probed.metered_network);
+                    *current = probed;
+                }
+            }
+            queue.wake();
+            tokio::time::sleep(PROBE_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_environment(state: tauri::State<'_, EnvironmentState>) -> Result<Environment, CollectorError> {
+    Ok(state.lock().unwrap().clone())
+}
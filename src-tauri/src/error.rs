@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+use crate::disk_space::InsufficientSpace;
+use crate::quota::QuotaExceeded;
+
+/// What went wrong, for the frontend to pick its handling (retry, ask for space, fix a setting)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    DiskFull,
+    QuotaExceeded,
+    Timeout,
+    Network,
+    RateLimited,
+    Authentication,
+    PermissionDenied,
+    NotFound,
+    /// A checksum, size or gzip check failed
+    Integrity,
+    /// The task or resource is already running or exists
+    Conflict,
+    InvalidInput,
+    Cancelled,
+    Other,
+}
+
+impl ErrorKind {
+    /// Whether the same operation may succeed when simply tried again later
+    pub fn is_retryable(self) -> bool {
+        matches!(self, ErrorKind::Timeout | ErrorKind::Network | ErrorKind::RateLimited)
+    }
+
+    /// Kind of a failed HTTP request
+    pub fn of_request(error: &reqwest::Error) -> ErrorKind {
+        match error.status() {
+            Some(status) => ErrorKind::of_status(status),
+            None if error.is_timeout() => ErrorKind::Timeout,
+            None => ErrorKind::Network,
+        }
+    }
+
+    /// Kind of an HTTP error status
+    pub fn of_status(status: reqwest::StatusCode) -> ErrorKind {
+        match status.as_u16() {
+            401 => ErrorKind::Authentication,
+            403 => ErrorKind::PermissionDenied,
+            404 | 410 => ErrorKind::NotFound,
+            408 | 504 => ErrorKind::Timeout,
+            // 503 is how S3 says "Slow Down"
+            429 | 503 => ErrorKind::RateLimited,
+            500..=599 => ErrorKind::Network,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Kind of a failed file system operation
+    pub fn of_io(error: &std::io::Error) -> ErrorKind {
+        match error.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            std::io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            std::io::ErrorKind::TimedOut => ErrorKind::Timeout,
+            _ if is_disk_full(error) => ErrorKind::DiskFull,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// ENOSPC, or ERROR_HANDLE_DISK_FULL and ERROR_DISK_FULL on Windows
+fn is_disk_full(error: &std::io::Error) -> bool {
+    #[cfg(target_os = "windows")]
+    const DISK_FULL: &[i32] = &[39, 112];
+    #[cfg(not(target_os = "windows"))]
+    const DISK_FULL: &[i32] = &[28];
+    error.raw_os_error().is_some_and(|code| DISK_FULL.contains(&code))
+}
+
+/// Error returned by every command and recorded on failed tasks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectorError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub retryable: bool,
+    /// File the error happened on, when it concerns one file of a task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    /// Delay a `RateLimited` answer asked for in its Retry-After header
+    #[serde(skip)]
+    pub retry_after: Option<Duration>,
+}
+
+impl CollectorError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> CollectorError {
+        CollectorError {
+            kind,
+            message: message.into(),
+            retryable: kind.is_retryable(),
+            file: None,
+            task_id: None,
+            retry_after: None,
+        }
+    }
+
+    pub fn rate_limited(message: impl Into<String>, retry_after: Option<Duration>) -> CollectorError {
+        CollectorError {
+            retry_after,
+            ..CollectorError::new(ErrorKind::RateLimited, message)
+        }
+    }
+
+    pub fn for_task(mut self, task_id: &str) -> CollectorError {
+        self.task_id = Some(task_id.to_string());
+        self
+    }
+
+    pub fn in_file(mut self, file: Option<String>) -> CollectorError {
+        self.file = file;
+        self
+    }
+
+    /// Same kind, with the message put into context
+    pub fn context(mut self, context: impl fmt::Display) -> CollectorError {
+        self.message = format!("{}: {}", context, self.message);
+        self
+    }
+
+    pub fn from_request(context: impl fmt::Display, error: &reqwest::Error) -> CollectorError {
+        CollectorError::new(ErrorKind::of_request(error), format!("{}: {}", context, error))
+    }
+
+    pub fn from_io(context: impl fmt::Display, error: &std::io::Error) -> CollectorError {
+        CollectorError::new(ErrorKind::of_io(error), format!("{}: {}", context, error))
+    }
+}
+
+impl fmt::Display for CollectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Errors still reported as messages; where they happen does not say what went wrong
+impl From<String> for CollectorError {
+    fn from(message: String) -> CollectorError {
+        CollectorError::new(ErrorKind::Other, message)
+    }
+}
+
+impl From<&str> for CollectorError {
+    fn from(message: &str) -> CollectorError {
+        CollectorError::from(message.to_string())
+    }
+}
+
+/// For the callers that still report errors as messages
+impl From<CollectorError> for String {
+    fn from(error: CollectorError) -> String {
+        error.message
+    }
+}
+
+impl From<InsufficientSpace> for CollectorError {
+    fn from(error: InsufficientSpace) -> CollectorError {
+        CollectorError::new(ErrorKind::DiskFull, error.to_string())
+    }
+}
+
+impl From<QuotaExceeded> for CollectorError {
+    fn from(error: QuotaExceeded) -> CollectorError {
+        CollectorError::new(ErrorKind::QuotaExceeded, error.to_string())
+    }
+}
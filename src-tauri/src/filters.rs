@@ -0,0 +1,160 @@
+use regex::Regex;
+
+use crate::lanes;
+
+/// Include/exclude selection applied to a dataset listing before transfer.
+///
+/// Task payload fields (all optional, relative to the dataset root):
+/// - `includePatterns`: glob patterns, a file must match at least one
+/// - `excludePatterns`: glob patterns, a file matching any of them is dropped
+/// - `subjects` / `sessions` / `modalities`: shorthands such as `"01"`, `"ses-pre"`, `"anat"`
+/// - `metadataOnly`: only JSON/TSV sidecars, `.bval`/`.bvec` and README-like files, to inspect
+///   a dataset's structure and acquisition parameters before collecting the imaging data
+///
+/// Patterns without a `/` match the file name anywhere in the tree (`*_T1w.nii.gz`),
+/// patterns with a `/` match the whole relative path (`sub-01/**`).
+/// Top-level files (`dataset_description.json`, `participants.tsv`, ...) are kept unless
+/// explicitly excluded so the selection remains a valid BIDS dataset.
+#[derive(Debug, Default)]
+pub struct FileFilter {
+    /// Every group must be satisfied by at least one of its patterns
+    include_groups: Vec<Vec<Regex>>,
+    exclude: Vec<Regex>,
+    metadata_only: bool,
+}
+
+impl FileFilter {
+    pub fn from_task(task: &serde_json::Value) -> Result<FileFilter, String> {
+        let mut filter = FileFilter::default();
+
+        let include = string_list(task, "includePatterns")?;
+        if !include.is_empty() {
+            filter.include_groups.push(compile_all(&include)?);
+        }
+
+        let subjects: Vec<String> = string_list(task, "subjects")?
+            .iter()
+            .map(|s| format!("sub-{}/**", s.trim_start_matches("sub-")))
+            .collect();
+        if !subjects.is_empty() {
+            filter.include_groups.push(compile_all(&subjects)?);
+        }
+
+        let sessions: Vec<String> = string_list(task, "sessions")?
+            .iter()
+            .map(|s| format!("**/ses-{}/**", s.trim_start_matches("ses-")))
+            .collect();
+        if !sessions.is_empty() {
+            filter.include_groups.push(compile_all(&sessions)?);
+        }
+
+        let modalities: Vec<String> = string_list(task, "modalities")?
+            .iter()
+            .map(|m| format!("**/{}/**", m))
+            .collect();
+        if !modalities.is_empty() {
+            filter.include_groups.push(compile_all(&modalities)?);
+        }
+
+        filter.exclude = compile_all(&string_list(task, "excludePatterns")?)?;
+        filter.metadata_only = match task.get("metadataOnly") {
+            None | Some(serde_json::Value::Null) => false,
+            Some(value) => value.as_bool().ok_or("Invalid metadataOnly: expected a boolean")?,
+        };
+
+        Ok(filter)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include_groups.is_empty() && self.exclude.is_empty() && !self.metadata_only
+    }
+
+    /// Check a path relative to the dataset root against the selection
+    pub fn matches(&self, relative_path: &str) -> bool {
+        if self.exclude.iter().any(|re| re.is_match(relative_path)) {
+            return false;
+        }
+        if self.metadata_only && !lanes::is_metadata(relative_path) {
+            return false;
+        }
+
+        if !relative_path.contains('/') {
+            return true;
+        }
+
+        self.include_groups
+            .iter()
+            .all(|group| group.iter().any(|re| re.is_match(relative_path)))
+    }
+}
+
+/// Task fields that make up a selection
+pub const SELECTION_FIELDS: [&str; 7] = ["includePatterns", "excludePatterns", "subjects", "sessions", "modalities", "metadataOnly", "derivatives"];
+
+/// The selection fields present on a task, so the same selection can be replayed later
+pub fn selection(task: &serde_json::Value) -> serde_json::Value {
+    let fields: serde_json::Map<String, serde_json::Value> = SELECTION_FIELDS
+        .iter()
+        .filter_map(|field| task.get(*field).map(|value| (field.to_string(), value.clone())))
+        .collect();
+    serde_json::Value::Object(fields)
+}
+
+fn string_list(task: &serde_json::Value, field: &str) -> Result<Vec<String>, String> {
+    match task.get(field) {
+        None | Some(serde_json::Value::Null) => Ok(Vec::new()),
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map(|s| s.trim().to_string())
+                    .ok_or_else(|| format!("Invalid entry in {}: expected a string", field))
+            })
+            .filter(|item| item.as_ref().map(|s| !s.is_empty()).unwrap_or(true))
+            .collect(),
+        Some(_) => Err(format!("Invalid {}: expected an array of strings", field)),
+    }
+}
+
+fn compile_all(patterns: &[String]) -> Result<Vec<Regex>, String> {
+    patterns.iter().map(|p| glob_to_regex(p)).collect()
+}
+
+/// Translate a glob (`*`, `**`, `?`) into an anchored regex over relative paths
+fn glob_to_regex(pattern: &str) -> Result<Regex, String> {
+    let mut glob = pattern.trim_start_matches("./").to_string();
+    if glob.ends_with('/') {
+        glob.push_str("**");
+    }
+
+    // Name-only patterns may match at any depth
+    if !glob.contains('/') {
+        glob = format!("**/{}", glob);
+    }
+
+    let mut regex = String::from("^");
+    let chars: Vec<char> = glob.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    regex.push('$');
+
+    Regex::new(&regex).map_err(|e| format!("Invalid filter pattern '{}': {}", pattern, e))
+}
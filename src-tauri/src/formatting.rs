@@ -0,0 +1,231 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{OnceLock, RwLock};
+
+use crate::error::CollectorError;
+
+/// How sizes, numbers, durations and dates are written in reports, notifications and exports.
+/// Machine-readable fields (JSON sizes, RFC 3339 timestamps) are never localized.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleFormat {
+    /// BCP 47 tag of the preset, or "custom" for user-edited values
+    pub locale: String,
+    pub decimal_separator: String,
+    /// Inserted between groups of three digits; empty to disable grouping
+    pub grouping_separator: String,
+    /// Separator between a number and its unit
+    pub unit_separator: String,
+    /// KiB/MiB (1024) instead of kB/MB (1000)
+    pub binary_units: bool,
+    /// chrono format string for dates with time, e.g. "%Y-%m-%d %H:%M"
+    pub datetime_format: String,
+}
+
+static CURRENT: OnceLock<RwLock<LocaleFormat>> = OnceLock::new();
+
+fn configured() -> &'static RwLock<LocaleFormat> {
+    CURRENT.get_or_init(|| RwLock::new(LocaleFormat::default()))
+}
+
+impl LocaleFormat {
+    fn preset(locale: &str, decimal: &str, grouping: &str, unit: &str, datetime_format: &str) -> LocaleFormat {
+        LocaleFormat {
+            locale: locale.to_string(),
+            decimal_separator: decimal.to_string(),
+            grouping_separator: grouping.to_string(),
+            unit_separator: unit.to_string(),
+            binary_units: false,
+            datetime_format: datetime_format.to_string(),
+        }
+    }
+
+    pub fn presets() -> Vec<LocaleFormat> {
+        vec![
+            LocaleFormat::preset("en-US", ".", ",", " ", "%m/%d/%Y %I:%M %p"),
+            LocaleFormat::preset("en-GB", ".", ",", " ", "%d/%m/%Y %H:%M"),
+            LocaleFormat::preset("de-DE", ",", ".", " ", "%d.%m.%Y %H:%M"),
+            LocaleFormat::preset("fr-FR", ",", "\u{202f}", "\u{a0}", "%d/%m/%Y %H:%M"),
+            LocaleFormat::preset("es-ES", ",", ".", " ", "%d/%m/%Y %H:%M"),
+            LocaleFormat::preset("it-IT", ",", ".", " ", "%d/%m/%Y %H:%M"),
+            LocaleFormat::preset("nl-NL", ",", ".", " ", "%d-%m-%Y %H:%M"),
+            LocaleFormat::preset("ja-JP", ".", ",", " ", "%Y/%m/%d %H:%M"),
+            LocaleFormat::preset("zh-CN", ".", ",", " ", "%Y/%m/%d %H:%M"),
+        ]
+    }
+
+    /// Preset for a locale tag; falls back to the language alone ("de-AT" -> "de-DE")
+    pub fn for_locale(tag: &str) -> Option<LocaleFormat> {
+        let tag = tag.replace('_', "-");
+        let language = tag.split('-').next().unwrap_or_default().to_lowercase();
+        let presets = LocaleFormat::presets();
+
+        presets.iter()
+            .find(|p| p.locale.eq_ignore_ascii_case(&tag))
+            .or_else(|| presets.iter().find(|p| p.locale.split('-').next() == Some(language.as_str())))
+            .cloned()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.decimal_separator.is_empty() {
+            return Err("Decimal separator must not be empty".to_string());
+        }
+        if self.decimal_separator == self.grouping_separator {
+            return Err("Decimal and grouping separators must differ".to_string());
+        }
+        if self.datetime_format.is_empty() {
+            return Err("Date format must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// Integer with digit grouping, e.g. 1,234,567
+    pub fn count(&self, value: u64) -> String {
+        let digits = value.to_string();
+        if self.grouping_separator.is_empty() {
+            return digits;
+        }
+
+        let mut grouped = String::new();
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index) % 3 == 0 {
+                grouped.push_str(&self.grouping_separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+
+    /// Decimal number with `decimals` fraction digits
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        let formatted = format!("{:.*}", decimals, value.abs());
+        let (whole, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+        let sign = if value < 0.0 { "-" } else { "" };
+        let whole = self.count(whole.parse().unwrap_or(0));
+
+        if fraction.is_empty() {
+            format!("{}{}", sign, whole)
+        } else {
+            format!("{}{}{}{}", sign, whole, self.decimal_separator, fraction)
+        }
+    }
+
+    /// Byte count with the largest unit that keeps the value >= 1, e.g. 3.2 GB
+    pub fn bytes(&self, bytes: u64) -> String {
+        let (base, units): (f64, &[&str]) = if self.binary_units {
+            (1024.0, &["B", "KiB", "MiB", "GiB", "TiB", "PiB"])
+        } else {
+            (1000.0, &["B", "kB", "MB", "GB", "TB", "PB"])
+        };
+
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= base && unit < units.len() - 1 {
+            value /= base;
+            unit += 1;
+        }
+
+        if unit == 0 {
+            format!("{}{}{}", self.count(bytes), self.unit_separator, units[0])
+        } else {
+            format!("{}{}{}", self.number(value, 1), self.unit_separator, units[unit])
+        }
+    }
+
+    /// Transfer rate, e.g. 12.5 MB/s
+    pub fn rate(&self, bytes_per_sec: f64) -> String {
+        format!("{}/s", self.bytes(bytes_per_sec.max(0.0) as u64))
+    }
+
+    /// Duration as its two largest units, e.g. "2h 05m" or "4m 12s"
+    pub fn duration(&self, seconds: u64) -> String {
+        let (days, hours, minutes, secs) = (seconds / 86_400, seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+        if days > 0 {
+            format!("{}d {}h", self.count(days), hours)
+        } else if hours > 0 {
+            format!("{}h {:02}m", hours, minutes)
+        } else if minutes > 0 {
+            format!("{}m {:02}s", minutes, secs)
+        } else {
+            format!("{}s", secs)
+        }
+    }
+
+    /// Date and time in the local time zone
+    pub fn datetime(&self, timestamp: &DateTime<Utc>) -> String {
+        timestamp.with_timezone(&chrono::Local).format(&self.datetime_format).to_string()
+    }
+
+    /// Localized form of an RFC 3339 timestamp, or the input unchanged when it does not parse
+    pub fn timestamp(&self, rfc3339: &str) -> String {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .map(|t| self.datetime(&t.with_timezone(&Utc)))
+            .unwrap_or_else(|_| rfc3339.to_string())
+    }
+}
+
+impl Default for LocaleFormat {
+    fn default() -> Self {
+        LocaleFormat::for_locale("en-US").unwrap()
+    }
+}
+
+/// The format currently configured for the application
+pub fn current() -> LocaleFormat {
+    configured().read().unwrap().clone()
+}
+
+pub fn bytes(bytes: u64) -> String {
+    configured().read().unwrap().bytes(bytes)
+}
+
+pub fn timestamp(rfc3339: &str) -> String {
+    configured().read().unwrap().timestamp(rfc3339)
+}
+
+/// One-line report of a finished transfer, e.g. "1,204 files (3.2 GB) in 4m 12s"
+pub fn transfer_summary(files: usize, bytes: u64, started_at: Option<&str>) -> String {
+    let format = current();
+    let mut summary = format!("{} files ({})", format.count(files as u64), format.bytes(bytes));
+
+    let elapsed = started_at
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|start| (Utc::now() - start.with_timezone(&Utc)).num_seconds().max(0) as u64);
+    if let Some(seconds) = elapsed {
+        summary.push_str(&format!(" in {}", format.duration(seconds)));
+        if seconds > 0 {
+            summary.push_str(&format!(", {}", format.rate(bytes as f64 / seconds as f64)));
+        }
+    }
+    summary
+}
+
+#[tauri::command]
+pub async fn list_format_locales() -> Result<Vec<LocaleFormat>, CollectorError> {
+    Ok(LocaleFormat::presets())
+}
+
+#[tauri::command]
+pub async fn get_format_locale() -> Result<LocaleFormat, CollectorError> {
+    Ok(current())
+}
+
+/// Switch to the preset for a locale tag, or store custom values when `format` is given
+#[tauri::command]
+pub async fn set_format_locale(locale: Option<String>, format: Option<LocaleFormat>) -> Result<LocaleFormat, CollectorError> {
+    let new_format = match (locale, format) {
+        (_, Some(mut custom)) => {
+            custom.locale = "custom".to_string();
+            custom
+        }
+        (Some(tag), None) => LocaleFormat::for_locale(&tag)
+            .ok_or_else(|| format!("No formatting preset for locale: {}", tag))?,
+        (None, None) => return Err("Either a locale or custom format values are required".into()),
+    };
+
+    new_format.validate()?;
+    log::info!("Report formatting set to {:?}", new_format);
+
+    *configured().write().unwrap() = new_format.clone();
+    Ok(new_format)
+}
@@ -0,0 +1,52 @@
+use flate2::write::MultiGzDecoder;
+use std::io::{self, Write};
+
+/// Number of attempts for a file whose gzip stream fails validation
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// Whether a path is gzip-compressed (`.nii.gz`, `.tsv.gz`, ...)
+pub fn is_gzip(path: &str) -> bool {
+    path.to_lowercase().ends_with(".gz")
+}
+
+/// Validates a gzip stream chunk by chunk while it is downloaded.
+///
+/// The data is inflated into a sink, so memory use stays constant; each member's
+/// trailer CRC-32 and length are checked as soon as the member ends. A broken deflate
+/// stream fails at the chunk that contains it, a truncated file fails in `finish`.
+pub struct GzipValidator {
+    decoder: MultiGzDecoder<io::Sink>,
+}
+
+impl GzipValidator {
+    pub fn new() -> GzipValidator {
+        GzipValidator {
+            decoder: MultiGzDecoder::new(io::sink()),
+        }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) -> Result<(), String> {
+        self.decoder.write_all(chunk)
+            .map_err(|e| format!("Corrupt gzip data: {}", e))
+    }
+
+    /// Check that the last member is complete and its CRC matches
+    pub fn finish(self) -> Result<(), String> {
+        self.decoder.finish()
+            .map(|_| ())
+            .map_err(|e| format!("Truncated or corrupt gzip file: {}", e))
+    }
+}
+
+impl Default for GzipValidator {
+    fn default() -> Self {
+        GzipValidator::new()
+    }
+}
+
+/// Validate a gzip file that is fully in memory
+pub fn verify_bytes(content: &[u8]) -> Result<(), String> {
+    let mut validator = GzipValidator::new();
+    validator.update(content)?;
+    validator.finish()
+}
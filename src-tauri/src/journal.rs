@@ -0,0 +1,179 @@
+use serde_json::{json, Map, Value};
+
+use crate::catalog::{CatalogEntry, CatalogState};
+use crate::error::CollectorError;
+use crate::manifest::{self, Manifest};
+use crate::ro_crate;
+use crate::storage;
+
+/// Namespace for terms without a PROV equivalent
+const NAMESPACE: &str = "https://github.com/kevinpan45/bids-collector-desktop/ns#";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalFormat {
+    /// W3C PROV-JSON document
+    ProvJson,
+    /// RO-Crate 1.1 `ro-crate-metadata.json`
+    RoCrate,
+}
+
+impl JournalFormat {
+    pub fn parse(format: &str) -> Result<JournalFormat, String> {
+        match format.to_lowercase().as_str() {
+            "prov-json" | "prov" => Ok(JournalFormat::ProvJson),
+            "ro-crate" | "rocrate" => Ok(JournalFormat::RoCrate),
+            other => Err(format!("Unknown journal format: {} (expected prov-json or ro-crate)", other)),
+        }
+    }
+}
+
+fn software_version() -> String {
+    format!("bids-collector {}", env!("CARGO_PKG_VERSION"))
+}
+
+/// PROV-JSON document: one transfer activity per file that used the source URL
+/// and generated the collected file
+pub fn to_prov_json(entry: &CatalogEntry, manifest: &Manifest) -> Value {
+    let dataset_id = format!("bidsc:dataset-{}", entry.id);
+    let agent_id = "bidsc:bids-collector";
+
+    let mut entities = Map::new();
+    let mut activities = Map::new();
+    let mut used = Map::new();
+    let mut generated = Map::new();
+    let mut derived = Map::new();
+    let mut associated = Map::new();
+    let mut members = Map::new();
+
+    let mut dataset = json!({
+        "prov:type": "prov:Collection",
+        "prov:label": entry.identifier,
+        "prov:location": entry.location,
+        "bidsc:provider": entry.provider,
+        "bidsc:checksumRoot": entry.checksum_root,
+    });
+    if let Some(version) = &entry.version {
+        dataset["bidsc:version"] = json!(version);
+    }
+    if let Some(doi) = &entry.doi {
+        dataset["bidsc:doi"] = json!(doi);
+    }
+    entities.insert(dataset_id.clone(), dataset);
+
+    for (index, file) in manifest.files.iter().enumerate() {
+        let file_id = format!("bidsc:file-{}", index);
+        entities.insert(file_id.clone(), json!({
+            "prov:label": file.path,
+            "bidsc:size": { "$": file.size, "type": "xsd:long" },
+            "bidsc:sha256": file.sha256,
+        }));
+        members.insert(format!("_:m{}", index), json!({
+            "prov:collection": dataset_id,
+            "prov:entity": file_id,
+        }));
+
+        // Files without a source (e.g. extracted from an archive) have no transfer of their own
+        let Some(source_url) = &file.source_url else {
+            continue;
+        };
+
+        let source_id = format!("bidsc:source-{}", index);
+        let mut source = json!({ "prov:location": source_url });
+        if let Some(etag) = &file.etag {
+            source["bidsc:etag"] = json!(etag);
+        }
+        entities.insert(source_id.clone(), source);
+
+        let transfer_id = format!("bidsc:transfer-{}", index);
+        let mut transfer = json!({ "prov:type": "bidsc:Transfer" });
+        if let Some(started_at) = &file.started_at {
+            transfer["prov:startTime"] = json!(started_at);
+        }
+        if let Some(finished_at) = &file.finished_at {
+            transfer["prov:endTime"] = json!(finished_at);
+        }
+        if let Some(attempts) = file.attempts {
+            transfer["bidsc:attempts"] = json!({ "$": attempts, "type": "xsd:int" });
+        }
+        activities.insert(transfer_id.clone(), transfer);
+
+        used.insert(format!("_:u{}", index), json!({
+            "prov:activity": transfer_id,
+            "prov:entity": source_id,
+        }));
+        let mut generation = json!({
+            "prov:entity": file_id,
+            "prov:activity": transfer_id,
+        });
+        if let Some(finished_at) = &file.finished_at {
+            generation["prov:time"] = json!(finished_at);
+        }
+        generated.insert(format!("_:g{}", index), generation);
+        derived.insert(format!("_:d{}", index), json!({
+            "prov:generatedEntity": file_id,
+            "prov:usedEntity": source_id,
+            "prov:activity": transfer_id,
+        }));
+        associated.insert(format!("_:a{}", index), json!({
+            "prov:activity": transfer_id,
+            "prov:agent": agent_id,
+        }));
+    }
+
+    json!({
+        "prefix": {
+            "bidsc": NAMESPACE,
+            "xsd": "http://www.w3.org/2001/XMLSchema#",
+        },
+        "entity": entities,
+        "activity": activities,
+        "agent": {
+            agent_id: {
+                "prov:type": "prov:SoftwareAgent",
+                "prov:label": software_version(),
+            }
+        },
+        "used": used,
+        "wasGeneratedBy": generated,
+        "wasDerivedFrom": derived,
+        "wasAssociatedWith": associated,
+        "hadMember": members,
+    })
+}
+
+/// Manifest of a cataloged collection, read from local disk or the remote destination
+pub async fn load_manifest(entry: &CatalogEntry, storage_location: Option<&Value>) -> Result<Manifest, String> {
+    if entry.storage_type == "local" {
+        return manifest::read_local(&entry.location).await
+            .ok_or_else(|| format!("No manifest found in {}", entry.location));
+    }
+
+    let location = storage_location
+        .ok_or_else(|| format!("The storage location is needed to read a manifest from {} storage", entry.storage_type))?;
+    let storage = storage::from_storage_location(location)?;
+    let key = format!("{}/{}", entry.identifier, manifest::MANIFEST_PATH);
+    let content = storage.get(&key).await?
+        .ok_or_else(|| format!("No manifest found at {}", storage.location(&key)))?;
+    Manifest::from_json(&content)
+}
+
+/// Export the per-file transfer records of a collection as PROV-JSON or RO-Crate metadata
+#[tauri::command]
+pub async fn export_transfer_journal(
+    dataset_id: String,
+    format: String,
+    storage_location: Option<Value>,
+    catalog: tauri::State<'_, CatalogState>,
+) -> Result<Value, CollectorError> {
+    let format = JournalFormat::parse(&format)?;
+    let entry = catalog.lock().unwrap().get(&dataset_id)?
+        .ok_or_else(|| format!("Dataset {} is not in the catalog", dataset_id))?;
+
+    let manifest = load_manifest(&entry, storage_location.as_ref()).await?;
+    log::info!("Exporting transfer journal of {} ({} files) as {:?}", dataset_id, manifest.files.len(), format);
+
+    Ok(match format {
+        JournalFormat::ProvJson => to_prov_json(&entry, &manifest),
+        JournalFormat::RoCrate => ro_crate::metadata(&entry, &manifest, None),
+    })
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+use crate::providers::RemoteFile;
+
+/// Files at or below this size that carry BIDS metadata go through the metadata lane
+const METADATA_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Extensions that make a dataset browsable/validatable before the imaging data lands
+const METADATA_EXTENSIONS: &[&str] = &[".json", ".tsv", ".bval", ".bvec"];
+
+/// Top-level files without an extension that BIDS treats as metadata
+const METADATA_FILENAMES: &[&str] = &["README", "CHANGES", "LICENSE"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferLane {
+    /// Small JSON/TSV sidecars, transferred first
+    Metadata,
+    /// Imaging data and everything else
+    Bulk,
+}
+
+/// Whether a file carries BIDS metadata by its name alone: sidecars, tables and gradient files
+pub fn is_metadata(key: &str) -> bool {
+    let file_name = key.rsplit('/').next().unwrap_or(key);
+    let lower = file_name.to_lowercase();
+
+    METADATA_EXTENSIONS.iter().any(|ext| lower.ends_with(ext))
+        || METADATA_FILENAMES.iter().any(|name| file_name.eq_ignore_ascii_case(name))
+}
+
+/// Whether a file is a processed output under the dataset's `derivatives/` folder
+pub fn is_derivative(key: &str) -> bool {
+    key.starts_with("derivatives/")
+}
+
+/// Decide which lane a file belongs to based on its key and size
+pub fn classify(key: &str, size: u64) -> TransferLane {
+    if size <= METADATA_MAX_SIZE && is_metadata(key) {
+        TransferLane::Metadata
+    } else {
+        TransferLane::Bulk
+    }
+}
+
+/// Reorder a listing so the metadata lane runs before the bulk lane.
+/// Returns the number of files in the metadata lane (they form the head of the list).
+pub fn order_by_lane(files: &mut [RemoteFile]) -> usize {
+    // Stable sort keeps the listing order within each lane
+    files.sort_by_key(|f| match classify(&f.path, f.size) {
+        TransferLane::Metadata => 0,
+        TransferLane::Bulk => 1,
+    });
+
+    files
+        .iter()
+        .take_while(|f| classify(&f.path, f.size) == TransferLane::Metadata)
+        .count()
+}
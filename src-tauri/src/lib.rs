@@ -1,70 +1,215 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use regex::Regex;
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
+use tauri_plugin_log::TargetKind;
 
+mod anonymize;
+mod archive;
+mod automation;
+mod aws_profile;
+mod bids_validator;
+mod bundle;
+mod catalog;
+mod catalog_backup;
+mod checksum;
+mod cli;
+mod constraints;
+mod content_type;
+mod credentials;
+mod daemon;
+mod datalad;
+mod dataset_delete;
+mod dataset_export;
+mod dedup;
+mod diagnostics;
+mod disk_space;
+mod environment;
+mod error;
+mod download_window;
+mod filters;
+mod formatting;
+mod gzip;
+mod journal;
+mod lanes;
+mod logging;
+mod network;
+mod library;
+mod manifest;
+mod notifications;
+mod os_progress;
+mod path_guard;
+mod performance;
+mod post_process;
+mod preview;
+mod provenance;
+mod providers;
+mod quota;
+mod rate_limit;
+mod range_support;
+mod recovery;
+mod restore;
+mod ro_crate;
 mod s3_client;
-use s3_client::test_s3_connection;
+mod safe_path;
+mod scheduler;
+mod scheduler_state;
+mod settings;
+mod skip_log;
+mod staging;
+mod statistics;
+mod storage;
+mod sync;
+mod task_queue;
+mod transfer;
+mod transfer_rate;
+mod tray;
+mod tuning;
+mod upload_journal;
+mod verify;
+mod watch_folders;
+mod work_queue;
+use s3_client::{generate_presigned_url, test_s3_connection, S3ConnectionConfig};
+use storage::RemoteStorage;
+use storage::azure::test_azure_connection;
+use storage::gcs::test_gcs_connection;
+use storage::sftp::test_sftp_connection;
+use storage::webdav::test_webdav_connection;
+use anonymize::{Anonymizer, AuditLog};
+use automation::{get_automation_settings, regenerate_automation_token, update_automation_settings, AutomationServer, AutomationState};
+use filters::FileFilter;
+use network::{get_network_settings, update_network_settings, Network, NetworkState};
+use notifications::{clear_notification_badge, get_notification_settings, send_test_notification, update_notification_settings, Dispatcher, Notification, NotificationKind, NotificationState};
+use constraints::TaskConstraints;
+use environment::{get_environment, Environment, EnvironmentState};
+use error::{CollectorError, ErrorKind};
+use daemon::{get_daemon_status, Engine, EngineState, Mode};
+use datalad::DataladExport;
+use dataset_delete::{delete_dataset, plan_dataset_deletion};
+use dataset_export::{cancel_dataset_archive_export, export_dataset_archive, ExportState};
+use dedup::{find_duplicate_data, DedupStats, Deduplicator};
+use diagnostics::export_diagnostics_bundle;
+use download_window::{get_download_window, update_download_window, WindowSchedule, WindowState};
+use path_guard::{add_destination_root, list_destination_roots, remove_destination_root};
+use performance::{get_performance_mode, set_performance_mode, PerformanceMode, PerformanceState};
+use provenance::Provenance;
+use providers::{list_supported_providers, DatasetProvider, RemoteFile};
+use providers::openneuro_api::get_dataset_metadata;
+use providers::signed_urls::SignedUrl;
+use preview::preview_dataset;
+use quota::{get_storage_quota_usage, StorageQuota};
+use manifest::{Manifest, ManifestEntry};
+use catalog::{CatalogEntry, CatalogState};
+use catalog_backup::{
+    create_catalog_backup, get_catalog_backup_settings, list_catalog_backups, restore_catalog_backup,
+    update_catalog_backup_settings, BackupManager, CatalogBackupState,
+};
+use bundle::{export_collection_bundle, import_collection_bundle};
+use bids_validator::validate_dataset;
+use checksum::{ChecksumAlgorithm, ChecksumHasher};
+use credentials::{delete_storage_location, get_storage_locations, save_storage_credentials};
+use formatting::{get_format_locale, list_format_locales, set_format_locale};
+use gzip::GzipValidator;
+use journal::export_transfer_journal;
+use library::{list_local_datasets, rescan_library};
+use ro_crate::export_ro_crate;
+use skip_log::{SkipLog, SkipVerification, VerifyPolicy};
+use statistics::{get_transfer_statistics, TaskOrigin};
+use sync::SyncDecision;
+use task_queue::{
+    get_download_queue, pause_all_tasks, promote_queued_task, reorder_queued_tasks, resume_all_tasks,
+    set_max_concurrent_tasks, QueuePriority, TaskQueue, TaskQueueState,
+};
+use scheduler::{Scheduler, SchedulerState, TaskPriority, WaitKind, WaitReason};
+use scheduler_state::get_scheduler_state;
+use settings::{get_settings, update_settings, RetryPolicy, Settings, SettingsState};
+use staging::{discard_staged_task, get_staged_task, list_staged_tasks, StagedTask, StagedTasks, StagingState};
+use rate_limit::{get_provider_rate_limits, set_provider_rate_limits, RateLimitState, RateLimiter};
+use range_support::{clear_range_quirks, list_range_quirks, RangeQuirkState, RangeQuirks, ResumeError};
+use recovery::{dismiss_interrupted_task, InterruptedTask, PersistedTask, RecoveryState};
+use restore::start_restore_task;
+use transfer::transfer_dataset;
+use transfer_rate::TransferRate;
+use tuning::{get_transfer_tuning, list_network_profiles, set_transfer_tuning, TransferTuning, TuningState};
+use upload_journal::UploadJournal;
+use verify::verify_dataset;
+use work_queue::{deprioritize_files, get_task_remaining, skip_files, FileState, QueueState};
 
-/// Extract OpenNeuro accession number from DOI or path
-/// Example: "10.18112_openneuro.ds006486.v1.0.0" -> "ds006486"
-fn extract_openneuro_accession(path: &str) -> String {
-    // If path already looks like an accession (ds followed by numbers), return as-is
-    if let Some(re) = Regex::new(r"^ds\d+$").ok() {
-        if re.is_match(path) {
-            return path.to_lowercase();
-        }
-    }
-    
-    // Extract accession from DOI-like path (e.g., "10.18112_openneuro.ds006486.v1.0.0" -> "ds006486")
-    if let Some(re) = Regex::new(r"ds(\d+)").ok() {
-        if let Some(captures) = re.captures(path) {
-            if let Some(number) = captures.get(1) {
-                return format!("ds{}", number.as_str());
-            }
-        }
-    }
-    
-    // If no accession found, return the original path
-    path.to_string()
-}
-
-async fn download_openneuro_dataset(
-    accession: &str,
+/// Download a provider's file listing into a local directory
+async fn download_files_to_local(
+    file_list: Vec<RemoteFile>,
     dest_dir: &str,
+    options: &DownloadOptions,
     task_id: &str,
     state: &DownloadState,
     app_handle: &tauri::AppHandle,
-) -> Result<(), String> {
-    println!("Starting complete dataset download for accession: {}", accession);
-    
-    // First, list all files in the dataset by requesting the S3 bucket listing
-    let list_url = format!("https://s3.amazonaws.com/openneuro.org?list-type=2&prefix={}/", accession);
-    println!("Listing files from: {}", list_url);
-    
-    let client = reqwest::Client::new();
-    let list_response = client.get(&list_url).send().await
-        .map_err(|e| format!("Failed to list dataset files: {}", e))?;
-    
-    if !list_response.status().is_success() {
-        return Err(format!("Failed to list files: HTTP {}", list_response.status()));
+) -> Result<Manifest, CollectorError> {
+    log::info!("Starting complete dataset download into: {}", dest_dir);
+    log::info!("Found {} files to download", file_list.len());
+    
+    // Every listed path, so rename detection never moves a file the filter merely excludes
+    let listed_paths: HashSet<String> = file_list.iter().map(|f| f.path.clone()).collect();
+    let mut file_list = apply_file_filter(file_list, &options.filter, task_id, state, app_handle)?;
+    
+    // Metadata lane first so the dataset is browsable before the imaging data arrives
+    let metadata_count = lanes::order_by_lane(&mut file_list);
+    log::info!("Metadata lane: {} files, bulk lane: {} files", metadata_count, file_list.len() - metadata_count);
+    
+    // Providers such as Zenodo often ship the whole dataset as one tarball or zip
+    let extract_archives = options.extract_archives
+        .unwrap_or_else(|| archive::is_archived_dataset(file_list.iter().map(|f| f.path.as_str())));
+    if extract_archives && options.extract_archives.is_none() {
+        log::info!("Dataset is distributed as archives; they will be unpacked into {}", dest_dir);
     }
     
-    let xml_content = list_response.text().await
-        .map_err(|e| format!("Failed to read listing response: {}", e))?;
-    
-    // Parse XML to extract file keys and sizes
-    let file_list = parse_s3_listing(&xml_content)?;
-    
-    if file_list.is_empty() {
-        return Err(format!("No files found for dataset: {}", accession));
+    // Calculate total size
+    let total_size: u64 = file_list.iter().map(|f| f.size).sum();
+    log::info!("Total dataset size: {} bytes", total_size);
+    
+    // Files already at the destination and unchanged at the provider are candidates for skipping
+    let mut previous_manifest = if options.skip_existing { manifest::read_local(dest_dir).await } else { None };
+    let mut existing = Vec::new();
+    let mut renamed = Vec::new();
+    if options.skip_existing {
+        let mut missing = Vec::new();
+        let previous_entries = previous_manifest.as_ref().map(|m| m.by_path()).unwrap_or_default();
+        for file_info in &file_list {
+            let path = safe_path::join(dest_dir, &file_info.path)?;
+            let existing_size = fs::metadata(&path).await.ok().filter(|m| m.is_file()).map(|m| m.len());
+            match sync::compare(file_info, existing_size, previous_entries.get(file_info.path.as_str()).copied()) {
+                SyncDecision::Unchanged => existing.push(file_info),
+                SyncDecision::Changed(reason) => log::info!("{} changed ({}), transferring again", file_info.path, reason),
+                SyncDecision::Missing => missing.push(file_info),
+            }
+        }
+        
+        // Files the provider moved to a new path are renamed locally instead of downloaded again
+        if let Some(previous) = previous_manifest.as_mut() {
+            let renames = sync::detect_renames(&missing, &listed_paths, previous);
+            renamed = sync::apply_renames(dest_dir, renames, previous).await;
+            let renamed_paths: HashSet<&str> = renamed.iter().map(|r| r.to.as_str()).collect();
+            existing.extend(missing.iter().filter(|f| renamed_paths.contains(f.path.as_str())));
+            record_renamed_files(task_id, renamed.len(), state);
+        }
+        log::info!("{} of {} files are already up to date at the destination", existing.len(), file_list.len());
     }
+    let existing_size: u64 = existing.iter().map(|f| f.size).sum();
     
-    println!("Found {} files to download", file_list.len());
+    // Fail fast instead of filling the disk halfway through the dataset
+    disk_space::check_available_space(dest_dir, total_size - existing_size)?;
+    if let Some(quota) = &options.quota {
+        quota::enforce(quota, total_size - existing_size).await?;
+    }
     
-    // Calculate total size
-    let total_size: u64 = file_list.iter().map(|f| f.size).sum();
-    println!("Total dataset size: {} bytes", total_size);
+    let existing_paths: Vec<&str> = existing.iter().map(|f| f.path.as_str()).collect();
+    let verify_paths = options.verify_skipped.select(&existing_paths, task_id);
+    // Looked up once per file, so both are indexed by path
+    let existing_paths: HashSet<&str> = existing_paths.into_iter().collect();
+    let previous_entries = previous_manifest.as_ref().map(|m| m.by_path()).unwrap_or_default();
+    let mut skip_log = SkipLog::new(task_id, &options.verify_skipped);
+    for rename in &renamed {
+        skip_log.record_renamed(&rename.from, &rename.to, rename.size);
+    }
     
     // Update task with total size
     {
@@ -75,38 +220,152 @@ async fn download_openneuro_dataset(
     }
     
     let mut downloaded_bytes = 0u64;
+    let mut transferred_files = 0u32;
+    let mut rate = TransferRate::new();
+    let mut metadata_ready = false;
+    let mut manifest = Manifest::new(dest_dir);
+    let mut audit_log = AuditLog::new(task_id);
+    let mut deduplicator = options.dedup.then(|| Deduplicator::new(app_handle.state::<CatalogState>().inner().clone()));
+    
+    // Files are taken from the task's queue, which the user can reorder while the task runs
+    let queues = app_handle.state::<QueueState>().inner().clone();
+    work_queue::register(&queues, task_id, &file_list);
     
     // Download each file
-    for (index, file_info) in file_list.iter().enumerate() {
-        println!("Downloading file {}/{}: {}", index + 1, file_list.len(), file_info.key);
+    let mut position = 0;
+    while let Some(next) = work_queue::next(&queues, task_id) {
+        if is_cancelled(task_id, state) {
+            return Err(CollectorError::new(ErrorKind::Cancelled, "Download cancelled"));
+        }
+        wait_until_allowed(task_id, &options.constraints, state, app_handle).await?;
+        
+        let index = next.index;
+        let file_info = &file_list[index];
+        position += 1;
+        log::info!("Downloading file {}/{}: {}", position, file_list.len(), file_info.path);
         
         // Update current file
         {
             let mut downloads = state.lock().unwrap();
             if let Some(progress) = downloads.get_mut(task_id) {
-                progress.current_file = Some(file_info.key.clone());
+                progress.current_file = Some(file_info.path.clone());
             }
         }
         
-        // Build file URL and destination path
-        let file_url = format!("https://s3.amazonaws.com/openneuro.org/{}", file_info.key);
+        let relative_path = &file_info.path;
+        let dest_file_path = safe_path::join(dest_dir, relative_path)?;
+        
+        if existing_paths.contains(relative_path.as_str()) {
+            let (sha256, verification) = check_existing_file(
+                file_info,
+                &dest_file_path,
+                previous_entries.get(relative_path.as_str()).copied(),
+                verify_paths.contains(relative_path),
+                options.tuning.chunk_size,
+            ).await?;
+            skip_log.record(relative_path, file_info.size, verification);
+            
+            if verification == SkipVerification::Mismatch {
+                log::info!("{} does not match its recorded hash, downloading again", relative_path);
+            } else {
+                log::info!("Skipping existing file {} ({:?})", relative_path, verification);
+                downloaded_bytes += file_info.size;
+                manifest.add_remote(file_info, file_info.size, &sha256);
+                if let Some(deduplicator) = &deduplicator {
+                    deduplicator.remember_existing(&dest_file_path, &sha256, file_info.size);
+                }
+                
+                {
+                    let mut downloads = state.lock().unwrap();
+                    if let Some(progress) = downloads.get_mut(task_id) {
+                        progress.skipped_files += 1;
+                        progress.downloaded_size = downloaded_bytes;
+                        progress.update_rate(&rate);
+                    }
+                }
+                
+                work_queue::finish(&queues, task_id, index, FileState::Skipped);
+                if !metadata_ready && work_queue::lane_settled(&queues, task_id, metadata_count) {
+                    metadata_ready = true;
+                    mark_metadata_ready(task_id, metadata_count, state, app_handle);
+                }
+                continue;
+            }
+        }
         
-        // Remove the accession prefix from the key to get the relative path
-        let relative_path = file_info.key.strip_prefix(&format!("{}/", accession))
-            .unwrap_or(&file_info.key);
-        let dest_file_path = format!("{}/{}", dest_dir, relative_path);
+        path_guard::check(&dest_file_path)
+            .map_err(|e| CollectorError::new(ErrorKind::PermissionDenied, e))?;
         
         // Create directory for nested files
         if let Some(parent_dir) = std::path::Path::new(&dest_file_path).parent() {
             if let Err(e) = fs::create_dir_all(parent_dir).await {
-                return Err(format!("Failed to create directory {}: {}", parent_dir.display(), e));
+                return Err(CollectorError::from_io(format!("Failed to create directory {}", parent_dir.display()), &e));
             }
         }
         
+        // Content already collected into another dataset is linked instead of downloaded
+        if let Some(deduplicator) = deduplicator.as_mut() {
+            if let Some(sha256) = deduplicator.link_published(file_info, &dest_file_path) {
+                downloaded_bytes += file_info.size;
+                manifest.add_remote(file_info, file_info.size, &sha256);
+                record_deduplicated(task_id, deduplicator.stats, downloaded_bytes, state);
+                
+                work_queue::finish(&queues, task_id, index, FileState::Done);
+                if !metadata_ready && work_queue::lane_settled(&queues, task_id, metadata_count) {
+                    metadata_ready = true;
+                    mark_metadata_ready(task_id, metadata_count, state, app_handle);
+                }
+                continue;
+            }
+        }
+        
+        // A signed URL that lapsed while the file waited for its turn cannot be fetched any more
+        if options.signed_urls.is_some() && providers::signed_urls::is_expired(&file_info.url) {
+            record_expired_url(task_id, file_info, state);
+            skip_log.record_expired(relative_path, file_info.size);
+            work_queue::finish(&queues, task_id, index, FileState::Skipped);
+            if !metadata_ready && work_queue::lane_settled(&queues, task_id, metadata_count) {
+                metadata_ready = true;
+                mark_metadata_ready(task_id, metadata_count, state, app_handle);
+            }
+            continue;
+        }
+        
+        // Background tasks wait here for a slot while a foreground task is running
+        let _slot = options.scheduler.acquire_slot(task_id, options.priority).await;
+        
         // Download the file
-        match download_single_file(&file_url, &dest_file_path).await {
-            Ok(file_size) => {
+        let started_at = chrono::Utc::now().to_rfc3339();
+        let mut report = file_progress_reporter(task_id, file_info, downloaded_bytes, state, app_handle);
+        match download_single_file(file_info, &dest_file_path, options, &next.dropped, &mut report).await {
+            Ok(Some((file_size, sha256, attempts))) => {
                 downloaded_bytes += file_size;
+                transferred_files += 1;
+                rate.record(file_size);
+                
+                // Identifying fields go before the file is recorded, so the manifest matches the disk
+                let (mut stored_size, mut sha256) = (file_size, sha256);
+                if let Some(anonymizer) = &options.anonymizer {
+                    let outcome = anonymizer.scrub_file(relative_path, &dest_file_path).await;
+                    if matches!(outcome, Ok(Some(_))) {
+                        (stored_size, sha256) = manifest::hash_file(&dest_file_path, options.tuning.chunk_size).await?;
+                    }
+                    audit_log.record(relative_path, outcome);
+                }
+                manifest.add_remote(file_info, stored_size, &sha256);
+                manifest.record_transfer(relative_path, &started_at, attempts);
+                // Gzip streams were validated while they were written
+                let checks = post_process::run_on_disk(relative_path.clone(), std::path::PathBuf::from(&dest_file_path), true).await;
+                manifest.record_checks(relative_path, checks);
+                
+                if extract_archives && archive::ArchiveFormat::detect(relative_path).is_some() {
+                    let report = extraction_reporter(task_id, relative_path, state, app_handle);
+                    extract_downloaded_archive(&dest_file_path, dest_dir, relative_path, &file_info.url, options, &mut manifest, report).await?;
+                } else if let Some(deduplicator) = deduplicator.as_mut() {
+                    if deduplicator.link_downloaded(&dest_file_path, &sha256, stored_size) {
+                        record_deduplicated(task_id, deduplicator.stats, downloaded_bytes, state);
+                    }
+                }
                 
                 // Update progress
                 let progress_percent = if total_size > 0 {
@@ -120,29 +379,135 @@ async fn download_openneuro_dataset(
                     if let Some(progress) = downloads.get_mut(task_id) {
                         progress.progress = progress_percent;
                         progress.downloaded_size = downloaded_bytes;
+                        progress.completed_files = Some(transferred_files);
+                        progress.retries += attempts.saturating_sub(1);
+                        if lanes::is_derivative(relative_path) {
+                            progress.completed_derivative_files += 1;
+                        }
+                        progress.update_rate(&rate);
                     }
                 }
                 
-                println!("Downloaded {}: {} bytes ({}%)", relative_path, file_size, progress_percent);
+                let _ = app_handle.emit("download_progress", serde_json::json!({
+                    "taskId": task_id,
+                    "progress": progress_percent,
+                    "downloadedSize": downloaded_bytes,
+                    "totalSize": total_size,
+                    "currentFile": relative_path,
+                    "completedFiles": transferred_files,
+                    "totalFiles": file_list.len(),
+                    "speed": rate.instantaneous(),
+                    "averageSpeed": rate.average(),
+                    "etaSeconds": rate.eta_seconds(total_size.saturating_sub(downloaded_bytes)),
+                    "status": "collecting"
+                }));
+                
+                log::info!("Downloaded {}: {} bytes ({}%)", relative_path, file_size, progress_percent);
+                
+                work_queue::finish(&queues, task_id, index, FileState::Done);
+                if !metadata_ready && work_queue::lane_settled(&queues, task_id, metadata_count) {
+                    metadata_ready = true;
+                    mark_metadata_ready(task_id, metadata_count, state, app_handle);
+                }
+            }
+            Ok(None) => {
+                // Dropped by the user mid-transfer; don't leave a partial file behind
+                if let Err(e) = fs::remove_file(&dest_file_path).await {
+                    log::warn!("Failed to remove partial file {}: {}", dest_file_path, e);
+                }
+                log::info!("Dropped {} while downloading", relative_path);
+                if !metadata_ready && work_queue::lane_settled(&queues, task_id, metadata_count) {
+                    metadata_ready = true;
+                    mark_metadata_ready(task_id, metadata_count, state, app_handle);
+                }
+            }
+            // The URL ran out during the transfer or its retries
+            Err(e) if options.signed_urls.is_some() && providers::signed_urls::is_expired(&file_info.url) => {
+                log::warn!("Failed to download {}: {}", file_info.path, e);
+                if let Err(e) = fs::remove_file(&dest_file_path).await {
+                    log::warn!("Failed to remove partial file {}: {}", dest_file_path, e);
+                }
+                record_expired_url(task_id, file_info, state);
+                skip_log.record_expired(relative_path, file_info.size);
+                work_queue::finish(&queues, task_id, index, FileState::Skipped);
+                if !metadata_ready && work_queue::lane_settled(&queues, task_id, metadata_count) {
+                    metadata_ready = true;
+                    mark_metadata_ready(task_id, metadata_count, state, app_handle);
+                }
             }
             Err(e) => {
-                return Err(format!("Failed to download {}: {}", file_info.key, e));
+                return Err(e.context(format!("Failed to download {}", file_info.path)));
             }
         }
     }
     
+    if let Some(provenance) = &options.provenance {
+        provenance::augment_local(dest_dir, provenance).await?;
+        
+        // The description was rewritten, so record its new checksum
+        let description_path = format!("{}/dataset_description.json", dest_dir);
+        if let Ok((size, sha256)) = manifest::hash_file(&description_path, options.tuning.chunk_size).await {
+            match manifest.files.iter_mut().find(|e| e.path == "dataset_description.json") {
+                Some(entry) => {
+                    entry.size = size;
+                    entry.sha256 = sha256;
+                }
+                None => manifest.add("dataset_description.json", size, &sha256, None),
+            }
+        }
+    }
+    
+    manifest::write_local(dest_dir, &manifest).await?;
+    
+    if !audit_log.is_empty() {
+        let log_path = audit_log.write_local(dest_dir).await?;
+        record_anonymized(task_id, &audit_log, log_path, state);
+    }
+    
+    if let Some(datalad) = &options.datalad {
+        let records = DataladExport::records(&manifest, &rewritten_files(&audit_log, options));
+        let manifest_path = datalad.write_local(dest_dir, records).await?;
+        record_datalad_manifest(task_id, manifest_path, state);
+    }
+    
+    let dropped = work_queue::dropped(&queues, task_id);
+    for file in &dropped {
+        skip_log.record_dropped(&file.path, file.size);
+    }
+    record_dropped_files(task_id, dropped.len(), state);
+    
+    if let Some(deduplicator) = &deduplicator {
+        log::info!(
+            "Deduplicated {} files of task {}, saving {}",
+            deduplicator.stats.linked_files, task_id, formatting::bytes(deduplicator.stats.saved_bytes)
+        );
+    }
+    
+    if !skip_log.is_empty() {
+        let log_path = skip_log.write_local(dest_dir).await?;
+        let mut downloads = state.lock().unwrap();
+        if let Some(progress) = downloads.get_mut(task_id) {
+            progress.skip_log_path = Some(log_path);
+        }
+    }
+    
     // Mark as completed
     {
         let mut downloads = state.lock().unwrap();
         if let Some(progress) = downloads.get_mut(task_id) {
             progress.status = "completed".to_string();
+            progress.speed = 0.0;
+            progress.eta_seconds = None;
             progress.progress = 100.0;
             progress.completed_at = Some(chrono::Utc::now().to_rfc3339());
-            progress.current_file = Some(format!("Completed - {} files", file_list.len()));
+            progress.current_file = Some(format!(
+                "Completed - {}",
+                formatting::transfer_summary(file_list.len(), downloaded_bytes, progress.started_at.as_deref())
+            ));
             
             // Emit event to frontend about completion
             if let Err(e) = app_handle.emit("download-completed", &*progress) {
-                println!("Failed to emit download completion event: {}", e);
+                log::warn!("Failed to emit download completion event: {}", e);
             }
         }
     }
@@ -151,78 +516,591 @@ async fn download_openneuro_dataset(
     // Note: In a real implementation, we would emit a Tauri event here
     // For now, the periodic sync should pick this up
     
-    println!("Dataset download completed: {} files, {} bytes", file_list.len(), downloaded_bytes);
+    log::info!("Dataset download completed: {} files, {} bytes", file_list.len(), downloaded_bytes);
+    Ok(manifest)
+}
+
+/// Decide whether an existing file can be kept, returning its SHA-256 for the manifest.
+/// Files are hashed when selected for verification or when no earlier hash is on record.
+async fn check_existing_file(
+    file_info: &RemoteFile,
+    dest_file_path: &str,
+    previous_entry: Option<&ManifestEntry>,
+    verify: bool,
+    chunk_size: usize,
+) -> Result<(String, SkipVerification), String> {
+    let recorded = previous_entry
+        .filter(|e| e.size == file_info.size)
+        .map(|e| e.sha256.clone());
+    
+    if let (Some(sha256), false) = (&recorded, verify) {
+        return Ok((sha256.clone(), SkipVerification::NotChecked));
+    }
+    
+    let (_, actual) = manifest::hash_file(dest_file_path, chunk_size).await?;
+    let reference = recorded.or_else(|| {
+        file_info.checksum.as_ref()
+            .filter(|c| c.algorithm == ChecksumAlgorithm::Sha256)
+            .map(|c| c.value.clone())
+    });
+    
+    let verification = match reference {
+        Some(expected) if expected.eq_ignore_ascii_case(&actual) => SkipVerification::Verified,
+        Some(_) => SkipVerification::Mismatch,
+        None => SkipVerification::Unverifiable,
+    };
+    Ok((actual, verification))
+}
+
+/// Report extraction progress on the task and through `archive-extract-progress` events,
+/// at most a few times per second
+fn extraction_reporter(
+    task_id: &str,
+    relative_path: &str,
+    state: &DownloadState,
+    app_handle: &tauri::AppHandle,
+) -> impl FnMut(archive::ExtractProgress) + Send + 'static {
+    let task_id = task_id.to_string();
+    let archive = relative_path.to_string();
+    let state = state.clone();
+    let app_handle = app_handle.clone();
+    let mut last_report: Option<std::time::Instant> = None;
+    
+    move |progress| {
+        if last_report.is_some_and(|at| at.elapsed() < std::time::Duration::from_millis(250)) {
+            return;
+        }
+        last_report = Some(std::time::Instant::now());
+        
+        {
+            let mut downloads = state.lock().unwrap();
+            if let Some(task) = downloads.get_mut(&task_id) {
+                task.current_file = Some(format!("Extracting {} ({} files)", archive, progress.entries));
+            }
+        }
+        let _ = app_handle.emit("archive-extract-progress", serde_json::json!({
+            "taskId": task_id,
+            "archive": archive,
+            "entries": progress.entries,
+            "bytesWritten": progress.bytes_written,
+            "archiveRead": progress.archive_read,
+            "archiveSize": progress.archive_size
+        }));
+    }
+}
+
+/// Report the bytes of the file being transferred on the task and through
+/// `download_file_progress` events, at most once per second. `done_before` counts the bytes of
+/// the task's earlier files, so the task's progress moves along with the file; it never moves
+/// back when a file is fetched again or uploaded after being fetched.
+fn file_progress_reporter(
+    task_id: &str,
+    file_info: &RemoteFile,
+    done_before: u64,
+    state: &DownloadState,
+    app_handle: &tauri::AppHandle,
+) -> impl FnMut(u64) + Send + 'static {
+    let task_id = task_id.to_string();
+    let path = file_info.path.clone();
+    let file_size = file_info.size;
+    let state = state.clone();
+    let app_handle = app_handle.clone();
+    let mut last_report: Option<std::time::Instant> = None;
+    
+    move |file_bytes| {
+        if last_report.is_some_and(|at| at.elapsed() < std::time::Duration::from_secs(1)) {
+            return;
+        }
+        last_report = Some(std::time::Instant::now());
+        
+        let (downloaded_size, total_size) = {
+            let mut downloads = state.lock().unwrap();
+            let Some(task) = downloads.get_mut(&task_id) else {
+                return;
+            };
+            task.current_file = Some(path.clone());
+            task.current_file_bytes = file_bytes;
+            task.current_file_size = Some(file_size);
+            task.downloaded_size = task.downloaded_size.max(done_before + file_bytes);
+            if task.total_size > 0 {
+                task.progress = (task.downloaded_size as f64 / task.total_size as f64 * 100.0).min(100.0);
+            }
+            (task.downloaded_size, task.total_size)
+        };
+        let _ = app_handle.emit("download_file_progress", serde_json::json!({
+            "taskId": task_id,
+            "currentFile": path,
+            "fileBytes": file_bytes,
+            "fileSize": file_size,
+            "downloadedSize": downloaded_size,
+            "totalSize": total_size
+        }));
+    }
+}
+
+/// Replace a downloaded archive with its contents, both on disk and in the manifest
+async fn extract_downloaded_archive(
+    archive_path: &str,
+    dest_dir: &str,
+    relative_path: &str,
+    source_url: &str,
+    options: &DownloadOptions,
+    manifest: &mut Manifest,
+    report: impl FnMut(archive::ExtractProgress) + Send + 'static,
+) -> Result<(), String> {
+    let extracted = archive::extract_archive(archive_path, dest_dir, report).await?;
+    
+    manifest.files.retain(|entry| entry.path != relative_path);
+    for extracted_path in &extracted {
+        let local_path = safe_path::join(dest_dir, extracted_path)?;
+        let (size, sha256) = manifest::hash_file(&local_path, options.tuning.chunk_size).await?;
+        manifest.add(extracted_path, size, &sha256, Some(source_url));
+        let checks = post_process::run_on_disk(extracted_path.clone(), std::path::PathBuf::from(local_path), false).await;
+        manifest.record_checks(extracted_path, checks);
+    }
+    
+    path_guard::check(archive_path)?;
+    if let Err(e) = fs::remove_file(archive_path).await {
+        log::warn!("Failed to remove archive {} after extraction: {}", archive_path, e);
+    }
+    
+    log::info!("Extracted {} files from {}", extracted.len(), relative_path);
     Ok(())
 }
 
-#[derive(Debug)]
-struct S3FileInfo {
-    key: String,
-    size: u64,
+/// Apply the task's include/exclude selection to a listing and report what will be transferred
+fn apply_file_filter(
+    file_list: Vec<RemoteFile>,
+    filter: &FileFilter,
+    task_id: &str,
+    state: &DownloadState,
+    app_handle: &tauri::AppHandle,
+) -> Result<Vec<RemoteFile>, String> {
+    let listed_files = file_list.len();
+    let listed_size: u64 = file_list.iter().map(|f| f.size).sum();
+    
+    // Keys that would land outside the destination are never transferred
+    let (file_list, unsafe_files): (Vec<RemoteFile>, Vec<RemoteFile>) = file_list
+        .into_iter()
+        .partition(|f| safe_path::relative(&f.path).is_ok());
+    for file in &unsafe_files {
+        log::warn!("Skipping {:?}: {}", file.path, safe_path::relative(&file.path).unwrap_err());
+    }
+    
+    let selected: Vec<RemoteFile> = if filter.is_empty() {
+        file_list
+    } else {
+        file_list
+            .into_iter()
+            .filter(|f| filter.matches(&f.path))
+            .collect()
+    };
+    
+    if selected.is_empty() {
+        return Err("No files in the dataset match the selection filters".to_string());
+    }
+    
+    let selected_size: u64 = selected.iter().map(|f| f.size).sum();
+    let derivative_files = selected.iter().filter(|f| lanes::is_derivative(&f.path)).count() as u32;
+    let raw_files = selected.len() as u32 - derivative_files;
+    log::info!("Selected {} of {} files ({} of {} bytes)", selected.len(), listed_files, selected_size, listed_size);
+    if derivative_files > 0 {
+        log::info!("Selection has {} raw and {} derivative files", raw_files, derivative_files);
+    }
+    
+    {
+        let mut downloads = state.lock().unwrap();
+        if let Some(progress) = downloads.get_mut(task_id) {
+            progress.total_files = Some(selected.len() as u32);
+            progress.total_size = selected_size;
+            progress.raw_files = Some(raw_files);
+            progress.derivative_files = Some(derivative_files);
+        }
+    }
+    
+    let _ = app_handle.emit("download-selection", serde_json::json!({
+        "taskId": task_id,
+        "listedFiles": listed_files,
+        "listedSize": listed_size,
+        "selectedFiles": selected.len(),
+        "selectedSize": selected_size,
+        "rawFiles": raw_files,
+        "derivativeFiles": derivative_files
+    }));
+    
+    Ok(selected)
 }
 
-fn parse_s3_listing(xml_content: &str) -> Result<Vec<S3FileInfo>, String> {
-    let mut files = Vec::new();
+/// Note a file whose signed URL expired before it could be transferred; the task goes on
+fn record_expired_url(task_id: &str, file_info: &RemoteFile, state: &DownloadState) {
+    log::info!("Task {}: the signed URL for {} expired before it was transferred", task_id, file_info.path);
+    if let Some(progress) = state.lock().unwrap().get_mut(task_id) {
+        progress.expired_files.push(file_info.path.clone());
+    }
+}
+
+/// Count the files the user dropped from a running task
+fn record_dropped_files(task_id: &str, count: usize, state: &DownloadState) {
+    if count == 0 {
+        return;
+    }
+    log::info!("Task {}: {} files were dropped by the user", task_id, count);
     
-    // Simple XML parsing - look for <Key> and <Size> tags
-    let key_regex = Regex::new(r"<Key>([^<]+)</Key>").map_err(|e| format!("Regex error: {}", e))?;
-    let size_regex = Regex::new(r"<Size>([^<]+)</Size>").map_err(|e| format!("Regex error: {}", e))?;
+    let mut downloads = state.lock().unwrap();
+    if let Some(progress) = downloads.get_mut(task_id) {
+        progress.dropped_files = count as u32;
+    }
+}
+
+fn record_renamed_files(task_id: &str, count: usize, state: &DownloadState) {
+    if count == 0 {
+        return;
+    }
+    log::info!("Task {}: {} files were renamed at the provider and moved locally", task_id, count);
     
-    let keys: Vec<&str> = key_regex.captures_iter(xml_content)
-        .map(|cap| cap.get(1).unwrap().as_str())
+    let mut downloads = state.lock().unwrap();
+    if let Some(progress) = downloads.get_mut(task_id) {
+        progress.renamed_files = count as u32;
+    }
+}
+
+/// Report the files hardlinked to content collected earlier instead of downloaded
+fn record_deduplicated(task_id: &str, stats: DedupStats, downloaded_bytes: u64, state: &DownloadState) {
+    let mut downloads = state.lock().unwrap();
+    if let Some(progress) = downloads.get_mut(task_id) {
+        progress.deduplicated_files = stats.linked_files;
+        progress.dedup_saved_bytes = stats.saved_bytes;
+        progress.downloaded_size = downloaded_bytes;
+    }
+}
+
+/// Report how many files the anonymization pass changed and where its audit log went
+fn record_anonymized(task_id: &str, audit_log: &AuditLog, log_path: String, state: &DownloadState) {
+    let mut downloads = state.lock().unwrap();
+    if let Some(progress) = downloads.get_mut(task_id) {
+        progress.anonymized_files = audit_log.scrubbed_files();
+        progress.anonymization_log_path = Some(log_path);
+    }
+}
+
+/// Files stored with other content than their source URL serves, which DataLad must not
+/// fetch from there
+fn rewritten_files<'a>(audit_log: &'a AuditLog, options: &DownloadOptions) -> HashSet<&'a str> {
+    let mut rewritten: HashSet<&str> = audit_log.files.iter()
+        .filter(|f| f.error.is_none())
+        .map(|f| f.path.as_str())
         .collect();
+    if options.provenance.is_some() {
+        rewritten.insert("dataset_description.json");
+    }
+    rewritten
+}
+
+fn record_datalad_manifest(task_id: &str, manifest_path: String, state: &DownloadState) {
+    let mut downloads = state.lock().unwrap();
+    if let Some(progress) = downloads.get_mut(task_id) {
+        progress.datalad_manifest_path = Some(manifest_path);
+    }
+}
+
+/// Whether the task was cancelled from the frontend; checked between files
+fn is_cancelled(task_id: &str, state: &DownloadState) -> bool {
+    let downloads = state.lock().unwrap();
+    downloads.get(task_id)
+        .map(|progress| progress.status == "cancelled")
+        .unwrap_or(false)
+}
+
+/// Why a task cannot transfer right now: "Pause all", the global download window or its own
+/// constraints
+fn pause_reason(constraints: &TaskConstraints, app_handle: &tauri::AppHandle) -> Option<WaitReason> {
+    if app_handle.state::<TaskQueueState>().is_paused() {
+        return Some(WaitReason::new(WaitKind::Paused, "Paused by the user"));
+    }
+    if !app_handle.state::<TaskQueueState>().is_window_open() {
+        return Some(WaitReason::new(WaitKind::DownloadWindow, "Waiting for the download window"));
+    }
+    constraints.unmet(&app_handle.state::<EnvironmentState>().lock().unwrap())
+}
+
+/// Hold a running task between files while tasks are paused, the download window is closed
+/// or its constraints are not met. The task keeps its slot and continues with the next file
+/// once they are.
+async fn wait_until_allowed(
+    task_id: &str,
+    constraints: &TaskConstraints,
+    state: &DownloadState,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), CollectorError> {
+    let Some(reason) = pause_reason(constraints, app_handle) else {
+        return Ok(());
+    };
     
-    let sizes: Vec<u64> = size_regex.captures_iter(xml_content)
-        .map(|cap| cap.get(1).unwrap().as_str().parse::<u64>().unwrap_or(0))
-        .collect();
+    log::info!("Pausing task {}: {}", task_id, reason.message);
+    set_status_unless_cancelled(task_id, "paused", Some(reason), state);
+    let queue = app_handle.state::<TaskQueueState>().inner().clone();
+    let environment = app_handle.state::<EnvironmentState>().inner().clone();
+    let allowed = queue.wait_until_allowed(
+        || is_cancelled(task_id, state),
+        || constraints.unmet(&environment.lock().unwrap()),
+    ).await;
+    if !allowed {
+        return Err(CollectorError::new(ErrorKind::Cancelled, "Download cancelled"));
+    }
+    log::info!("Resuming task {}", task_id);
+    set_status_unless_cancelled(task_id, "collecting", None, state);
+    Ok(())
+}
+
+fn set_status_unless_cancelled(task_id: &str, status: &str, pause_reason: Option<WaitReason>, state: &DownloadState) {
+    let mut downloads = state.lock().unwrap();
+    if let Some(progress) = downloads.get_mut(task_id).filter(|p| p.status != "cancelled") {
+        progress.status = status.to_string();
+        progress.pause_kind = pause_reason.as_ref().map(|reason| reason.kind);
+        progress.pause_reason = pause_reason.map(|reason| reason.message);
+    }
+}
+
+/// Send the completed, failed or cancelled notification for a task that stopped running,
+/// naming the dataset (its `downloadPath`) and what was transferred in how long
+fn notify_task_finished(
+    task_id: &str,
+    dataset: Option<&str>,
+    error: Option<String>,
+    state: &DownloadState,
+    app_handle: &tauri::AppHandle,
+) {
+    let Some(progress) = state.lock().unwrap().get(task_id).cloned() else {
+        return;
+    };
+    let dataset = dataset.unwrap_or(task_id);
+    let summary = formatting::transfer_summary(
+        progress.completed_files.unwrap_or(0) as usize,
+        progress.downloaded_size,
+        progress.started_at.as_deref(),
+    );
+    
+    let notification = match error {
+        _ if progress.status == "cancelled" => Notification::new(
+            NotificationKind::TaskCancelled,
+            "Download cancelled".to_string(),
+            format!("{} was cancelled after {}", dataset, summary),
+        ),
+        Some(e) => Notification::new(
+            NotificationKind::TaskFailed,
+            "Download failed".to_string(),
+            format!("{} failed after {}: {}", dataset, summary, e),
+        ),
+        None => Notification::new(
+            NotificationKind::TaskCompleted,
+            "Download completed".to_string(),
+            format!("{} collected {}", dataset, summary),
+        ),
+    };
+    
+    notifications::notify(app_handle, notification.for_task(task_id).with_data(serde_json::json!(progress)));
+}
+
+/// Record that the metadata lane finished and notify the frontend
+fn mark_metadata_ready(
+    task_id: &str,
+    metadata_files: usize,
+    state: &DownloadState,
+    app_handle: &tauri::AppHandle,
+) {
+    log::info!("Metadata lane completed for task {}: {} files", task_id, metadata_files);
     
-    // Pair up keys and sizes
-    for (key, size) in keys.iter().zip(sizes.iter()) {
-        // Skip directories (keys ending with /)
-        if !key.ends_with('/') {
-            files.push(S3FileInfo {
-                key: key.to_string(),
-                size: *size,
-            });
+    {
+        let mut downloads = state.lock().unwrap();
+        if let Some(progress) = downloads.get_mut(task_id) {
+            progress.metadata_ready = true;
         }
     }
     
-    Ok(files)
+    let _ = app_handle.emit("dataset-metadata-ready", serde_json::json!({
+        "taskId": task_id,
+        "metadataFiles": metadata_files
+    }));
+    
+    notifications::notify(app_handle, Notification::new(
+        NotificationKind::MetadataReady,
+        "Metadata ready".to_string(),
+        format!("{} metadata files of task {} are available", metadata_files, task_id),
+    ).for_task(task_id));
 }
 
-async fn download_single_file(url: &str, dest_path: &str) -> Result<u64, String> {
-    let client = reqwest::Client::new();
-    let response = client.get(url).send().await
-        .map_err(|e| format!("HTTP request failed: {}", e))?;
-    
-    if !response.status().is_success() {
-        return Err(format!("HTTP error: {}", response.status()));
+/// Stream a file to disk, returning its size, SHA-256 and the attempts it took, or None when the
+/// file was dropped mid-transfer.
+/// Gzip files are validated while streaming and fetched again when the stream is corrupt or truncated.
+/// Broken streams are resumed with Range requests, or fetched again where the server mishandles them.
+async fn download_single_file(
+    file_info: &RemoteFile,
+    dest_path: &str,
+    options: &DownloadOptions,
+    dropped: &AtomicBool,
+    report: &mut impl FnMut(u64),
+) -> Result<Option<(u64, String, u32)>, CollectorError> {
+    let client = network::client();
+    let mut attempt = 1;
+    
+    loop {
+        let Some(streamed) = stream_to_file(&client, file_info, dest_path, options, dropped, report).await? else {
+            return Ok(None);
+        };
+        if let Some(e) = streamed.interrupted {
+            if attempt >= options.retry.max_full_attempts {
+                return Err(CollectorError::new(ErrorKind::Network, format!("{} (after {} attempts)", e, attempt)));
+            }
+            log::info!("{} was interrupted (attempt {}/{}), downloading it again: {}", file_info.path, attempt, options.retry.max_full_attempts, e);
+            attempt += 1;
+            continue;
+        }
+        match streamed.gzip_error {
+            None => return Ok(Some((streamed.size, streamed.sha256, attempt))),
+            Some(e) if attempt < options.retry.max_gzip_attempts => {
+                log::warn!("{} failed gzip validation (attempt {}/{}), retrying: {}", file_info.path, attempt, options.retry.max_gzip_attempts, e);
+                attempt += 1;
+            }
+            Some(e) => return Err(CollectorError::new(ErrorKind::Integrity, format!("{} (after {} attempts)", e, attempt))),
+        }
+    }
+}
+
+struct StreamedFile {
+    size: u64,
+    sha256: String,
+    /// Set when gzip validation failed; the file on disk is incomplete
+    gzip_error: Option<String>,
+    /// Set when the stream broke and could not be resumed; the file on disk is incomplete
+    interrupted: Option<String>,
+}
+
+impl StreamedFile {
+    fn failed_gzip(size: u64, error: String) -> StreamedFile {
+        StreamedFile { size, sha256: String::new(), gzip_error: Some(error), interrupted: None }
     }
     
-    // Create file and write content
-    let mut file = fs::File::create(dest_path).await
-        .map_err(|e| format!("Failed to create file: {}", e))?;
+    fn interrupted(size: u64, error: String) -> StreamedFile {
+        StreamedFile { size, sha256: String::new(), gzip_error: None, interrupted: Some(error) }
+    }
+}
+
+async fn stream_to_file(
+    client: &reqwest::Client,
+    file_info: &RemoteFile,
+    dest_path: &str,
+    options: &DownloadOptions,
+    dropped: &AtomicBool,
+    report: &mut impl FnMut(u64),
+) -> Result<Option<StreamedFile>, CollectorError> {
+    let tuning = &options.tuning;
+    // The connection counts against the provider's cap until the file is fully streamed
+    let (mut _connection, response) = options.rate_limiter
+        .request(options.provider.id(), || options.provider.fetch_file_stream(client, file_info))
+        .await?;
+    let expected = file_info.checksum.as_ref();
+    let mut gzip_validator = gzip::is_gzip(&file_info.path).then(GzipValidator::new);
+    
+    // Create file and write content through a buffer sized by the network profile
+    path_guard::check(dest_path)
+        .map_err(|e| CollectorError::new(ErrorKind::PermissionDenied, e))?;
+    let file = fs::File::create(dest_path).await
+        .map_err(|e| CollectorError::from_io("Failed to create file", &e))?;
+    let mut file = BufWriter::with_capacity(tuning.write_buffer_size, file);
     
     // Stream the content to file
     let mut stream = response.bytes_stream();
     let mut bytes_written = 0u64;
+    let mut unflushed = 0u64;
+    let mut hasher = Sha256::new();
+    // SHA-256 is always computed for the manifest; other published algorithms need their own pass
+    let mut expected_hasher = expected
+        .filter(|c| c.algorithm != ChecksumAlgorithm::Sha256)
+        .map(|c| ChecksumHasher::new(c.algorithm));
     
+    let mut resumes = 0;
     while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| format!("Failed to read chunk: {}", e))?;
+        if dropped.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) if bytes_written == 0 => return Err(CollectorError::from_request("Failed to read chunk", &e)),
+            Err(e) => {
+                // Continue where the stream broke instead of fetching the whole file again
+                let error = format!("Failed to read chunk at byte {}: {}", bytes_written, e);
+                if resumes >= options.retry.max_resumes || !options.range_quirks.supports_ranges(&file_info.url) {
+                    return Ok(Some(StreamedFile::interrupted(bytes_written, error)));
+                }
+                resumes += 1;
+                log::warn!("{}; resuming {} (attempt {}/{})", error, file_info.path, resumes, options.retry.max_resumes);
+                // The broken connection is released first, so resuming never waits on itself
+                drop(_connection);
+                match range_support::resume(options.provider, client, file_info, bytes_written, &options.rate_limiter).await {
+                    Ok((connection, response)) => {
+                        _connection = connection;
+                        stream = response.bytes_stream();
+                        continue;
+                    }
+                    Err(ResumeError::Unsupported(quirk)) => {
+                        options.range_quirks.record(&file_info.url, &quirk);
+                        return Ok(Some(StreamedFile::interrupted(bytes_written, format!("{}; server {}", error, quirk))));
+                    }
+                    Err(ResumeError::Failed(e)) => return Err(e.context(format!("{}; resuming failed", error))),
+                }
+            }
+        };
         file.write_all(&chunk).await
-            .map_err(|e| format!("Failed to write to file: {}", e))?;
+            .map_err(|e| CollectorError::from_io("Failed to write to file", &e))?;
+        hasher.update(&chunk);
+        if let Some(expected_hasher) = expected_hasher.as_mut() {
+            expected_hasher.update(&chunk);
+        }
         bytes_written += chunk.len() as u64;
+        unflushed += chunk.len() as u64;
+        report(bytes_written);
+        options.scheduler.pace(options.priority, chunk.len()).await;
+        
+        if tuning.should_flush(unflushed) {
+            file.flush().await
+                .map_err(|e| CollectorError::from_io("Failed to flush file", &e))?;
+            unflushed = 0;
+        }
+        
+        // Abort on the first corrupt chunk instead of downloading the rest of a broken file
+        if let Some(validator) = gzip_validator.as_mut() {
+            if let Err(e) = validator.update(&chunk) {
+                return Ok(Some(StreamedFile::failed_gzip(bytes_written, e)));
+            }
+        }
     }
     
     file.flush().await
-        .map_err(|e| format!("Failed to flush file: {}", e))?;
+        .map_err(|e| CollectorError::from_io("Failed to flush file", &e))?;
     
-    Ok(bytes_written)
+    if let Some(validator) = gzip_validator {
+        if let Err(e) = validator.finish() {
+            return Ok(Some(StreamedFile::failed_gzip(bytes_written, e)));
+        }
+    }
+    
+    let sha256 = hex::encode(hasher.finalize());
+    if let Some(expected) = expected {
+        let actual = match expected_hasher {
+            Some(expected_hasher) => expected_hasher.finalize_hex(),
+            None => sha256.clone(),
+        };
+        expected.verify_hex(&actual)
+            .map_err(|e| CollectorError::new(ErrorKind::Integrity, e))?;
+    }
+    
+    Ok(Some(StreamedFile { size: bytes_written, sha256, gzip_error: None, interrupted: None }))
 }
 use serde::{Deserialize, Serialize};
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncWriteExt, BufWriter};
 use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadProgress {
@@ -231,73 +1109,488 @@ pub struct DownloadProgress {
     pub progress: f64,
     pub total_size: u64,
     pub downloaded_size: u64,
+    /// Bytes per second over the last few seconds
     pub speed: f64,
+    /// Bytes per second since the task started transferring
+    pub average_speed: f64,
+    /// Estimated seconds until the task is done, once a speed is known
+    pub eta_seconds: Option<u64>,
     pub current_file: Option<String>,
+    /// Bytes of the current file transferred so far, updated while it streams
+    pub current_file_bytes: u64,
+    pub current_file_size: Option<u64>,
     pub total_files: Option<u32>,
     pub completed_files: Option<u32>,
+    /// Selected files of the raw dataset and of its `derivatives/` folder, which together
+    /// make up `total_files`
+    pub raw_files: Option<u32>,
+    pub derivative_files: Option<u32>,
+    pub completed_derivative_files: u32,
     pub error_message: Option<String>,
+    /// Why a failed task failed, with its kind and whether retrying may help
+    pub error: Option<CollectorError>,
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
+    pub metadata_ready: bool,
+    /// Files left alone because they already existed (`skipExisting`)
+    pub skipped_files: u32,
+    /// Where the list of skipped files and their verification results was written
+    pub skip_log_path: Option<String>,
+    /// Files removed from the task while it was running (`skip_files`)
+    pub dropped_files: u32,
+    /// Files moved locally in sync mode because the provider renamed them
+    pub renamed_files: u32,
+    /// Dataset version (snapshot) the provider resolved the task to, when versioned
+    pub resolved_version: Option<String>,
+    /// Where the BIDS validation report was written (`validateBids`)
+    pub validation_report_path: Option<String>,
+    /// What a paused or blocked task is waiting for (download window, AC power, ...)
+    pub pause_reason: Option<String>,
+    pub pause_kind: Option<WaitKind>,
+    /// Files hardlinked to identical content collected earlier (`dedup`)
+    pub deduplicated_files: u32,
+    /// Disk space the hardlinks saved
+    pub dedup_saved_bytes: u64,
+    /// Files whose signed URL expired before they were transferred (`signedUrls`)
+    pub expired_files: Vec<String>,
+    /// Files that had identifying fields or columns removed (`anonymize`)
+    pub anonymized_files: u32,
+    /// Where the audit log of the anonymization pass was written
+    pub anonymization_log_path: Option<String>,
+    /// Where the manifest for `datalad addurls` was written (`datalad`)
+    pub datalad_manifest_path: Option<String>,
+    /// Extra attempts files needed after broken or corrupt transfers
+    pub retries: u32,
 }
 
-type DownloadState = Arc<Mutex<HashMap<String, DownloadProgress>>>;
-
-// Tauri commands for download management
-#[tauri::command]
-async fn start_download_task(
-    task_id: String,
-    task_data: serde_json::Value,
-    state: tauri::State<'_, DownloadState>,
-    app_handle: tauri::AppHandle,
-) -> Result<String, String> {
-    println!("Starting background download for task: {}", task_id);
-    
-    // Initialize progress tracking
-    {
-        let mut downloads = state.lock().unwrap();
-        downloads.insert(task_id.clone(), DownloadProgress {
-            task_id: task_id.clone(),
+impl DownloadProgress {
+    /// Fresh progress record for a task that is about to start
+    fn new(task_id: &str) -> DownloadProgress {
+        DownloadProgress {
+            task_id: task_id.to_string(),
             status: "starting".to_string(),
             progress: 0.0,
             total_size: 0,
             downloaded_size: 0,
             speed: 0.0,
+            average_speed: 0.0,
+            eta_seconds: None,
             current_file: None,
+            current_file_bytes: 0,
+            current_file_size: None,
             total_files: None,
             completed_files: None,
+            raw_files: None,
+            derivative_files: None,
+            completed_derivative_files: 0,
             error_message: None,
+            error: None,
             started_at: Some(chrono::Utc::now().to_rfc3339()),
             completed_at: None,
-        });
+            metadata_ready: false,
+            skipped_files: 0,
+            skip_log_path: None,
+            dropped_files: 0,
+            renamed_files: 0,
+            resolved_version: None,
+            validation_report_path: None,
+            pause_reason: None,
+            pause_kind: None,
+            deduplicated_files: 0,
+            dedup_saved_bytes: 0,
+            expired_files: Vec::new(),
+            anonymized_files: 0,
+            anonymization_log_path: None,
+            datalad_manifest_path: None,
+            retries: 0,
+        }
+    }
+}
+
+impl DownloadProgress {
+    /// Mark the task failed; unless the error names a file, the file the task was on is
+    /// recorded with it
+    fn fail(&mut self, error: CollectorError) {
+        self.status = "failed".to_string();
+        self.error_message = Some(error.message.clone());
+        let file = error.file.clone().or_else(|| self.current_file.clone());
+        self.error = Some(error.for_task(&self.task_id).in_file(file));
+    }
+    
+    /// Refresh the speeds and the time remaining from the task's transfer rate
+    fn update_rate(&mut self, rate: &TransferRate) {
+        self.speed = rate.instantaneous();
+        self.average_speed = rate.average();
+        self.eta_seconds = rate.eta_seconds(self.total_size.saturating_sub(self.downloaded_size));
+    }
+}
+
+type DownloadState = Arc<Mutex<HashMap<String, DownloadProgress>>>;
+
+/// Per-task options parsed from the task payload
+struct DownloadOptions {
+    provider: &'static dyn DatasetProvider,
+    filter: FileFilter,
+    /// Set when the task asks for `writeProvenance`
+    provenance: Option<Provenance>,
+    /// Quota of the destination storage location, if one is configured
+    quota: Option<StorageQuota>,
+    /// Buffer sizes and flush strategy for this task's transfers
+    tuning: TransferTuning,
+    /// Unpack downloaded .zip/.tar/.tar.gz files into the destination (`extractArchives`);
+    /// unset means only when the dataset is shipped as archives
+    extract_archives: Option<bool>,
+    /// Checksum root from an imported collection bundle (`expectedChecksumRoot`)
+    expected_checksum_root: Option<String>,
+    /// Keep files that already exist unchanged at the destination (`skipExisting` or `mode: "sync"`)
+    skip_existing: bool,
+    /// Which skipped files are hashed before they are trusted (`verifySkipped`)
+    verify_skipped: VerifyPolicy,
+    /// Run the BIDS structural validation once a local download finishes (`validateBids`)
+    validate_bids: bool,
+    /// Background tasks yield concurrency and bandwidth to foreground tasks
+    priority: TaskPriority,
+    /// Power, network and time conditions the task only transfers under (`constraints`)
+    constraints: TaskConstraints,
+    /// Hardlink files whose content was already collected on local storage (`dedup`)
+    dedup: bool,
+    scheduler: SchedulerState,
+    /// Per-provider request rate and connection caps, shared by all tasks
+    rate_limiter: RateLimitState,
+    /// Endpoints whose broken transfers are restarted instead of resumed
+    range_quirks: RangeQuirkState,
+    /// How often broken or corrupt files are fetched again, from the app settings
+    retry: RetryPolicy,
+    /// Presigned URLs the files are fetched from (`signedUrls`), instead of a provider listing
+    signed_urls: Option<Vec<SignedUrl>>,
+    /// OpenNeuro derivatives pipelines whose outputs are listed along with the raw data (`derivatives`)
+    derivatives: Vec<String>,
+    /// Removes identifying sidecar fields and participants.tsv columns as files land (`anonymize`)
+    anonymizer: Option<Anonymizer>,
+    /// Writes a manifest for DataLad and optionally sets up a git-annex repository (`datalad`)
+    datalad: Option<DataladExport>,
+}
+
+impl DownloadOptions {
+    fn from_task(
+        task: &serde_json::Value,
+        storage_location: &serde_json::Value,
+        dataset_provider: &str,
+        download_path: &str,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<DownloadOptions, String> {
+        let provider = providers::registry().get(dataset_provider)?;
+        let filter = FileFilter::from_task(task)?;
+        let signed_urls = signed_urls_of(task, provider)?;
+        let derivatives = providers::openneuro::derivatives_of(task, provider)?;
+        
+        let write_provenance = task.get("writeProvenance")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        
+        let provenance = match &signed_urls {
+            Some(urls) if write_provenance => Some(providers::signed_urls::provenance(download_path, urls)),
+            _ if write_provenance => Some(provider.provenance(download_path)),
+            _ => None,
+        };
+        
+        let quota = StorageQuota::from_location(storage_location);
+        
+        // A task may pick a network profile preset instead of the global tuning
+        let tuning = match task.get("networkProfile").and_then(|v| v.as_str()) {
+            Some(name) => TransferTuning::preset(name)
+                .ok_or_else(|| format!("Unknown network profile: {}", name))?,
+            None => app_handle.state::<TuningState>().lock().unwrap().clone(),
+        };
+        
+        let extract_archives = task.get("extractArchives")
+            .and_then(|v| v.as_bool());
+        
+        let expected_checksum_root = task.get("expectedChecksumRoot")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        
+        // Sync mode only transfers files that are missing or changed at the destination
+        let sync_mode = task.get("mode").and_then(|v| v.as_str()) == Some("sync");
+        let skip_existing = sync_mode || task.get("skipExisting")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let verify_skipped = VerifyPolicy::from_task(task)?;
+        
+        let validate_bids = task.get("validateBids")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        
+        let dedup = task.get("dedup")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        
+        Ok(DownloadOptions {
+            provider,
+            filter,
+            provenance,
+            quota,
+            tuning,
+            extract_archives,
+            expected_checksum_root,
+            skip_existing,
+            verify_skipped,
+            validate_bids,
+            priority: TaskPriority::from_task(task),
+            constraints: TaskConstraints::from_task(task)?,
+            dedup,
+            scheduler: app_handle.state::<SchedulerState>().inner().clone(),
+            rate_limiter: app_handle.state::<RateLimitState>().inner().clone(),
+            range_quirks: app_handle.state::<RangeQuirkState>().inner().clone(),
+            retry: app_handle.state::<SettingsState>().retry_policy(),
+            signed_urls,
+            derivatives,
+            anonymizer: Anonymizer::from_task(task)?,
+            datalad: DataladExport::from_task(task)?,
+        })
+    }
+}
+
+/// Signed URLs of a task, which only the signed URL provider takes and requires
+fn signed_urls_of(task: &serde_json::Value, provider: &dyn DatasetProvider) -> Result<Option<Vec<SignedUrl>>, String> {
+    let urls = providers::signed_urls::from_task(task)?;
+    match (provider.id() == providers::signed_urls::SignedUrls.id(), urls.is_some()) {
+        (true, false) => Err("Signed URL tasks need a signedUrls list".to_string()),
+        (false, true) => Err(format!("signedUrls is only supported with the {} provider", providers::signed_urls::SignedUrls.display_name())),
+        _ => Ok(urls),
+    }
+}
+
+// Tauri commands for download management
+#[tauri::command]
+async fn start_download_task(
+    task_id: String,
+    task_data: serde_json::Value,
+    state: tauri::State<'_, DownloadState>,
+    queue: tauri::State<'_, TaskQueueState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, CollectorError> {
+    log::info!("Starting background download for task: {}", task_id);
+    match daemon::daemon_client(&app_handle) {
+        Some(daemon) => daemon.enqueue(&task_id, &task_data).await?,
+        None => {
+            queue_download_task(task_id, task_data, state.inner().clone(), queue.inner().clone(), app_handle)?;
+        }
+    }
+    Ok("Download started in background".to_string())
+}
+
+/// List a staged task's dataset, holding one of the provider's connections
+async fn list_staged_dataset(task_data: &serde_json::Value, app_handle: &tauri::AppHandle) -> Result<providers::DatasetListing, CollectorError> {
+    let (provider, download_path) = staging::listing_source(task_data)?;
+    let provider = providers::registry().get(&provider)?;
+    let task = task_data.get("task").ok_or("No task data found")?;
+    let signed_urls = signed_urls_of(task, provider)?;
+    let derivatives = providers::openneuro::derivatives_of(task, provider)?;
+    let rate_limiter = app_handle.state::<RateLimitState>().inner().clone();
+    
+    // Signed URLs are probed one connection each
+    if let Some(urls) = &signed_urls {
+        return providers::signed_urls::list(&download_path, urls, &rate_limiter).await;
+    }
+    let (_connection, listing) = rate_limiter.request(provider.id(), || async {
+        let mut listing = providers::list_dataset_files(provider, &download_path).await?;
+        if !derivatives.is_empty() {
+            providers::openneuro::append_derivatives(&mut listing, &derivatives).await?;
+        }
+        Ok::<_, CollectorError>(listing)
+    }).await?;
+    Ok(listing)
+}
+
+/// Check the parts of a task payload that can be edited while it is staged
+fn validate_staged_task(task_data: &serde_json::Value) -> Result<(), String> {
+    let task = task_data.get("task").ok_or("No task data found")?;
+    select_storage_location(task_data)?;
+    FileFilter::from_task(task)?;
+    TaskConstraints::from_task(task)?;
+    Ok(())
+}
+
+/// Create a task without starting it: the dataset is listed and the task is returned for
+/// editing with `update_staged_task` until `commit_task` starts the transfer
+#[tauri::command]
+async fn create_task(
+    task_id: String,
+    task_data: serde_json::Value,
+    state: tauri::State<'_, DownloadState>,
+    staging: tauri::State<'_, StagingState>,
+    app_handle: tauri::AppHandle,
+) -> Result<StagedTask, CollectorError> {
+    if state.lock().unwrap().contains_key(&task_id) {
+        return Err(CollectorError::new(ErrorKind::Conflict, format!("Task {} was already started", task_id)));
     }
+    validate_staged_task(&task_data)?;
+    
+    let listing = list_staged_dataset(&task_data, &app_handle).await?;
+    let staged = staging.stage(&task_id, task_data, listing)?;
+    log::info!("Staged task {}: {} of {} files selected", task_id, staged.selected_files, staged.listed_files);
+    Ok(staged)
+}
+
+/// Replace the payload of a staged task (filters, destination, options). The dataset is only
+/// listed again when the provider or download path changed.
+#[tauri::command]
+async fn update_staged_task(
+    task_id: String,
+    task_data: serde_json::Value,
+    staging: tauri::State<'_, StagingState>,
+    app_handle: tauri::AppHandle,
+) -> Result<StagedTask, CollectorError> {
+    let previous = staging.task_data(&task_id)
+        .ok_or_else(|| format!("Task {} is not staged", task_id))?;
+    validate_staged_task(&task_data)?;
+    
+    if staging::listing_source(&previous)? == staging::listing_source(&task_data)? {
+        Ok(staging.edit(&task_id, task_data)?)
+    } else {
+        let listing = list_staged_dataset(&task_data, &app_handle).await?;
+        Ok(staging.stage(&task_id, task_data, listing)?)
+    }
+}
+
+/// Start a staged task with its edited payload
+#[tauri::command]
+async fn commit_task(
+    task_id: String,
+    state: tauri::State<'_, DownloadState>,
+    queue: tauri::State<'_, TaskQueueState>,
+    staging: tauri::State<'_, StagingState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, CollectorError> {
+    let task_data = staging.take(&task_id)?;
+    log::info!("Committing staged task {}", task_id);
+    match daemon::daemon_client(&app_handle) {
+        Some(daemon) => daemon.enqueue(&task_id, &task_data).await?,
+        None => {
+            queue_download_task(task_id, task_data, state.inner().clone(), queue.inner().clone(), app_handle)?;
+        }
+    }
+    Ok("Download started in background".to_string())
+}
+
+/// Local storage locations without a path collect into the default download directory
+fn apply_default_download_directory(task_data: &mut serde_json::Value, app_handle: &tauri::AppHandle) {
+    let Some(dir) = app_handle.state::<SettingsState>().default_download_directory() else {
+        return;
+    };
+    let Some(locations) = task_data.get_mut("storageLocations").and_then(|v| v.as_array_mut()) else {
+        return;
+    };
+    for location in locations {
+        let local = location.get("type").and_then(|t| t.as_str()) == Some("local");
+        let has_path = location.get("path").and_then(|p| p.as_str()).is_some_and(|p| !p.is_empty());
+        if local && !has_path {
+            location["path"] = serde_json::json!(dir);
+        }
+    }
+}
+
+/// Put a task in the download queue and run it in the background once it gets a slot. The
+/// returned handle finishes once the task stopped and its notification was sent.
+fn queue_download_task(
+    task_id: String,
+    mut task_data: serde_json::Value,
+    state: DownloadState,
+    queue: TaskQueueState,
+    app_handle: tauri::AppHandle,
+) -> Result<tokio::task::JoinHandle<()>, String> {
+    let constraints = match task_data.get("task") {
+        Some(task) => TaskConstraints::from_task(task)?,
+        None => TaskConstraints::default(),
+    };
+    apply_default_download_directory(&mut task_data, &app_handle);
     
-    // Start download in background task
-    let state_clone = state.inner().clone();
-    let task_id_clone = task_id.clone();
-    let app_handle_clone = app_handle.clone();
+    // Initialize progress tracking; the task waits in the download queue until a slot is free
+    {
+        let mut downloads = state.lock().unwrap();
+        let mut progress = DownloadProgress::new(&task_id);
+        progress.status = "queued".to_string();
+        downloads.insert(task_id.clone(), progress);
+    }
+    let priority = task_data.get("task").map(QueuePriority::from_task).unwrap_or(QueuePriority::Normal);
+    let task_field = |field: &str| task_data.get("task")
+        .and_then(|task| task.get(field))
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string());
+    let origin = TaskOrigin {
+        kind: "download",
+        provider: task_field("datasetProvider"),
+        dataset: task_field("downloadPath"),
+    };
+    queue.enqueue(&task_id, priority);
     
+    let download = perform_download(task_id.clone(), task_data, state.clone(), app_handle.clone());
+    Ok(run_when_queued(task_id, origin, constraints, state, queue, app_handle, download))
+}
+
+/// Run the work of a queued task in the background once it gets a slot, recording a failure
+/// in its progress, its statistics and sending the notification when it stops. The origin's
+/// dataset names the task there.
+fn run_when_queued(
+    task_id: String,
+    origin: TaskOrigin,
+    constraints: TaskConstraints,
+    state: DownloadState,
+    queue: TaskQueueState,
+    app_handle: tauri::AppHandle,
+    work: impl std::future::Future<Output = Result<(), CollectorError>> + Send + 'static,
+) -> tokio::task::JoinHandle<()> {
+    let environment = app_handle.state::<EnvironmentState>().inner().clone();
     tokio::spawn(async move {
-        // Simulate download process
-        if let Err(e) = perform_download(task_id_clone.clone(), task_data, state_clone.clone(), app_handle_clone).await {
-            println!("Download failed: {}", e);
+        let turn = queue.wait_turn(
+            &task_id,
+            || is_cancelled(&task_id, &state),
+            || constraints.unmet(&environment.lock().unwrap()),
+        );
+        let Some(_running) = turn.await else {
+            log::info!("Task {} was cancelled while queued", task_id);
+            notify_task_finished(&task_id, origin.dataset.as_deref(), None, &state, &app_handle);
+            return;
+        };
+        {
+            let mut downloads = state.lock().unwrap();
+            if let Some(progress) = downloads.get_mut(&task_id) {
+                progress.status = "starting".to_string();
+                progress.started_at = Some(chrono::Utc::now().to_rfc3339());
+            }
+        }
+        
+        let result = work.await;
+        if let Err(e) = &result {
+            log::warn!("Download failed: {}", e);
             // Update status to failed
-            let mut downloads = state_clone.lock().unwrap();
-            if let Some(progress) = downloads.get_mut(&task_id_clone) {
-                progress.status = "failed".to_string();
-                progress.error_message = Some(e);
-                progress.completed_at = Some(chrono::Utc::now().to_rfc3339());
+            let mut downloads = state.lock().unwrap();
+            if let Some(progress) = downloads.get_mut(&task_id) {
+                // A cancelled task stops with an error but keeps its cancelled status
+                if progress.status != "cancelled" {
+                    progress.fail(e.clone());
+                    progress.completed_at = Some(chrono::Utc::now().to_rfc3339());
+                }
             }
         }
-    });
-    
-    Ok("Download started in background".to_string())
+        // The task stopped on its own terms, so there is nothing to recover
+        if let Err(e) = recovery::clear(&app_handle, &task_id) {
+            log::warn!("{}", e);
+        }
+        let finished = state.lock().unwrap().get(&task_id).cloned();
+        if let Some(progress) = finished {
+            statistics::record(&progress, &origin, &app_handle);
+        }
+        notify_task_finished(&task_id, origin.dataset.as_deref(), result.err().map(|e| e.message), &state, &app_handle);
+    })
 }
 
 #[tauri::command]
 async fn get_download_progress(
     task_id: String,
     state: tauri::State<'_, DownloadState>,
-) -> Result<Option<DownloadProgress>, String> {
+) -> Result<Option<DownloadProgress>, CollectorError> {
     let downloads = state.lock().unwrap();
     Ok(downloads.get(&task_id).cloned())
 }
@@ -305,7 +1598,7 @@ async fn get_download_progress(
 #[tauri::command]
 async fn get_all_download_progress(
     state: tauri::State<'_, DownloadState>,
-) -> Result<Vec<DownloadProgress>, String> {
+) -> Result<Vec<DownloadProgress>, CollectorError> {
     let downloads = state.lock().unwrap();
     Ok(downloads.values().cloned().collect())
 }
@@ -313,28 +1606,163 @@ async fn get_all_download_progress(
 #[tauri::command]
 async fn cancel_download_task(
     task_id: String,
-    state: tauri::State<'_, DownloadState>,
-) -> Result<String, String> {
-    let mut downloads = state.lock().unwrap();
-    if let Some(progress) = downloads.get_mut(&task_id) {
-        progress.status = "cancelled".to_string();
-        progress.completed_at = Some(chrono::Utc::now().to_rfc3339());
+    app_handle: tauri::AppHandle,
+) -> Result<String, CollectorError> {
+    match daemon::daemon_client(&app_handle) {
+        Some(daemon) => daemon.cancel(&task_id).await?,
+        None => cancel_task(&task_id, &app_handle),
     }
     Ok("Download cancelled".to_string())
 }
 
+/// Mark a task cancelled; its transfer loop or its place in the queue notices and stops
+fn cancel_task(task_id: &str, app_handle: &tauri::AppHandle) {
+    {
+        let state = app_handle.state::<DownloadState>();
+        let mut downloads = state.lock().unwrap();
+        if let Some(progress) = downloads.get_mut(task_id) {
+            progress.status = "cancelled".to_string();
+            progress.completed_at = Some(chrono::Utc::now().to_rfc3339());
+        }
+    }
+    // A queued task notices the cancellation and leaves the queue
+    app_handle.state::<TaskQueueState>().wake();
+}
+
+/// How much of an interrupted task is still missing at its destination, from a fresh listing
+async fn inspect_interrupted_task(persisted: &PersistedTask, app_handle: &tauri::AppHandle) -> Result<InterruptedTask, String> {
+    let task = persisted.task_data.get("task")
+        .ok_or("No task data found")?;
+    let storage_location = select_storage_location(&persisted.task_data)?;
+    let storage_type = storage_location.get("type")
+        .and_then(|t| t.as_str())
+        .ok_or("No storage type specified")?;
+    let storage_path = storage_location.get("path")
+        .and_then(|p| p.as_str())
+        .ok_or("No storage path specified")?;
+    let download_path = persisted.download_path();
+    
+    let options = DownloadOptions::from_task(task, storage_location, persisted.provider(), download_path, app_handle)?;
+    
+    let listing = list_with_limits(&options, download_path).await?;
+    let selected: Vec<RemoteFile> = listing.files
+        .into_iter()
+        .filter(|f| options.filter.is_empty() || options.filter.matches(&f.path))
+        .collect();
+    
+    let existing_sizes = if storage_type == "local" {
+        let dest_dir = safe_path::join(storage_path, download_path)?;
+        let mut sizes = HashMap::new();
+        for file_info in &selected {
+            let Ok(path) = safe_path::join(&dest_dir, &file_info.path) else {
+                continue;
+            };
+            if let Ok(metadata) = fs::metadata(path).await {
+                if metadata.is_file() {
+                    sizes.insert(file_info.path.clone(), metadata.len());
+                }
+            }
+        }
+        sizes
+    } else {
+        let storage = storage::from_storage_location(storage_location)?;
+        storage.list_sizes(&format!("{}/", download_path)).await?
+    };
+    
+    // A file cut off by the crash has the wrong size and counts as missing
+    let remaining: Vec<&RemoteFile> = selected.iter()
+        .filter(|f| existing_sizes.get(&f.path) != Some(&f.size))
+        .collect();
+    
+    let mut interrupted = InterruptedTask::new(persisted);
+    interrupted.total_files = Some(selected.len() as u32);
+    interrupted.total_size = Some(selected.iter().map(|f| f.size).sum());
+    interrupted.remaining_files = Some(remaining.len() as u32);
+    interrupted.remaining_size = Some(remaining.iter().map(|f| f.size).sum());
+    Ok(interrupted)
+}
+
+/// Tasks that were collecting when the app last stopped, with how much of each is missing
+#[tauri::command]
+async fn list_interrupted_tasks(
+    recovery: tauri::State<'_, RecoveryState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<InterruptedTask>, CollectorError> {
+    let tasks = recovery.lock().unwrap().clone();
+    
+    let mut interrupted = Vec::new();
+    for task in &tasks {
+        interrupted.push(match inspect_interrupted_task(task, &app_handle).await {
+            Ok(inspected) => inspected,
+            Err(e) => InterruptedTask { error: Some(e), ..InterruptedTask::new(task) },
+        });
+    }
+    Ok(interrupted)
+}
+
+/// Start interrupted tasks again under their original IDs, all of them unless `task_ids` is
+/// given. Files that already reached the destination are skipped, so each task continues after
+/// its last completed file.
+#[tauri::command]
+async fn resume_interrupted_tasks(
+    task_ids: Option<Vec<String>>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<String>, CollectorError> {
+    Ok(resume_interrupted(task_ids.as_deref(), &app_handle)?)
+}
+
+fn resume_interrupted(task_ids: Option<&[String]>, app_handle: &tauri::AppHandle) -> Result<Vec<String>, String> {
+    let recovery = app_handle.state::<RecoveryState>();
+    let state = app_handle.state::<DownloadState>();
+    let queue = app_handle.state::<TaskQueueState>();
+    
+    let resumed: Vec<PersistedTask> = {
+        let mut tasks = recovery.lock().unwrap();
+        if let Some(unknown) = task_ids.unwrap_or_default().iter().find(|id| !tasks.iter().any(|t| &t.task_id == *id)) {
+            return Err(format!("Task {} is not an interrupted task", unknown));
+        }
+        let (resumed, remaining) = tasks.drain(..)
+            .partition(|t| task_ids.map_or(true, |ids| ids.contains(&t.task_id)));
+        *tasks = remaining;
+        resumed
+    };
+    
+    for task in &resumed {
+        log::info!("Resuming interrupted task {}", task.task_id);
+        queue_download_task(task.task_id.clone(), task.resume_data(), state.inner().clone(), queue.inner().clone(), app_handle.clone())?;
+    }
+    Ok(resumed.into_iter().map(|task| task.task_id).collect())
+}
+
+/// The first storage location of the task that can be collected into (local, or a remote
+/// type such as S3-compatible or SFTP)
+fn select_storage_location(task_data: &serde_json::Value) -> Result<&serde_json::Value, String> {
+    let storage_locations = task_data.get("storageLocations")
+        .and_then(|v| v.as_array())
+        .ok_or("No storage locations specified")?;
+    
+    storage_locations
+        .iter()
+        .find(|loc| {
+            let storage_type = loc.get("type").and_then(|t| t.as_str()).unwrap_or("");
+            storage_type == "local" || storage::REMOTE_TYPES.contains(&storage_type)
+        })
+        .ok_or_else(|| format!("No compatible storage location found (local, {})", storage::REMOTE_TYPES.join(", ")))
+}
+
 async fn perform_download(
     task_id: String,
     task_data: serde_json::Value,
     state: DownloadState,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    println!("Performing REAL download for task: {}", task_id);
-    println!("Task data received: {}", serde_json::to_string_pretty(&task_data).unwrap_or_else(|_| "Invalid JSON".to_string()));
+) -> Result<(), CollectorError> {
+    log::info!("Performing REAL download for task: {}", task_id);
+    log::info!("Task data received: {}", serde_json::to_string_pretty(&credentials::redact(&task_data)).unwrap_or_else(|_| "Invalid JSON".to_string()));
     
     // Parse task data - handle nested structure
+    let invalid = |message: &str| CollectorError::new(ErrorKind::InvalidInput, message);
     let task = task_data.get("task")
-        .ok_or("No task data found")?;
+        .ok_or_else(|| invalid("No task data found"))?;
     
     let dataset_provider = task.get("datasetProvider")
         .and_then(|v| v.as_str())
@@ -342,30 +1770,26 @@ async fn perform_download(
     
     let download_path = task.get("downloadPath")
         .and_then(|v| v.as_str())
-        .ok_or("No download path specified")?;
-    
-    let storage_locations = task_data.get("storageLocations")
-        .and_then(|v| v.as_array())
-        .ok_or("No storage locations specified")?;
+        .ok_or_else(|| invalid("No download path specified"))?;
     
-    // Get the first available storage location (local or S3-compatible)
-    let storage_location = storage_locations
-        .iter()
-        .find(|loc| {
-            let storage_type = loc.get("type").and_then(|t| t.as_str());
-            storage_type == Some("local") || storage_type == Some("s3-compatible")
-        })
-        .ok_or("No compatible storage location found (local or s3-compatible)")?;
+    let storage_location = select_storage_location(&task_data)
+        .map_err(|e| invalid(&e))?;
     
     let storage_type = storage_location.get("type")
         .and_then(|t| t.as_str())
-        .ok_or("No storage type specified")?;
+        .ok_or_else(|| invalid("No storage type specified"))?;
     
     let storage_path = storage_location.get("path")
         .and_then(|p| p.as_str())
-        .ok_or("No storage path specified")?;
+        .ok_or_else(|| invalid("No storage path specified"))?;
+    
+    log::info!("Using storage location: type={}, path={}", storage_type, storage_path);
     
-    println!("Using storage location: type={}, path={}", storage_type, storage_path);
+    let options = DownloadOptions::from_task(task, storage_location, dataset_provider, download_path, &app_handle)
+        .map_err(|e| invalid(&e))?;
+    
+    // Counts this task as foreground or background until it returns
+    let _priority_guard = options.scheduler.register(&task_id, options.priority);
     
     // Update status to collecting
     {
@@ -374,153 +1798,277 @@ async fn perform_download(
             progress.status = "collecting".to_string();
         }
     }
+    if let Err(e) = recovery::record(&app_handle, &task_id, &task_data) {
+        log::warn!("Task {} cannot be recovered after a crash: {}", task_id, e);
+    }
     
     // Handle different storage types
-    match storage_type {
+    let (manifest, location) = match storage_type {
         "local" => {
             // For local storage, create destination directory
-            let dest_dir = format!("{}/{}", storage_path, download_path);
-            println!("Creating local destination directory: {}", dest_dir);
+            let dest_dir = safe_path::join(storage_path, download_path)?;
+            log::info!("Creating local destination directory: {}", dest_dir);
             
+            path_guard::check(&dest_dir)
+                .map_err(|e| CollectorError::new(ErrorKind::PermissionDenied, e))?;
             if let Err(e) = fs::create_dir_all(&dest_dir).await {
-                return Err(format!("Failed to create directory {}: {}", dest_dir, e));
+                return Err(CollectorError::from_io(format!("Failed to create directory {}", dest_dir), &e));
             }
             
             // Download to local storage
-            download_to_local_storage(&task_id, &dest_dir, dataset_provider, download_path, &state, &app_handle).await
-        },
-        "s3-compatible" => {
-            // For S3-compatible storage, upload to S3 bucket
-            println!("Downloading to S3-compatible storage: {}", storage_path);
-            download_to_s3_storage(&task_id, storage_location, dataset_provider, download_path, &state, &app_handle).await
+            let manifest = download_to_local_storage(&task_id, &dest_dir, download_path, &options, &state, &app_handle).await?;
+            if options.validate_bids {
+                run_bids_validation(&task_id, &dest_dir, &state, &app_handle).await;
+            }
+            (manifest, dest_dir)
         },
         _ => {
-            Err(format!("Unsupported storage type: {}", storage_type))
+            // Remote storage: stream each file from the provider to the destination
+            let storage = storage::from_storage_location(storage_location)?;
+            log::info!("Downloading to {}: {}", storage.display_name(), storage_path);
+            if options.validate_bids {
+                log::info!("BIDS validation is only supported for local storage; skipping it for task {}", task_id);
+            }
+            let manifest = download_to_remote_storage(&task_id, storage.as_ref(), download_path, &options, &state, &app_handle).await?;
+            (manifest, storage.location(download_path))
+        }
+    };
+    
+    // Record the collection so it can be exported as part of a collection bundle
+    let source = options.provider.provenance(download_path);
+    let resolved_version = state.lock().unwrap().get(&task_id).and_then(|p| p.resolved_version.clone());
+    let rewritten: &[&str] = if options.provenance.is_some() { &["dataset_description.json"] } else { &[] };
+    let entry = CatalogEntry {
+        id: task_id.clone(),
+        provider: options.provider.id().to_string(),
+        identifier: download_path.to_string(),
+        version: resolved_version.or(source.snapshot),
+        doi: source.doi,
+        storage_type: storage_type.to_string(),
+        location,
+        selection: filters::selection(task),
+        file_count: manifest.files.len() as u64,
+        total_size: manifest.files.iter().map(|f| f.size).sum(),
+        checksum_root: manifest.checksum_root(rewritten),
+        collected_at: chrono::Utc::now().to_rfc3339(),
+    };
+    if let Err(e) = app_handle.state::<CatalogState>().lock().unwrap().record(&entry) {
+        log::warn!("Failed to record task {} in catalog: {}", task_id, e);
+    }
+    
+    if let Some(expected) = &options.expected_checksum_root {
+        if *expected != entry.checksum_root {
+            return Err(CollectorError::new(ErrorKind::Integrity, format!(
+                "Collected files differ from the collection bundle: checksum root {} does not match expected {}",
+                entry.checksum_root, expected
+            )));
+        }
+        log::info!("Checksum root matches the collection bundle: {}", expected);
+    }
+    
+    Ok(())
+}
+
+/// Validate a collected dataset and report the result; problems are reported, not fatal
+async fn run_bids_validation(task_id: &str, dest_dir: &str, state: &DownloadState, app_handle: &tauri::AppHandle) {
+    let report = match bids_validator::validate_and_write(dest_dir).await {
+        Ok(report) => report,
+        Err(e) => {
+            log::warn!("BIDS validation failed for task {}: {}", task_id, e);
+            return;
+        }
+    };
+    
+    {
+        let mut downloads = state.lock().unwrap();
+        if let Some(progress) = downloads.get_mut(task_id) {
+            progress.validation_report_path = Some(format!("{}/{}", dest_dir, bids_validator::REPORT_PATH));
         }
     }
+    
+    if !report.valid {
+        notifications::notify(app_handle, Notification::new(
+            NotificationKind::ValidationFailed,
+            "BIDS validation failed".to_string(),
+            format!("{} errors and {} warnings in {}", report.errors, report.warnings, dest_dir),
+        ).for_task(task_id).with_data(serde_json::json!({ "reportPath": report.path })));
+    }
+    
+    let _ = app_handle.emit("dataset-validated", serde_json::json!({
+        "taskId": task_id,
+        "report": report
+    }));
+}
+
+/// Keep the version the provider resolved to on the task, so the UI shows what is being collected
+fn record_resolved_version(task_id: &str, version: Option<&str>, state: &DownloadState) {
+    let Some(version) = version else {
+        return;
+    };
+    log::info!("Task {} collects version {}", task_id, version);
+    
+    let mut downloads = state.lock().unwrap();
+    if let Some(progress) = downloads.get_mut(task_id) {
+        progress.resolved_version = Some(version.to_string());
+    }
 }
 
 async fn download_to_local_storage(
     task_id: &str,
     dest_dir: &str,
-    dataset_provider: &str,
     download_path: &str,
+    options: &DownloadOptions,
     state: &DownloadState,
     app_handle: &tauri::AppHandle,
-) -> Result<(), String> {
-    // For OpenNeuro datasets, download all files in the dataset
-    if dataset_provider.to_lowercase() == "openneuro" {
-        // Extract OpenNeuro accession from DOI-based path (e.g., "10.18112_openneuro.ds006486.v1.0.0" -> "ds006486")
-        let accession = extract_openneuro_accession(download_path);
-        println!("OpenNeuro: Using accession {} instead of {}", accession, download_path);
-        
-        match download_openneuro_dataset(&accession, dest_dir, task_id, state, app_handle).await {
-            Ok(_) => {
-                println!("Download completed for task: {}", task_id);
-                Ok(())
-            }
-            Err(e) => {
-                println!("Failed to download dataset: {}", e);
-                Err(format!("Download failed: {}", e))
-            }
+) -> Result<Manifest, CollectorError> {
+    // List all files in the dataset from its provider
+    let listing = list_with_limits(options, download_path).await?;
+    record_resolved_version(task_id, listing.version.as_deref(), state);
+    
+    match download_files_to_local(listing.files, dest_dir, options, task_id, state, app_handle).await {
+        Ok(manifest) => {
+            log::info!("Download completed for task: {}", task_id);
+            Ok(manifest)
+        }
+        Err(e) => {
+            log::warn!("Failed to download dataset: {}", e);
+            Err(e)
         }
-    } else {
-        Err("Only OpenNeuro datasets are currently supported".to_string())
     }
 }
 
-async fn download_to_s3_storage(
+/// List the dataset while holding one of the provider's connections
+async fn list_with_limits(options: &DownloadOptions, download_path: &str) -> Result<providers::DatasetListing, CollectorError> {
+    // Signed URLs are probed one connection each
+    let mut listing = match &options.signed_urls {
+        Some(urls) => providers::signed_urls::list(download_path, urls, &options.rate_limiter).await?,
+        None => {
+            let (_connection, listing) = options.rate_limiter.request(options.provider.id(), || async move {
+                let mut listing = providers::list_dataset_files(options.provider, download_path).await?;
+                if !options.derivatives.is_empty() {
+                    providers::openneuro::append_derivatives(&mut listing, &options.derivatives).await?;
+                }
+                Ok::<_, CollectorError>(listing)
+            }).await?;
+            listing
+        }
+    };
+    providers::checksum_files::import(options.provider, &mut listing.files, &options.rate_limiter).await;
+    Ok(listing)
+}
+
+async fn download_to_remote_storage(
     task_id: &str,
-    storage_location: &serde_json::Value,
-    dataset_provider: &str,
+    storage: &dyn RemoteStorage,
     download_path: &str,
+    options: &DownloadOptions,
     state: &DownloadState,
     app_handle: &tauri::AppHandle,
-) -> Result<(), String> {
-    // Extract S3 configuration from storage location
-    let bucket_name = storage_location.get("bucketName")
-        .and_then(|b| b.as_str())
-        .ok_or("No bucket name in S3 storage location")?;
-    
-    let endpoint = storage_location.get("endpoint")
-        .and_then(|e| e.as_str())
-        .ok_or("No endpoint in S3 storage location")?;
-    
-    let access_key_id = storage_location.get("accessKeyId")
-        .and_then(|k| k.as_str())
-        .ok_or("No access key ID in S3 storage location")?;
-    
-    let secret_access_key = storage_location.get("secretAccessKey")
-        .and_then(|s| s.as_str())
-        .ok_or("No secret access key in S3 storage location")?;
-    
-    let region = storage_location.get("region")
-        .and_then(|r| r.as_str())
-        .unwrap_or("us-east-1");
-    
-    println!("S3 destination: bucket={}, endpoint={}, region={}", bucket_name, endpoint, region);
-    
-    // For OpenNeuro datasets, upload all files directly to S3
-    if dataset_provider.to_lowercase() == "openneuro" {
-        // Extract OpenNeuro accession from DOI-based path
-        let accession = extract_openneuro_accession(download_path);
-        println!("OpenNeuro: Uploading accession {} to S3-compatible storage", accession);
-        
-        // Upload the entire dataset to S3-compatible storage
-        upload_openneuro_to_s3(
-            &accession,
-            download_path,
-            bucket_name,
-            endpoint,
-            access_key_id,
-            secret_access_key,
-            region,
-            task_id,
-            state,
-            app_handle,
-        ).await
-    } else {
-        Err("Only OpenNeuro datasets are currently supported".to_string())
+) -> Result<Manifest, CollectorError> {
+    log::info!("{} destination: {}", storage.display_name(), storage.location(download_path));
+    
+    // List all files in the dataset and upload them directly to the destination
+    let listing = list_with_limits(options, download_path).await?;
+    record_resolved_version(task_id, listing.version.as_deref(), state);
+    log::info!("Uploading {} to {}", download_path, storage.display_name());
+    
+    upload_files_to_remote(
+        listing.files,
+        download_path,
+        storage,
+        options,
+        task_id,
+        state,
+        app_handle,
+    ).await
+}
+
+/// Fetch a file into memory along with the attempts it took, fetching gzip files again when they
+/// fail validation. `report` gets the bytes received so far as they arrive.
+async fn fetch_file_bytes(
+    provider: &dyn DatasetProvider,
+    rate_limiter: &RateLimiter,
+    client: &reqwest::Client,
+    file_info: &RemoteFile,
+    max_attempts: u32,
+    report: &mut impl FnMut(u64),
+) -> Result<(Vec<u8>, u32), CollectorError> {
+    let mut attempt = 1;
+    
+    loop {
+        let (_connection, download_response) = rate_limiter
+            .request(provider.id(), || provider.fetch_file_stream(client, file_info))
+            .await
+            .map_err(|e| e.context(format!("Failed to download file {}", file_info.path)))?;
+        
+        let mut file_content = Vec::new();
+        let mut stream = download_response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk
+                .map_err(|e| CollectorError::from_request(format!("Failed to read file content for {}", file_info.path), &e))?;
+            file_content.extend_from_slice(&chunk);
+            report(file_content.len() as u64);
+        }
+        
+        if !gzip::is_gzip(&file_info.path) {
+            return Ok((file_content, attempt));
+        }
+        
+        match gzip::verify_bytes(&file_content) {
+            Ok(()) => return Ok((file_content, attempt)),
+            Err(e) if attempt < max_attempts => {
+                log::warn!("{} failed gzip validation (attempt {}/{}), retrying: {}", file_info.path, attempt, max_attempts, e);
+                attempt += 1;
+            }
+            Err(e) => return Err(CollectorError::new(ErrorKind::Integrity, format!("{}: {} (after {} attempts)", file_info.path, e, attempt))),
+        }
     }
 }
 
-async fn upload_openneuro_to_s3(
-    accession: &str,
+/// File sizes under the dataset prefix and the manifest left by the previous collection, if any
+async fn load_remote_sync_state(
+    storage: &dyn RemoteStorage,
     download_path: &str,
-    bucket_name: &str,
-    endpoint: &str,
-    access_key_id: &str,
-    secret_access_key: &str,
-    region: &str,
+) -> Result<(HashMap<String, u64>, Option<Manifest>), CollectorError> {
+    let prefix = format!("{}/", download_path);
+    let existing_sizes = storage.list_sizes(&prefix).await?;
+    
+    let manifest_key = format!("{}/{}", download_path, manifest::MANIFEST_PATH);
+    let previous_manifest = storage.get(&manifest_key).await?
+        .and_then(|content| Manifest::from_json(&content).ok());
+    
+    log::info!(
+        "Sync: {} objects already under {}, previous manifest {}",
+        existing_sizes.len(),
+        prefix,
+        if previous_manifest.is_some() { "found" } else { "not found" }
+    );
+    Ok((existing_sizes, previous_manifest))
+}
+
+/// Stream a provider's file listing into remote storage under `download_path`
+async fn upload_files_to_remote(
+    file_list: Vec<RemoteFile>,
+    download_path: &str,
+    storage: &dyn RemoteStorage,
+    options: &DownloadOptions,
     task_id: &str,
     state: &DownloadState,
     app_handle: &tauri::AppHandle,
-) -> Result<(), String> {
-    println!("Starting direct upload of OpenNeuro dataset {} to S3", accession);
-    
-    // First, list all files in the OpenNeuro dataset
-    let list_url = format!("https://s3.amazonaws.com/openneuro.org?list-type=2&prefix={}/", accession);
-    println!("Listing files from: {}", list_url);
-    
-    let client = reqwest::Client::new();
-    let list_response = client.get(&list_url).send().await
-        .map_err(|e| format!("Failed to list dataset files: {}", e))?;
-    
-    if !list_response.status().is_success() {
-        return Err(format!("Failed to list files: HTTP {}", list_response.status()));
+) -> Result<Manifest, CollectorError> {
+    log::info!("Starting direct upload of dataset {} to {}", download_path, storage.display_name());
+    log::info!("Found {} files to upload", file_list.len());
+    if options.extract_archives == Some(true) {
+        log::info!("Archive extraction is only supported for local storage; archives will be uploaded as-is");
     }
     
-    let xml_content = list_response.text().await
-        .map_err(|e| format!("Failed to read listing response: {}", e))?;
+    let client = network::client();
     
-    // Parse the XML response to get file list
-    let file_list = parse_s3_listing(&xml_content)?;
-    
-    if file_list.is_empty() {
-        return Err(format!("No files found for dataset: {}", accession));
-    }
+    let mut file_list = apply_file_filter(file_list, &options.filter, task_id, state, app_handle)?;
     
-    println!("Found {} files to upload to S3", file_list.len());
+    // Metadata lane first so the dataset is browsable before the imaging data arrives
+    let metadata_count = lanes::order_by_lane(&mut file_list);
+    log::info!("Metadata lane: {} files, bulk lane: {} files", metadata_count, file_list.len() - metadata_count);
     
     // Update progress tracking
     let total_files = file_list.len() as u32;
@@ -535,44 +2083,196 @@ async fn upload_openneuro_to_s3(
         }
     }
     
-    // Stream each file from OpenNeuro directly to S3-compatible storage
+    // In sync mode, compare against what the bucket holds and the manifest of the last run
+    let (existing_sizes, previous_manifest) = if options.skip_existing {
+        load_remote_sync_state(storage, download_path).await?
+    } else {
+        (HashMap::new(), None)
+    };
+    let previous_entries = previous_manifest.as_ref().map(|m| m.by_path()).unwrap_or_default();
+    
+    // Objects an earlier run stored before it was interrupted, failed or was cancelled are kept
+    let journal = match UploadJournal::open(app_handle, &storage.location(download_path)) {
+        Ok(journal) => Some(journal),
+        Err(e) => {
+            log::warn!("Uploads of task {} cannot be resumed after an interruption: {}", task_id, e);
+            None
+        }
+    };
+    let uploaded_before = match &journal {
+        Some(journal) => journal.confirmed(storage, download_path).await.unwrap_or_else(|e| {
+            log::warn!("Cannot check what an earlier upload left at the destination, uploading everything: {}", e);
+            HashMap::new()
+        }),
+        None => HashMap::new(),
+    };
+    
+    // Files the destination already holds keep their manifest entry instead of being uploaded
+    let carried_over: Vec<Option<&ManifestEntry>> = file_list.iter()
+        .map(|file_info| match uploaded_before.get(&file_info.path).filter(|upload| upload.matches(file_info)) {
+            Some(upload) => {
+                log::info!("Skipping {}, uploaded before the task was interrupted", file_info.path);
+                Some(&upload.entry)
+            }
+            None if options.skip_existing => {
+                let previous = previous_entries.get(file_info.path.as_str()).copied();
+                let existing_size = existing_sizes.get(&file_info.path).copied();
+                match (sync::compare(file_info, existing_size, previous), previous) {
+                    // Without a manifest entry there is no hash to carry over, so the file is transferred
+                    (SyncDecision::Unchanged, Some(previous)) => {
+                        log::info!("Skipping unchanged file {}", file_info.path);
+                        Some(previous)
+                    }
+                    (SyncDecision::Changed(reason), _) => {
+                        log::info!("{} changed ({}), uploading again", file_info.path, reason);
+                        None
+                    }
+                    _ => None,
+                }
+            }
+            None => None,
+        })
+        .collect();
+    
+    // Only what is still to be uploaded counts against the quota
+    if let Some(quota) = &options.quota {
+        let carried_size: u64 = file_list.iter()
+            .zip(&carried_over)
+            .filter(|(_, entry)| entry.is_some())
+            .map(|(file_info, _)| file_info.size)
+            .sum();
+        quota::enforce(quota, total_size - carried_size).await?;
+    }
+    
+    // Stream each file from the provider directly to the destination
     let mut uploaded_files = 0u32;
+    let mut skipped_files = 0u32;
     let mut uploaded_size = 0u64;
-    
-    for file_info in &file_list {
-        println!("Uploading file {}/{}: {}", uploaded_files + 1, total_files, file_info.key);
+    let mut rate = TransferRate::new();
+    let mut metadata_ready = false;
+    let mut manifest = Manifest::new(download_path);
+    let mut audit_log = AuditLog::new(task_id);
+    
+    // Files are taken from the task's queue, which the user can reorder while the task runs
+    let queues = app_handle.state::<QueueState>().inner().clone();
+    work_queue::register(&queues, task_id, &file_list);
+    
+    let mut position = 0;
+    while let Some(next) = work_queue::next(&queues, task_id) {
+        if is_cancelled(task_id, state) {
+            return Err(CollectorError::new(ErrorKind::Cancelled, "Download cancelled"));
+        }
+        wait_until_allowed(task_id, &options.constraints, state, app_handle).await?;
+        
+        let index = next.index;
+        let file_info = &file_list[index];
+        position += 1;
+        
+        if let Some(entry) = carried_over[index] {
+            manifest.files.push(entry.clone());
+            skipped_files += 1;
+            uploaded_size += file_info.size;
+            
+            {
+                let mut downloads = state.lock().unwrap();
+                if let Some(progress) = downloads.get_mut(task_id) {
+                    progress.skipped_files = skipped_files;
+                    progress.downloaded_size = uploaded_size;
+                    progress.update_rate(&rate);
+                }
+            }
+            
+            work_queue::finish(&queues, task_id, index, FileState::Skipped);
+            if !metadata_ready && work_queue::lane_settled(&queues, task_id, metadata_count) {
+                metadata_ready = true;
+                mark_metadata_ready(task_id, metadata_count, state, app_handle);
+            }
+            continue;
+        }
+        
+        log::info!("Uploading file {}/{}: {}", position, total_files, file_info.path);
+        
+        // Background tasks wait here for a slot while a foreground task is running
+        let _slot = options.scheduler.acquire_slot(task_id, options.priority).await;
+        
+        // Download file from the provider; a signed URL that lapsed before or during the
+        // transfer is reported instead of failing the task
+        let started_at = chrono::Utc::now().to_rfc3339();
+        let expired = |file_info: &RemoteFile| options.signed_urls.is_some() && providers::signed_urls::is_expired(&file_info.url);
+        let fetched = if expired(file_info) {
+            Err("signed URL expired".into())
+        } else {
+            let mut report = file_progress_reporter(task_id, file_info, uploaded_size, state, app_handle);
+            fetch_file_bytes(options.provider, &options.rate_limiter, &client, file_info, options.retry.max_gzip_attempts, &mut report).await
+        };
+        let (mut file_content, attempts) = match fetched {
+            Ok(fetched) => fetched,
+            Err(_) if expired(file_info) => {
+                record_expired_url(task_id, file_info, state);
+                work_queue::finish(&queues, task_id, index, FileState::Skipped);
+                if !metadata_ready && work_queue::lane_settled(&queues, task_id, metadata_count) {
+                    metadata_ready = true;
+                    mark_metadata_ready(task_id, metadata_count, state, app_handle);
+                }
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        options.scheduler.pace(options.priority, file_content.len()).await;
+        
+        // Dropped by the user while it was being fetched
+        if next.dropped.load(Ordering::SeqCst) {
+            log::info!("Dropped {} before uploading", file_info.path);
+            if !metadata_ready && work_queue::lane_settled(&queues, task_id, metadata_count) {
+                metadata_ready = true;
+                mark_metadata_ready(task_id, metadata_count, state, app_handle);
+            }
+            continue;
+        }
+        
+        if let Some(expected) = &file_info.checksum {
+            expected.verify_bytes(&file_content)
+                .map_err(|e| CollectorError::new(ErrorKind::Integrity, format!("{}: {}", file_info.path, e)))?;
+        }
         
-        // Download file from OpenNeuro
-        let file_url = format!("https://s3.amazonaws.com/openneuro.org/{}", file_info.key);
-        let download_response = client.get(&file_url).send().await
-            .map_err(|e| format!("Failed to download file {}: {}", file_info.key, e))?;
+        // Destination key is the relative path under download_path
+        let relative_path = file_info.path.as_str();
         
-        if !download_response.status().is_success() {
-            return Err(format!("Failed to download file {}: HTTP {}", file_info.key, download_response.status()));
+        if relative_path == "dataset_description.json" {
+            if let Some(provenance) = &options.provenance {
+                file_content = provenance::augment_description(&file_content, provenance)?;
+            }
         }
         
-        // Get file content as bytes
-        let file_content = download_response.bytes().await
-            .map_err(|e| format!("Failed to read file content for {}: {}", file_info.key, e))?;
+        if let Some(anonymizer) = &options.anonymizer {
+            let outcome = match anonymizer.scrub(relative_path, &file_content) {
+                Ok(Some(scrubbed)) => {
+                    file_content = scrubbed.content;
+                    Ok(Some(scrubbed.removed))
+                }
+                other => other.map(|_| None),
+            };
+            audit_log.record(relative_path, outcome);
+        }
         
-        // Create S3 key for destination (remove accession prefix, use download_path)
-        let relative_path = file_info.key.strip_prefix(&format!("{}/", accession))
-            .unwrap_or(&file_info.key);
-        let s3_key = format!("{}/{}", download_path, relative_path);
+        let key = format!("{}/{}", download_path, relative_path);
+        manifest.add_remote(file_info, file_content.len() as u64, &hex::encode(Sha256::digest(&file_content)));
+        manifest.record_transfer(relative_path, &started_at, attempts);
+        manifest.record_checks(relative_path, post_process::run_in_memory(relative_path, &file_content, true));
         
-        // Upload to S3-compatible storage using PUT request with AWS signature
-        upload_to_s3_compatible(
-            endpoint,
-            bucket_name,
-            &s3_key,
-            &file_content,
-            access_key_id,
-            secret_access_key,
-            region,
-        ).await.map_err(|e| format!("Failed to upload {}: {}", file_info.key, e))?;
+        let report = Mutex::new(file_progress_reporter(task_id, file_info, uploaded_size, state, app_handle));
+        let progress: storage::UploadProgress = Arc::new(move |sent| (report.lock().unwrap())(sent));
+        storage.put_with_source(&key, &file_content, &storage::SourceMetadata::of(file_info), &progress).await
+            .map_err(|e| e.context(format!("Failed to upload {}", file_info.path)))?;
+        if let (Some(journal), Some(entry)) = (&journal, manifest.files.iter().rev().find(|entry| entry.path == relative_path)) {
+            if let Err(e) = journal.record(file_info, entry, &file_content) {
+                log::warn!("{} will be uploaded again if the task is interrupted: {}", file_info.path, e);
+            }
+        }
         
         uploaded_files += 1;
         uploaded_size += file_info.size;
+        rate.record(file_content.len() as u64);
         
         // Update progress
         let progress_percent = (uploaded_size as f64 / total_size as f64 * 100.0).min(100.0);
@@ -583,7 +2283,12 @@ async fn upload_openneuro_to_s3(
                 progress.progress = progress_percent;
                 progress.downloaded_size = uploaded_size;
                 progress.completed_files = Some(uploaded_files);
+                progress.retries += attempts.saturating_sub(1);
+                if lanes::is_derivative(relative_path) {
+                    progress.completed_derivative_files += 1;
+                }
                 progress.current_file = Some(relative_path.to_string());
+                progress.update_rate(&rate);
             }
         }
         
@@ -595,20 +2300,66 @@ async fn upload_openneuro_to_s3(
             "totalSize": total_size,
             "currentFile": relative_path,
             "completedFiles": uploaded_files,
+            "skippedFiles": skipped_files,
             "totalFiles": total_files,
+            "speed": rate.instantaneous(),
+            "averageSpeed": rate.average(),
+            "etaSeconds": rate.eta_seconds(total_size.saturating_sub(uploaded_size)),
             "status": "uploading"
         }));
         
-        println!("Uploaded file {}/{}: {} ({} bytes)", uploaded_files, total_files, relative_path, file_info.size);
+        log::info!("Uploaded file {}/{}: {} ({} bytes)", position, total_files, relative_path, file_info.size);
+        
+        work_queue::finish(&queues, task_id, index, FileState::Done);
+        if !metadata_ready && work_queue::lane_settled(&queues, task_id, metadata_count) {
+            metadata_ready = true;
+            mark_metadata_ready(task_id, metadata_count, state, app_handle);
+        }
+    }
+    
+    // Store the manifest next to the data so restores can be verified
+    let manifest_key = format!("{}/{}", download_path, manifest::MANIFEST_PATH);
+    storage.put(&manifest_key, &manifest.to_json()?).await.map_err(|e| e.context("Failed to upload manifest"))?;
+    if let Some(journal) = &journal {
+        if let Err(e) = journal.finish() {
+            log::warn!("{}", e);
+        }
+    }
+    
+    if !audit_log.is_empty() {
+        let log_key = format!("{}/{}", download_path, audit_log.relative_path());
+        storage.put(&log_key, &audit_log.to_json()?).await.map_err(|e| e.context("Failed to upload anonymization log"))?;
+        log::info!("Anonymized {} files, audit log uploaded to {}", audit_log.scrubbed_files(), log_key);
+        record_anonymized(task_id, &audit_log, storage.location(&log_key), state);
+    }
+    
+    if let Some(datalad) = &options.datalad {
+        let records = DataladExport::records(&manifest, &rewritten_files(&audit_log, options));
+        let datalad_key = format!("{}/{}", download_path, datalad::ADDURLS_PATH);
+        storage.put(&datalad_key, &DataladExport::to_json(&records)?).await.map_err(|e| e.context("Failed to upload DataLad manifest"))?;
+        if datalad.init_repository() {
+            log::warn!("Only local datasets can be set up as git-annex repositories; uploaded the DataLad manifest only");
+        }
+        log::info!("DataLad manifest with {} files uploaded to {}", records.len(), datalad_key);
+        record_datalad_manifest(task_id, storage.location(&datalad_key), state);
     }
     
+    let dropped = work_queue::dropped(&queues, task_id);
+    record_dropped_files(task_id, dropped.len(), state);
+    
     // Mark as completed
     {
         let mut downloads = state.lock().unwrap();
         if let Some(progress) = downloads.get_mut(task_id) {
             progress.status = "completed".to_string();
+            progress.speed = 0.0;
+            progress.eta_seconds = None;
             progress.progress = 100.0;
             progress.completed_at = Some(chrono::Utc::now().to_rfc3339());
+            progress.current_file = Some(format!(
+                "Completed - {}",
+                formatting::transfer_summary(uploaded_files as usize, uploaded_size, progress.started_at.as_deref())
+            ));
         }
     }
     
@@ -617,36 +2368,31 @@ async fn upload_openneuro_to_s3(
         "taskId": task_id,
         "status": "completed",
         "totalFiles": total_files,
-        "totalSize": total_size
+        "transferredFiles": uploaded_files,
+        "skippedFiles": skipped_files,
+        "droppedFiles": dropped,
+        "totalSize": total_size,
+        "summary": formatting::transfer_summary(uploaded_files as usize, uploaded_size, None)
     }));
     
-    println!("Successfully uploaded all {} files to S3-compatible storage", total_files);
-    Ok(())
+    log::info!("Successfully uploaded all {} files to {}", total_files, storage.display_name());
+    Ok(manifest)
 }
 
-async fn upload_to_s3_compatible(
-    endpoint: &str,
-    bucket_name: &str,
+pub(crate) async fn upload_to_s3_compatible(
+    config: &S3ConnectionConfig,
     key: &str,
     content: &[u8],
-    access_key_id: &str,
-    secret_access_key: &str,
-    region: &str,
-) -> Result<(), String> {
+    source: &storage::SourceMetadata,
+    progress: &storage::UploadProgress,
+) -> Result<(), CollectorError> {
     use std::collections::HashMap;
     use chrono::Utc;
     use sha2::{Sha256, Digest};
     use url::Url;
     
-    // Create the URL for the PUT request (force path-style for S3-compatible services)
-    let base_url = if endpoint.starts_with("http") {
-        endpoint.to_string()
-    } else {
-        format!("https://{}", endpoint)
-    };
-    
-    // Use path-style URL: http://endpoint/bucket/key
-    let url = format!("{}/{}/{}", base_url, bucket_name, key);
+    // Path-style (http://endpoint/bucket/key) or virtual-hosted-style, as configured or detected
+    let url = s3_client::object_url(config, key);
     
     let now = Utc::now();
     let timestamp_str = now.format("%Y%m%dT%H%M%SZ").to_string();
@@ -668,52 +2414,53 @@ async fn upload_to_s3_compatible(
     hasher.update(content);
     let content_hash = hex::encode(hasher.finalize());
     
-    println!("Uploading to URL: {}", url);
-    println!("Host header: {}", host_header);
-    println!("Content hash: {}", content_hash);
+    log::info!("Uploading to URL: {}", url);
+    log::info!("Host header: {}", host_header);
+    log::info!("Content hash: {}", content_hash);
     
     // Create headers for AWS signature (minimal set for better compatibility)
     let mut headers = HashMap::new();
     headers.insert("host".to_string(), host_header.clone());
     headers.insert("x-amz-date".to_string(), timestamp_str.clone());
     headers.insert("x-amz-content-sha256".to_string(), content_hash.clone());
+    config.add_session_header(&mut headers);
+    config.add_upload_headers(&mut headers);
+    S3ConnectionConfig::add_object_headers(key, source, &mut headers);
     
     // Generate AWS signature for PUT request
     let authorization = generate_aws_signature_v4_simple(
         "PUT",
         &url,
         &headers,
-        access_key_id,
-        secret_access_key,
-        region,
+        &config.access_key_id,
+        &config.secret_access_key,
+        config.region_or_default(),
         &now,
         &content_hash,
     )?;
     
-    println!("Authorization: {}", authorization);
-    
     // Create the PUT request
-    let client = reqwest::Client::new();
-    let response = client
-        .put(&url)
-        .header("Host", host_header)
+    let client = network::client();
+    let mut request_builder = client.put(&url);
+    for (key, value) in &headers {
+        request_builder = request_builder.header(key, value);
+    }
+    let response = request_builder
         .header("Authorization", authorization)
-        .header("x-amz-date", timestamp_str)
-        .header("x-amz-content-sha256", content_hash)
         .header("Content-Length", content.len())
-        .body(content.to_vec())
+        .body(storage::counting_body(content.to_vec(), progress))
         .send()
         .await
-        .map_err(|e| format!("Failed to upload file: {}", e))?;
+        .map_err(|e| CollectorError::from_request("Failed to upload file", &e))?;
     
     if response.status().is_success() {
-        println!("Upload successful!");
+        log::info!("Upload successful!");
         Ok(())
     } else {
         let status = response.status();
         let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        println!("Upload failed - Status: {}, Error: {}", status, error_text);
-        Err(format!("Upload failed with status {}: {}", status, error_text))
+        log::warn!("Upload failed - Status: {}, Error: {}", status, error_text);
+        Err(CollectorError::new(ErrorKind::of_status(status), format!("Upload failed with status {}: {}", status, error_text)))
     }
 }
 
@@ -763,7 +2510,7 @@ fn generate_aws_signature_v4_simple(
         content_hash
     );
     
-    println!("Canonical request:\n{}", canonical_request);
+    log::debug!("Canonical request:\n{}", canonical_request);
     
     // Create string to sign
     let date = timestamp.format("%Y%m%d").to_string();
@@ -781,7 +2528,7 @@ fn generate_aws_signature_v4_simple(
         canonical_request_hash
     );
     
-    println!("String to sign:\n{}", string_to_sign);
+    log::debug!("String to sign:\n{}", string_to_sign);
     
     // Calculate signature
     let date_key = hmac_sha256_simple(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes())?;
@@ -792,8 +2539,6 @@ fn generate_aws_signature_v4_simple(
     let signature = hmac_sha256_simple(&signing_key, string_to_sign.as_bytes())?;
     let signature_hex = hex::encode(signature);
     
-    println!("Signature: {}", signature_hex);
-    
     // Create authorization header
     let authorization = format!(
         "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
@@ -820,44 +2565,273 @@ fn hmac_sha256_simple(key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
 async fn cleanup_download_task(
     task_id: String,
     state: tauri::State<'_, DownloadState>,
-) -> Result<String, String> {
-    println!("Cleaning up download task: {}", task_id);
+    queues: tauri::State<'_, QueueState>,
+) -> Result<String, CollectorError> {
+    log::info!("Cleaning up download task: {}", task_id);
     
     // Remove from the download state
     let mut downloads = state.lock().unwrap();
     downloads.remove(&task_id);
+    work_queue::remove(&queues, &task_id);
     
     Ok("Download task cleaned up".to_string())
 }
 
+/// Internals exercised by the benchmarks in `benches/`; not a stable API
+#[doc(hidden)]
+pub mod bench_support {
+    pub use crate::checksum::{ChecksumAlgorithm, ChecksumHasher};
+    pub use crate::manifest::hash_file;
+    pub use crate::s3_client::parse_list_objects;
+    pub use crate::storage::azure::parse_blob_list;
+    pub use crate::tuning::TransferTuning;
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let download_state: DownloadState = Arc::new(Mutex::new(HashMap::new()));
+    let tuning_state: TuningState = Arc::new(Mutex::new(TransferTuning::default()));
+    let scheduler_state: SchedulerState = Arc::new(Scheduler::new());
+    let queue_state: QueueState = Arc::new(Mutex::new(HashMap::new()));
+    let rate_limit_state: RateLimitState = Arc::new(RateLimiter::new());
+    let task_queue_state: TaskQueueState = Arc::new(TaskQueue::new());
+    let environment_state: EnvironmentState = Arc::new(Mutex::new(Environment::default()));
+    
+    let mode = Mode::from_args();
+    if mode == Mode::PrintService {
+        match daemon::service_definition() {
+            Ok(definition) => print!("{}", definition),
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+    
+    let mut collect = None;
+    if mode == Mode::Collect {
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        match cli::CollectArgs::parse(&args) {
+            Ok(args) => collect = Some(args),
+            Err(e) => {
+                eprintln!("{}\n\n{}", e, cli::USAGE);
+                std::process::exit(cli::EXIT_USAGE);
+            }
+        }
+    }
+    
+    // The daemon and command-line collections run the same app without creating the webview
+    let mut context = tauri::generate_context!();
+    if mode == Mode::Daemon || mode == Mode::Collect {
+        context.config_mut().app.windows.clear();
+    }
+    
+    let mut performance_mode = PerformanceMode::detect();
+    performance_mode.apply(&tuning_state, &scheduler_state);
+    let performance_state: PerformanceState = Arc::new(Mutex::new(performance_mode));
     
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_notification::init())
         .manage(download_state)
+        .manage(tuning_state)
+        .manage(scheduler_state)
+        .manage(queue_state)
+        .manage(rate_limit_state)
+        .manage(task_queue_state.clone())
+        .manage(environment_state.clone())
+        .manage(performance_state)
+        .manage(ExportState::default())
+        .manage(StagingState::new(StagedTasks::default()))
+        .on_window_event(tray::handle_window_event)
         .invoke_handler(tauri::generate_handler![
             start_download_task,
+            create_task,
+            update_staged_task,
+            commit_task,
+            get_staged_task,
+            list_staged_tasks,
+            discard_staged_task,
             get_download_progress,
             get_all_download_progress,
             cancel_download_task,
             cleanup_download_task,
-            test_s3_connection
+            get_download_queue,
+            get_scheduler_state,
+            get_transfer_statistics,
+            pause_all_tasks,
+            resume_all_tasks,
+            set_max_concurrent_tasks,
+            reorder_queued_tasks,
+            promote_queued_task,
+            get_task_remaining,
+            deprioritize_files,
+            skip_files,
+            preview_dataset,
+            get_dataset_metadata,
+            get_storage_quota_usage,
+            start_restore_task,
+            list_network_profiles,
+            get_transfer_tuning,
+            set_transfer_tuning,
+            get_performance_mode,
+            set_performance_mode,
+            get_provider_rate_limits,
+            set_provider_rate_limits,
+            list_format_locales,
+            get_format_locale,
+            set_format_locale,
+            list_destination_roots,
+            add_destination_root,
+            remove_destination_root,
+            list_supported_providers,
+            export_collection_bundle,
+            import_collection_bundle,
+            export_transfer_journal,
+            export_ro_crate,
+            export_dataset_archive,
+            find_duplicate_data,
+            cancel_dataset_archive_export,
+            validate_dataset,
+            save_storage_credentials,
+            get_storage_locations,
+            delete_storage_location,
+            test_s3_connection,
+            generate_presigned_url,
+            test_sftp_connection,
+            test_webdav_connection,
+            test_azure_connection,
+            test_gcs_connection,
+            get_notification_settings,
+            update_notification_settings,
+            send_test_notification,
+            clear_notification_badge,
+            list_catalog_backups,
+            create_catalog_backup,
+            restore_catalog_backup,
+            get_catalog_backup_settings,
+            update_catalog_backup_settings,
+            get_download_window,
+            update_download_window,
+            list_interrupted_tasks,
+            resume_interrupted_tasks,
+            dismiss_interrupted_task,
+            get_daemon_status,
+            list_local_datasets,
+            rescan_library,
+            get_environment,
+            list_range_quirks,
+            clear_range_quirks,
+            get_network_settings,
+            update_network_settings,
+            get_automation_settings,
+            update_automation_settings,
+            regenerate_automation_token,
+            export_diagnostics_bundle,
+            get_settings,
+            update_settings,
+            verify_dataset,
+            plan_dataset_deletion,
+            delete_dataset,
+            transfer_dataset
         ])
-        .setup(|app| {
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
+        .setup(move |app| {
+            // Logging comes first so setup itself is logged; `collect` keeps stdout for its report
+            logging::rotate_by_age(&logging::log_dir(app.handle())?);
+            let console = if mode == Mode::Collect { TargetKind::Stderr } else { TargetKind::Stdout };
+            app.handle().plugin(logging::builder(console).build())?;
+            
+            path_guard::init(app.handle())?;
+            credentials::init(app.handle())?;
+            let settings_state: SettingsState = Arc::new(Settings::open(app.handle())?);
+            if let Err(e) = settings_state.apply(&task_queue_state, &app.state::<SchedulerState>()) {
+                log::warn!("Ignoring settings: {}", e);
+            }
+            app.manage(settings_state);
+            // Proxy and CA settings apply before anything touches the network
+            let network_state: NetworkState = Arc::new(Network::open(app.handle())?);
+            app.manage(network_state);
+            if mode == Mode::Daemon {
+                daemon::start(app.handle())?;
+            }
+            // Only the holder of the engine lock resumes and runs tasks; a desktop app or
+            // collection started while the daemon holds it hands its tasks to the daemon
+            let engine: EngineState = Arc::new(daemon::lock_engine(app.handle(), mode)?);
+            app.manage(engine.clone());
+            let runs_tasks = matches!(*engine, Engine::Local);
+            
+            // Tasks that were collecting when the app stopped stay listed until resumed or dismissed
+            let interrupted = if runs_tasks { recovery::load_interrupted(app.handle())? } else { Vec::new() };
+            {
+                let download_state = app.state::<DownloadState>();
+                let mut downloads = download_state.lock().unwrap();
+                for task in &interrupted {
+                    let mut progress = DownloadProgress::new(&task.task_id);
+                    progress.status = "interrupted".to_string();
+                    progress.started_at = Some(task.started_at.clone());
+                    downloads.insert(task.task_id.clone(), progress);
+                }
+            }
+            let recovery_state: RecoveryState = Arc::new(Mutex::new(interrupted));
+            app.manage(recovery_state);
+            
+            let backups: CatalogBackupState = Arc::new(BackupManager::open(app.handle())?);
+            let catalog_state: CatalogState = Arc::new(Mutex::new(backups.open_catalog()?));
+            app.manage(catalog_state);
+            app.manage(backups.clone());
+            
+            let notification_state: NotificationState = Arc::new(Dispatcher::open(app.handle())?);
+            app.manage(notification_state);
+            
+            os_progress::spawn(app.handle().clone(), app.state::<DownloadState>().inner().clone());
+            // Transfers keep running in the tray while the window is closed; the daemon has no tray
+            if mode == Mode::Desktop {
+                let tray = tray::spawn(app.handle(), app.state::<DownloadState>().inner().clone(), task_queue_state.clone());
+                if let Err(e) = tray {
+                    log::warn!("{}", e);
+                }
+            }
+            catalog_backup::spawn(app.handle().clone(), backups);
+            
+            let window_state: WindowState = Arc::new(WindowSchedule::open(app.handle())?);
+            app.manage(window_state.clone());
+            download_window::spawn(window_state, task_queue_state.clone());
+            environment::spawn(environment_state, task_queue_state);
+            
+            let range_quirk_state: RangeQuirkState = Arc::new(RangeQuirks::open(app.handle())?);
+            app.manage(range_quirk_state);
+            
+            // The API enqueues tasks, so it starts once every state is managed; a command-line
+            // collection leaves the port to the app, and the daemon always serves it so the
+            // desktop app can hand it tasks
+            let automation_state: AutomationState = Arc::new(AutomationServer::open(app.handle())?);
+            if mode == Mode::Daemon {
+                automation_state.enable();
+            }
+            if mode != Mode::Collect && runs_tasks {
+                if let Err(e) = automation_state.restart() {
+                    log::warn!("{}", e);
+                }
+            }
+            app.manage(automation_state);
+            
+            match engine.as_ref() {
+                Engine::Daemon(daemon) => daemon::spawn_control(app.handle().clone(), daemon.clone()),
+                Engine::Local if mode != Mode::Collect => watch_folders::spawn(app.handle().clone()),
+                Engine::Local => {}
+            }
+            
+            // Nobody is there to resume interrupted tasks by hand; every state they use is managed by now
+            if mode == Mode::Daemon {
+                resume_interrupted(None, app.handle())?;
+            }
+            if let Some(args) = collect.take() {
+                cli::start(app.handle(), args);
             }
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(context)
+        .expect("error while building tauri application")
+        .run(move |app_handle, event| daemon::handle_run_event(mode, app_handle, event));
 }
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+use crate::catalog::CatalogState;
+use crate::error::CollectorError;
+use crate::manifest::{Manifest, MANIFEST_PATH};
+use crate::{credentials, storage};
+
+const DESCRIPTION_FILE: &str = "dataset_description.json";
+
+/// How deep below a local storage root datasets are looked for
+const MAX_SCAN_DEPTH: usize = 4;
+
+/// A BIDS dataset found in one of the storage locations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalDataset {
+    /// Local directory, or the storage URI for remote locations
+    pub path: String,
+    pub storage_location_id: String,
+    /// `Name` from dataset_description.json
+    pub name: Option<String>,
+    /// Accession such as ds000001, from the DOI or the directory name
+    pub accession: Option<String>,
+    pub size: u64,
+    pub file_count: u64,
+    /// When the collector last transferred into the dataset, from its manifest
+    pub last_sync: Option<String>,
+    pub scanned_at: String,
+}
+
+/// OpenNeuro-style accession in a DOI or directory name
+fn accession_in(text: &str) -> Option<String> {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .find(|part| part.len() == 8 && part.starts_with("ds") && part[2..].bytes().all(|b| b.is_ascii_digit()))
+        .map(|part| part.to_string())
+}
+
+fn describe(description: &[u8], dir_name: &str) -> (Option<String>, Option<String>) {
+    let description: Value = serde_json::from_slice(description).unwrap_or(Value::Null);
+    let name = description.get("Name").and_then(|v| v.as_str()).map(|s| s.to_string());
+    let accession = description.get("DatasetDOI")
+        .and_then(|v| v.as_str())
+        .and_then(accession_in)
+        .or_else(|| accession_in(dir_name));
+    (name, accession)
+}
+
+fn last_sync(manifest: Option<&[u8]>) -> Option<String> {
+    Manifest::from_json(manifest?).ok().map(|manifest| manifest.created_at)
+}
+
+/// Size and number of files below `dir`
+fn directory_totals(dir: &Path) -> (u64, u64) {
+    let mut totals = (0, 0);
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return totals;
+    };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            let (size, count) = directory_totals(&entry.path());
+            totals.0 += size;
+            totals.1 += count;
+        } else if let Ok(metadata) = entry.metadata() {
+            totals.0 += metadata.len();
+            totals.1 += 1;
+        }
+    }
+    totals
+}
+
+/// Datasets at or below `dir`; a dataset's own subdirectories are not searched further
+fn scan_directory(dir: &Path, depth: usize, location_id: &str, scanned_at: &str, found: &mut Vec<LocalDataset>) {
+    if let Ok(description) = std::fs::read(dir.join(DESCRIPTION_FILE)) {
+        let dir_name = dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let (name, accession) = describe(&description, &dir_name);
+        let (size, file_count) = directory_totals(dir);
+        found.push(LocalDataset {
+            path: dir.to_string_lossy().to_string(),
+            storage_location_id: location_id.to_string(),
+            name,
+            accession,
+            size,
+            file_count,
+            last_sync: last_sync(std::fs::read(dir.join(MANIFEST_PATH)).ok().as_deref()),
+            scanned_at: scanned_at.to_string(),
+        });
+        return;
+    }
+
+    if depth == MAX_SCAN_DEPTH {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if !hidden && entry.file_type().is_ok_and(|t| t.is_dir()) {
+            scan_directory(&entry.path(), depth + 1, location_id, scanned_at, found);
+        }
+    }
+}
+
+async fn scan_local(root: String, location_id: String, scanned_at: String) -> Result<Vec<LocalDataset>, String> {
+    tokio::task::spawn_blocking(move || {
+        let mut found = Vec::new();
+        scan_directory(Path::new(&root), 0, &location_id, &scanned_at, &mut found);
+        found
+    })
+    .await
+    .map_err(|e| format!("Library scan failed: {}", e))
+}
+
+/// Datasets in a remote location: every prefix holding a dataset_description.json that is
+/// not itself inside a dataset (such as a derivative)
+async fn scan_remote(location: &Value, location_id: &str, scanned_at: &str) -> Result<Vec<LocalDataset>, String> {
+    let storage = storage::from_storage_location(location)?;
+    let sizes = storage.list_sizes("").await?;
+
+    let prefixes: Vec<&str> = sizes.keys()
+        .filter_map(|key| key.strip_suffix(DESCRIPTION_FILE))
+        .filter(|prefix| prefix.is_empty() || prefix.ends_with('/'))
+        .collect();
+    let top_level = prefixes.iter()
+        .filter(|prefix| !prefixes.iter().any(|other| other.len() < prefix.len() && prefix.starts_with(*other)));
+
+    let mut found = Vec::new();
+    for prefix in top_level {
+        let key = format!("{}{}", prefix, DESCRIPTION_FILE);
+        let dataset_files: Vec<u64> = sizes.iter()
+            .filter(|(k, _)| k.starts_with(prefix))
+            .map(|(_, size)| *size)
+            .collect();
+
+        let dir_name = prefix.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+        let description = storage.get(&key).await?.unwrap_or_default();
+        let (name, accession) = describe(&description, dir_name);
+        let manifest = storage.get(&format!("{}{}", prefix, MANIFEST_PATH)).await.ok().flatten();
+
+        found.push(LocalDataset {
+            path: storage.location(prefix.trim_end_matches('/')),
+            storage_location_id: location_id.to_string(),
+            name,
+            accession,
+            size: dataset_files.iter().sum(),
+            file_count: dataset_files.len() as u64,
+            last_sync: last_sync(manifest.as_deref()),
+            scanned_at: scanned_at.to_string(),
+        });
+    }
+    Ok(found)
+}
+
+/// Scan every saved storage location. A location that cannot be scanned keeps its
+/// previously indexed datasets.
+async fn scan_locations(locations: &[Value], previous: &[LocalDataset]) -> Vec<LocalDataset> {
+    let scanned_at = chrono::Utc::now().to_rfc3339();
+    let mut datasets = Vec::new();
+
+    for location in locations {
+        let location_id = location.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let storage_type = location.get("type").and_then(|v| v.as_str()).unwrap_or_default();
+
+        let result = match storage_type {
+            "local" => match location.get("path").and_then(|v| v.as_str()) {
+                Some(root) => scan_local(root.to_string(), location_id.clone(), scanned_at.clone()).await,
+                None => Err("No storage path specified".to_string()),
+            },
+            other if storage::REMOTE_TYPES.contains(&other) => scan_remote(location, &location_id, &scanned_at).await,
+            other => Err(format!("Unsupported storage type: {}", other)),
+        };
+
+        match result {
+            Ok(found) => {
+                log::info!("Found {} dataset(s) in storage location {}", found.len(), location_id);
+                datasets.extend(found);
+            }
+            Err(e) => {
+                log::warn!("Failed to scan storage location {}: {}", location_id, e);
+                datasets.extend(previous.iter().filter(|d| d.storage_location_id == location_id).cloned());
+            }
+        }
+    }
+
+    datasets.sort_by(|a, b| a.path.cmp(&b.path));
+    datasets
+}
+
+/// Datasets found by the last scan of the storage locations
+#[tauri::command]
+pub async fn list_local_datasets(catalog: tauri::State<'_, CatalogState>) -> Result<Vec<LocalDataset>, CollectorError> {
+    Ok(catalog.lock().unwrap().library()?)
+}
+
+/// Scan all saved storage locations for BIDS datasets and replace the library with the result
+#[tauri::command]
+pub async fn rescan_library(
+    catalog: tauri::State<'_, CatalogState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<LocalDataset>, CollectorError> {
+    let locations = credentials::saved_locations(&app_handle)?;
+    let previous = catalog.lock().unwrap().library()?;
+
+    let datasets = scan_locations(&locations, &previous).await;
+    catalog.lock().unwrap().replace_library(&datasets)?;
+    log::info!("Library holds {} dataset(s)", datasets.len());
+    Ok(datasets)
+}
@@ -0,0 +1,103 @@
+use log::LevelFilter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::Manager;
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+
+/// Log file in the app log directory; rotated files are named `app_<date>.log`
+pub const LOG_FILE_NAME: &str = "app";
+
+/// Size at which the log file is rotated
+const MAX_FILE_SIZE: u128 = 5 * 1024 * 1024;
+
+/// Log files kept, the current one included
+const KEPT_FILES: usize = 10;
+
+/// Rotated log files older than this are removed at startup
+const MAX_AGE: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// The current log file is rotated at startup once it is older than this
+const MAX_FILE_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Environment variable with level filters, e.g. `debug` or `info,app_lib::s3_client=debug`
+pub const FILTER_ENV: &str = "BIDS_COLLECTOR_LOG";
+
+/// Dependencies that log too much below warnings
+const QUIET_MODULES: &[&str] = &["hyper", "reqwest", "rustls", "tao", "tracing", "wry"];
+
+pub fn log_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app_handle.path().app_log_dir()
+        .map_err(|e| format!("Failed to resolve app log directory: {}", e))
+}
+
+/// Logger writing to the rotated log file, and to stdout or stderr for whoever started the app.
+/// `FILTER_ENV` sets the levels: a bare level applies to everything, `module=level` to one
+/// module and its children.
+pub fn builder(console: TargetKind) -> tauri_plugin_log::Builder {
+    let mut builder = tauri_plugin_log::Builder::new()
+        .clear_targets()
+        .target(Target::new(TargetKind::LogDir { file_name: Some(LOG_FILE_NAME.to_string()) }))
+        .target(Target::new(console))
+        .max_file_size(MAX_FILE_SIZE)
+        .rotation_strategy(RotationStrategy::KeepSome(KEPT_FILES))
+        .level(LevelFilter::Info);
+    for module in QUIET_MODULES {
+        builder = builder.level_for(*module, LevelFilter::Warn);
+    }
+
+    let filters = std::env::var(FILTER_ENV).unwrap_or_default();
+    for directive in filters.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        let (module, level) = match directive.split_once('=') {
+            Some((module, level)) => (Some(module.trim().to_string()), level.trim()),
+            None => (None, directive),
+        };
+        let Ok(level) = level.parse::<LevelFilter>() else {
+            eprintln!("Ignoring log filter {:?} in {}: unknown level", directive, FILTER_ENV);
+            continue;
+        };
+        builder = match module {
+            Some(module) => builder.level_for(module, level),
+            None => builder.level(level),
+        };
+    }
+    builder
+}
+
+/// Date suffix of rotated files, in the format the log plugin uses
+fn rotated_name(modified: SystemTime) -> String {
+    let modified: chrono::DateTime<chrono::Local> = modified.into();
+    format!("{}_{}.log", LOG_FILE_NAME, modified.format("%Y-%m-%d_%H-%M-%S"))
+}
+
+/// Time-based rotation next to the plugin's size-based one: a log file from an earlier day
+/// is rotated, and rotated files past `MAX_AGE` are removed. Runs before the logger opens
+/// the file.
+pub fn rotate_by_age(log_dir: &Path) {
+    let now = SystemTime::now();
+    let age = |path: &Path| {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        Some((modified, now.duration_since(modified).unwrap_or_default()))
+    };
+
+    let current = log_dir.join(format!("{}.log", LOG_FILE_NAME));
+    if let Some((modified, age)) = age(&current).filter(|(_, age)| *age > MAX_FILE_AGE) {
+        let rotated = log_dir.join(rotated_name(modified));
+        if let Err(e) = std::fs::rename(&current, &rotated) {
+            eprintln!("Failed to rotate {} after {} hours: {}", current.display(), age.as_secs() / 3600, e);
+        }
+    }
+
+    let Ok(entries) = std::fs::read_dir(log_dir) else {
+        return;
+    };
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        let rotated = path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(&format!("{}_", LOG_FILE_NAME)));
+        if rotated && age(&path).is_some_and(|(_, age)| age > MAX_AGE) {
+            if let Err(e) = std::fs::remove_file(&path) {
+                eprintln!("Failed to remove old log file {}: {}", path.display(), e);
+            }
+        }
+    }
+}
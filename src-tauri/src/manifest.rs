@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+
+use crate::path_guard;
+use crate::post_process::FileCheck;
+use crate::providers::RemoteFile;
+
+/// Location of the manifest relative to the dataset root.
+/// Dot-directories are ignored by BIDS tooling, so the dataset stays valid.
+pub const MANIFEST_PATH: &str = ".bids-collector/manifest.json";
+
+const MANIFEST_VERSION: u32 = 1;
+
+/// One transferred file, as recorded at collection time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    #[serde(default)]
+    pub source_url: Option<String>,
+    /// Provider ETag / Last-Modified at collection time, used by sync mode
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    /// When the transfer started and finished, and how many attempts it took
+    #[serde(default)]
+    pub started_at: Option<String>,
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    #[serde(default)]
+    pub attempts: Option<u32>,
+    /// Results of the post-processing checks run once the file landed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<FileCheck>,
+}
+
+/// Per-dataset record of every collected file and its checksum
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub dataset: String,
+    pub created_at: String,
+    pub files: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn new(dataset: &str) -> Manifest {
+        Manifest {
+            version: MANIFEST_VERSION,
+            dataset: dataset.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            files: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, path: &str, size: u64, sha256: &str, source_url: Option<&str>) {
+        self.files.retain(|entry| entry.path != path);
+        self.files.push(ManifestEntry {
+            path: path.to_string(),
+            size,
+            sha256: sha256.to_string(),
+            source_url: source_url.map(|u| u.to_string()),
+            etag: None,
+            last_modified: None,
+            started_at: None,
+            finished_at: None,
+            attempts: None,
+            checks: Vec::new(),
+        });
+    }
+
+    /// Record a file transferred from a provider, keeping its version markers for later syncs
+    pub fn add_remote(&mut self, file: &RemoteFile, size: u64, sha256: &str) {
+        self.add(&file.path, size, sha256, Some(&crate::providers::signed_urls::strip_signature(&file.url)));
+        if let Some(entry) = self.files.last_mut() {
+            entry.etag = file.etag.clone();
+            entry.last_modified = file.last_modified.clone();
+        }
+    }
+
+    /// Record the timing of a transfer that just finished, for the transfer journal
+    pub fn record_transfer(&mut self, path: &str, started_at: &str, attempts: u32) {
+        if let Some(entry) = self.files.iter_mut().find(|entry| entry.path == path) {
+            entry.started_at = Some(started_at.to_string());
+            entry.finished_at = Some(chrono::Utc::now().to_rfc3339());
+            entry.attempts = Some(attempts);
+        }
+    }
+
+    /// Attach post-processing results to a recorded file
+    pub fn record_checks(&mut self, path: &str, checks: Vec<FileCheck>) {
+        if let Some(entry) = self.files.iter_mut().find(|entry| entry.path == path) {
+            entry.checks = checks;
+        }
+    }
+
+    /// Entries keyed by relative path
+    pub fn by_path(&self) -> HashMap<&str, &ManifestEntry> {
+        self.files.iter().map(|entry| (entry.path.as_str(), entry)).collect()
+    }
+
+    /// SHA-256 over "path  sha256" lines sorted by path, identifying the exact file set.
+    /// Files in `skip` (e.g. a description rewritten with per-copy provenance) are left out.
+    pub fn checksum_root(&self, skip: &[&str]) -> String {
+        let mut entries: Vec<&ManifestEntry> = self.files
+            .iter()
+            .filter(|entry| !skip.contains(&entry.path.as_str()))
+            .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut hasher = Sha256::new();
+        for entry in entries {
+            hasher.update(format!("{}  {}\n", entry.path, entry.sha256).as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>, String> {
+        serde_json::to_vec_pretty(self).map_err(|e| format!("Failed to serialize manifest: {}", e))
+    }
+
+    pub fn from_json(content: &[u8]) -> Result<Manifest, String> {
+        serde_json::from_slice(content).map_err(|e| format!("Invalid manifest: {}", e))
+    }
+}
+
+/// Read the manifest of a previous collection into `dest_dir`, if there is one
+pub async fn read_local(dest_dir: &str) -> Option<Manifest> {
+    let path = format!("{}/{}", dest_dir, MANIFEST_PATH);
+    let content = fs::read(&path).await.ok()?;
+    match Manifest::from_json(&content) {
+        Ok(manifest) => Some(manifest),
+        Err(e) => {
+            log::warn!("Ignoring unreadable manifest {}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Write the manifest into a locally collected dataset
+pub async fn write_local(dest_dir: &str, manifest: &Manifest) -> Result<(), String> {
+    let path = format!("{}/{}", dest_dir, MANIFEST_PATH);
+    path_guard::check(&path)?;
+    if let Some(parent) = std::path::Path::new(&path).parent() {
+        fs::create_dir_all(parent).await
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+
+    fs::write(&path, manifest.to_json()?).await
+        .map_err(|e| format!("Failed to write manifest {}: {}", path, e))?;
+
+    log::info!("Wrote manifest with {} files to {}", manifest.files.len(), path);
+    Ok(())
+}
+
+/// Size and SHA-256 of a file on disk, read in `chunk_size` pieces
+pub async fn hash_file(path: &str, chunk_size: usize) -> Result<(u64, String), String> {
+    let mut file = fs::File::open(path).await
+        .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; chunk_size];
+    let mut size = 0u64;
+
+    loop {
+        let read = file.read(&mut buffer).await
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+
+    Ok((size, hex::encode(hasher.finalize())))
+}
@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use tauri::Manager;
+
+use crate::credentials;
+use crate::error::CollectorError;
+
+/// File in the app data directory holding the network settings, without the proxy password
+pub const SETTINGS_FILE: &str = "network.json";
+
+/// Keychain entry holding the proxy password
+const PROXY_CREDENTIALS_ID: &str = "network-proxy";
+
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+/// How every HTTP request of the app reaches the network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkSettings {
+    /// "http://", "https://", "socks5://" or "socks5h://" proxy all requests go through
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    #[serde(default)]
+    pub proxy_username: Option<String>,
+    /// Only accepted by `update_network_settings`; kept in the keychain. An empty string
+    /// removes the stored password, leaving it out keeps it.
+    #[serde(default, skip_serializing)]
+    pub proxy_password: Option<String>,
+    /// Whether the keychain holds a proxy password; reported, never read from the file
+    #[serde(default, skip_deserializing)]
+    pub has_proxy_password: bool,
+    /// Comma-separated hosts and domains that bypass the proxy
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    /// PEM file with extra CA certificates trusted besides the system ones, e.g. of a
+    /// TLS-inspecting institutional proxy
+    #[serde(default)]
+    pub ca_certificate_path: Option<String>,
+    /// Honor HTTP_PROXY/HTTPS_PROXY/NO_PROXY when no proxy is configured here
+    #[serde(default = "default_use_system_proxy")]
+    pub use_system_proxy: bool,
+}
+
+fn default_use_system_proxy() -> bool {
+    true
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            proxy_url: None,
+            proxy_username: None,
+            proxy_password: None,
+            has_proxy_password: false,
+            no_proxy: None,
+            ca_certificate_path: None,
+            use_system_proxy: true,
+        }
+    }
+}
+
+/// Proxy and certificates the shared client is built with
+struct ClientConfig {
+    proxy: Option<reqwest::Proxy>,
+    certificates: Vec<reqwest::Certificate>,
+    use_system_proxy: bool,
+}
+
+impl ClientConfig {
+    fn from_settings(settings: &NetworkSettings, password: Option<&str>) -> Result<ClientConfig, String> {
+        let proxy = match settings.proxy_url.as_deref().filter(|url| !url.trim().is_empty()) {
+            Some(url) => {
+                let parsed = url::Url::parse(url.trim())
+                    .map_err(|e| format!("Invalid proxy URL {}: {}", url, e))?;
+                if !PROXY_SCHEMES.contains(&parsed.scheme()) {
+                    return Err(format!(
+                        "Unsupported proxy scheme {} (expected {})",
+                        parsed.scheme(), PROXY_SCHEMES.join(", ")
+                    ));
+                }
+                let mut proxy = reqwest::Proxy::all(parsed.as_str())
+                    .map_err(|e| format!("Invalid proxy URL {}: {}", url, e))?;
+                if let Some(username) = settings.proxy_username.as_deref().filter(|u| !u.is_empty()) {
+                    proxy = proxy.basic_auth(username, password.unwrap_or_default());
+                }
+                if let Some(no_proxy) = settings.no_proxy.as_deref() {
+                    proxy = proxy.no_proxy(reqwest::NoProxy::from_string(no_proxy));
+                }
+                Some(proxy)
+            }
+            None => None,
+        };
+
+        let certificates = match settings.ca_certificate_path.as_deref().filter(|path| !path.is_empty()) {
+            Some(path) => {
+                let pem = std::fs::read(path)
+                    .map_err(|e| format!("Failed to read CA certificates from {}: {}", path, e))?;
+                let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+                    .map_err(|e| format!("Invalid CA certificates in {}: {}", path, e))?;
+                if certificates.is_empty() {
+                    return Err(format!("No PEM certificates found in {}", path));
+                }
+                certificates
+            }
+            None => Vec::new(),
+        };
+
+        Ok(ClientConfig {
+            proxy,
+            certificates,
+            use_system_proxy: settings.use_system_proxy,
+        })
+    }
+
+    fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        for certificate in &self.certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        match &self.proxy {
+            Some(proxy) => builder.proxy(proxy.clone()),
+            None if !self.use_system_proxy => builder.no_proxy(),
+            None => builder,
+        }
+    }
+}
+
+static CONFIG: RwLock<Option<ClientConfig>> = RwLock::new(None);
+static SHARED_CLIENT: RwLock<Option<reqwest::Client>> = RwLock::new(None);
+
+/// Client builder with the network settings applied; clients that need their own options
+/// (e.g. a timeout) start from this instead of `reqwest::Client::builder()`
+pub fn builder() -> reqwest::ClientBuilder {
+    match CONFIG.read().unwrap().as_ref() {
+        Some(config) => config.apply(reqwest::Client::builder()),
+        None => reqwest::Client::builder(),
+    }
+}
+
+/// The client shared by every transfer and connection test, rebuilt when the settings change
+pub fn client() -> reqwest::Client {
+    if let Some(client) = SHARED_CLIENT.read().unwrap().as_ref() {
+        return client.clone();
+    }
+    let client = builder().build().unwrap_or_else(|e| {
+        log::warn!("Failed to create HTTP client with the network settings, using the defaults: {}", e);
+        reqwest::Client::new()
+    });
+    *SHARED_CLIENT.write().unwrap() = Some(client.clone());
+    client
+}
+
+/// Make `settings` the configuration of every client built from now on. The settings are
+/// checked by building a client first, so invalid ones leave the current configuration.
+fn install(settings: &NetworkSettings, password: Option<&str>) -> Result<(), String> {
+    let config = ClientConfig::from_settings(settings, password)?;
+    config.apply(reqwest::Client::builder())
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client with the network settings: {}", e))?;
+
+    *CONFIG.write().unwrap() = Some(config);
+    *SHARED_CLIENT.write().unwrap() = None;
+    Ok(())
+}
+
+fn stored_password() -> Option<String> {
+    match credentials::load_app_secret(PROXY_CREDENTIALS_ID) {
+        Ok(password) => password,
+        Err(e) => {
+            log::warn!("{}", e);
+            None
+        }
+    }
+}
+
+/// The network settings, persisted in the app data directory
+pub struct Network {
+    settings_path: PathBuf,
+    settings: Mutex<NetworkSettings>,
+}
+
+pub type NetworkState = Arc<Network>;
+
+impl Network {
+    /// Load the settings from the app data directory and apply them. Settings that no longer
+    /// apply (e.g. a removed CA file) are reported and the defaults are used instead.
+    pub fn open(app_handle: &tauri::AppHandle) -> Result<Network, String> {
+        let settings_path = app_handle.path().app_data_dir()
+            .map(|dir| dir.join(SETTINGS_FILE))
+            .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+
+        let mut settings: NetworkSettings = match std::fs::read(&settings_path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", settings_path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => NetworkSettings::default(),
+            Err(e) => return Err(format!("Failed to read {}: {}", settings_path.display(), e)),
+        };
+
+        let password = settings.proxy_username.as_ref().and_then(|_| stored_password());
+        settings.has_proxy_password = password.is_some();
+        if let Err(e) = install(&settings, password.as_deref()) {
+            log::warn!("Ignoring network settings: {}", e);
+        }
+
+        Ok(Network {
+            settings_path,
+            settings: Mutex::new(settings),
+        })
+    }
+
+    pub fn settings(&self) -> NetworkSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    fn save(&self, settings: &NetworkSettings) -> Result<(), String> {
+        crate::path_guard::check(&self.settings_path)?;
+        if let Some(parent) = self.settings_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        let content = serde_json::to_vec_pretty(settings)
+            .map_err(|e| format!("Failed to serialize network settings: {}", e))?;
+        std::fs::write(&self.settings_path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.settings_path.display(), e))
+    }
+}
+
+/// Credentials given in the proxy URL move to the username and password fields, so the
+/// password never ends up in the settings file
+fn split_proxy_credentials(settings: &mut NetworkSettings) {
+    let Some(mut url) = settings.proxy_url.as_deref().and_then(|url| url::Url::parse(url.trim()).ok()) else {
+        return;
+    };
+    if !url.username().is_empty() {
+        settings.proxy_username = Some(url.username().to_string());
+    }
+    if let Some(password) = url.password() {
+        settings.proxy_password = Some(password.to_string());
+    }
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    settings.proxy_url = Some(url.to_string());
+}
+
+#[tauri::command]
+pub async fn get_network_settings(state: tauri::State<'_, NetworkState>) -> Result<NetworkSettings, CollectorError> {
+    Ok(state.settings())
+}
+
+/// Apply and save the proxy and CA settings. They take effect for requests started after
+/// this returns; running transfers keep the client they started with.
+#[tauri::command]
+pub async fn update_network_settings(
+    mut settings: NetworkSettings,
+    state: tauri::State<'_, NetworkState>,
+) -> Result<NetworkSettings, CollectorError> {
+    split_proxy_credentials(&mut settings);
+
+    let password = match settings.proxy_password.take() {
+        Some(password) if password.is_empty() => None,
+        Some(password) => Some(password),
+        None => stored_password(),
+    };
+    let password = password.filter(|_| settings.proxy_username.as_deref().is_some_and(|u| !u.is_empty()));
+    install(&settings, password.as_deref())?;
+
+    match &password {
+        Some(password) => credentials::store_app_secret(PROXY_CREDENTIALS_ID, password)?,
+        None => credentials::forget_app_secret(PROXY_CREDENTIALS_ID)?,
+    }
+    settings.has_proxy_password = password.is_some();
+    state.save(&settings)?;
+    *state.settings.lock().unwrap() = settings.clone();
+
+    log::info!(
+        "Network settings updated: proxy {}, {} CA file, system proxy {}",
+        settings.proxy_url.as_deref().unwrap_or("none"),
+        settings.ca_certificate_path.as_deref().unwrap_or("no extra"),
+        if settings.use_system_proxy { "honored" } else { "ignored" }
+    );
+    Ok(settings)
+}
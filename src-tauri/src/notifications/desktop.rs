@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use tauri::Emitter;
+use tauri_plugin_notification::NotificationExt;
+
+use super::{Notification, NotificationSink};
+
+/// Shown as a native OS notification, so it is seen while the window is in the background,
+/// and passed to the frontend for its notification list
+pub struct DesktopSink {
+    app_handle: tauri::AppHandle,
+    native: bool,
+}
+
+impl DesktopSink {
+    pub fn new(app_handle: &tauri::AppHandle, native: bool) -> DesktopSink {
+        DesktopSink {
+            app_handle: app_handle.clone(),
+            native,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for DesktopSink {
+    async fn send(&self, notification: &Notification) -> Result<(), String> {
+        self.app_handle.emit("desktop-notification", notification)
+            .map_err(|e| format!("Failed to emit desktop notification: {}", e))?;
+        if self.native {
+            self.app_handle.notification()
+                .builder()
+                .title(&notification.title)
+                .body(&notification.body)
+                .show()
+                .map_err(|e| format!("Failed to show OS notification: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts unseen notifications for the badge on the app/tray icon
+pub struct TrayBadgeSink {
+    app_handle: tauri::AppHandle,
+    badge: Arc<AtomicU32>,
+}
+
+impl TrayBadgeSink {
+    pub fn new(app_handle: &tauri::AppHandle, badge: Arc<AtomicU32>) -> TrayBadgeSink {
+        TrayBadgeSink {
+            app_handle: app_handle.clone(),
+            badge,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for TrayBadgeSink {
+    async fn send(&self, _notification: &Notification) -> Result<(), String> {
+        let count = self.badge.fetch_add(1, Ordering::SeqCst) + 1;
+        self.app_handle.emit("tray-badge", serde_json::json!({ "count": count }))
+            .map_err(|e| format!("Failed to emit tray badge update: {}", e))
+    }
+}
+
+pub fn clear_badge(app_handle: &tauri::AppHandle, badge: &AtomicU32) {
+    badge.store(0, Ordering::SeqCst);
+    let _ = app_handle.emit("tray-badge", serde_json::json!({ "count": 0 }));
+}
@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use super::{Notification, NotificationSink};
+use crate::credentials;
+
+/// Keychain entry holding the SMTP password
+const CREDENTIALS_ID: &str = "notifications-email";
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Implicit TLS, usually port 465
+    Tls,
+    /// Plain connection upgraded with STARTTLS, usually port 587
+    #[default]
+    StartTls,
+    /// Unencrypted; only for relays on the local network
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailSettings {
+    pub smtp_host: String,
+    #[serde(default)]
+    pub smtp_port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    /// Login for AUTH PLAIN; the password is kept in the OS keychain
+    #[serde(default)]
+    pub username: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+impl EmailSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.smtp_host.is_empty() {
+            return Err("SMTP host is required".to_string());
+        }
+        if self.to.is_empty() {
+            return Err("At least one recipient is required".to_string());
+        }
+        for address in self.to.iter().chain(std::iter::once(&self.from)) {
+            if !address.contains('@') || address.contains(['\r', '\n', '<', '>']) {
+                return Err(format!("Invalid email address: {}", address));
+            }
+        }
+        Ok(())
+    }
+
+    fn port(&self) -> u16 {
+        self.smtp_port.unwrap_or(match self.security {
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::None => 25,
+        })
+    }
+}
+
+pub fn store_password(password: &str) -> Result<(), String> {
+    credentials::store_app_secret(CREDENTIALS_ID, password)
+}
+
+pub fn forget_password() -> Result<(), String> {
+    credentials::forget_app_secret(CREDENTIALS_ID)
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// Just enough of an SMTP client to hand one message to a submission server
+struct SmtpConnection {
+    stream: BufReader<Box<dyn Stream>>,
+}
+
+impl SmtpConnection {
+    /// Read a (possibly multi-line) reply and fail unless its code is `expected`
+    async fn reply(&mut self, expected: u16) -> Result<String, String> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            let read = tokio::time::timeout(TIMEOUT, self.stream.read_line(&mut line)).await
+                .map_err(|_| "SMTP server did not answer in time".to_string())?
+                .map_err(|e| format!("Failed to read from SMTP server: {}", e))?;
+            if read == 0 {
+                return Err("SMTP server closed the connection".to_string());
+            }
+
+            text.push_str(&line);
+            // "250-..." continues the reply, "250 ..." ends it
+            if line.as_bytes().get(3) != Some(&b'-') {
+                let code: u16 = line.get(..3).and_then(|c| c.parse().ok()).unwrap_or(0);
+                if code != expected {
+                    return Err(format!("SMTP server replied: {}", text.trim()));
+                }
+                return Ok(text);
+            }
+        }
+    }
+
+    async fn command(&mut self, line: &str, expected: u16) -> Result<String, String> {
+        self.stream.get_mut().write_all(format!("{}\r\n", line).as_bytes()).await
+            .map_err(|e| format!("Failed to write to SMTP server: {}", e))?;
+        self.reply(expected).await
+    }
+
+    async fn connect(settings: &EmailSettings) -> Result<SmtpConnection, String> {
+        let address = (settings.smtp_host.as_str(), settings.port());
+        let tcp = tokio::time::timeout(TIMEOUT, TcpStream::connect(address)).await
+            .map_err(|_| format!("Timed out connecting to {}:{}", settings.smtp_host, settings.port()))?
+            .map_err(|e| format!("Cannot connect to {}:{}: {}", settings.smtp_host, settings.port(), e))?;
+
+        let stream: Box<dyn Stream> = match settings.security {
+            SmtpSecurity::Tls => Box::new(tls(&settings.smtp_host, tcp).await?),
+            _ => Box::new(tcp),
+        };
+        let mut connection = SmtpConnection { stream: BufReader::new(stream) };
+        connection.reply(220).await?;
+        connection.command("EHLO bids-collector", 250).await?;
+
+        if settings.security == SmtpSecurity::StartTls {
+            connection.command("STARTTLS", 220).await?;
+            let plain = connection.stream.into_inner();
+            let stream: Box<dyn Stream> = Box::new(tls(&settings.smtp_host, plain).await?);
+            connection = SmtpConnection { stream: BufReader::new(stream) };
+            connection.command("EHLO bids-collector", 250).await?;
+        }
+
+        Ok(connection)
+    }
+}
+
+async fn tls<S: AsyncRead + AsyncWrite + Unpin>(host: &str, stream: S) -> Result<tokio_native_tls::TlsStream<S>, String> {
+    let connector = native_tls::TlsConnector::new()
+        .map_err(|e| format!("Failed to set up TLS: {}", e))?;
+    tokio_native_tls::TlsConnector::from(connector)
+        .connect(host, stream)
+        .await
+        .map_err(|e| format!("TLS handshake with {} failed: {}", host, e))
+}
+
+/// RFC 2047 encoded-word for non-ASCII subjects
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", BASE64.encode(value))
+    }
+}
+
+fn message(settings: &EmailSettings, notification: &Notification) -> String {
+    let body = format!(
+        "{}\r\n\r\n{}Time: {}\r\n-- \r\nBIDS Collector",
+        notification.body,
+        notification.task_id.as_ref().map(|id| format!("Task: {}\r\n", id)).unwrap_or_default(),
+        crate::formatting::timestamp(&notification.created_at),
+    );
+    // Base64 keeps the body 7-bit clean and free of lines starting with '.'
+    let encoded = BASE64.encode(body);
+    let wrapped: Vec<&str> = encoded.as_bytes().chunks(76).map(|c| std::str::from_utf8(c).unwrap()).collect();
+
+    format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+        settings.from,
+        settings.to.join(", "),
+        encode_header(&format!("[BIDS Collector] {}", notification.title)),
+        chrono::Utc::now().to_rfc2822(),
+        wrapped.join("\r\n")
+    )
+}
+
+pub struct EmailSink {
+    settings: EmailSettings,
+    password: Option<String>,
+}
+
+impl EmailSink {
+    pub fn new(settings: EmailSettings) -> Result<EmailSink, String> {
+        let password = match &settings.username {
+            Some(_) => credentials::load_app_secret(CREDENTIALS_ID)?,
+            None => None,
+        };
+        Ok(EmailSink { settings, password })
+    }
+}
+
+#[async_trait]
+impl NotificationSink for EmailSink {
+    async fn send(&self, notification: &Notification) -> Result<(), String> {
+        let mut connection = SmtpConnection::connect(&self.settings).await?;
+
+        if let Some(username) = &self.settings.username {
+            let password = self.password.as_deref()
+                .ok_or("No SMTP password stored; save the email settings with a password")?;
+            let token = BASE64.encode(format!("\0{}\0{}", username, password));
+            connection.command(&format!("AUTH PLAIN {}", token), 235).await?;
+        }
+
+        connection.command(&format!("MAIL FROM:<{}>", self.settings.from), 250).await?;
+        for recipient in &self.settings.to {
+            connection.command(&format!("RCPT TO:<{}>", recipient), 250).await?;
+        }
+        connection.command("DATA", 354).await?;
+        connection.command(&format!("{}.", message(&self.settings, notification)), 250).await?;
+        let _ = connection.command("QUIT", 221).await;
+
+        log::info!("Sent {:?} notification to {}", notification.kind, self.settings.to.join(", "));
+        Ok(())
+    }
+}
@@ -0,0 +1,283 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU32;
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+use crate::error::CollectorError;
+
+pub mod desktop;
+pub mod email;
+pub mod webhook;
+
+/// File in the app data directory holding the notification settings (without the SMTP password)
+pub const SETTINGS_FILE: &str = "notifications.json";
+
+/// What happened; routing rules are keyed by this
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    TaskCompleted,
+    TaskFailed,
+    TaskCancelled,
+    /// Sidecars and other metadata of a task have arrived
+    MetadataReady,
+    /// The BIDS validation after a download reported errors
+    ValidationFailed,
+}
+
+/// Where a notification can be delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkKind {
+    Desktop,
+    Webhook,
+    Email,
+    TrayBadge,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    pub task_id: Option<String>,
+    pub created_at: String,
+    /// Event-specific details, passed on as-is to webhooks
+    #[serde(default)]
+    pub data: Value,
+}
+
+impl Notification {
+    pub fn new(kind: NotificationKind, title: String, body: String) -> Notification {
+        Notification {
+            kind,
+            title,
+            body,
+            task_id: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            data: Value::Null,
+        }
+    }
+
+    pub fn for_task(mut self, task_id: &str) -> Notification {
+        self.task_id = Some(task_id.to_string());
+        self
+    }
+
+    pub fn with_data(mut self, data: Value) -> Notification {
+        self.data = data;
+        self
+    }
+}
+
+/// A destination for notifications.
+///
+/// The dispatcher only talks to destinations through this trait, so a new one needs an
+/// implementation here, a `SinkKind` and an arm in `Dispatcher::sink`.
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    async fn send(&self, notification: &Notification) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// Show desktop notifications through the OS; when off they only reach the app window
+    #[serde(default = "default_native_notifications")]
+    pub native_notifications: bool,
+    /// Sinks each kind of event is delivered to; kinds without an entry are not delivered
+    pub routes: HashMap<NotificationKind, Vec<SinkKind>>,
+    #[serde(default)]
+    pub webhook: Option<webhook::WebhookSettings>,
+    #[serde(default)]
+    pub email: Option<email::EmailSettings>,
+}
+
+fn default_native_notifications() -> bool {
+    true
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        use NotificationKind::*;
+        use SinkKind::*;
+
+        NotificationSettings {
+            enabled: true,
+            native_notifications: true,
+            routes: HashMap::from([
+                (TaskCompleted, vec![Desktop, TrayBadge]),
+                (TaskFailed, vec![Desktop, Email, TrayBadge]),
+                (TaskCancelled, vec![]),
+                (MetadataReady, vec![]),
+                (ValidationFailed, vec![Desktop, Email]),
+            ]),
+            webhook: None,
+            email: None,
+        }
+    }
+}
+
+/// Sends notifications to the sinks their kind is routed to
+pub struct Dispatcher {
+    app_handle: tauri::AppHandle,
+    settings_path: PathBuf,
+    settings: Mutex<NotificationSettings>,
+    /// Notifications delivered to the tray badge since it was last cleared
+    badge: Arc<AtomicU32>,
+}
+
+pub type NotificationState = Arc<Dispatcher>;
+
+impl Dispatcher {
+    /// Load the settings from the app data directory, falling back to the defaults
+    pub fn open(app_handle: &tauri::AppHandle) -> Result<Dispatcher, String> {
+        let settings_path = app_handle.path().app_data_dir()
+            .map(|dir| dir.join(SETTINGS_FILE))
+            .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+
+        let settings = match std::fs::read(&settings_path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", settings_path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => NotificationSettings::default(),
+            Err(e) => return Err(format!("Failed to read {}: {}", settings_path.display(), e)),
+        };
+
+        Ok(Dispatcher {
+            app_handle: app_handle.clone(),
+            settings_path,
+            settings: Mutex::new(settings),
+            badge: Arc::new(AtomicU32::new(0)),
+        })
+    }
+
+    pub fn settings(&self) -> NotificationSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    /// Validate and save new settings; the SMTP password is managed separately
+    pub fn update(&self, settings: NotificationSettings) -> Result<(), String> {
+        if let Some(email) = &settings.email {
+            email.validate()?;
+        }
+        if let Some(webhook) = &settings.webhook {
+            webhook.validate()?;
+        }
+        self.save(settings)
+    }
+
+    fn save(&self, settings: NotificationSettings) -> Result<(), String> {
+        crate::path_guard::check(&self.settings_path)?;
+        if let Some(parent) = self.settings_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        let content = serde_json::to_vec_pretty(&settings)
+            .map_err(|e| format!("Failed to serialize notification settings: {}", e))?;
+        std::fs::write(&self.settings_path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.settings_path.display(), e))?;
+
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+
+    fn sink(&self, kind: SinkKind, settings: &NotificationSettings) -> Result<Box<dyn NotificationSink>, String> {
+        match kind {
+            SinkKind::Desktop => Ok(Box::new(desktop::DesktopSink::new(&self.app_handle, settings.native_notifications))),
+            SinkKind::TrayBadge => Ok(Box::new(desktop::TrayBadgeSink::new(&self.app_handle, self.badge.clone()))),
+            SinkKind::Webhook => {
+                let webhook = settings.webhook.clone().ok_or("No webhook is configured")?;
+                Ok(Box::new(webhook::WebhookSink::new(webhook)?))
+            }
+            SinkKind::Email => {
+                let email = settings.email.clone().ok_or("No email server is configured")?;
+                Ok(Box::new(email::EmailSink::new(email)?))
+            }
+        }
+    }
+
+    /// Deliver a notification in the background to every sink its kind is routed to.
+    /// A failing sink is logged and does not keep the others from receiving it.
+    pub fn notify(self: &Arc<Self>, notification: Notification) {
+        let settings = self.settings();
+        if !settings.enabled {
+            return;
+        }
+        let Some(sinks) = settings.routes.get(&notification.kind).filter(|s| !s.is_empty()).cloned() else {
+            return;
+        };
+
+        let dispatcher = self.clone();
+        tokio::spawn(async move {
+            for kind in sinks {
+                let result = match dispatcher.sink(kind, &settings) {
+                    Ok(sink) => sink.send(&notification).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    log::warn!("Failed to deliver {:?} notification to {:?}: {}", notification.kind, kind, e);
+                }
+            }
+        });
+    }
+}
+
+/// Send a notification through the dispatcher managed by the app
+pub fn notify(app_handle: &tauri::AppHandle, notification: Notification) {
+    if let Some(dispatcher) = app_handle.try_state::<NotificationState>() {
+        dispatcher.notify(notification);
+    }
+}
+
+#[tauri::command]
+pub async fn get_notification_settings(state: tauri::State<'_, NotificationState>) -> Result<NotificationSettings, CollectorError> {
+    Ok(state.settings())
+}
+
+/// Replace the notification settings. The SMTP password goes to the OS keychain when given;
+/// an empty string removes it.
+#[tauri::command]
+pub async fn update_notification_settings(
+    settings: NotificationSettings,
+    email_password: Option<String>,
+    state: tauri::State<'_, NotificationState>,
+) -> Result<NotificationSettings, CollectorError> {
+    if let Some(email) = &settings.email {
+        email.validate()?;
+    }
+
+    match email_password.as_deref() {
+        Some("") => email::forget_password()?,
+        Some(password) => email::store_password(password)?,
+        None => {}
+    }
+
+    log::info!("Notification settings updated");
+    state.update(settings)?;
+    Ok(state.settings())
+}
+
+/// Send a test notification through one sink, regardless of the routing rules
+#[tauri::command]
+pub async fn send_test_notification(sink: SinkKind, state: tauri::State<'_, NotificationState>) -> Result<String, CollectorError> {
+    let notification = Notification::new(
+        NotificationKind::TaskCompleted,
+        "Test notification".to_string(),
+        "Notifications from BIDS Collector reach you here.".to_string(),
+    );
+
+    state.sink(sink, &state.settings())?.send(&notification).await?;
+    Ok(format!("Test notification sent to {:?}", sink))
+}
+
+#[tauri::command]
+pub async fn clear_notification_badge(state: tauri::State<'_, NotificationState>) -> Result<(), CollectorError> {
+    desktop::clear_badge(&state.app_handle, &state.badge);
+    Ok(())
+}
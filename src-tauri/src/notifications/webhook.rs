@@ -0,0 +1,66 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::{Notification, NotificationSink};
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookSettings {
+    pub url: String,
+    /// Extra headers, e.g. an Authorization token expected by the receiver
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl WebhookSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        let url = url::Url::parse(&self.url)
+            .map_err(|e| format!("Invalid webhook URL {}: {}", self.url, e))?;
+        if url.scheme() != "https" && url.scheme() != "http" {
+            return Err(format!("Webhook URL must be http(s), got {}", url.scheme()));
+        }
+        Ok(())
+    }
+}
+
+/// POSTs the notification as JSON. The `text` field makes the payload readable by
+/// Slack, Mattermost and Teams incoming webhooks without further mapping.
+pub struct WebhookSink {
+    settings: WebhookSettings,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(settings: WebhookSettings) -> Result<WebhookSink, String> {
+        let client = crate::network::builder()
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to create webhook client: {}", e))?;
+        Ok(WebhookSink { settings, client })
+    }
+}
+
+#[async_trait]
+impl NotificationSink for WebhookSink {
+    async fn send(&self, notification: &Notification) -> Result<(), String> {
+        let mut payload = serde_json::to_value(notification)
+            .map_err(|e| format!("Failed to serialize notification: {}", e))?;
+        payload["text"] = serde_json::json!(format!("{}: {}", notification.title, notification.body));
+
+        let mut request = self.client.post(&self.settings.url).json(&payload);
+        for (name, value) in &self.settings.headers {
+            request = request.header(name, value);
+        }
+
+        let response = request.send().await
+            .map_err(|e| format!("Webhook request failed: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Webhook returned HTTP {}", response.status()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::Manager;
+
+use crate::DownloadState;
+
+/// How often the taskbar/Dock indicator is refreshed from the task manager
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Statuses of tasks that no longer count towards the aggregate progress
+pub const FINISHED_STATUSES: &[&str] = &["completed", "failed", "cancelled", "interrupted"];
+
+/// Progress of all running tasks together, as shown by the OS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AggregateProgress {
+    running_tasks: usize,
+    /// Percent of the known bytes, None while no task knows its size yet
+    percent: Option<u64>,
+    /// A failed task has not been cleaned up yet
+    has_failures: bool,
+}
+
+fn aggregate(state: &DownloadState) -> AggregateProgress {
+    let downloads = state.lock().unwrap();
+    let mut running_tasks = 0;
+    let mut has_failures = false;
+    let (mut total, mut done) = (0u64, 0u64);
+
+    for progress in downloads.values() {
+        if progress.status == "failed" {
+            has_failures = true;
+        }
+        if FINISHED_STATUSES.contains(&progress.status.as_str()) {
+            continue;
+        }
+        running_tasks += 1;
+        total += progress.total_size;
+        done += progress.downloaded_size.min(progress.total_size);
+    }
+
+    AggregateProgress {
+        running_tasks,
+        percent: (total > 0).then(|| done * 100 / total),
+        has_failures,
+    }
+}
+
+/// Show the aggregate on the main window: Windows taskbar progress, macOS Dock progress and
+/// badge, and the Unity launcher on Linux. Platforms without support ignore it.
+fn apply(app_handle: &tauri::AppHandle, progress: AggregateProgress) {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return;
+    };
+
+    let state = match progress {
+        AggregateProgress { running_tasks: 0, .. } => ProgressBarState {
+            status: Some(ProgressBarStatus::None),
+            progress: None,
+        },
+        AggregateProgress { percent: None, .. } => ProgressBarState {
+            status: Some(ProgressBarStatus::Indeterminate),
+            progress: None,
+        },
+        AggregateProgress { percent, has_failures, .. } => ProgressBarState {
+            status: Some(if has_failures { ProgressBarStatus::Error } else { ProgressBarStatus::Normal }),
+            progress: percent,
+        },
+    };
+    if let Err(e) = window.set_progress_bar(state) {
+        log::warn!("Failed to update taskbar progress: {}", e);
+    }
+
+    // The badge shows how many tasks are running; Windows has no badge count
+    if !cfg!(target_os = "windows") {
+        let count = (progress.running_tasks > 0).then_some(progress.running_tasks as i64);
+        let _ = window.set_badge_count(count);
+    }
+}
+
+/// Keep the OS progress indicators in sync with the running tasks for the lifetime of the app
+pub fn spawn(app_handle: tauri::AppHandle, state: DownloadState) {
+    tauri::async_runtime::spawn(async move {
+        let mut shown = None;
+        loop {
+            let progress = aggregate(&state);
+            if shown != Some(progress) {
+                apply(&app_handle, progress);
+                shown = Some(progress);
+            }
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+        }
+    });
+}
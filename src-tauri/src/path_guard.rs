@@ -0,0 +1,164 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use tauri::Manager;
+
+use crate::error::CollectorError;
+
+/// File in the app data directory listing the destination roots the user registered
+pub const ROOTS_FILE: &str = "destination_roots.json";
+
+/// Directories the backend may write to or delete from.
+///
+/// Every filesystem write, rename and delete checks its target with `check` first, so a bug in
+/// the frontend or a crafted task payload cannot touch files outside the registered
+/// destinations and the app data directory.
+#[derive(Debug, Default)]
+struct AllowList {
+    app_data_dir: Option<PathBuf>,
+    /// Destination roots, persisted in `ROOTS_FILE`
+    roots: Vec<PathBuf>,
+}
+
+static ALLOWED: OnceLock<RwLock<AllowList>> = OnceLock::new();
+
+fn allowed() -> &'static RwLock<AllowList> {
+    ALLOWED.get_or_init(|| RwLock::new(AllowList::default()))
+}
+
+/// Resolve `path` to an absolute path. Symlinks are resolved for the part of the path that
+/// exists, so a link cannot lead out of a root; `..` is rejected outright.
+fn resolve(path: &Path) -> Result<PathBuf, String> {
+    if !path.is_absolute() {
+        return Err(format!("Refusing to use relative path {}", path.display()));
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("Refusing to use path {} containing '..'", path.display()));
+    }
+
+    // Canonicalize the deepest existing ancestor and append the rest
+    let mut existing = path;
+    let mut rest = Vec::new();
+    let base = loop {
+        if let Ok(canonical) = std::fs::canonicalize(existing) {
+            break canonical;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => break existing.to_path_buf(),
+        }
+    };
+    Ok(rest.iter().rev().fold(base, |resolved, name| resolved.join(name)))
+}
+
+/// Fail unless `path` lies inside the app data directory or a registered destination root.
+/// Returns the resolved path.
+pub fn check(path: impl AsRef<Path>) -> Result<PathBuf, String> {
+    let path = path.as_ref();
+    let resolved = resolve(path)?;
+
+    let allowed = allowed().read().unwrap();
+    let permitted = allowed.app_data_dir.iter()
+        .chain(allowed.roots.iter())
+        .any(|root| resolved.starts_with(root));
+    if !permitted {
+        log::info!("Blocked filesystem access outside the allowed roots: {}", path.display());
+        return Err(format!(
+            "{} is outside the registered destination folders; add its folder as a destination first",
+            path.display()
+        ));
+    }
+    Ok(resolved)
+}
+
+fn roots_path() -> Result<PathBuf, String> {
+    allowed().read().unwrap().app_data_dir.as_ref()
+        .map(|dir| dir.join(ROOTS_FILE))
+        .ok_or_else(|| "Path guard is not initialized".to_string())
+}
+
+fn save_roots(roots: &[PathBuf]) -> Result<(), String> {
+    let path = roots_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_vec_pretty(roots)
+        .map_err(|e| format!("Failed to serialize destination roots: {}", e))?;
+    std::fs::write(&path, content)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Allow the app data directory and load the registered destination roots
+pub fn init(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let app_data_dir = app_handle.path().app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+    let app_data_dir = resolve(&app_data_dir)?;
+    let path = app_data_dir.join(ROOTS_FILE);
+
+    let roots: Vec<PathBuf> = match std::fs::read(&path) {
+        Ok(content) => serde_json::from_slice(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+
+    log::info!("Path guard allows {} and {} destination roots", app_data_dir.display(), roots.len());
+    *allowed().write().unwrap() = AllowList {
+        app_data_dir: Some(app_data_dir),
+        roots,
+    };
+    Ok(())
+}
+
+/// Register a destination root; returns false when it was already allowed
+pub fn add_root(path: &str) -> Result<bool, String> {
+    let root = resolve(Path::new(path))?;
+    if root.parent().is_none() {
+        return Err(format!("Refusing to register the filesystem root {} as a destination", root.display()));
+    }
+
+    let roots = {
+        let mut allowed = allowed().write().unwrap();
+        if allowed.roots.contains(&root) {
+            return Ok(false);
+        }
+        allowed.roots.push(root.clone());
+        allowed.roots.clone()
+    };
+    save_roots(&roots)?;
+    log::info!("Registered destination root {}", root.display());
+    Ok(true)
+}
+
+fn remove_root(path: &str) -> Result<(), String> {
+    let root = resolve(Path::new(path))?;
+    let roots = {
+        let mut allowed = allowed().write().unwrap();
+        allowed.roots.retain(|r| r != &root);
+        allowed.roots.clone()
+    };
+    save_roots(&roots)?;
+    log::info!("Removed destination root {}", root.display());
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_destination_roots() -> Result<Vec<PathBuf>, CollectorError> {
+    Ok(allowed().read().unwrap().roots.clone())
+}
+
+/// Allow the backend to write into `path` and everything below it
+#[tauri::command]
+pub async fn add_destination_root(path: String) -> Result<Vec<PathBuf>, CollectorError> {
+    add_root(&path)?;
+    list_destination_roots().await
+}
+
+#[tauri::command]
+pub async fn remove_destination_root(path: String) -> Result<Vec<PathBuf>, CollectorError> {
+    remove_root(&path)?;
+    list_destination_roots().await
+}
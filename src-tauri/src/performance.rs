@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use sysinfo::System;
+
+use crate::error::CollectorError;
+use crate::scheduler::SchedulerState;
+use crate::tuning::{TransferTuning, TuningState};
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+/// Minimum logical cores for performance mode to be picked automatically
+const CAPABLE_CPU_CORES: usize = 8;
+
+/// Minimum memory currently available; performance mode buffers up to 64 MiB per transfer
+const CAPABLE_AVAILABLE_MEMORY: u64 = 4 * GIB;
+
+/// What the quick hardware probe found at startup (or when re-probed)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HardwareProbe {
+    pub cpu_cores: usize,
+    pub total_memory: u64,
+    pub available_memory: u64,
+}
+
+impl HardwareProbe {
+    pub fn run() -> HardwareProbe {
+        let mut system = System::new();
+        system.refresh_memory();
+
+        HardwareProbe {
+            cpu_cores: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            total_memory: system.total_memory(),
+            available_memory: system.available_memory(),
+        }
+    }
+
+    /// Whether the machine can take larger buffers and more parallel transfers
+    pub fn is_capable(&self) -> bool {
+        self.cpu_cores >= CAPABLE_CPU_CORES && self.available_memory >= CAPABLE_AVAILABLE_MEMORY
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceMode {
+    pub enabled: bool,
+    /// Chosen by the hardware probe rather than by the user
+    pub automatic: bool,
+    pub probe: HardwareProbe,
+    /// Tuning to go back to when performance mode is turned off
+    #[serde(skip)]
+    previous_tuning: Option<TransferTuning>,
+}
+
+pub type PerformanceState = Arc<Mutex<PerformanceMode>>;
+
+impl PerformanceMode {
+    /// Probe the hardware and decide automatically
+    pub fn detect() -> PerformanceMode {
+        let probe = HardwareProbe::run();
+        log::info!(
+            "Hardware probe: {} cores, {} of {} memory available",
+            probe.cpu_cores,
+            crate::formatting::bytes(probe.available_memory),
+            crate::formatting::bytes(probe.total_memory)
+        );
+
+        PerformanceMode {
+            enabled: probe.is_capable(),
+            automatic: true,
+            probe,
+            previous_tuning: None,
+        }
+    }
+
+    /// Switch the transfer tuning and the scheduler to match `enabled`
+    pub fn apply(&mut self, tuning: &TuningState, scheduler: &SchedulerState) {
+        let mut tuning = tuning.lock().unwrap();
+        if self.enabled {
+            if tuning.profile != TransferTuning::performance().profile {
+                self.previous_tuning = Some(tuning.clone());
+                *tuning = TransferTuning::performance();
+            }
+        } else if tuning.profile == TransferTuning::performance().profile {
+            *tuning = self.previous_tuning.take().unwrap_or_default();
+        }
+        scheduler.set_performance_mode(self.enabled);
+
+        log::info!("Performance mode {} ({})", if self.enabled { "on" } else { "off" }, if self.automatic { "automatic" } else { "manual" });
+    }
+}
+
+#[tauri::command]
+pub async fn get_performance_mode(state: tauri::State<'_, PerformanceState>) -> Result<PerformanceMode, CollectorError> {
+    Ok(state.lock().unwrap().clone())
+}
+
+/// Turn performance mode on or off, or hand the choice back to the hardware probe when
+/// `enabled` is not given
+#[tauri::command]
+pub async fn set_performance_mode(
+    enabled: Option<bool>,
+    state: tauri::State<'_, PerformanceState>,
+    tuning: tauri::State<'_, TuningState>,
+    scheduler: tauri::State<'_, SchedulerState>,
+) -> Result<PerformanceMode, CollectorError> {
+    let mut mode = state.lock().unwrap();
+    match enabled {
+        Some(enabled) => {
+            mode.enabled = enabled;
+            mode.automatic = false;
+        }
+        None => {
+            let detected = PerformanceMode::detect();
+            mode.enabled = detected.enabled;
+            mode.automatic = true;
+            mode.probe = detected.probe;
+        }
+    }
+
+    mode.apply(tuning.inner(), scheduler.inner());
+    Ok(mode.clone())
+}
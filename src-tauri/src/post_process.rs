@@ -0,0 +1,318 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::gzip::{self, GzipValidator};
+
+/// Text files above this size are not parsed by the sidecar and TSV checks
+const MAX_TEXT_SIZE: u64 = 16 * 1024 * 1024;
+
+const READ_CHUNK: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Passed,
+    Warning,
+    Failed,
+}
+
+/// Outcome of one post-processing handler for one file, kept in the manifest entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileCheck {
+    pub handler: String,
+    pub status: CheckStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl FileCheck {
+    fn passed(handler: &str) -> FileCheck {
+        FileCheck { handler: handler.to_string(), status: CheckStatus::Passed, message: None }
+    }
+
+    fn warning(handler: &str, message: String) -> FileCheck {
+        FileCheck { handler: handler.to_string(), status: CheckStatus::Warning, message: Some(message) }
+    }
+
+    fn failed(handler: &str, message: String) -> FileCheck {
+        FileCheck { handler: handler.to_string(), status: CheckStatus::Failed, message: Some(message) }
+    }
+}
+
+/// Where the content of a file that just landed can be read from
+pub enum Content<'a> {
+    Disk(&'a Path),
+    Memory(&'a [u8]),
+}
+
+impl Content<'_> {
+    /// The whole content, unless it is larger than `limit`
+    fn read_small(&self, limit: u64) -> Result<Option<Cow<'_, [u8]>>, String> {
+        match self {
+            Content::Memory(bytes) => Ok((bytes.len() as u64 <= limit).then_some(Cow::Borrowed(*bytes))),
+            Content::Disk(path) => {
+                let size = std::fs::metadata(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+                    .len();
+                if size > limit {
+                    return Ok(None);
+                }
+                std::fs::read(path)
+                    .map(|bytes| Some(Cow::Owned(bytes)))
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+            }
+        }
+    }
+}
+
+pub struct LandedFile<'a> {
+    /// Path relative to the dataset root
+    pub path: &'a str,
+    pub content: Content<'a>,
+    /// The gzip stream was already validated while it was transferred
+    pub gzip_verified: bool,
+}
+
+/// A check run on every file it applies to once the file has landed.
+///
+/// Handlers only report; a failing check is recorded in the manifest and does not stop the
+/// collection. A new handler needs an implementation here and an entry in `handlers`.
+pub trait PostProcessor: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn applies_to(&self, path: &str) -> bool;
+    fn check(&self, file: &LandedFile) -> FileCheck;
+}
+
+fn handlers() -> Vec<Box<dyn PostProcessor>> {
+    vec![Box::new(GzipIntegrity), Box::new(JsonSidecar), Box::new(TsvColumns)]
+}
+
+/// Run every handler that applies to the file
+pub fn run(file: &LandedFile) -> Vec<FileCheck> {
+    let checks: Vec<FileCheck> = handlers()
+        .iter()
+        .filter(|handler| handler.applies_to(file.path))
+        .map(|handler| handler.check(file))
+        .collect();
+
+    for check in checks.iter().filter(|c| c.status != CheckStatus::Passed) {
+        log::info!("{} check of {}: {:?} - {}", check.handler, file.path, check.status, check.message.as_deref().unwrap_or(""));
+    }
+    checks
+}
+
+/// Run the handlers on a file on disk without blocking the async runtime
+pub async fn run_on_disk(path: String, disk_path: PathBuf, gzip_verified: bool) -> Vec<FileCheck> {
+    let relative = path.clone();
+    tokio::task::spawn_blocking(move || {
+        run(&LandedFile { path: &path, content: Content::Disk(&disk_path), gzip_verified })
+    })
+    .await
+    .unwrap_or_else(|e| vec![FileCheck::failed("post-processing", format!("Checks of {} did not finish: {}", relative, e))])
+}
+
+/// Run the handlers on content held in memory, e.g. before it is uploaded
+pub fn run_in_memory(path: &str, content: &[u8], gzip_verified: bool) -> Vec<FileCheck> {
+    run(&LandedFile { path, content: Content::Memory(content), gzip_verified })
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Inflates `.gz` files to the end, checking every member's CRC and length
+struct GzipIntegrity;
+
+impl PostProcessor for GzipIntegrity {
+    fn name(&self) -> &'static str {
+        "gzip-integrity"
+    }
+
+    fn applies_to(&self, path: &str) -> bool {
+        gzip::is_gzip(path)
+    }
+
+    fn check(&self, file: &LandedFile) -> FileCheck {
+        if file.gzip_verified {
+            return FileCheck::passed(self.name());
+        }
+
+        let result = match &file.content {
+            Content::Memory(bytes) => gzip::verify_bytes(bytes),
+            Content::Disk(path) => verify_gzip_file(path),
+        };
+        match result {
+            Ok(()) => FileCheck::passed(self.name()),
+            Err(e) => FileCheck::failed(self.name(), e),
+        }
+    }
+}
+
+fn verify_gzip_file(path: &Path) -> Result<(), String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut validator = GzipValidator::new();
+    let mut buffer = vec![0u8; READ_CHUNK];
+    loop {
+        let read = file.read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            return validator.finish();
+        }
+        validator.update(&buffer[..read])?;
+    }
+}
+
+#[derive(Clone, Copy)]
+enum JsonType {
+    String,
+    Number,
+    Array,
+    NumberOrArray,
+}
+
+impl JsonType {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            JsonType::String => value.is_string(),
+            JsonType::Number => value.is_number(),
+            JsonType::Array => value.is_array(),
+            JsonType::NumberOrArray => value.is_number() || value.is_array(),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            JsonType::String => "a string",
+            JsonType::Number => "a number",
+            JsonType::Array => "an array",
+            JsonType::NumberOrArray => "a number or an array",
+        }
+    }
+}
+
+/// Types of common sidecar fields, from the BIDS specification
+const SIDECAR_FIELDS: &[(&str, JsonType)] = &[
+    ("RepetitionTime", JsonType::Number),
+    ("EchoTime", JsonType::NumberOrArray),
+    ("FlipAngle", JsonType::Number),
+    ("SliceTiming", JsonType::Array),
+    ("TaskName", JsonType::String),
+    ("PhaseEncodingDirection", JsonType::String),
+    ("SamplingFrequency", JsonType::Number),
+    ("Name", JsonType::String),
+    ("BIDSVersion", JsonType::String),
+    ("Authors", JsonType::Array),
+];
+
+/// Parses JSON sidecars and checks the types of well-known fields
+struct JsonSidecar;
+
+impl PostProcessor for JsonSidecar {
+    fn name(&self) -> &'static str {
+        "json-sidecar"
+    }
+
+    fn applies_to(&self, path: &str) -> bool {
+        path.to_lowercase().ends_with(".json")
+    }
+
+    fn check(&self, file: &LandedFile) -> FileCheck {
+        let content = match file.content.read_small(MAX_TEXT_SIZE) {
+            Ok(Some(content)) => content,
+            Ok(None) => return FileCheck::warning(self.name(), "Too large to check".to_string()),
+            Err(e) => return FileCheck::failed(self.name(), e),
+        };
+        let value: Value = match serde_json::from_slice(&content) {
+            Ok(value) => value,
+            Err(e) => return FileCheck::failed(self.name(), format!("Invalid JSON: {}", e)),
+        };
+        let Some(object) = value.as_object() else {
+            return FileCheck::failed(self.name(), "Sidecar is not a JSON object".to_string());
+        };
+
+        let mut problems = Vec::new();
+        if file_name(file.path) == "dataset_description.json" {
+            for required in ["Name", "BIDSVersion"] {
+                if !object.contains_key(required) {
+                    problems.push(format!("{} is missing", required));
+                }
+            }
+        }
+        for (field, expected) in SIDECAR_FIELDS {
+            if let Some(value) = object.get(*field) {
+                if !expected.matches(value) {
+                    problems.push(format!("{} should be {}", field, expected.describe()));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            FileCheck::passed(self.name())
+        } else {
+            FileCheck::failed(self.name(), problems.join("; "))
+        }
+    }
+}
+
+/// Checks that every row of a TSV file has as many columns as its header;
+/// for `participants.tsv` also that the IDs are unique `sub-` labels
+struct TsvColumns;
+
+impl PostProcessor for TsvColumns {
+    fn name(&self) -> &'static str {
+        "tsv-columns"
+    }
+
+    fn applies_to(&self, path: &str) -> bool {
+        path.to_lowercase().ends_with(".tsv")
+    }
+
+    fn check(&self, file: &LandedFile) -> FileCheck {
+        let content = match file.content.read_small(MAX_TEXT_SIZE) {
+            Ok(Some(content)) => content,
+            Ok(None) => return FileCheck::warning(self.name(), "Too large to check".to_string()),
+            Err(e) => return FileCheck::failed(self.name(), e),
+        };
+        let text = String::from_utf8_lossy(&content);
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+
+        let Some(header) = lines.next() else {
+            return FileCheck::failed(self.name(), "File is empty".to_string());
+        };
+        let columns: Vec<&str> = header.split('\t').collect();
+        let participants = file_name(file.path) == "participants.tsv";
+        if participants && columns.first() != Some(&"participant_id") {
+            return FileCheck::failed(self.name(), "First column must be participant_id".to_string());
+        }
+
+        let mut problems = Vec::new();
+        let mut seen = HashSet::new();
+        for (number, line) in lines.enumerate() {
+            let row = number + 2;
+            let values: Vec<&str> = line.split('\t').collect();
+            if values.len() != columns.len() {
+                problems.push(format!("row {} has {} columns, header has {}", row, values.len(), columns.len()));
+            }
+            if participants {
+                let id = values[0];
+                if !id.starts_with("sub-") {
+                    problems.push(format!("row {}: {} is not a sub- label", row, id));
+                }
+                if !seen.insert(id) {
+                    problems.push(format!("row {}: {} is listed twice", row, id));
+                }
+            }
+        }
+
+        match problems.len() {
+            0 => FileCheck::passed(self.name()),
+            count if count > 5 => FileCheck::failed(self.name(), format!("{} (and {} more)", problems[..5].join("; "), count - 5)),
+            _ => FileCheck::failed(self.name(), problems.join("; ")),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::CollectorError;
+use crate::providers;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// Directory node of the previewed file tree; `size` and `file_count` include all descendants
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewNode {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub file_count: u64,
+    pub children: Vec<PreviewNode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetPreview {
+    pub accession: String,
+    pub total_size: u64,
+    pub file_count: u64,
+    pub files: Vec<PreviewFile>,
+    pub tree: PreviewNode,
+}
+
+impl PreviewNode {
+    fn dir(name: &str, path: &str) -> PreviewNode {
+        PreviewNode {
+            name: name.to_string(),
+            path: path.to_string(),
+            is_dir: true,
+            size: 0,
+            file_count: 0,
+            children: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, relative_path: &str, size: u64) {
+        self.size += size;
+        self.file_count += 1;
+
+        match relative_path.split_once('/') {
+            Some((dir_name, rest)) => {
+                let child_path = join(&self.path, dir_name);
+                let index = match self.children.iter().position(|c| c.is_dir && c.name == dir_name) {
+                    Some(index) => index,
+                    None => {
+                        self.children.push(PreviewNode::dir(dir_name, &child_path));
+                        self.children.len() - 1
+                    }
+                };
+                self.children[index].insert(rest, size);
+            }
+            None => self.children.push(PreviewNode {
+                name: relative_path.to_string(),
+                path: join(&self.path, relative_path),
+                is_dir: false,
+                size,
+                file_count: 1,
+                children: Vec::new(),
+            }),
+        }
+    }
+
+    /// Directories first, then files, each alphabetically
+    fn sort(&mut self) {
+        self.children.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        for child in &mut self.children {
+            child.sort();
+        }
+    }
+}
+
+fn join(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+/// List a dataset without downloading it, so the UI can show its size before a task is created.
+/// `provider` defaults to OpenNeuro.
+#[tauri::command]
+pub async fn preview_dataset(accession: String, provider: Option<String>) -> Result<DatasetPreview, CollectorError> {
+    let provider = providers::registry().get(provider.as_deref().unwrap_or("openneuro"))?;
+    log::info!("Previewing {} dataset: {}", provider.display_name(), accession);
+
+    let listing = providers::list_dataset_files(provider, &accession).await?;
+    let accession = listing.identifier;
+    let file_list = listing.files;
+
+    let mut tree = PreviewNode::dir(&accession, "");
+    let mut files = Vec::with_capacity(file_list.len());
+
+    for file_info in &file_list {
+        tree.insert(&file_info.path, file_info.size);
+        files.push(PreviewFile {
+            path: file_info.path.clone(),
+            size: file_info.size,
+        });
+    }
+    tree.sort();
+
+    log::info!("Preview of {}: {} files, {} bytes", accession, tree.file_count, tree.size);
+
+    Ok(DatasetPreview {
+        accession,
+        total_size: tree.size,
+        file_count: tree.file_count,
+        files,
+        tree,
+    })
+}
@@ -0,0 +1,146 @@
+use serde_json::{json, Map, Value};
+use tokio::fs;
+
+use crate::path_guard;
+
+const COLLECTOR_NAME: &str = "bids-collector";
+
+/// Where a collected copy came from, recorded in `dataset_description.json`
+#[derive(Debug, Clone)]
+pub struct Provenance {
+    pub accession: String,
+    pub doi: Option<String>,
+    pub snapshot: Option<String>,
+    pub source_url: String,
+    pub retrieved_at: String,
+}
+
+impl Provenance {
+    /// Build provenance for an OpenNeuro dataset from the task's download path
+    /// Example: "10.18112_openneuro.ds006486.v1.0.0" -> doi "10.18112/openneuro.ds006486.v1.0.0", snapshot "1.0.0"
+    pub fn openneuro(accession: &str, download_path: &str) -> Provenance {
+        let doi = if download_path.starts_with("10.") {
+            Some(download_path.replacen('_', "/", 1))
+        } else {
+            None
+        };
+
+        let snapshot = crate::providers::openneuro::extract_openneuro_snapshot(download_path);
+
+        let source_url = match &snapshot {
+            Some(version) => format!("https://openneuro.org/datasets/{}/versions/{}", accession, version),
+            None => format!("https://openneuro.org/datasets/{}", accession),
+        };
+
+        Provenance {
+            accession: accession.to_string(),
+            doi,
+            snapshot,
+            source_url,
+            retrieved_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Build provenance for a dandiset; only published versions have a DOI
+    pub fn dandi(dandiset_id: &str, version: Option<&str>) -> Provenance {
+        let published = version.filter(|v| *v != "draft");
+
+        Provenance {
+            accession: dandiset_id.to_string(),
+            doi: published.map(|v| format!("10.48324/dandi.{}/{}", dandiset_id, v)),
+            snapshot: version.map(|v| v.to_string()),
+            source_url: match version {
+                Some(v) => format!("https://dandiarchive.org/dandiset/{}/{}", dandiset_id, v),
+                None => format!("https://dandiarchive.org/dandiset/{}", dandiset_id),
+            },
+            retrieved_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Build provenance for a DOI-identified deposit (Zenodo or any DataCite DOI)
+    pub fn doi(identifier: &str) -> Provenance {
+        let doi = crate::providers::zenodo::normalize_doi(identifier);
+
+        Provenance {
+            accession: doi.clone(),
+            source_url: format!("https://doi.org/{}", doi),
+            doi: Some(doi),
+            snapshot: None,
+            retrieved_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Build provenance for files shared through signed URLs, from the host they came from
+    pub fn signed_urls(name: &str, origin: Option<&str>) -> Provenance {
+        Provenance {
+            accession: name.to_string(),
+            doi: None,
+            snapshot: None,
+            source_url: origin.unwrap_or("signed URLs").to_string(),
+            retrieved_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Add `GeneratedBy` and `SourceDatasets` entries to a dataset_description.json document.
+/// Both fields are part of the BIDS spec, so the result stays valid.
+pub fn augment_description(content: &[u8], provenance: &Provenance) -> Result<Vec<u8>, String> {
+    let mut description: Map<String, Value> = serde_json::from_slice(content)
+        .map_err(|e| format!("Invalid dataset_description.json: {}", e))?;
+
+    let generated_by = json!({
+        "Name": COLLECTOR_NAME,
+        "Version": env!("CARGO_PKG_VERSION"),
+        "Description": format!("Retrieved from {} on {}", provenance.source_url, provenance.retrieved_at),
+    });
+
+    let mut source = Map::new();
+    if let Some(doi) = &provenance.doi {
+        source.insert("DOI".to_string(), json!(format!("doi:{}", doi)));
+    }
+    source.insert("URL".to_string(), json!(provenance.source_url));
+    if let Some(version) = &provenance.snapshot {
+        source.insert("Version".to_string(), json!(version));
+    }
+
+    // Replace our own earlier entry on re-collection instead of stacking duplicates
+    let entries = array_field(&mut description, "GeneratedBy");
+    entries.retain(|entry| entry.get("Name").and_then(|n| n.as_str()) != Some(COLLECTOR_NAME));
+    entries.push(generated_by);
+
+    let sources = array_field(&mut description, "SourceDatasets");
+    sources.retain(|entry| entry.get("URL") != source.get("URL"));
+    sources.push(Value::Object(source));
+
+    serde_json::to_vec_pretty(&Value::Object(description))
+        .map_err(|e| format!("Failed to serialize dataset_description.json: {}", e))
+}
+
+/// Augment the dataset_description.json of a dataset collected to local storage
+pub async fn augment_local(dest_dir: &str, provenance: &Provenance) -> Result<(), String> {
+    let path = format!("{}/dataset_description.json", dest_dir);
+
+    let content = match fs::read(&path).await {
+        Ok(content) => content,
+        Err(e) => {
+            log::warn!("Skipping provenance, cannot read {}: {}", path, e);
+            return Ok(());
+        }
+    };
+
+    let augmented = augment_description(&content, provenance)?;
+    path_guard::check(&path)?;
+    fs::write(&path, augmented).await
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    log::info!("Recorded provenance for {} in {}", provenance.accession, path);
+    Ok(())
+}
+
+fn array_field<'a>(object: &'a mut Map<String, Value>, field: &str) -> &'a mut Vec<Value> {
+    let value = object.entry(field.to_string()).or_insert_with(|| json!([]));
+    if !value.is_array() {
+        *value = json!([]);
+    }
+    value.as_array_mut().unwrap()
+}
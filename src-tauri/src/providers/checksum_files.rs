@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use super::{DatasetProvider, RemoteFile};
+use crate::checksum::{Checksum, ChecksumAlgorithm};
+use crate::error::CollectorError;
+use crate::rate_limit::RateLimiter;
+
+/// Checksum files larger than this are not a digest list and are left alone
+const MAX_CHECKSUM_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Algorithm of a checksum file such as PhysioNet's SHA256SUMS.txt, by file name
+fn checksum_file_algorithm(path: &str) -> Option<ChecksumAlgorithm> {
+    let name = path.rsplit('/').next().unwrap_or(path).to_uppercase();
+    match name.strip_suffix(".TXT").unwrap_or(&name) {
+        "SHA256SUMS" => Some(ChecksumAlgorithm::Sha256),
+        "MD5SUMS" => Some(ChecksumAlgorithm::Md5),
+        _ => None,
+    }
+}
+
+/// Entries of a sha256sum/md5sum style file, in either the GNU ("<digest>  <path>",
+/// "<digest> *<path>") or the BSD ("SHA256 (<path>) = <digest>") format
+fn parse_checksum_file(content: &str, algorithm: ChecksumAlgorithm) -> Vec<(String, Checksum)> {
+    let length = match algorithm {
+        ChecksumAlgorithm::Sha256 => 64,
+        ChecksumAlgorithm::Md5 => 32,
+    };
+
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_end_matches('\r');
+            let (digest, path) = match line.split_once(" (") {
+                Some((_, rest)) => {
+                    let (path, digest) = rest.rsplit_once(") = ")?;
+                    (digest.trim(), path)
+                }
+                None => {
+                    let (digest, path) = line.split_once(char::is_whitespace)?;
+                    (digest, path.trim_start().trim_start_matches('*'))
+                }
+            };
+            if digest.len() != length || !digest.bytes().all(|b| b.is_ascii_hexdigit()) || path.is_empty() {
+                return None;
+            }
+            let checksum = Checksum { algorithm, value: digest.to_lowercase() };
+            Some((path.trim_start_matches("./").to_string(), checksum))
+        })
+        .collect()
+}
+
+/// Content of a checksum file, read on one of the provider's connections
+async fn fetch_text(
+    provider: &dyn DatasetProvider,
+    client: &reqwest::Client,
+    file: &RemoteFile,
+    rate_limiter: &RateLimiter,
+) -> Result<String, CollectorError> {
+    let (_connection, response) = rate_limiter
+        .request(provider.id(), || provider.fetch_file_stream(client, file))
+        .await?;
+    let content = response.text().await
+        .map_err(|e| CollectorError::from_request(format!("Failed to read {}", file.path), &e))?;
+    Ok(content)
+}
+
+/// Fill in the expected checksum of listed files from the checksum files the publisher
+/// placed in the dataset, so they are verified against the publisher's digests after
+/// transfer. Digests the provider's own API reports take precedence; a checksum file
+/// that cannot be read only loses its digests.
+pub async fn import(provider: &dyn DatasetProvider, files: &mut [RemoteFile], rate_limiter: &RateLimiter) {
+    let checksum_files: Vec<(RemoteFile, ChecksumAlgorithm)> = files.iter()
+        .filter(|file| file.size <= MAX_CHECKSUM_FILE_SIZE)
+        .filter_map(|file| checksum_file_algorithm(&file.path).map(|algorithm| (file.clone(), algorithm)))
+        .collect();
+
+    let client = crate::network::client();
+    let mut published: HashMap<String, Checksum> = HashMap::new();
+    for (file, algorithm) in &checksum_files {
+        let content = match fetch_text(provider, &client, file, rate_limiter).await {
+            Ok(content) => content,
+            Err(e) => {
+                log::info!("{}: Skipping checksum file {}: {}", provider.display_name(), file.path, e);
+                continue;
+            }
+        };
+
+        // Paths in a checksum file are relative to the directory holding it
+        let dir = file.path.rsplit_once('/').map(|(dir, _)| format!("{}/", dir)).unwrap_or_default();
+        let entries = parse_checksum_file(&content, *algorithm);
+        log::info!("{}: Read {} digest(s) from {}", provider.display_name(), entries.len(), file.path);
+        for (path, checksum) in entries {
+            published.entry(format!("{}{}", dir, path)).or_insert(checksum);
+        }
+    }
+
+    let mut imported = 0;
+    for file in files.iter_mut() {
+        let Some(checksum) = published.remove(&file.path) else {
+            continue;
+        };
+        match &file.checksum {
+            None => {
+                file.checksum = Some(checksum);
+                imported += 1;
+            }
+            Some(existing) if existing.algorithm == checksum.algorithm && existing.value != checksum.value => {
+                log::info!(
+                    "{}: Checksum file lists {} {} for {}, the API reports {}; verifying against the API",
+                    provider.display_name(), checksum, checksum.value, file.path, existing.value
+                );
+            }
+            Some(_) => {}
+        }
+    }
+
+    let verified = files.iter().filter(|file| file.checksum.is_some()).count();
+    log::info!(
+        "{}: {} of {} files have a published checksum ({} from checksum files)",
+        provider.display_name(), verified, files.len(), imported
+    );
+}
@@ -0,0 +1,171 @@
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::Value;
+
+use super::{DatasetProvider, RemoteFile};
+use crate::checksum::Checksum;
+use crate::error::{CollectorError, ErrorKind};
+use crate::provenance::Provenance;
+
+const DANDI_API: &str = "https://api.dandiarchive.org/api";
+
+/// DANDI Archive dandisets, listed through the DANDI REST API
+pub struct Dandi;
+
+#[async_trait]
+impl DatasetProvider for Dandi {
+    fn id(&self) -> &'static str {
+        "dandi"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "DANDI Archive"
+    }
+
+    async fn resolve_identifier(&self, download_path: &str) -> Result<String, CollectorError> {
+        let dandiset = DandisetRef::parse(download_path);
+        let version = resolve_version(&crate::network::client(), &dandiset).await?;
+        Ok(format!("{}/{}", dandiset.id, version))
+    }
+
+    async fn list_files(&self, identifier: &str) -> Result<Vec<RemoteFile>, CollectorError> {
+        list_dandiset_files(identifier).await
+    }
+
+    fn resolved_version(&self, identifier: &str) -> Option<String> {
+        DandisetRef::parse(identifier).version
+    }
+
+    fn provenance(&self, download_path: &str) -> Provenance {
+        let dandiset = DandisetRef::parse(download_path);
+        Provenance::dandi(&dandiset.id, dandiset.version.as_deref())
+    }
+}
+
+/// A dandiset identifier with an optional pinned version
+#[derive(Debug, Clone)]
+pub struct DandisetRef {
+    pub id: String,
+    pub version: Option<String>,
+}
+
+impl DandisetRef {
+    /// Parse "000003", "DANDI:000003", "000003/0.210812.1448", a DOI-like path
+    /// ("10.48324_dandi.000003_0.210812.1448") or a dandiarchive.org URL
+    pub fn parse(input: &str) -> DandisetRef {
+        let captures = Regex::new(r"(\d{6})(?:[/@_.](draft|\d+\.\d+\.\d+))?")
+            .ok()
+            .and_then(|re| re.captures(input));
+
+        match captures {
+            Some(caps) => DandisetRef {
+                id: caps[1].to_string(),
+                version: caps.get(2).map(|m| m.as_str().to_string()),
+            },
+            None => DandisetRef {
+                id: input.trim().to_string(),
+                version: None,
+            },
+        }
+    }
+}
+
+/// Resolve the version to download: the pinned one, else the most recent published version, else the draft
+async fn resolve_version(client: &reqwest::Client, dandiset: &DandisetRef) -> Result<String, CollectorError> {
+    if let Some(version) = &dandiset.version {
+        return Ok(version.clone());
+    }
+
+    let url = format!("{}/dandisets/{}/", DANDI_API, dandiset.id);
+    let info = get_json(client, &url).await
+        .map_err(|e| e.context(format!("Failed to resolve dandiset {}", dandiset.id)))?;
+
+    let version = info.get("most_recent_published_version")
+        .and_then(|v| v.get("version"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("draft")
+        .to_string();
+
+    log::info!("DANDI: Resolved dandiset {} to version {}", dandiset.id, version);
+    Ok(version)
+}
+
+/// List the assets of a dandiset with their sizes and SHA-256 digests
+pub async fn list_dandiset_files(identifier: &str) -> Result<Vec<RemoteFile>, CollectorError> {
+    let client = crate::network::client();
+    let dandiset = DandisetRef::parse(identifier);
+    let version = resolve_version(&client, &dandiset).await?;
+
+    let mut files = Vec::new();
+    let mut next_url = Some(format!(
+        "{}/dandisets/{}/versions/{}/assets/?page_size=1000&metadata=true",
+        DANDI_API, dandiset.id, version
+    ));
+
+    while let Some(url) = next_url {
+        log::info!("Listing DANDI assets from: {}", url);
+        let page = get_json(&client, &url).await
+            .map_err(|e| e.context(format!("Failed to list assets of dandiset {}", dandiset.id)))?;
+
+        let results = page.get("results")
+            .and_then(|r| r.as_array())
+            .ok_or("Unexpected DANDI asset listing response")?;
+
+        for asset in results {
+            if let Some(file) = asset_to_remote_file(asset) {
+                files.push(file);
+            }
+        }
+
+        next_url = page.get("next").and_then(|n| n.as_str()).map(|n| n.to_string());
+    }
+
+    log::info!("DANDI: Found {} assets in dandiset {} version {}", files.len(), dandiset.id, version);
+    Ok(files)
+}
+
+fn asset_to_remote_file(asset: &Value) -> Option<RemoteFile> {
+    let path = asset.get("path")?.as_str()?;
+
+    // Zarr assets are directories of chunks and cannot be fetched as a single file
+    if asset.get("zarr").map(|z| !z.is_null()).unwrap_or(false) {
+        log::info!("DANDI: Skipping Zarr asset {}", path);
+        return None;
+    }
+
+    let asset_id = asset.get("asset_id")?.as_str()?;
+    let size = asset.get("size").and_then(|s| s.as_u64()).unwrap_or(0);
+    let checksum = asset.get("metadata")
+        .and_then(|m| m.get("digest"))
+        .and_then(|d| d.get("dandi:sha2-256"))
+        .and_then(|d| d.as_str())
+        .map(Checksum::sha256);
+
+    Some(RemoteFile {
+        path: path.to_string(),
+        size,
+        // Redirects to a presigned S3 URL
+        url: format!("{}/assets/{}/download/", DANDI_API, asset_id),
+        checksum,
+        etag: None,
+        last_modified: asset.get("modified").and_then(|m| m.as_str()).map(|m| m.to_string()),
+    })
+}
+
+async fn get_json(client: &reqwest::Client, url: &str) -> Result<Value, CollectorError> {
+    let response = client.get(url)
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .map_err(|e| CollectorError::from_request("HTTP request failed", &e))?;
+
+    if let Some(e) = crate::rate_limit::throttled_error(&response) {
+        return Err(e);
+    }
+    if !response.status().is_success() {
+        return Err(CollectorError::new(ErrorKind::of_status(response.status()), format!("HTTP {}", response.status())));
+    }
+
+    response.json::<Value>().await
+        .map_err(|e| CollectorError::from_request("Invalid JSON response", &e))
+}
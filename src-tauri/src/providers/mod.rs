@@ -0,0 +1,173 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::checksum::Checksum;
+use crate::error::{CollectorError, ErrorKind};
+use crate::provenance::Provenance;
+
+pub mod checksum_files;
+pub mod dandi;
+pub mod openneuro;
+pub mod openneuro_api;
+pub mod signed_urls;
+pub mod zenodo;
+
+/// A file offered by a dataset provider, with its path relative to the dataset root
+#[derive(Debug, Clone)]
+pub struct RemoteFile {
+    pub path: String,
+    pub size: u64,
+    pub url: String,
+    /// Checksum published by the provider, verified after download when present
+    pub checksum: Option<Checksum>,
+    /// Provider-side version markers, compared against the manifest in sync mode
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// A public archive that datasets can be collected from.
+///
+/// The download orchestration only talks to providers through this trait, so a new
+/// archive needs an implementation here and an entry in `ProviderRegistry::builtin`.
+#[async_trait]
+pub trait DatasetProvider: Send + Sync {
+    /// Name used in the task's `datasetProvider` field
+    fn id(&self) -> &'static str;
+
+    fn display_name(&self) -> &'static str;
+
+    /// Other `datasetProvider` values accepted for this provider
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Turn the task's download path (DOI folder name, accession, URL, ...) into the
+    /// identifier `list_files` expects, pinning the version where the provider has one
+    async fn resolve_identifier(&self, download_path: &str) -> Result<String, CollectorError>;
+
+    async fn list_files(&self, identifier: &str) -> Result<Vec<RemoteFile>, CollectorError>;
+
+    /// Version pinned by a resolved identifier, for providers with versioned datasets
+    fn resolved_version(&self, _identifier: &str) -> Option<String> {
+        None
+    }
+
+    /// Open a streaming response for one listed file
+    async fn fetch_file_stream(&self, client: &reqwest::Client, file: &RemoteFile) -> Result<reqwest::Response, CollectorError> {
+        let response = client.get(&file.url).send().await
+            .map_err(|e| CollectorError::from_request("HTTP request failed", &e))?;
+
+        if let Some(e) = crate::rate_limit::throttled_error(&response) {
+            return Err(e);
+        }
+        if !response.status().is_success() {
+            return Err(CollectorError::new(ErrorKind::of_status(response.status()), format!("HTTP error: {}", response.status())));
+        }
+
+        Ok(response)
+    }
+
+    /// Open a streaming response for the rest of a file, from byte `offset` on. Servers may
+    /// ignore the Range header; callers check the response before appending it.
+    async fn fetch_file_range(&self, client: &reqwest::Client, file: &RemoteFile, offset: u64) -> Result<reqwest::Response, CollectorError> {
+        let response = client.get(&file.url)
+            .header(reqwest::header::RANGE, format!("bytes={}-", offset))
+            .send()
+            .await
+            .map_err(|e| CollectorError::from_request("HTTP request failed", &e))?;
+
+        if let Some(e) = crate::rate_limit::throttled_error(&response) {
+            return Err(e);
+        }
+        if !response.status().is_success() {
+            return Err(CollectorError::new(ErrorKind::of_status(response.status()), format!("HTTP error: {}", response.status())));
+        }
+
+        Ok(response)
+    }
+
+    /// Where a copy collected from `download_path` came from
+    fn provenance(&self, download_path: &str) -> Provenance;
+}
+
+/// Summary of a registered provider for the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderInfo {
+    pub id: String,
+    pub name: String,
+    pub aliases: Vec<String>,
+}
+
+pub struct ProviderRegistry {
+    providers: Vec<Box<dyn DatasetProvider>>,
+}
+
+impl ProviderRegistry {
+    fn builtin() -> ProviderRegistry {
+        ProviderRegistry {
+            providers: vec![
+                Box::new(openneuro::OpenNeuro),
+                Box::new(dandi::Dandi),
+                Box::new(zenodo::Zenodo),
+                Box::new(signed_urls::SignedUrls),
+            ],
+        }
+    }
+
+    /// Look up a provider by id or alias, case-insensitively
+    pub fn get(&self, name: &str) -> Result<&dyn DatasetProvider, String> {
+        let name = name.to_lowercase();
+        self.providers
+            .iter()
+            .find(|p| p.id() == name || p.aliases().contains(&name.as_str()))
+            .map(|p| p.as_ref())
+            .ok_or_else(|| format!("Unsupported dataset provider: {}", name))
+    }
+
+    pub fn list(&self) -> Vec<ProviderInfo> {
+        self.providers
+            .iter()
+            .map(|p| ProviderInfo {
+                id: p.id().to_string(),
+                name: p.display_name().to_string(),
+                aliases: p.aliases().iter().map(|a| a.to_string()).collect(),
+            })
+            .collect()
+    }
+}
+
+/// The providers compiled into this build
+pub fn registry() -> &'static ProviderRegistry {
+    static REGISTRY: OnceLock<ProviderRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(ProviderRegistry::builtin)
+}
+
+/// A dataset listing together with the identifier and version it was resolved to
+pub struct DatasetListing {
+    pub identifier: String,
+    pub version: Option<String>,
+    pub files: Vec<RemoteFile>,
+}
+
+/// List a dataset's files, failing on an empty listing
+pub async fn list_dataset_files(provider: &dyn DatasetProvider, download_path: &str) -> Result<DatasetListing, CollectorError> {
+    let identifier = provider.resolve_identifier(download_path).await?;
+    log::info!("{}: Using identifier {} for {}", provider.display_name(), identifier, download_path);
+
+    let files = provider.list_files(&identifier).await?;
+    if files.is_empty() {
+        return Err(CollectorError::new(ErrorKind::NotFound, format!("No files found for dataset: {}", download_path)));
+    }
+
+    Ok(DatasetListing {
+        version: provider.resolved_version(&identifier),
+        identifier,
+        files,
+    })
+}
+
+#[tauri::command]
+pub async fn list_supported_providers() -> Result<Vec<ProviderInfo>, CollectorError> {
+    Ok(registry().list())
+}
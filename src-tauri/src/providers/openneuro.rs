@@ -0,0 +1,236 @@
+use async_trait::async_trait;
+use regex::Regex;
+
+use super::openneuro_api;
+use super::{DatasetListing, DatasetProvider, RemoteFile};
+use crate::error::{CollectorError, ErrorKind};
+use crate::provenance::Provenance;
+use crate::s3_client::parse_list_objects;
+
+/// Public bucket holding the raw datasets
+const BUCKET_URL: &str = "https://s3.amazonaws.com/openneuro.org";
+
+/// Public bucket of the OpenNeuroDerivatives project, with one `<pipeline>/<accession>-<pipeline>/`
+/// prefix per processed dataset
+const DERIVATIVES_BUCKET_URL: &str = "https://s3.amazonaws.com/openneuro-derivatives";
+
+/// Pipelines published to the derivatives bucket
+pub const DERIVATIVE_PIPELINES: &[&str] = &["fmriprep", "mriqc"];
+
+/// OpenNeuro datasets. Unpinned paths are listed from the public `openneuro.org` S3 bucket;
+/// paths with a snapshot version ("...ds006486.v1.0.0") are listed from that snapshot's
+/// file index, whose URLs point at the exact object versions of the snapshot.
+pub struct OpenNeuro;
+
+#[async_trait]
+impl DatasetProvider for OpenNeuro {
+    fn id(&self) -> &'static str {
+        "openneuro"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "OpenNeuro"
+    }
+
+    /// "ds006486" for the current bucket contents, "ds006486/1.0.0" for a snapshot that exists
+    async fn resolve_identifier(&self, download_path: &str) -> Result<String, CollectorError> {
+        let accession = extract_openneuro_accession(download_path);
+        let Some(snapshot) = extract_openneuro_snapshot(download_path) else {
+            return Ok(accession);
+        };
+
+        let snapshots = openneuro_api::list_snapshots(&crate::network::client(), &accession).await?;
+        if !snapshots.contains(&snapshot) {
+            return Err(CollectorError::new(ErrorKind::NotFound, format!(
+                "Snapshot {} of {} no longer exists on OpenNeuro (available: {})",
+                snapshot,
+                accession,
+                if snapshots.is_empty() { "none".to_string() } else { snapshots.join(", ") }
+            )));
+        }
+
+        Ok(format!("{}/{}", accession, snapshot))
+    }
+
+    async fn list_files(&self, identifier: &str) -> Result<Vec<RemoteFile>, CollectorError> {
+        match identifier.split_once('/') {
+            Some((accession, snapshot)) => list_snapshot_files(accession, snapshot).await,
+            None => list_openneuro_files(identifier).await,
+        }
+    }
+
+    fn resolved_version(&self, identifier: &str) -> Option<String> {
+        identifier.split_once('/').map(|(_, snapshot)| snapshot.to_string())
+    }
+
+    fn provenance(&self, download_path: &str) -> Provenance {
+        Provenance::openneuro(&extract_openneuro_accession(download_path), download_path)
+    }
+}
+
+/// Extract OpenNeuro accession number from DOI or path
+/// Example: "10.18112_openneuro.ds006486.v1.0.0" -> "ds006486"
+pub fn extract_openneuro_accession(path: &str) -> String {
+    // If path already looks like an accession (ds followed by numbers), return as-is
+    if let Some(re) = Regex::new(r"^ds\d+$").ok() {
+        if re.is_match(path) {
+            return path.to_lowercase();
+        }
+    }
+
+    // Extract accession from DOI-like path (e.g., "10.18112_openneuro.ds006486.v1.0.0" -> "ds006486")
+    if let Some(re) = Regex::new(r"ds(\d+)").ok() {
+        if let Some(captures) = re.captures(path) {
+            if let Some(number) = captures.get(1) {
+                return format!("ds{}", number.as_str());
+            }
+        }
+    }
+
+    // If no accession found, return the original path
+    path.to_string()
+}
+
+/// Extract the snapshot version from a DOI or path, if one is pinned
+/// Example: "10.18112_openneuro.ds006486.v1.0.0" -> "1.0.0", "ds006486" -> None
+pub fn extract_openneuro_snapshot(path: &str) -> Option<String> {
+    Regex::new(r"ds\d+(?:\.v|[/@:]v?)(\d+\.\d+\.\d+)$")
+        .ok()?
+        .captures(path)?
+        .get(1)
+        .map(|m| m.as_str().to_string())
+}
+
+/// List the files of a snapshot from its file index
+async fn list_snapshot_files(accession: &str, snapshot: &str) -> Result<Vec<RemoteFile>, CollectorError> {
+    let client = crate::network::client();
+    let files = openneuro_api::list_snapshot_files(&client, accession, snapshot).await?;
+
+    Ok(files
+        .into_iter()
+        .map(|file| RemoteFile {
+            url: file.url.unwrap_or_else(|| format!("{}/{}/{}", BUCKET_URL, accession, file.path)),
+            path: file.path,
+            size: file.size,
+            checksum: file.checksum,
+            etag: None,
+            last_modified: None,
+        })
+        .collect())
+}
+
+/// List every file of an OpenNeuro dataset, following ListObjectsV2 pagination
+pub async fn list_openneuro_files(accession: &str) -> Result<Vec<RemoteFile>, CollectorError> {
+    list_bucket(BUCKET_URL, &format!("{}/", accession), "").await
+}
+
+/// Pipelines named by the task's `derivatives` field: `true` for every published pipeline, or
+/// a list such as `["fmriprep"]`. Only OpenNeuro datasets have derivatives to add.
+pub fn derivatives_of(task: &serde_json::Value, provider: &dyn DatasetProvider) -> Result<Vec<String>, String> {
+    let pipelines: Vec<String> = match task.get("derivatives") {
+        None | Some(serde_json::Value::Null) | Some(serde_json::Value::Bool(false)) => return Ok(Vec::new()),
+        Some(serde_json::Value::Bool(true)) => DERIVATIVE_PIPELINES.iter().map(|p| p.to_string()).collect(),
+        Some(serde_json::Value::Array(items)) => items.iter()
+            .map(|item| item.as_str()
+                .map(|s| s.trim().to_lowercase())
+                .ok_or_else(|| "Invalid entry in derivatives: expected a pipeline name".to_string()))
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err("Invalid derivatives: expected true or a list of pipelines".to_string()),
+    };
+
+    if provider.id() != OpenNeuro.id() && !pipelines.is_empty() {
+        return Err(format!("{} datasets have no published derivatives", provider.display_name()));
+    }
+    if let Some(unknown) = pipelines.iter().find(|p| !DERIVATIVE_PIPELINES.contains(&p.as_str())) {
+        return Err(format!("Unknown derivatives pipeline {} (available: {})", unknown, DERIVATIVE_PIPELINES.join(", ")));
+    }
+    Ok(pipelines)
+}
+
+/// Add the outputs of `pipelines` to a dataset listing, under `derivatives/<pipeline>/` as BIDS
+/// lays them out. Pipelines that never processed the dataset add nothing, and files the raw
+/// dataset already ships at the same path are kept from the raw dataset.
+pub async fn append_derivatives(listing: &mut DatasetListing, pipelines: &[String]) -> Result<(), CollectorError> {
+    // Derivatives are not versioned, so a snapshot gets the current outputs
+    let accession = listing.identifier.split('/').next().unwrap_or(&listing.identifier).to_string();
+    for pipeline in pipelines {
+        let prefix = format!("{}/{}-{}/", pipeline, accession, pipeline);
+        let derivatives = list_bucket(DERIVATIVES_BUCKET_URL, &prefix, &format!("derivatives/{}/", pipeline)).await?;
+        if derivatives.is_empty() {
+            log::info!("No {} derivatives published for {}", pipeline, accession);
+            continue;
+        }
+        log::info!("Adding {} {} derivative files of {}", derivatives.len(), pipeline, accession);
+
+        let raw_paths: std::collections::HashSet<String> = listing.files.iter().map(|f| f.path.clone()).collect();
+        listing.files.extend(derivatives.into_iter().filter(|f| !raw_paths.contains(&f.path)));
+    }
+    Ok(())
+}
+
+/// List the objects under `prefix` in a public bucket, with their paths relative to the prefix
+/// and put under `path_prefix`
+async fn list_bucket(bucket_url: &str, prefix: &str, path_prefix: &str) -> Result<Vec<RemoteFile>, CollectorError> {
+    let client = crate::network::client();
+    let prefix = prefix.to_string();
+    let mut files = Vec::new();
+    let mut continuation_token: Option<String> = None;
+
+    loop {
+        let mut params = vec![("list-type", "2".to_string()), ("prefix", prefix.clone())];
+        if let Some(token) = &continuation_token {
+            params.push(("continuation-token", token.clone()));
+        }
+        let list_url = url::Url::parse_with_params(bucket_url, &params)
+            .map_err(|e| format!("Invalid listing URL: {}", e))?;
+        log::info!("Listing files from: {}", list_url);
+
+        let list_response = client.get(list_url).send().await
+            .map_err(|e| CollectorError::from_request("Failed to list dataset files", &e))?;
+
+        if let Some(e) = crate::rate_limit::throttled_error(&list_response) {
+            return Err(e.context("Failed to list files"));
+        }
+        let status = list_response.status();
+        if !status.is_success() {
+            return Err(CollectorError::new(ErrorKind::of_status(status), format!("Failed to list files: HTTP {}", status)));
+        }
+
+        let xml_content = list_response.text().await
+            .map_err(|e| CollectorError::from_request("Failed to read listing response", &e))?;
+
+        // Skip directory placeholders (keys ending with /)
+        for object in parse_list_objects(&xml_content).into_iter().filter(|o| !o.key.ends_with('/')) {
+            // Remove the listed prefix from the key to get the relative path
+            let path = object.key.strip_prefix(&prefix)
+                .unwrap_or(&object.key);
+            files.push(RemoteFile {
+                path: format!("{}{}", path_prefix, path),
+                size: object.size,
+                url: format!("{}/{}", bucket_url, object.key),
+                checksum: None,
+                etag: object.etag,
+                last_modified: object.last_modified,
+            });
+        }
+
+        continuation_token = parse_continuation_token(&xml_content);
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+
+    Ok(files)
+}
+
+/// Return the token for the next listing page, if the response was truncated
+fn parse_continuation_token(xml_content: &str) -> Option<String> {
+    if !xml_content.contains("<IsTruncated>true</IsTruncated>") {
+        return None;
+    }
+
+    Regex::new(r"<NextContinuationToken>([^<]+)</NextContinuationToken>").ok()?
+        .captures(xml_content)?
+        .get(1)
+        .map(|m| m.as_str().to_string())
+}
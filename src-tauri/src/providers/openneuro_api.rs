@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::openneuro::extract_openneuro_accession;
+use crate::checksum::Checksum;
+use crate::error::{CollectorError, ErrorKind};
+
+const GRAPHQL_URL: &str = "https://openneuro.org/crn/graphql";
+
+/// What the UI shows before a dataset is collected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetMetadata {
+    pub accession: String,
+    pub name: Option<String>,
+    pub doi: Option<String>,
+    /// Tag of the newest snapshot, if the dataset was ever published
+    pub latest_snapshot: Option<String>,
+    /// Every snapshot tag, oldest first
+    pub snapshots: Vec<String>,
+    /// Snapshot the summary and files below describe
+    pub snapshot: Option<String>,
+    pub modalities: Vec<String>,
+    pub subjects: u64,
+    pub tasks: Vec<String>,
+    pub total_size: u64,
+    pub file_count: u64,
+    /// Only filled when requested, since large datasets take many queries to list
+    pub files: Option<Vec<SnapshotFile>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub path: String,
+    pub size: u64,
+    pub url: Option<String>,
+    /// Digest from the file's git-annex key; small files kept in git have none
+    pub checksum: Option<Checksum>,
+}
+
+const DATASET_QUERY: &str = "
+query Dataset($id: ID!) {
+  dataset(id: $id) {
+    id
+    latestSnapshot { tag }
+    snapshots { tag created }
+  }
+}";
+
+const SNAPSHOT_QUERY: &str = "
+query Snapshot($id: ID!, $tag: String!) {
+  snapshot(datasetId: $id, tag: $tag) {
+    tag
+    description { Name DatasetDOI }
+    summary { modalities size totalFiles subjects tasks }
+  }
+}";
+
+const SNAPSHOT_FILES_QUERY: &str = "
+query SnapshotFiles($id: ID!, $tag: String!, $tree: String) {
+  snapshot(datasetId: $id, tag: $tag) {
+    files(tree: $tree) { id key filename size directory urls }
+  }
+}";
+
+/// Fetch title, snapshots, modality summary and optionally the file list of an OpenNeuro dataset.
+/// Without `snapshot` the latest one is described; an unknown snapshot tag is an error.
+#[tauri::command]
+pub async fn get_dataset_metadata(
+    accession: String,
+    snapshot: Option<String>,
+    include_files: Option<bool>,
+) -> Result<DatasetMetadata, CollectorError> {
+    let client = crate::network::client();
+    let accession = extract_openneuro_accession(&accession);
+    log::info!("Fetching OpenNeuro metadata for {}", accession);
+
+    let snapshots = list_snapshots(&client, &accession).await?;
+    let latest_snapshot = snapshots.last().cloned();
+
+    let tag = match snapshot {
+        Some(requested) => {
+            if !snapshots.contains(&requested) {
+                return Err(CollectorError::new(ErrorKind::NotFound, format!(
+                    "Snapshot {} of {} does not exist (available: {})",
+                    requested, accession, snapshots.join(", ")
+                )));
+            }
+            Some(requested)
+        }
+        None => latest_snapshot.clone(),
+    };
+
+    let mut metadata = DatasetMetadata {
+        accession: accession.clone(),
+        name: None,
+        doi: None,
+        latest_snapshot,
+        snapshots,
+        snapshot: tag.clone(),
+        modalities: Vec::new(),
+        subjects: 0,
+        tasks: Vec::new(),
+        total_size: 0,
+        file_count: 0,
+        files: None,
+    };
+
+    // Drafts without any snapshot have no summary to show
+    let Some(tag) = tag else {
+        return Ok(metadata);
+    };
+
+    let data = graphql(&client, SNAPSHOT_QUERY, json!({ "id": accession, "tag": tag })).await?;
+    let snapshot = data.get("snapshot").ok_or("Snapshot missing from OpenNeuro response")?;
+
+    let description = snapshot.get("description");
+    metadata.name = description.and_then(|d| d.get("Name")).and_then(|n| n.as_str()).map(|n| n.to_string());
+    metadata.doi = description.and_then(|d| d.get("DatasetDOI")).and_then(|n| n.as_str()).map(|n| n.to_string());
+
+    if let Some(summary) = snapshot.get("summary").filter(|s| !s.is_null()) {
+        metadata.modalities = string_array(summary.get("modalities"));
+        metadata.tasks = string_array(summary.get("tasks"));
+        metadata.subjects = summary.get("subjects").and_then(|s| s.as_array()).map(|s| s.len() as u64).unwrap_or(0);
+        metadata.total_size = summary.get("size").and_then(|s| s.as_u64()).unwrap_or(0);
+        metadata.file_count = summary.get("totalFiles").and_then(|s| s.as_u64()).unwrap_or(0);
+    }
+
+    if include_files.unwrap_or(false) {
+        metadata.files = Some(list_snapshot_files(&client, &accession, &tag).await?);
+    }
+
+    Ok(metadata)
+}
+
+/// Snapshot tags of a dataset, oldest first
+pub async fn list_snapshots(client: &reqwest::Client, accession: &str) -> Result<Vec<String>, CollectorError> {
+    let data = graphql(client, DATASET_QUERY, json!({ "id": accession })).await?;
+    let dataset = data.get("dataset")
+        .filter(|d| !d.is_null())
+        .ok_or_else(|| CollectorError::new(ErrorKind::NotFound, format!("Dataset {} not found on OpenNeuro", accession)))?;
+
+    let mut snapshots: Vec<(String, String)> = dataset.get("snapshots")
+        .and_then(|s| s.as_array())
+        .map(|snapshots| {
+            snapshots
+                .iter()
+                .filter_map(|s| {
+                    let tag = s.get("tag")?.as_str()?.to_string();
+                    let created = s.get("created").and_then(|c| c.as_str()).unwrap_or("").to_string();
+                    Some((created, tag))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    snapshots.sort();
+
+    Ok(snapshots.into_iter().map(|(_, tag)| tag).collect())
+}
+
+/// Walk the snapshot's file tree, one query per directory
+pub async fn list_snapshot_files(client: &reqwest::Client, accession: &str, tag: &str) -> Result<Vec<SnapshotFile>, CollectorError> {
+    let mut files = Vec::new();
+    // (tree key, path of that directory relative to the dataset root)
+    let mut pending: Vec<(Option<String>, String)> = vec![(None, String::new())];
+
+    while let Some((tree, prefix)) = pending.pop() {
+        let data = graphql(
+            client,
+            SNAPSHOT_FILES_QUERY,
+            json!({ "id": accession, "tag": tag, "tree": tree }),
+        ).await?;
+
+        let entries = data.get("snapshot")
+            .and_then(|s| s.get("files"))
+            .and_then(|f| f.as_array())
+            .ok_or_else(|| CollectorError::new(ErrorKind::NotFound, format!("No file listing for {} snapshot {}", accession, tag)))?;
+
+        for entry in entries {
+            let Some(name) = entry.get("filename").and_then(|n| n.as_str()) else {
+                continue;
+            };
+            let path = if prefix.is_empty() { name.to_string() } else { format!("{}/{}", prefix, name) };
+
+            if entry.get("directory").and_then(|d| d.as_bool()).unwrap_or(false) {
+                let key = entry.get("key").and_then(|k| k.as_str()).map(|k| k.to_string());
+                pending.push((key, path));
+            } else {
+                files.push(SnapshotFile {
+                    path,
+                    size: entry.get("size").and_then(|s| s.as_u64()).unwrap_or(0),
+                    url: entry.get("urls")
+                        .and_then(|u| u.as_array())
+                        .and_then(|u| u.first())
+                        .and_then(|u| u.as_str())
+                        .map(|u| u.to_string()),
+                    checksum: entry.get("key")
+                        .and_then(|k| k.as_str())
+                        .and_then(Checksum::from_annex_key),
+                });
+            }
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    log::info!("OpenNeuro: Found {} files in {} snapshot {}", files.len(), accession, tag);
+    Ok(files)
+}
+
+async fn graphql(client: &reqwest::Client, query: &str, variables: Value) -> Result<Value, CollectorError> {
+    let response = client.post(GRAPHQL_URL)
+        .json(&json!({ "query": query, "variables": variables }))
+        .send()
+        .await
+        .map_err(|e| CollectorError::from_request("OpenNeuro GraphQL request failed", &e))?;
+
+    if let Some(e) = crate::rate_limit::throttled_error(&response) {
+        return Err(e.context("OpenNeuro GraphQL request failed"));
+    }
+    let status = response.status();
+    if !status.is_success() {
+        return Err(CollectorError::new(ErrorKind::of_status(status), format!("OpenNeuro GraphQL request failed: HTTP {}", status)));
+    }
+
+    let body: Value = response.json().await
+        .map_err(|e| CollectorError::from_request("Invalid OpenNeuro GraphQL response", &e))?;
+
+    if let Some(message) = body.get("errors")
+        .and_then(|e| e.as_array())
+        .and_then(|e| e.first())
+        .and_then(|e| e.get("message"))
+        .and_then(|m| m.as_str())
+    {
+        return Err(format!("OpenNeuro GraphQL error: {}", message).into());
+    }
+
+    body.get("data").cloned().ok_or_else(|| "OpenNeuro GraphQL response has no data".into())
+}
+
+fn string_array(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|items| items.iter().filter_map(|i| i.as_str().map(|s| s.to_string())).collect())
+        .unwrap_or_default()
+}
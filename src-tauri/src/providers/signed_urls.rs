@@ -0,0 +1,252 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashSet;
+
+use super::{DatasetListing, DatasetProvider, RemoteFile};
+use crate::error::{CollectorError, ErrorKind};
+use crate::provenance::Provenance;
+use crate::rate_limit::RateLimiter;
+
+/// Signed URLs probed for their size at the same time while listing, within the
+/// provider's connection cap
+const PROBE_CONCURRENCY: usize = 8;
+
+/// Query parameters that carry a signature, so the query is never recorded
+const SIGNATURE_PARAMS: &[&str] = &["x-amz-signature", "x-goog-signature", "signature", "sig"];
+
+/// Files behind presigned URLs a collaborator shared from a private bucket (S3, GCS or an
+/// Azure SAS). The URLs come from the task's `signedUrls` field; `downloadPath` only names
+/// the dataset they are collected into.
+pub struct SignedUrls;
+
+#[async_trait]
+impl DatasetProvider for SignedUrls {
+    fn id(&self) -> &'static str {
+        "signed-urls"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Signed URLs"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["presigned", "presigned-urls"]
+    }
+
+    async fn resolve_identifier(&self, download_path: &str) -> Result<String, CollectorError> {
+        Ok(download_path.to_string())
+    }
+
+    async fn list_files(&self, _identifier: &str) -> Result<Vec<RemoteFile>, CollectorError> {
+        Err(CollectorError::new(ErrorKind::InvalidInput, "Signed URL tasks list their files in the task's signedUrls field"))
+    }
+
+    fn provenance(&self, download_path: &str) -> Provenance {
+        Provenance::signed_urls(download_path, None)
+    }
+}
+
+/// One entry of `signedUrls`: a bare URL, or an object naming the file's path in the
+/// dataset and, optionally, its size so it does not have to be probed
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SignedUrl {
+    Url(String),
+    File {
+        url: String,
+        #[serde(default)]
+        path: Option<String>,
+        #[serde(default)]
+        size: Option<u64>,
+    },
+}
+
+impl SignedUrl {
+    fn url(&self) -> &str {
+        match self {
+            SignedUrl::Url(url) | SignedUrl::File { url, .. } => url,
+        }
+    }
+
+    /// Path in the dataset; the URL's file name unless given
+    fn path(&self) -> Result<String, String> {
+        let path = match self {
+            SignedUrl::File { path: Some(path), .. } => path.trim_matches('/').to_string(),
+            _ => url::Url::parse(self.url())
+                .ok()
+                .and_then(|url| url.path_segments()?.next_back().map(|s| s.to_string()))
+                .map(|name| percent_encoding::percent_decode_str(&name).decode_utf8_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        if path.is_empty() || path.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+            return Err(format!("Invalid path {:?} for a signed URL; give each URL a relative path", path));
+        }
+        Ok(path)
+    }
+
+    fn size(&self) -> Option<u64> {
+        match self {
+            SignedUrl::File { size, .. } => *size,
+            SignedUrl::Url(_) => None,
+        }
+    }
+}
+
+/// Task field `signedUrls`; None when the task does not use signed URLs
+pub fn from_task(task: &Value) -> Result<Option<Vec<SignedUrl>>, String> {
+    match task.get("signedUrls") {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => {
+            let urls: Vec<SignedUrl> = serde_json::from_value(value.clone())
+                .map_err(|e| format!("Invalid signedUrls: {}", e))?;
+            if urls.is_empty() {
+                return Err("signedUrls is empty".to_string());
+            }
+            Ok(Some(urls))
+        }
+    }
+}
+
+fn query_param(url: &url::Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.to_string())
+}
+
+fn parse_amz_date(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ").ok().map(|date| date.and_utc())
+}
+
+/// When a presigned URL stops working, from its signature parameters: AWS and GCS V4
+/// (`X-Amz-Date` + `X-Amz-Expires`, `X-Goog-Date` + `X-Goog-Expires`), V2 `Expires`
+/// (seconds since the epoch) and Azure SAS `se`
+pub fn expiry(url: &str) -> Option<DateTime<Utc>> {
+    let url = url::Url::parse(url).ok()?;
+
+    for (date, expires) in [("X-Amz-Date", "X-Amz-Expires"), ("X-Goog-Date", "X-Goog-Expires")] {
+        if let (Some(date), Some(expires)) = (query_param(&url, date), query_param(&url, expires)) {
+            let seconds: i64 = expires.parse().ok()?;
+            return Some(parse_amz_date(&date)? + chrono::Duration::seconds(seconds));
+        }
+    }
+    if let Some(expires) = query_param(&url, "Expires") {
+        return DateTime::from_timestamp(expires.parse().ok()?, 0);
+    }
+    if let Some(se) = query_param(&url, "se") {
+        return DateTime::parse_from_rfc3339(&se).ok().map(|date| date.with_timezone(&Utc))
+            .or_else(|| chrono::NaiveDate::parse_from_str(&se, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0).map(|d| d.and_utc()));
+    }
+    None
+}
+
+/// Whether a signed URL's expiry has passed; URLs without a known expiry never expire
+pub fn is_expired(url: &str) -> bool {
+    expiry(url).is_some_and(|expiry| expiry <= Utc::now())
+}
+
+/// The URL without its query when the query carries a signature, for manifests and logs
+pub fn strip_signature(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(mut parsed) if parsed.query_pairs().any(|(key, _)| SIGNATURE_PARAMS.contains(&key.to_lowercase().as_str())) => {
+            parsed.set_query(None);
+            parsed.to_string()
+        }
+        _ => url.to_string(),
+    }
+}
+
+/// Size and version markers of a signed URL, read with a one-byte Range request since
+/// URLs presigned for GET usually refuse HEAD
+async fn probe(client: &reqwest::Client, url: &str) -> Result<(u64, Option<String>, Option<String>), CollectorError> {
+    let response = client.get(url)
+        .header(reqwest::header::RANGE, "bytes=0-0")
+        .send()
+        .await
+        .map_err(|e| CollectorError::from_request("HTTP request failed", &e))?;
+    if let Some(e) = crate::rate_limit::throttled_error(&response) {
+        return Err(e);
+    }
+    let status = response.status();
+    if !status.is_success() {
+        return Err(CollectorError::new(ErrorKind::of_status(status), format!("HTTP error: {}", status)));
+    }
+
+    let header = |name: reqwest::header::HeaderName| response.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let size = header(reqwest::header::CONTENT_RANGE)
+        .and_then(|range| range.rsplit_once('/')?.1.trim().parse().ok())
+        .or_else(|| (response.status() == reqwest::StatusCode::OK).then(|| response.content_length()).flatten())
+        .ok_or("Server reported no size")?;
+    Ok((size, header(reqwest::header::ETAG), header(reqwest::header::LAST_MODIFIED)))
+}
+
+/// A listed file for one signed URL, probing its size unless the task gave it. Each probe
+/// takes one of the provider's connections.
+async fn list_file(client: reqwest::Client, signed: SignedUrl, path: String, rate_limiter: &RateLimiter) -> Result<RemoteFile, CollectorError> {
+    let url = signed.url().to_string();
+    let (size, etag, last_modified) = match signed.size() {
+        Some(size) => (size, None, None),
+        None if is_expired(&url) => (0, None, None),
+        None => rate_limiter.request(SignedUrls.id(), || probe(&client, &url)).await
+            .map(|(_, probed)| probed)
+            .map_err(|e| e.context(format!("Failed to read signed URL for {}", path)))?,
+    };
+    Ok(RemoteFile { path, size, url, checksum: None, etag, last_modified })
+}
+
+/// List the files behind a task's signed URLs, those expiring soonest first so they are
+/// transferred before they lapse. URLs that already expired stay listed without being
+/// probed; the transfer reports them instead of failing the task.
+pub async fn list(download_path: &str, urls: &[SignedUrl], rate_limiter: &RateLimiter) -> Result<DatasetListing, CollectorError> {
+    let mut entries = Vec::with_capacity(urls.len());
+    let mut paths = HashSet::new();
+    for signed in urls {
+        let path = signed.path()?;
+        if !paths.insert(path.clone()) {
+            return Err(CollectorError::new(ErrorKind::InvalidInput, format!("More than one signed URL for {}", path)));
+        }
+        entries.push((signed.clone(), path));
+    }
+
+    let client = crate::network::client();
+    let probes = futures_util::stream::iter(entries.into_iter().map(|(signed, path)| list_file(client.clone(), signed, path, rate_limiter)))
+        .buffer_unordered(PROBE_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+    let mut files = probes.into_iter().collect::<Result<Vec<_>, CollectorError>>()?;
+
+    files.sort_by(|a, b| match (expiry(&a.url), expiry(&b.url)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.path.cmp(&b.path),
+    });
+
+    let expired = files.iter().filter(|file| is_expired(&file.url)).count();
+    match files.iter().filter_map(|file| expiry(&file.url)).find(|expiry| *expiry > Utc::now()) {
+        Some(first) => log::info!(
+            "Signed URLs: {} files for {}, the first URL expires at {}; {} already expired",
+            files.len(), download_path, first.to_rfc3339(), expired
+        ),
+        None => log::info!("Signed URLs: {} files for {}, {} already expired", files.len(), download_path, expired),
+    }
+
+    Ok(DatasetListing {
+        identifier: download_path.to_string(),
+        version: None,
+        files,
+    })
+}
+
+/// Provenance recording the host the URLs were shared from, never their signatures
+pub fn provenance(download_path: &str, urls: &[SignedUrl]) -> Provenance {
+    let origin = urls.first()
+        .and_then(|signed| url::Url::parse(signed.url()).ok())
+        .map(|url| format!("{}://{}/", url.scheme(), url.host_str().unwrap_or_default()));
+    Provenance::signed_urls(download_path, origin.as_deref())
+}
@@ -0,0 +1,213 @@
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::Value;
+
+use super::{DatasetProvider, RemoteFile};
+use crate::checksum::Checksum;
+use crate::error::{CollectorError, ErrorKind};
+use crate::provenance::Provenance;
+
+const ZENODO_API: &str = "https://zenodo.org/api";
+const DATACITE_API: &str = "https://api.datacite.org";
+
+/// Zenodo records and other DataCite DOIs that link downloadable files
+pub struct Zenodo;
+
+#[async_trait]
+impl DatasetProvider for Zenodo {
+    fn id(&self) -> &'static str {
+        "zenodo"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Zenodo / DOI"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["doi"]
+    }
+
+    async fn resolve_identifier(&self, download_path: &str) -> Result<String, CollectorError> {
+        Ok(normalize_doi(download_path))
+    }
+
+    async fn list_files(&self, identifier: &str) -> Result<Vec<RemoteFile>, CollectorError> {
+        list_doi_files(identifier).await
+    }
+
+    fn provenance(&self, download_path: &str) -> Provenance {
+        Provenance::doi(download_path)
+    }
+}
+
+/// What a DOI (or Zenodo reference) points at
+#[derive(Debug, Clone)]
+enum DoiTarget {
+    ZenodoRecord(String),
+    /// Any other DataCite DOI that advertises direct content URLs
+    ContentUrls(Vec<String>),
+}
+
+/// Normalize a DOI from the task path: "10.5281_zenodo.123" -> "10.5281/zenodo.123"
+pub fn normalize_doi(identifier: &str) -> String {
+    let trimmed = identifier
+        .trim()
+        .trim_start_matches("https://doi.org/")
+        .trim_start_matches("doi:");
+
+    if trimmed.starts_with("10.") && !trimmed.contains('/') {
+        trimmed.replacen('_', "/", 1)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Zenodo record ID from a Zenodo DOI, record URL or bare number
+fn zenodo_record_id(identifier: &str) -> Option<String> {
+    let patterns = [
+        r"^(\d+)$",
+        r"zenodo\.(\d+)",
+        r"zenodo\.org/(?:records?|api/records)/(\d+)",
+    ];
+
+    patterns.iter().find_map(|pattern| {
+        Regex::new(pattern)
+            .ok()?
+            .captures(identifier)?
+            .get(1)
+            .map(|m| m.as_str().to_string())
+    })
+}
+
+async fn resolve(client: &reqwest::Client, identifier: &str) -> Result<DoiTarget, CollectorError> {
+    let doi = normalize_doi(identifier);
+
+    if let Some(record_id) = zenodo_record_id(&doi) {
+        return Ok(DoiTarget::ZenodoRecord(record_id));
+    }
+
+    // Generic DOI: ask DataCite where it resolves to
+    let url = format!("{}/dois/{}", DATACITE_API, doi);
+    log::info!("Resolving DOI via DataCite: {}", url);
+    let response = get_json(client, &url).await
+        .map_err(|e| e.context(format!("Failed to resolve DOI {}", doi)))?;
+    let attributes = response.get("data")
+        .and_then(|d| d.get("attributes"))
+        .ok_or_else(|| format!("Unexpected DataCite response for {}", doi))?;
+
+    if let Some(landing) = attributes.get("url").and_then(|u| u.as_str()) {
+        if let Some(record_id) = zenodo_record_id(landing) {
+            return Ok(DoiTarget::ZenodoRecord(record_id));
+        }
+    }
+
+    let content_urls: Vec<String> = attributes.get("contentUrl")
+        .and_then(|c| c.as_array())
+        .map(|urls| urls.iter().filter_map(|u| u.as_str().map(|s| s.to_string())).collect())
+        .unwrap_or_default();
+
+    if content_urls.is_empty() {
+        return Err(CollectorError::new(
+            ErrorKind::NotFound,
+            format!("DOI {} does not point at a Zenodo record or downloadable files", doi),
+        ));
+    }
+
+    Ok(DoiTarget::ContentUrls(content_urls))
+}
+
+/// List the files deposited under a DOI or Zenodo record
+pub async fn list_doi_files(identifier: &str) -> Result<Vec<RemoteFile>, CollectorError> {
+    let client = crate::network::client();
+
+    match resolve(&client, identifier).await? {
+        DoiTarget::ZenodoRecord(record_id) => list_zenodo_record(&client, &record_id).await,
+        DoiTarget::ContentUrls(urls) => list_content_urls(&client, urls).await,
+    }
+}
+
+async fn list_zenodo_record(client: &reqwest::Client, record_id: &str) -> Result<Vec<RemoteFile>, CollectorError> {
+    let url = format!("{}/records/{}", ZENODO_API, record_id);
+    log::info!("Listing Zenodo record: {}", url);
+
+    let record = get_json(client, &url).await
+        .map_err(|e| e.context(format!("Failed to fetch Zenodo record {}", record_id)))?;
+
+    let entries = record.get("files")
+        .and_then(|f| f.as_array())
+        .ok_or_else(|| CollectorError::new(ErrorKind::NotFound, format!("Zenodo record {} has no public files", record_id)))?;
+
+    let files: Vec<RemoteFile> = entries
+        .iter()
+        .filter_map(|entry| {
+            let key = entry.get("key")?.as_str()?;
+            let url = entry.get("links")
+                .and_then(|l| l.get("self"))
+                .and_then(|s| s.as_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| format!("{}/records/{}/files/{}/content", ZENODO_API, record_id, key));
+
+            Some(RemoteFile {
+                path: key.to_string(),
+                size: entry.get("size").and_then(|s| s.as_u64()).unwrap_or(0),
+                url,
+                checksum: entry.get("checksum").and_then(|c| c.as_str()).and_then(Checksum::parse),
+                etag: None,
+                last_modified: None,
+            })
+        })
+        .collect();
+
+    log::info!("Zenodo: Found {} files in record {}", files.len(), record_id);
+    Ok(files)
+}
+
+/// Files linked directly from DataCite metadata; sizes come from a HEAD request
+async fn list_content_urls(client: &reqwest::Client, urls: Vec<String>) -> Result<Vec<RemoteFile>, CollectorError> {
+    let mut files = Vec::with_capacity(urls.len());
+
+    for url in urls {
+        let name = url::Url::parse(&url)
+            .ok()
+            .and_then(|u| u.path_segments().and_then(|mut s| s.next_back().map(|n| n.to_string())))
+            .filter(|n| !n.is_empty())
+            .ok_or_else(|| format!("Cannot derive a file name from {}", url))?;
+
+        let size = match client.head(&url).send().await {
+            Ok(response) => match crate::rate_limit::throttled_error(&response) {
+                Some(e) => return Err(e),
+                None => response.content_length().unwrap_or(0),
+            },
+            Err(_) => 0,
+        };
+
+        files.push(RemoteFile {
+            path: name,
+            size,
+            url,
+            checksum: None,
+            etag: None,
+            last_modified: None,
+        });
+    }
+
+    Ok(files)
+}
+
+async fn get_json(client: &reqwest::Client, url: &str) -> Result<Value, CollectorError> {
+    let response = client.get(url)
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .map_err(|e| CollectorError::from_request("HTTP request failed", &e))?;
+
+    if let Some(e) = crate::rate_limit::throttled_error(&response) {
+        return Err(e);
+    }
+    if !response.status().is_success() {
+        return Err(CollectorError::new(ErrorKind::of_status(response.status()), format!("HTTP {}", response.status())));
+    }
+
+    response.json::<Value>().await
+        .map_err(|e| CollectorError::from_request("Invalid JSON response", &e))
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+use crate::error::CollectorError;
+use crate::formatting;
+use crate::storage;
+
+/// Byte quota configured on a storage location (`quotaBytes` in the storage payload)
+#[derive(Debug, Clone)]
+pub struct StorageQuota {
+    pub quota_bytes: u64,
+    pub location: serde_json::Value,
+}
+
+impl StorageQuota {
+    pub fn from_location(location: &serde_json::Value) -> Option<StorageQuota> {
+        let quota_bytes = location.get("quotaBytes").and_then(|q| q.as_u64())?;
+        Some(StorageQuota {
+            quota_bytes,
+            location: location.clone(),
+        })
+    }
+}
+
+/// Returned when a task would push a storage location over its quota
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaExceeded {
+    pub quota_bytes: u64,
+    pub used_bytes: u64,
+    pub requested_bytes: u64,
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Storage quota exceeded: {} used of {}, this task needs {} more",
+            formatting::bytes(self.used_bytes),
+            formatting::bytes(self.quota_bytes),
+            formatting::bytes(self.requested_bytes)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub quota_bytes: Option<u64>,
+    pub used_bytes: u64,
+    pub available_bytes: Option<u64>,
+    /// Fraction of the quota in use (0.0 - 1.0+), when a quota is set
+    pub utilization: Option<f64>,
+}
+
+/// Refuse a task whose files would not fit in the remaining quota.
+/// Existing data under the location counts as used, even if the task will overwrite it.
+pub async fn enforce(quota: &StorageQuota, requested_bytes: u64) -> Result<(), CollectorError> {
+    let used_bytes = storage_usage(&quota.location).await?;
+    log::info!("Quota check: {} of {} bytes used, {} bytes requested", used_bytes, quota.quota_bytes, requested_bytes);
+
+    if used_bytes.saturating_add(requested_bytes) > quota.quota_bytes {
+        return Err(QuotaExceeded {
+            quota_bytes: quota.quota_bytes,
+            used_bytes,
+            requested_bytes,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Bytes currently stored in a storage location
+pub async fn storage_usage(location: &serde_json::Value) -> Result<u64, String> {
+    let storage_type = location.get("type")
+        .and_then(|t| t.as_str())
+        .ok_or("No storage type specified")?;
+
+    match storage_type {
+        "local" => {
+            let path = location.get("path")
+                .and_then(|p| p.as_str())
+                .ok_or("No storage path specified")?
+                .to_string();
+
+            tokio::task::spawn_blocking(move || directory_size(Path::new(&path)))
+                .await
+                .map_err(|e| format!("Failed to measure storage usage: {}", e))
+        }
+        _ => {
+            let storage = storage::from_storage_location(location)?;
+            Ok(storage.list_sizes("").await?.values().sum())
+        }
+    }
+}
+
+/// Recursive size of a directory; unreadable entries and symlinks are ignored
+fn directory_size(path: &Path) -> u64 {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => directory_size(&entry.path()),
+            Ok(file_type) if file_type.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+#[tauri::command]
+pub async fn get_storage_quota_usage(storage_location: serde_json::Value) -> Result<QuotaUsage, CollectorError> {
+    let used_bytes = storage_usage(&storage_location).await?;
+    let quota_bytes = StorageQuota::from_location(&storage_location).map(|q| q.quota_bytes);
+
+    Ok(QuotaUsage {
+        quota_bytes,
+        used_bytes,
+        available_bytes: quota_bytes.map(|q| q.saturating_sub(used_bytes)),
+        utilization: quota_bytes.map(|q| if q == 0 { 1.0 } else { used_bytes as f64 / q as f64 }),
+    })
+}
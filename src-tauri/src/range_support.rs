@@ -0,0 +1,183 @@
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+use crate::error::CollectorError;
+use crate::providers::{DatasetProvider, RemoteFile};
+use crate::rate_limit::{ConnectionPermit, RateLimiter};
+
+/// File in the app data directory listing endpoints that mishandle Range requests
+pub const QUIRKS_FILE: &str = "range_quirks.json";
+
+/// Range resumes attempted for one file before it is downloaded again from the start
+pub const MAX_RESUMES: u32 = 3;
+
+/// Whole-file downloads of one file after broken streams that could not be resumed
+pub const MAX_FULL_ATTEMPTS: u32 = 3;
+
+/// An endpoint found to answer Range requests incorrectly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeQuirk {
+    /// "scheme://host[:port]"
+    pub endpoint: String,
+    pub reason: String,
+    pub recorded_at: String,
+}
+
+/// Endpoints whose broken transfers are restarted instead of resumed, persisted so a flaky
+/// mirror is not probed again on every file
+pub struct RangeQuirks {
+    quirks_path: PathBuf,
+    quirks: Mutex<Vec<RangeQuirk>>,
+}
+
+pub type RangeQuirkState = Arc<RangeQuirks>;
+
+/// Why a resume could not continue the file
+pub enum ResumeError {
+    /// The server ignored or mangled the Range request
+    Unsupported(String),
+    /// The request itself failed
+    Failed(CollectorError),
+}
+
+fn endpoint(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(parsed) => match parsed.port() {
+            Some(port) => format!("{}://{}:{}", parsed.scheme(), parsed.host_str().unwrap_or_default(), port),
+            None => format!("{}://{}", parsed.scheme(), parsed.host_str().unwrap_or_default()),
+        },
+        Err(_) => url.to_string(),
+    }
+}
+
+impl RangeQuirks {
+    /// Load the recorded quirks from the app data directory
+    pub fn open(app_handle: &tauri::AppHandle) -> Result<RangeQuirks, String> {
+        let quirks_path = app_handle.path().app_data_dir()
+            .map(|dir| dir.join(QUIRKS_FILE))
+            .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+
+        let quirks = match std::fs::read(&quirks_path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", quirks_path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", quirks_path.display(), e)),
+        };
+
+        Ok(RangeQuirks {
+            quirks_path,
+            quirks: Mutex::new(quirks),
+        })
+    }
+
+    pub fn list(&self) -> Vec<RangeQuirk> {
+        self.quirks.lock().unwrap().clone()
+    }
+
+    /// Whether broken transfers from `url` may be resumed with a Range request
+    pub fn supports_ranges(&self, url: &str) -> bool {
+        let endpoint = endpoint(url);
+        !self.quirks.lock().unwrap().iter().any(|quirk| quirk.endpoint == endpoint)
+    }
+
+    /// Remember that the endpoint of `url` mishandles Range requests
+    pub fn record(&self, url: &str, reason: &str) {
+        let endpoint = endpoint(url);
+        log::info!("{} does not support resuming transfers ({}); restarting files from there instead", endpoint, reason);
+
+        let quirks = {
+            let mut quirks = self.quirks.lock().unwrap();
+            if quirks.iter().any(|quirk| quirk.endpoint == endpoint) {
+                return;
+            }
+            quirks.push(RangeQuirk {
+                endpoint,
+                reason: reason.to_string(),
+                recorded_at: chrono::Utc::now().to_rfc3339(),
+            });
+            quirks.clone()
+        };
+        if let Err(e) = self.save(&quirks) {
+            log::warn!("Failed to save range quirks: {}", e);
+        }
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        self.save(&[])?;
+        self.quirks.lock().unwrap().clear();
+        Ok(())
+    }
+
+    fn save(&self, quirks: &[RangeQuirk]) -> Result<(), String> {
+        crate::path_guard::check(&self.quirks_path)?;
+        if let Some(parent) = self.quirks_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        let content = serde_json::to_vec_pretty(quirks)
+            .map_err(|e| format!("Failed to serialize range quirks: {}", e))?;
+        std::fs::write(&self.quirks_path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.quirks_path.display(), e))
+    }
+}
+
+/// Check that a response continues the file at `offset`: a 206 whose Content-Range starts
+/// there and whose Content-Length covers exactly the rest of the file
+fn check_partial_response(response: &reqwest::Response, offset: u64, size: u64) -> Result<(), String> {
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return Err(format!("answered a Range request with HTTP {}", response.status()));
+    }
+
+    let start = response.headers()
+        .get(reqwest::header::CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("bytes "))
+        .and_then(|value| value.split_once('-'))
+        .and_then(|(start, _)| start.trim().parse::<u64>().ok());
+    match start {
+        Some(start) if start == offset => {}
+        Some(start) => return Err(format!("returned bytes from {} instead of {}", start, offset)),
+        None => return Err("sent a partial response without a valid Content-Range".to_string()),
+    }
+
+    if let Some(length) = response.content_length() {
+        if size > 0 && length != size.saturating_sub(offset) {
+            return Err(format!("sent {} bytes for the {} bytes left", length, size.saturating_sub(offset)));
+        }
+    }
+    Ok(())
+}
+
+/// Continue a broken transfer at `offset`, on a new connection to the provider. A provider
+/// asking to slow down is waited for like for any other request.
+pub async fn resume(
+    provider: &dyn DatasetProvider,
+    client: &reqwest::Client,
+    file: &RemoteFile,
+    offset: u64,
+    rate_limiter: &RateLimiter,
+) -> Result<(ConnectionPermit, reqwest::Response), ResumeError> {
+    let (connection, response) = rate_limiter
+        .request(provider.id(), || provider.fetch_file_range(client, file, offset))
+        .await
+        .map_err(ResumeError::Failed)?;
+    check_partial_response(&response, offset, file.size).map_err(ResumeError::Unsupported)?;
+    Ok((connection, response))
+}
+
+#[tauri::command]
+pub async fn list_range_quirks(state: tauri::State<'_, RangeQuirkState>) -> Result<Vec<RangeQuirk>, CollectorError> {
+    Ok(state.list())
+}
+
+/// Forget the recorded quirks, e.g. after a mirror was fixed; transfers try resuming again
+#[tauri::command]
+pub async fn clear_range_quirks(state: tauri::State<'_, RangeQuirkState>) -> Result<(), CollectorError> {
+    state.clear()?;
+    log::info!("Cleared range quirks");
+    Ok(())
+}
@@ -0,0 +1,350 @@
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::error::{CollectorError, ErrorKind};
+use crate::providers;
+
+/// Tries a request gets while the provider keeps asking to slow down
+const MAX_THROTTLED_ATTEMPTS: u32 = 8;
+
+/// Wait after a 429 or 503 without Retry-After, doubled for every one in a row
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(2);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(120);
+
+/// Time without being throttled before a lowered connection cap goes up by one again
+const RECOVERY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Request rate and connection caps applied to one dataset provider, across all tasks
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderLimits {
+    pub requests_per_second: f64,
+    pub max_connections: usize,
+}
+
+impl ProviderLimits {
+    /// Defaults that stay well within the providers' fair-use guidance
+    pub fn default_for(provider: &str) -> ProviderLimits {
+        let (requests_per_second, max_connections) = match provider {
+            // S3-backed; the OpenNeuro team asks for no more than a handful of parallel streams per client
+            "openneuro" => (10.0, 8),
+            // The DANDI API sits in front of S3 redirects and throttles bursts of asset lookups
+            "dandi" => (5.0, 4),
+            // Zenodo allows about 133 requests per minute per IP
+            "zenodo" => (2.0, 4),
+            _ => (5.0, 4),
+        };
+        ProviderLimits {
+            requests_per_second,
+            max_connections,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(self.requests_per_second > 0.0 && self.requests_per_second <= 1000.0) {
+            return Err(format!("Requests per second must be between 0 and 1000, got {}", self.requests_per_second));
+        }
+        if !(1..=64).contains(&self.max_connections) {
+            return Err(format!("Concurrent connections must be between 1 and 64, got {}", self.max_connections));
+        }
+        Ok(())
+    }
+}
+
+/// Connections a provider's tasks hold against its cap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderUsage {
+    pub provider: String,
+    pub active_connections: usize,
+    /// Lowered connection cap while the provider asks to slow down
+    pub throttled_connections: Option<usize>,
+    #[serde(flatten)]
+    pub limits: ProviderLimits,
+}
+
+/// Current limits of a provider, as shown in the settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderRateLimit {
+    pub provider: String,
+    #[serde(flatten)]
+    pub limits: ProviderLimits,
+    /// False once the user changed the limits
+    pub is_default: bool,
+}
+
+/// How far a provider's connections were cut back after it answered 429 or 503
+struct Backoff {
+    /// Connection cap below the configured one, None once fully recovered
+    max_connections: Option<usize>,
+    /// Throttling responses without a long enough quiet period in between
+    strikes: u32,
+    since: Instant,
+}
+
+impl Backoff {
+    fn none() -> Backoff {
+        Backoff { max_connections: None, strikes: 0, since: Instant::now() }
+    }
+}
+
+struct Bucket {
+    limits: Mutex<ProviderLimits>,
+    /// Earliest time the next request may start
+    next_request: Mutex<Instant>,
+    active: Mutex<usize>,
+    released: Notify,
+    backoff: Mutex<Backoff>,
+}
+
+impl Bucket {
+    fn new(limits: ProviderLimits) -> Bucket {
+        Bucket {
+            limits: Mutex::new(limits),
+            next_request: Mutex::new(Instant::now()),
+            active: Mutex::new(0),
+            released: Notify::new(),
+            backoff: Mutex::new(Backoff::none()),
+        }
+    }
+
+    /// Connection cap in effect: the configured one, or the lowered one after throttling,
+    /// which goes back up by one connection for every quiet `RECOVERY_INTERVAL`
+    fn max_connections(&self) -> usize {
+        let configured = self.limits.lock().unwrap().max_connections;
+        let mut backoff = self.backoff.lock().unwrap();
+        if let Some(lowered) = backoff.max_connections {
+            if backoff.since.elapsed() >= RECOVERY_INTERVAL {
+                backoff.max_connections = (lowered + 1 < configured).then_some(lowered + 1);
+                backoff.strikes = 0;
+                backoff.since = Instant::now();
+            }
+        }
+        backoff.max_connections.map_or(configured, |lowered| lowered.min(configured))
+    }
+}
+
+/// `RateLimited` error for a 429 Too Many Requests or 503 Slow Down answer, with the delay
+/// from its Retry-After header, or None for any other response. Callers return it like any
+/// other HTTP error; `RateLimiter::request` backs off.
+pub fn throttled_error(response: &reqwest::Response) -> Option<CollectorError> {
+    let status = response.status();
+    if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+        return None;
+    }
+    let retry_after = response.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+    let message = match retry_after {
+        Some(delay) => format!("HTTP {}: slow down, retry after {}s", status, delay.as_secs()),
+        None => format!("HTTP {}: slow down", status),
+    };
+    Some(CollectorError::rate_limited(message, retry_after))
+}
+
+/// Retry-After as delay-seconds or as an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    Some((date.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Keeps every task's requests to a provider within that provider's caps.
+///
+/// Limits can be changed while tasks are running: a higher connection cap lets
+/// waiting requests through immediately, a lower one takes effect as connections finish.
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Arc<Bucket>>>,
+}
+
+pub type RateLimitState = Arc<RateLimiter>;
+
+impl RateLimiter {
+    pub fn new() -> RateLimiter {
+        RateLimiter {
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn bucket(&self, provider: &str) -> Arc<Bucket> {
+        self.buckets
+            .lock()
+            .unwrap()
+            .entry(provider.to_string())
+            .or_insert_with(|| Arc::new(Bucket::new(ProviderLimits::default_for(provider))))
+            .clone()
+    }
+
+    /// Wait for a free connection and the next request slot of `provider`.
+    /// The connection counts against the cap until the returned permit is dropped.
+    pub async fn acquire(&self, provider: &str) -> ConnectionPermit {
+        let bucket = self.bucket(provider);
+
+        loop {
+            let released = bucket.released.notified();
+            {
+                let max_connections = bucket.max_connections();
+                let mut active = bucket.active.lock().unwrap();
+                if *active < max_connections {
+                    *active += 1;
+                    break;
+                }
+            }
+            released.await;
+        }
+
+        let start_at = {
+            let interval = Duration::from_secs_f64(1.0 / bucket.limits.lock().unwrap().requests_per_second);
+            let mut next_request = bucket.next_request.lock().unwrap();
+            let start_at = (*next_request).max(Instant::now());
+            *next_request = start_at + interval;
+            start_at
+        };
+        tokio::time::sleep_until(start_at).await;
+
+        ConnectionPermit { bucket }
+    }
+
+    /// Run `request` while holding one of `provider`'s connections. When the provider answers
+    /// 429 or 503, every request to it waits for the Retry-After delay (or an exponential
+    /// backoff), its connection cap is halved and the request is tried again, so a busy
+    /// bucket slows the tasks down instead of failing them.
+    pub async fn request<T, E, F, Fut>(&self, provider: &str, mut request: F) -> Result<(ConnectionPermit, T), CollectorError>
+    where
+        E: Into<CollectorError>,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let permit = self.acquire(provider).await;
+            let e: CollectorError = match request().await {
+                Ok(value) => return Ok((permit, value)),
+                Err(e) => e.into(),
+            };
+            if e.kind != ErrorKind::RateLimited || attempt >= MAX_THROTTLED_ATTEMPTS {
+                return Err(e);
+            }
+            drop(permit);
+            let delay = self.back_off(provider, e.retry_after);
+            log::warn!(
+                "{} asked to slow down (attempt {}/{}), waiting {:.1}s: {}",
+                provider, attempt, MAX_THROTTLED_ATTEMPTS, delay.as_secs_f64(), e
+            );
+            attempt += 1;
+        }
+    }
+
+    /// Hold back every request to `provider` for `retry_after` or the next backoff step and
+    /// halve its connection cap, returning the delay
+    fn back_off(&self, provider: &str, retry_after: Option<Duration>) -> Duration {
+        let bucket = self.bucket(provider);
+        let configured = bucket.limits.lock().unwrap().max_connections;
+        let delay = {
+            let mut backoff = bucket.backoff.lock().unwrap();
+            // Connections throttled together count once, so the cap is not cut to 1 at once
+            let current = backoff.max_connections.unwrap_or(configured).min(configured);
+            if backoff.max_connections.is_none() || backoff.since.elapsed() >= Duration::from_secs(1) {
+                backoff.max_connections = Some((current / 2).max(1));
+                backoff.strikes += 1;
+            }
+            backoff.since = Instant::now();
+            let exponential = DEFAULT_BACKOFF * 2u32.pow(backoff.strikes.saturating_sub(1).min(6));
+            retry_after.unwrap_or(exponential).min(MAX_BACKOFF)
+        };
+
+        let mut next_request = bucket.next_request.lock().unwrap();
+        *next_request = (*next_request).max(Instant::now() + delay);
+        delay
+    }
+
+    pub fn limits(&self, provider: &str) -> ProviderRateLimit {
+        let limits = *self.bucket(provider).limits.lock().unwrap();
+        ProviderRateLimit {
+            provider: provider.to_string(),
+            limits,
+            is_default: limits == ProviderLimits::default_for(provider),
+        }
+    }
+
+    /// Connection use of every provider contacted since the app started
+    pub fn usage(&self) -> Vec<ProviderUsage> {
+        let mut usage: Vec<ProviderUsage> = self.buckets
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(provider, bucket)| ProviderUsage {
+                provider: provider.clone(),
+                active_connections: *bucket.active.lock().unwrap(),
+                throttled_connections: bucket.backoff.lock().unwrap().max_connections,
+                limits: *bucket.limits.lock().unwrap(),
+            })
+            .collect();
+        usage.sort_by(|a, b| a.provider.cmp(&b.provider));
+        usage
+    }
+
+    /// Apply new limits to `provider`, including requests already waiting
+    pub fn set_limits(&self, provider: &str, limits: ProviderLimits) {
+        let bucket = self.bucket(provider);
+        *bucket.limits.lock().unwrap() = limits;
+        *bucket.backoff.lock().unwrap() = Backoff::none();
+        // The next request may come sooner at a higher rate
+        *bucket.next_request.lock().unwrap() = Instant::now();
+        bucket.released.notify_waiters();
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter::new()
+    }
+}
+
+/// An open connection to a provider; releases its slot when dropped
+pub struct ConnectionPermit {
+    bucket: Arc<Bucket>,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        *self.bucket.active.lock().unwrap() -= 1;
+        self.bucket.released.notify_waiters();
+    }
+}
+
+#[tauri::command]
+pub async fn get_provider_rate_limits(state: tauri::State<'_, RateLimitState>) -> Result<Vec<ProviderRateLimit>, CollectorError> {
+    Ok(providers::registry()
+        .list()
+        .iter()
+        .map(|provider| state.limits(&provider.id))
+        .collect())
+}
+
+/// Change the caps of a provider while tasks are running, or restore its defaults when `limits` is omitted
+#[tauri::command]
+pub async fn set_provider_rate_limits(
+    provider: String,
+    limits: Option<ProviderLimits>,
+    state: tauri::State<'_, RateLimitState>,
+) -> Result<ProviderRateLimit, CollectorError> {
+    let provider = providers::registry().get(&provider)?.id();
+    let limits = limits.unwrap_or_else(|| ProviderLimits::default_for(provider));
+    limits.validate()?;
+
+    log::info!("Rate limits for {} set to {:?}", provider, limits);
+    state.set_limits(provider, limits);
+    Ok(state.limits(provider))
+}
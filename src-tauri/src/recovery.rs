@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+use crate::error::CollectorError;
+use crate::{credentials, path_guard, DownloadState};
+
+/// Directory in the app data directory holding one file per collecting task. A file that is
+/// still there at startup belongs to a task the app did not see finish.
+const TASKS_DIR: &str = "active-tasks";
+
+/// A task as persisted while it collects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedTask {
+    pub task_id: String,
+    /// Task payload without secrets; resuming reads them from the keychain again
+    pub task_data: Value,
+    pub started_at: String,
+}
+
+impl PersistedTask {
+    pub fn download_path(&self) -> &str {
+        self.task_data.get("task")
+            .and_then(|task| task.get("downloadPath"))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+    }
+
+    pub fn provider(&self) -> &str {
+        self.task_data.get("task")
+            .and_then(|task| task.get("datasetProvider"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+    }
+
+    /// Payload to start the task again with: files already at the destination are skipped
+    pub fn resume_data(&self) -> Value {
+        let mut task_data = self.task_data.clone();
+        if let Some(task) = task_data.get_mut("task").and_then(|t| t.as_object_mut()) {
+            task.insert("skipExisting".to_string(), Value::Bool(true));
+        }
+        task_data
+    }
+}
+
+/// Tasks found interrupted at startup that were neither resumed nor dismissed yet
+pub type RecoveryState = Arc<Mutex<Vec<PersistedTask>>>;
+
+fn tasks_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app_handle.path().app_data_dir()
+        .map(|dir| dir.join(TASKS_DIR))
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))
+}
+
+fn task_file(app_handle: &tauri::AppHandle, task_id: &str) -> Result<PathBuf, String> {
+    // Task IDs come from the frontend; keep them to a single path component
+    let file_name: String = task_id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    Ok(tasks_dir(app_handle)?.join(format!("{}.json", file_name)))
+}
+
+/// Remember a task that started collecting, so it can be resumed after a crash
+pub fn record(app_handle: &tauri::AppHandle, task_id: &str, task_data: &Value) -> Result<(), String> {
+    let path = task_file(app_handle, task_id)?;
+    path_guard::check(&path)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+
+    let task = PersistedTask {
+        task_id: task_id.to_string(),
+        task_data: credentials::strip_secrets(task_data),
+        started_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let content = serde_json::to_vec_pretty(&task)
+        .map_err(|e| format!("Failed to serialize task {}: {}", task_id, e))?;
+    std::fs::write(&path, content)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Forget a task that finished, failed or was cancelled
+pub fn clear(app_handle: &tauri::AppHandle, task_id: &str) -> Result<(), String> {
+    let path = task_file(app_handle, task_id)?;
+    path_guard::check(&path)?;
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+    }
+}
+
+/// Tasks that were collecting when the app last stopped
+pub fn load_interrupted(app_handle: &tauri::AppHandle) -> Result<Vec<PersistedTask>, String> {
+    let dir = tasks_dir(app_handle)?;
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+
+    let mut tasks = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let parsed = std::fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_slice::<PersistedTask>(&content).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(task) => tasks.push(task),
+            Err(e) => log::warn!("Ignoring unreadable interrupted task {}: {}", path.display(), e),
+        }
+    }
+
+    tasks.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    if !tasks.is_empty() {
+        log::info!("Found {} interrupted task(s) from the last run", tasks.len());
+    }
+    Ok(tasks)
+}
+
+/// An interrupted task and how much of it is still missing at the destination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterruptedTask {
+    pub task_id: String,
+    pub provider: String,
+    pub dataset: String,
+    pub started_at: String,
+    pub total_files: Option<u32>,
+    pub total_size: Option<u64>,
+    pub remaining_files: Option<u32>,
+    pub remaining_size: Option<u64>,
+    /// Why the listing could not be compared with the destination
+    pub error: Option<String>,
+}
+
+impl InterruptedTask {
+    pub fn new(task: &PersistedTask) -> InterruptedTask {
+        InterruptedTask {
+            task_id: task.task_id.clone(),
+            provider: task.provider().to_string(),
+            dataset: task.download_path().to_string(),
+            started_at: task.started_at.clone(),
+            total_files: None,
+            total_size: None,
+            remaining_files: None,
+            remaining_size: None,
+            error: None,
+        }
+    }
+}
+
+/// Discard an interrupted task without resuming it; what it collected stays in place
+#[tauri::command]
+pub async fn dismiss_interrupted_task(
+    task_id: String,
+    state: tauri::State<'_, RecoveryState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), CollectorError> {
+    state.lock().unwrap().retain(|task| task.task_id != task_id);
+    clear(&app_handle, &task_id)?;
+
+    let download_state = app_handle.state::<DownloadState>();
+    let mut downloads = download_state.lock().unwrap();
+    if downloads.get(&task_id).is_some_and(|progress| progress.status == "interrupted") {
+        downloads.remove(&task_id);
+    }
+    log::info!("Dismissed interrupted task {}", task_id);
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+use tauri::Emitter;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::error::CollectorError;
+use crate::manifest::{self, Manifest, ManifestEntry};
+use crate::path_guard;
+use crate::s3_client::{self, S3ConnectionConfig};
+use crate::safe_path;
+use crate::transfer_rate::TransferRate;
+use crate::{is_cancelled, DownloadProgress, DownloadState};
+
+/// Attempts per object before the restore fails
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Restore a dataset archived in an S3-compatible bucket back to local disk.
+/// Every object is checked against the dataset manifest while it streams in,
+/// and only renamed into place once size and SHA-256 match.
+#[tauri::command]
+pub async fn start_restore_task(
+    task_id: String,
+    storage_location: serde_json::Value,
+    prefix: String,
+    dest_dir: String,
+    state: tauri::State<'_, DownloadState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, CollectorError> {
+    log::info!("Starting restore task {}: {} -> {}", task_id, prefix, dest_dir);
+
+    let config = S3ConnectionConfig::from_storage_location(&storage_location)?;
+
+    {
+        let mut downloads = state.lock().unwrap();
+        downloads.insert(task_id.clone(), DownloadProgress::new(&task_id));
+    }
+
+    let state_clone = state.inner().clone();
+
+    tokio::spawn(async move {
+        let result = restore_dataset(&task_id, &config, &prefix, &dest_dir, &state_clone, &app_handle).await;
+
+        let mut downloads = state_clone.lock().unwrap();
+        if let Some(progress) = downloads.get_mut(&task_id) {
+            match result {
+                Ok(()) => {
+                    progress.status = "completed".to_string();
+                    progress.speed = 0.0;
+                    progress.eta_seconds = None;
+                    progress.progress = 100.0;
+                }
+                Err(_) if progress.status == "cancelled" => {}
+                Err(e) => {
+                    log::warn!("Restore failed: {}", e);
+                    progress.fail(e.into());
+                }
+            }
+            progress.completed_at = Some(chrono::Utc::now().to_rfc3339());
+
+            if let Err(e) = app_handle.emit("download-completed", &*progress) {
+                log::warn!("Failed to emit restore completion event: {}", e);
+            }
+        }
+    });
+
+    Ok("Restore started in background".to_string())
+}
+
+async fn restore_dataset(
+    task_id: &str,
+    config: &S3ConnectionConfig,
+    prefix: &str,
+    dest_dir: &str,
+    state: &DownloadState,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), String> {
+    let prefix = prefix.trim_end_matches('/');
+
+    // The manifest is the source of truth; without it nothing can be verified
+    let manifest_key = format!("{}/{}", prefix, manifest::MANIFEST_PATH);
+    let manifest_bytes = s3_client::get_object(config, &manifest_key).await
+        .map_err(|e| format!("No manifest found for {}, cannot verify restore: {}", prefix, e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read manifest: {}", e))?;
+    let manifest = Manifest::from_json(&manifest_bytes)?;
+
+    let total_size: u64 = manifest.files.iter().map(|f| f.size).sum();
+    let total_files = manifest.files.len() as u32;
+
+    {
+        let mut downloads = state.lock().unwrap();
+        if let Some(progress) = downloads.get_mut(task_id) {
+            progress.status = "restoring".to_string();
+            progress.total_size = total_size;
+            progress.total_files = Some(total_files);
+        }
+    }
+
+    let mut restored_size = 0u64;
+    let mut rate = TransferRate::new();
+
+    for (index, entry) in manifest.files.iter().enumerate() {
+        if is_cancelled(task_id, state) {
+            return Err("Restore cancelled".to_string());
+        }
+
+        let key = format!("{}/{}", prefix, entry.path);
+        let dest_path = safe_path::join(dest_dir, &entry.path)?;
+        restore_object(config, &key, &dest_path, entry).await?;
+
+        restored_size += entry.size;
+        rate.record(entry.size);
+        let progress_percent = if total_size > 0 {
+            (restored_size as f64 / total_size as f64 * 100.0).min(100.0)
+        } else {
+            100.0
+        };
+
+        {
+            let mut downloads = state.lock().unwrap();
+            if let Some(progress) = downloads.get_mut(task_id) {
+                progress.progress = progress_percent;
+                progress.downloaded_size = restored_size;
+                progress.completed_files = Some(index as u32 + 1);
+                progress.current_file = Some(entry.path.clone());
+                progress.update_rate(&rate);
+            }
+        }
+
+        let _ = app_handle.emit("download_progress", serde_json::json!({
+            "taskId": task_id,
+            "progress": progress_percent,
+            "downloadedSize": restored_size,
+            "totalSize": total_size,
+            "currentFile": entry.path,
+            "completedFiles": index + 1,
+            "totalFiles": total_files,
+            "speed": rate.instantaneous(),
+            "averageSpeed": rate.average(),
+            "etaSeconds": rate.eta_seconds(total_size.saturating_sub(restored_size)),
+            "status": "restoring"
+        }));
+    }
+
+    // Keep the manifest with the restored copy so it can be verified again later
+    manifest::write_local(dest_dir, &manifest).await?;
+
+    log::info!("Restored and verified {} files into {}", total_files, dest_dir);
+    Ok(())
+}
+
+/// Fetch one object, retrying when the streamed content does not match the manifest
+async fn restore_object(
+    config: &S3ConnectionConfig,
+    key: &str,
+    dest_path: &str,
+    entry: &ManifestEntry,
+) -> Result<(), String> {
+    path_guard::check(dest_path)?;
+    if let Some(parent) = std::path::Path::new(dest_path).parent() {
+        fs::create_dir_all(parent).await
+            .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+    }
+
+    let part_path = format!("{}.part", dest_path);
+    let mut last_error = String::new();
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        match stream_and_verify(config, key, &part_path, entry).await {
+            Ok(()) => {
+                fs::rename(&part_path, dest_path).await
+                    .map_err(|e| format!("Failed to move {} into place: {}", dest_path, e))?;
+                return Ok(());
+            }
+            Err(e) => {
+                log::warn!("Restore attempt {}/{} for {} failed: {}", attempt, MAX_ATTEMPTS, key, e);
+                let _ = fs::remove_file(&part_path).await;
+                last_error = e;
+            }
+        }
+    }
+
+    Err(format!("Failed to restore {} after {} attempts: {}", entry.path, MAX_ATTEMPTS, last_error))
+}
+
+async fn stream_and_verify(
+    config: &S3ConnectionConfig,
+    key: &str,
+    part_path: &str,
+    entry: &ManifestEntry,
+) -> Result<(), String> {
+    let response = s3_client::get_object(config, key).await?;
+
+    let mut file = fs::File::create(part_path).await
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+    let mut stream = response.bytes_stream();
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Failed to read chunk: {}", e))?;
+        size += chunk.len() as u64;
+
+        // Abort early instead of streaming a wrong object to the end
+        if size > entry.size {
+            return Err(format!("Object is larger than the {} bytes recorded in the manifest", entry.size));
+        }
+
+        hasher.update(&chunk);
+        file.write_all(&chunk).await
+            .map_err(|e| format!("Failed to write to file: {}", e))?;
+    }
+
+    file.flush().await
+        .map_err(|e| format!("Failed to flush file: {}", e))?;
+
+    if size != entry.size {
+        return Err(format!("Size mismatch: expected {} bytes, got {}", entry.size, size));
+    }
+
+    let sha256 = hex::encode(hasher.finalize());
+    if !sha256.eq_ignore_ascii_case(&entry.sha256) {
+        return Err(format!("Checksum mismatch: expected SHA-256 {}, got {}", entry.sha256, sha256));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::{archive, safe_path};
+use crate::catalog::{CatalogEntry, CatalogState};
+use crate::error::CollectorError;
+use crate::formatting;
+use crate::manifest::{self, Manifest};
+use crate::path_guard;
+
+/// Name of the metadata file at the root of every crate
+pub const METADATA_FILE: &str = "ro-crate-metadata.json";
+
+const RO_CRATE_CONTEXT: &str = "https://w3id.org/ro/crate/1.1/context";
+const RO_CRATE_SPEC: &str = "https://w3id.org/ro/crate/1.1";
+
+fn doi_url(doi: &str) -> String {
+    if doi.starts_with("http") {
+        doi.to_string()
+    } else {
+        format!("https://doi.org/{}", doi.trim_start_matches("doi:"))
+    }
+}
+
+/// RO-Crate metadata describing the collected files, with one CreateAction per transfer.
+/// Fields of `dataset_description.json` (name, authors, license, ...) go on the root Dataset.
+pub fn metadata(entry: &CatalogEntry, manifest: &Manifest, description: Option<&Value>) -> Value {
+    let instrument_id = "#bids-collector";
+    let mut graph = vec![
+        json!({
+            "@id": "ro-crate-metadata.json",
+            "@type": "CreativeWork",
+            "conformsTo": { "@id": RO_CRATE_SPEC },
+            "about": { "@id": "./" },
+        }),
+        json!({
+            "@id": instrument_id,
+            "@type": "SoftwareApplication",
+            "name": "bids-collector",
+            "version": env!("CARGO_PKG_VERSION"),
+        }),
+    ];
+
+    let mut root = json!({
+        "@id": "./",
+        "@type": "Dataset",
+        "name": entry.identifier,
+        "datePublished": entry.collected_at,
+        "description": format!(
+            "{} collected from {} on {} ({}, checksum root {})",
+            entry.identifier,
+            entry.provider,
+            formatting::timestamp(&entry.collected_at),
+            formatting::transfer_summary(manifest.files.len(), manifest.files.iter().map(|f| f.size).sum(), None),
+            entry.checksum_root
+        ),
+        "hasPart": manifest.files.iter().map(|f| json!({ "@id": f.path })).collect::<Vec<_>>(),
+    });
+    if let Some(version) = &entry.version {
+        root["version"] = json!(version);
+    }
+    if let Some(doi) = &entry.doi {
+        root["identifier"] = json!(doi_url(doi));
+    }
+    if let Some(description) = description {
+        apply_description(&mut root, &mut graph, description);
+    }
+    graph.push(root);
+
+    for (index, file) in manifest.files.iter().enumerate() {
+        let mut file_entity = json!({
+            "@id": file.path,
+            "@type": "File",
+            "contentSize": file.size.to_string(),
+            "sha256": file.sha256,
+        });
+        if let Some(source_url) = &file.source_url {
+            file_entity["contentUrl"] = json!(source_url);
+        }
+        graph.push(file_entity);
+
+        let Some(source_url) = &file.source_url else {
+            continue;
+        };
+        let mut action = json!({
+            "@id": format!("#transfer-{}", index),
+            "@type": "CreateAction",
+            "name": format!("Transfer of {}", file.path),
+            "instrument": { "@id": instrument_id },
+            "object": { "@id": source_url },
+            "result": { "@id": file.path },
+            "actionStatus": { "@id": "http://schema.org/CompletedActionStatus" },
+        });
+        if let Some(started_at) = &file.started_at {
+            action["startTime"] = json!(started_at);
+        }
+        if let Some(finished_at) = &file.finished_at {
+            action["endTime"] = json!(finished_at);
+        }
+        if let Some(attempts) = file.attempts {
+            action["description"] = json!(format!("Transferred in {} attempt(s)", attempts));
+        }
+        graph.push(action);
+    }
+
+    json!({
+        "@context": RO_CRATE_CONTEXT,
+        "@graph": graph,
+    })
+}
+
+/// Carry the `dataset_description.json` fields over to the root Dataset entity
+fn apply_description(root: &mut Value, graph: &mut Vec<Value>, description: &Value) {
+    let text = |name: &str| description.get(name).and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+    let list = |name: &str| -> Vec<String> {
+        description.get(name)
+            .and_then(|v| v.as_array())
+            .map(|items| items.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect())
+            .unwrap_or_default()
+    };
+
+    if let Some(name) = text("Name") {
+        root["name"] = json!(name);
+    }
+    if let Some(license) = text("License") {
+        root["license"] = json!(license);
+    }
+    if let Some(doi) = text("DatasetDOI") {
+        root["identifier"] = json!(doi_url(doi));
+    }
+    if let Some(acknowledge) = text("HowToAcknowledge") {
+        root["creditText"] = json!(acknowledge);
+    }
+
+    let keywords = list("Keywords");
+    if !keywords.is_empty() {
+        root["keywords"] = json!(keywords.join(", "));
+    }
+    let references = list("ReferencesAndLinks");
+    if !references.is_empty() {
+        root["citation"] = json!(references);
+    }
+
+    let mut authors = Vec::new();
+    for (index, name) in list("Authors").iter().enumerate() {
+        let id = format!("#author-{}", index);
+        graph.push(json!({ "@id": id, "@type": "Person", "name": name }));
+        authors.push(json!({ "@id": id }));
+    }
+    if !authors.is_empty() {
+        root["author"] = json!(authors);
+    }
+
+    let mut funders = Vec::new();
+    for (index, name) in list("Funding").iter().enumerate() {
+        let id = format!("#funder-{}", index);
+        graph.push(json!({ "@id": id, "@type": "Organization", "name": name }));
+        funders.push(json!({ "@id": id }));
+    }
+    if !funders.is_empty() {
+        root["funder"] = json!(funders);
+    }
+
+    if let Some(version) = text("BIDSVersion") {
+        let id = format!("https://bids-specification.readthedocs.io/en/v{}/", version);
+        graph.push(json!({ "@id": id, "@type": "CreativeWork", "name": format!("BIDS {}", version) }));
+        root["conformsTo"] = json!({ "@id": id });
+    }
+}
+
+/// A crate written by `export_ro_crate`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoCrateExport {
+    pub path: String,
+    pub file_count: usize,
+    pub total_size: u64,
+}
+
+/// Whether a dataset file is part of the selection (exact paths or directory prefixes)
+fn is_selected(path: &str, selection: &[String]) -> bool {
+    path == "dataset_description.json"
+        || selection.iter().any(|s| {
+            let s = s.trim_matches('/');
+            path == s || path.starts_with(&format!("{}/", s))
+        })
+}
+
+/// Source and in-crate path of every file, refusing paths that would escape the crate
+fn crate_files(dataset_dir: &str, manifest: &Manifest) -> Result<Vec<(PathBuf, PathBuf, String)>, String> {
+    manifest.files
+        .iter()
+        .map(|file| {
+            let relative = PathBuf::from(safe_path::relative(&file.path).map_err(|e| format!("{} in manifest", e))?);
+            Ok((Path::new(dataset_dir).join(&relative), relative, file.path.clone()))
+        })
+        .collect()
+}
+
+fn write_directory(output: &Path, files: &[(PathBuf, PathBuf, String)], metadata: &[u8]) -> Result<(), String> {
+    path_guard::check(output)?;
+    if output.exists() && output.read_dir().map(|mut d| d.next().is_some()).unwrap_or(true) {
+        return Err(format!("{} already exists and is not empty", output.display()));
+    }
+
+    for (source, relative, _) in files {
+        let target = output.join(relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        std::fs::copy(source, &target)
+            .map_err(|e| format!("Failed to copy {} to {}: {}", source.display(), target.display(), e))?;
+    }
+
+    std::fs::write(output.join(METADATA_FILE), metadata)
+        .map_err(|e| format!("Failed to write {}: {}", METADATA_FILE, e))
+}
+
+fn write_zip(output: &Path, files: &[(PathBuf, PathBuf, String)], metadata: &[u8]) -> Result<(), String> {
+    path_guard::check(output)?;
+    let file = File::create(output)
+        .map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+
+    for (source, _, name) in files {
+        let mut input = File::open(source)
+            .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+        let size = input.metadata().map(|m| m.len()).unwrap_or(0);
+
+        let options = archive::zip_file_options(name, size);
+
+        zip.start_file(name.as_str(), options)
+            .map_err(|e| format!("Failed to add {} to the crate: {}", name, e))?;
+        io::copy(&mut input, &mut zip)
+            .map_err(|e| format!("Failed to add {} to the crate: {}", name, e))?;
+    }
+
+    zip.start_file(METADATA_FILE, SimpleFileOptions::default())
+        .map_err(|e| format!("Failed to add {}: {}", METADATA_FILE, e))?;
+    io::Write::write_all(&mut zip, metadata)
+        .map_err(|e| format!("Failed to add {}: {}", METADATA_FILE, e))?;
+
+    zip.finish().map_err(|e| format!("Failed to finish {}: {}", output.display(), e))?;
+    Ok(())
+}
+
+/// Package a locally collected dataset, or a selection of its files, as an RO-Crate.
+/// `output_path` ending in `.zip` produces a zipped crate, anything else a crate directory.
+/// `dataset_description.json` is always included as the source of the crate metadata.
+#[tauri::command]
+pub async fn export_ro_crate(
+    dataset_id: String,
+    output_path: String,
+    paths: Option<Vec<String>>,
+    catalog: tauri::State<'_, CatalogState>,
+) -> Result<RoCrateExport, CollectorError> {
+    let entry = catalog.lock().unwrap().get(&dataset_id)?
+        .ok_or_else(|| format!("Dataset {} is not in the catalog", dataset_id))?;
+    if entry.storage_type != "local" {
+        return Err(format!("RO-Crate packaging needs a local copy; {} is stored at {}", dataset_id, entry.location).into());
+    }
+
+    let mut manifest = manifest::read_local(&entry.location).await
+        .ok_or_else(|| format!("No manifest found in {}", entry.location))?;
+    if let Some(selection) = &paths {
+        manifest.files.retain(|f| is_selected(&f.path, selection));
+    }
+    if manifest.files.is_empty() {
+        return Err("No files selected for the RO-Crate".into());
+    }
+
+    let description = tokio::fs::read(format!("{}/dataset_description.json", entry.location)).await
+        .ok()
+        .and_then(|content| serde_json::from_slice::<Value>(&content).ok());
+    let metadata = serde_json::to_vec_pretty(&metadata(&entry, &manifest, description.as_ref()))
+        .map_err(|e| format!("Failed to serialize crate metadata: {}", e))?;
+
+    let files = crate_files(&entry.location, &manifest)?;
+    let result = RoCrateExport {
+        path: output_path.clone(),
+        file_count: files.len(),
+        total_size: manifest.files.iter().map(|f| f.size).sum(),
+    };
+
+    log::info!("Packaging {} files of {} as RO-Crate {}", files.len(), dataset_id, output_path);
+    tokio::task::spawn_blocking(move || {
+        let output = Path::new(&output_path);
+        if output_path.to_lowercase().ends_with(".zip") {
+            write_zip(output, &files, &metadata)
+        } else {
+            write_directory(output, &files, &metadata)
+        }
+    })
+    .await
+    .map_err(|e| format!("RO-Crate export task failed: {}", e))??;
+
+    Ok(result)
+}
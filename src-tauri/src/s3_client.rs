@@ -1,25 +1,348 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Instant;
 use chrono::{DateTime, Utc};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use hmac::{Hmac, Mac};
+use md5::Md5;
 use sha2::{Sha256, Digest};
 use url::Url;
 
+use crate::aws_profile;
+use crate::credentials;
+use crate::content_type;
+use crate::error::{CollectorError, ErrorKind};
+use crate::storage::{self, SourceMetadata};
+
 type HmacSha256 = Hmac<Sha256>;
 
+/// Most keys one DeleteObjects request may name
+pub const DELETE_BATCH_SIZE: usize = 1000;
+
+/// Where `test_s3_connection` writes and deletes its probe object
+const PROBE_PREFIX: &str = ".bids-collector/connection-test/";
+
+/// Longest validity SigV4 allows for a presigned URL (7 days)
+pub const MAX_PRESIGNED_EXPIRY_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Storage classes accepted by AWS; MinIO and others support a subset
+pub const STORAGE_CLASSES: &[&str] = &[
+    "STANDARD",
+    "REDUCED_REDUNDANCY",
+    "STANDARD_IA",
+    "ONEZONE_IA",
+    "INTELLIGENT_TIERING",
+    "GLACIER",
+    "GLACIER_IR",
+    "DEEP_ARCHIVE",
+    "EXPRESS_ONEZONE",
+];
+
+/// Server-side encryption requested for uploaded objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServerSideEncryption {
+    /// Keys managed by the storage service (AES256)
+    #[serde(rename = "sse-s3")]
+    S3,
+    /// Keys in AWS KMS or a KMS-compatible service such as MinIO KES
+    #[serde(rename = "sse-kms")]
+    Kms,
+}
+
+impl ServerSideEncryption {
+    pub fn parse(value: &str) -> Result<ServerSideEncryption, String> {
+        match value.to_lowercase().as_str() {
+            "sse-s3" | "aes256" => Ok(ServerSideEncryption::S3),
+            "sse-kms" | "aws:kms" => Ok(ServerSideEncryption::Kms),
+            _ => Err(format!("Unknown server-side encryption: {} (expected sse-s3 or sse-kms)", value)),
+        }
+    }
+    
+    /// Value of the `x-amz-server-side-encryption` header
+    pub fn header_value(&self) -> &'static str {
+        match self {
+            ServerSideEncryption::S3 => "AES256",
+            ServerSideEncryption::Kms => "aws:kms",
+        }
+    }
+}
+
+/// Services that only accept virtual-hosted-style requests (or deprecated path style)
+const VIRTUAL_HOSTED_DOMAINS: &[&str] = &["amazonaws.com", "aliyuncs.com", "myqcloud.com"];
+
+/// Where the bucket goes in request URLs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressingStyle {
+    /// `https://endpoint/bucket/key`, what MinIO and most self-hosted services expect
+    Path,
+    /// `https://bucket.endpoint/key`, required by AWS in newer regions
+    VirtualHosted,
+}
+
+impl AddressingStyle {
+    pub fn parse(value: &str) -> Result<AddressingStyle, String> {
+        match value.to_lowercase().as_str() {
+            "path" => Ok(AddressingStyle::Path),
+            "virtual-hosted" | "virtual" => Ok(AddressingStyle::VirtualHosted),
+            _ => Err(format!("Unknown addressing style: {} (expected path or virtual-hosted)", value)),
+        }
+    }
+    
+    /// Best guess when the style is not configured: virtual-hosted for services known to need
+    /// it, as long as the bucket name is a valid DNS label; path style everywhere else
+    pub fn detect(endpoint: &str, bucket_name: &str) -> AddressingStyle {
+        let host = Url::parse(&with_scheme(endpoint))
+            .ok()
+            .and_then(|url| url.host_str().map(|h| h.to_lowercase()))
+            .unwrap_or_default();
+        let dns_compatible = !bucket_name.is_empty()
+            && bucket_name.len() <= 63
+            && bucket_name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        
+        if dns_compatible && VIRTUAL_HOSTED_DOMAINS.iter().any(|domain| host.ends_with(domain)) {
+            AddressingStyle::VirtualHosted
+        } else {
+            AddressingStyle::Path
+        }
+    }
+    
+    fn other(self) -> AddressingStyle {
+        match self {
+            AddressingStyle::Path => AddressingStyle::VirtualHosted,
+            AddressingStyle::VirtualHosted => AddressingStyle::Path,
+        }
+    }
+}
+
+/// Endpoint with scheme and without trailing slash
+fn with_scheme(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.starts_with("http") {
+        endpoint.to_string()
+    } else {
+        format!("https://{}", endpoint)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct S3ConnectionConfig {
     pub bucket_name: String,
     pub endpoint: String,
     pub region: Option<String>,
+    #[serde(default)]
     pub access_key_id: String,
+    #[serde(default)]
     pub secret_access_key: String,
+    /// STS session token of temporary credentials, sent as `x-amz-security-token`
+    #[serde(default)]
+    pub session_token: Option<String>,
+    /// Profile in `~/.aws/credentials` to take the keys from when none are given
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub server_side_encryption: Option<ServerSideEncryption>,
+    /// KMS key ID or ARN for SSE-KMS; the bucket's default key is used when absent
+    #[serde(default)]
+    pub kms_key_id: Option<String>,
+    /// Storage class of uploaded objects, e.g. STANDARD_IA or GLACIER_IR
+    #[serde(default)]
+    pub storage_class: Option<String>,
+    /// Path or virtual-hosted style; detected from the endpoint when unset
+    #[serde(default)]
+    pub addressing_style: Option<AddressingStyle>,
+}
+
+impl S3ConnectionConfig {
+    /// Read the S3 settings of a storage location from the task payload.
+    /// Keys missing from the payload are looked up in the keychain by the location's `id` (for a
+    /// payload matching the saved location), then in the AWS profile named by `awsProfile`.
+    pub fn from_storage_location(location: &serde_json::Value) -> Result<S3ConnectionConfig, String> {
+        let field = |name: &str| location.get(name).and_then(|v| v.as_str()).map(|s| s.to_string());
+        
+        let config = S3ConnectionConfig {
+            bucket_name: field("bucketName").ok_or("No bucket name in S3 storage location")?,
+            endpoint: field("endpoint").ok_or("No endpoint in S3 storage location")?,
+            region: field("region"),
+            access_key_id: credentials::secret_field(location, "accessKeyId")?.unwrap_or_default(),
+            secret_access_key: credentials::secret_field(location, "secretAccessKey")?.unwrap_or_default(),
+            session_token: credentials::secret_field(location, "sessionToken")?,
+            profile: field("awsProfile"),
+            server_side_encryption: field("serverSideEncryption")
+                .filter(|s| !s.is_empty())
+                .map(|s| ServerSideEncryption::parse(&s))
+                .transpose()?,
+            kms_key_id: field("kmsKeyId").filter(|s| !s.is_empty()),
+            storage_class: field("storageClass").filter(|s| !s.is_empty()).map(|s| s.to_uppercase()),
+            addressing_style: field("addressingStyle")
+                .filter(|s| !s.is_empty() && s != "auto")
+                .map(|s| AddressingStyle::parse(&s))
+                .transpose()?,
+        }.apply_profile()?;
+        config.validate_upload_options()?;
+        
+        if config.access_key_id.is_empty() {
+            return Err("No access key ID in S3 storage location, keychain or AWS profile".to_string());
+        }
+        if config.secret_access_key.is_empty() {
+            return Err("No secret access key in S3 storage location, keychain or AWS profile".to_string());
+        }
+        Ok(config)
+    }
+    
+    /// Take the key pair, session token and region from the configured AWS profile
+    /// when the keys were not given directly
+    pub fn apply_profile(mut self) -> Result<S3ConnectionConfig, String> {
+        let Some(profile) = self.profile.as_deref() else {
+            return Ok(self);
+        };
+        if !self.access_key_id.is_empty() && !self.secret_access_key.is_empty() {
+            return Ok(self);
+        }
+        
+        let loaded = aws_profile::load(profile)?;
+        self.access_key_id = loaded.access_key_id.unwrap_or_default();
+        self.secret_access_key = loaded.secret_access_key.unwrap_or_default();
+        self.session_token = loaded.session_token.or(self.session_token);
+        self.region = self.region.or(loaded.region);
+        Ok(self)
+    }
+    
+    /// Add the session token of temporary credentials to the headers to be signed
+    pub fn add_session_header(&self, headers: &mut HashMap<String, String>) {
+        if let Some(token) = &self.session_token {
+            headers.insert("x-amz-security-token".to_string(), token.clone());
+        }
+    }
+    
+    fn validate_upload_options(&self) -> Result<(), String> {
+        if let Some(class) = &self.storage_class {
+            if !STORAGE_CLASSES.contains(&class.as_str()) {
+                return Err(format!("Unknown storage class {} (expected one of {})", class, STORAGE_CLASSES.join(", ")));
+            }
+        }
+        if self.kms_key_id.is_some() && self.server_side_encryption != Some(ServerSideEncryption::Kms) {
+            return Err("A KMS key ID requires sse-kms server-side encryption".to_string());
+        }
+        Ok(())
+    }
+    
+    /// Add the encryption and storage class headers of a PutObject request to the headers to be signed
+    pub fn add_upload_headers(&self, headers: &mut HashMap<String, String>) {
+        if let Some(encryption) = self.server_side_encryption {
+            headers.insert("x-amz-server-side-encryption".to_string(), encryption.header_value().to_string());
+            if let (ServerSideEncryption::Kms, Some(key_id)) = (encryption, &self.kms_key_id) {
+                headers.insert("x-amz-server-side-encryption-aws-kms-key-id".to_string(), key_id.clone());
+            }
+        }
+        if let Some(class) = &self.storage_class {
+            headers.insert("x-amz-storage-class".to_string(), class.clone());
+        }
+    }
+    
+    /// Content type of the object at `key` and the source it was collected from, as
+    /// `x-amz-meta-source-url` and `x-amz-meta-source-etag`. Metadata values must be
+    /// plain ASCII, so anything else is left out.
+    pub fn add_object_headers(key: &str, source: &SourceMetadata, headers: &mut HashMap<String, String>) {
+        headers.insert("content-type".to_string(), content_type::of(key).to_string());
+        let printable = |value: &&String| value.chars().all(|c| c.is_ascii() && !c.is_ascii_control());
+        if let Some(url) = source.url.as_ref().filter(printable) {
+            headers.insert("x-amz-meta-source-url".to_string(), url.clone());
+        }
+        if let Some(etag) = source.etag.as_ref().filter(printable) {
+            headers.insert("x-amz-meta-source-etag".to_string(), etag.clone());
+        }
+    }
+    
+    /// Endpoint with scheme and without trailing slash
+    pub fn base_url(&self) -> String {
+        with_scheme(&self.endpoint)
+    }
+    
+    /// The configured addressing style, or the detected one when unset
+    pub fn addressing(&self) -> AddressingStyle {
+        self.addressing_style
+            .unwrap_or_else(|| AddressingStyle::detect(&self.endpoint, &self.bucket_name))
+    }
+    
+    /// URL of the bucket without trailing slash, in the addressing style in use
+    pub fn bucket_url(&self) -> String {
+        let base = self.base_url();
+        match self.addressing() {
+            AddressingStyle::Path => format!("{}/{}", base, self.bucket_name),
+            AddressingStyle::VirtualHosted => match base.split_once("://") {
+                Some((scheme, host)) => format!("{}://{}.{}", scheme, self.bucket_name, host),
+                None => format!("{}.{}", self.bucket_name, base),
+            },
+        }
+    }
+    
+    pub fn region_or_default(&self) -> &str {
+        self.region.as_deref().unwrap_or("us-east-1")
+    }
+}
+
+/// An object in a destination bucket, as reported by ListObjectsV2
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Object {
+    pub key: String,
+    pub size: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct S3ConnectionResult {
     pub success: bool,
     pub message: String,
+    /// Addressing style that worked, to be saved with the storage location
+    #[serde(default)]
+    pub addressing_style: Option<AddressingStyle>,
+    /// Region the bucket reported being in, when the service names it
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Checks in the order they ran; the first failed one ends the test
+    #[serde(default)]
+    pub steps: Vec<DiagnosticStep>,
+}
+
+impl S3ConnectionResult {
+    /// Add the outcome of a step, returning whether the test goes on
+    fn record(&mut self, name: &str, outcome: Result<String, String>, started: Instant) -> bool {
+        let (success, message) = match outcome {
+            Ok(message) => (true, message),
+            Err(e) => (false, e),
+        };
+        self.steps.push(DiagnosticStep::timed(name, success, &message, started));
+        if !success {
+            self.success = false;
+            self.message = format!("The bucket answered, but {} failed: {}", name, message);
+        }
+        success
+    }
+}
+
+/// One request of the connection test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticStep {
+    /// head_bucket, list_objects, put_object or delete_objects
+    pub name: String,
+    pub success: bool,
+    pub message: String,
+    pub latency_ms: u64,
+}
+
+impl DiagnosticStep {
+    fn timed(name: &str, success: bool, message: &str, started: Instant) -> DiagnosticStep {
+        DiagnosticStep {
+            name: name.to_string(),
+            success,
+            message: message.to_string(),
+            latency_ms: started.elapsed().as_millis() as u64,
+        }
+    }
 }
 
 /// Generate AWS Signature V4 for S3 requests
@@ -101,6 +424,313 @@ fn generate_aws_signature_v4(
     Ok(authorization)
 }
 
+/// URI-encode a string the way SigV4 canonical requests expect (RFC 3986 unreserved characters only)
+pub fn aws_uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Host header value, including the port when the URL has a non-default one
+fn host_header(url: &str) -> Result<String, String> {
+    let parsed_url = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    let host = parsed_url.host_str().ok_or("No host in URL")?;
+    Ok(match parsed_url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+/// Send a signed request without a body (unsigned payload)
+async fn signed_request(config: &S3ConnectionConfig, method: reqwest::Method, url: &str) -> Result<reqwest::Response, CollectorError> {
+    signed_request_with_body(config, method, url, HashMap::new(), None).await
+}
+
+/// Send a signed request; `headers` are signed along with the standard ones, the body is not
+async fn signed_request_with_body(
+    config: &S3ConnectionConfig,
+    method: reqwest::Method,
+    url: &str,
+    mut headers: HashMap<String, String>,
+    body: Option<Vec<u8>>,
+) -> Result<reqwest::Response, CollectorError> {
+    let now = Utc::now();
+    
+    headers.insert("host".to_string(), host_header(url)?);
+    headers.insert("x-amz-date".to_string(), now.format("%Y%m%dT%H%M%SZ").to_string());
+    headers.insert("x-amz-content-sha256".to_string(), "UNSIGNED-PAYLOAD".to_string());
+    config.add_session_header(&mut headers);
+    
+    let authorization = generate_aws_signature_v4(
+        method.as_str(),
+        url,
+        &headers,
+        &config.access_key_id,
+        &config.secret_access_key,
+        config.region_or_default(),
+        &now,
+    )?;
+    
+    let client = crate::network::client();
+    let mut request_builder = client.request(method, url);
+    for (key, value) in &headers {
+        request_builder = request_builder.header(key, value);
+    }
+    if let Some(body) = body {
+        request_builder = request_builder.body(body);
+    }
+    
+    request_builder
+        .header("Authorization", authorization)
+        .send()
+        .await
+        .map_err(|e| CollectorError::from_request("Request failed", &e))
+}
+
+/// Send a signed GET request and return the response body
+async fn signed_get(config: &S3ConnectionConfig, url: &str) -> Result<String, CollectorError> {
+    let response = signed_request(config, reqwest::Method::GET, url).await?;
+    
+    let status = response.status();
+    let body = response.text().await
+        .map_err(|e| CollectorError::from_request("Failed to read response", &e))?;
+    
+    if !status.is_success() {
+        return Err(CollectorError::new(ErrorKind::of_status(status), format!("Request failed with status {}: {}", status, body)));
+    }
+    
+    Ok(body)
+}
+
+/// URL of an object in the addressing style in use, with the key encoded for signing
+pub fn object_url(config: &S3ConnectionConfig, key: &str) -> String {
+    format!("{}/{}", config.bucket_url(), aws_uri_encode(key, false))
+}
+
+/// Start a signed GET for an object; the caller streams the body
+pub async fn get_object(config: &S3ConnectionConfig, key: &str) -> Result<reqwest::Response, CollectorError> {
+    let response = signed_request(config, reqwest::Method::GET, &object_url(config, key)).await?;
+    
+    let status = response.status();
+    if !status.is_success() {
+        return Err(CollectorError::new(ErrorKind::of_status(status), format!("Failed to get {}: HTTP {}", key, status)));
+    }
+    
+    Ok(response)
+}
+
+/// Presigned GET URL for an object (SigV4 query-string authentication), valid for `expiry_secs`
+/// from `timestamp`. Anyone holding the URL can download the object without credentials.
+pub fn presigned_get_url(
+    config: &S3ConnectionConfig,
+    key: &str,
+    expiry_secs: u64,
+    timestamp: &DateTime<Utc>,
+) -> Result<String, String> {
+    if expiry_secs == 0 || expiry_secs > MAX_PRESIGNED_EXPIRY_SECS {
+        return Err(format!("Expiry must be between 1 and {} seconds", MAX_PRESIGNED_EXPIRY_SECS));
+    }
+    
+    let url = object_url(config, key);
+    let parsed_url = Url::parse(&url).map_err(|e| format!("Invalid URL: {}", e))?;
+    let region = config.region_or_default();
+    let date = timestamp.format("%Y%m%d").to_string();
+    let timestamp_str = timestamp.format("%Y%m%dT%H%M%SZ").to_string();
+    let credential_scope = format!("{}/{}/s3/aws4_request", date, region);
+    
+    // Canonical query: parameters sorted by name, values SigV4-encoded
+    let mut params = vec![
+        ("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_string()),
+        ("X-Amz-Credential", format!("{}/{}", config.access_key_id, credential_scope)),
+        ("X-Amz-Date", timestamp_str.clone()),
+        ("X-Amz-Expires", expiry_secs.to_string()),
+        ("X-Amz-SignedHeaders", "host".to_string()),
+    ];
+    if let Some(token) = &config.session_token {
+        params.push(("X-Amz-Security-Token", token.clone()));
+    }
+    params.sort_by_key(|(name, _)| *name);
+    let canonical_query = params.iter()
+        .map(|(name, value)| format!("{}={}", name, aws_uri_encode(value, true)))
+        .collect::<Vec<_>>()
+        .join("&");
+    
+    let canonical_request = format!(
+        "GET\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD",
+        parsed_url.path(),
+        canonical_query,
+        host_header(&url)?
+    );
+    
+    let mut hasher = Sha256::new();
+    hasher.update(canonical_request.as_bytes());
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        timestamp_str,
+        credential_scope,
+        hex::encode(hasher.finalize())
+    );
+    
+    let date_key = hmac_sha256(format!("AWS4{}", config.secret_access_key).as_bytes(), date.as_bytes())?;
+    let date_region_key = hmac_sha256(&date_key, region.as_bytes())?;
+    let date_region_service_key = hmac_sha256(&date_region_key, b"s3")?;
+    let signing_key = hmac_sha256(&date_region_service_key, b"aws4_request")?;
+    let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes())?);
+    
+    Ok(format!("{}?{}&X-Amz-Signature={}", url, canonical_query, signature))
+}
+
+/// List all objects under `prefix` in the configured bucket, following pagination
+pub async fn list_objects(config: &S3ConnectionConfig, prefix: &str) -> Result<Vec<S3Object>, CollectorError> {
+    let mut objects = Vec::new();
+    let mut continuation_token: Option<String> = None;
+    
+    loop {
+        // Canonical query: parameters sorted by name, values SigV4-encoded
+        let mut query = String::new();
+        if let Some(token) = &continuation_token {
+            query.push_str(&format!("continuation-token={}&", aws_uri_encode(token, true)));
+        }
+        query.push_str("list-type=2");
+        if !prefix.is_empty() {
+            query.push_str(&format!("&prefix={}", aws_uri_encode(prefix, true)));
+        }
+        
+        let url = format!("{}?{}", config.bucket_url(), query);
+        let body = signed_get(config, &url).await
+            .map_err(|e| e.context(format!("Failed to list bucket {}", config.bucket_name)))?;
+        
+        objects.extend(parse_list_objects(&body));
+        
+        continuation_token = if body.contains("<IsTruncated>true</IsTruncated>") {
+            xml_tag(&body, "NextContinuationToken")
+        } else {
+            None
+        };
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+    
+    Ok(objects)
+}
+
+/// Delete up to `DELETE_BATCH_SIZE` objects with one DeleteObjects request. Returns the keys
+/// the bucket could not delete, with its reason; keys that did not exist count as deleted.
+pub async fn delete_objects(config: &S3ConnectionConfig, keys: &[String]) -> Result<Vec<(String, String)>, String> {
+    if keys.len() > DELETE_BATCH_SIZE {
+        return Err(format!("DeleteObjects takes at most {} keys, got {}", DELETE_BATCH_SIZE, keys.len()));
+    }
+    
+    // Quiet mode only reports the keys that failed
+    let mut body = String::from(r#"<?xml version="1.0" encoding="UTF-8"?><Delete><Quiet>true</Quiet>"#);
+    for key in keys {
+        body.push_str(&format!("<Object><Key>{}</Key></Object>", xml_escape(key)));
+    }
+    body.push_str("</Delete>");
+    
+    // DeleteObjects requires a checksum of the body
+    let mut headers = HashMap::new();
+    headers.insert("content-md5".to_string(), BASE64.encode(Md5::digest(body.as_bytes())));
+    headers.insert("content-type".to_string(), "application/xml".to_string());
+    
+    let url = format!("{}?delete=", config.bucket_url());
+    let response = signed_request_with_body(config, reqwest::Method::POST, &url, headers, Some(body.into_bytes())).await?;
+    let status = response.status();
+    let body = response.text().await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    if !status.is_success() {
+        return Err(format!("Failed to delete objects from {}: HTTP {}: {}", config.bucket_name, status, body));
+    }
+    
+    Ok(parse_delete_errors(&body))
+}
+
+/// Keys and messages of the `<Error>` elements of a DeleteObjects result
+fn parse_delete_errors(xml_content: &str) -> Vec<(String, String)> {
+    let mut errors = Vec::new();
+    let mut rest = xml_content;
+    
+    while let Some(start) = rest.find("<Error>") {
+        let after_start = &rest[start + "<Error>".len()..];
+        let end = match after_start.find("</Error>") {
+            Some(end) => end,
+            None => break,
+        };
+        let entry = &after_start[..end];
+        
+        if let Some(key) = xml_tag(entry, "Key") {
+            let code = xml_tag(entry, "Code").unwrap_or_default();
+            let message = xml_tag(entry, "Message").unwrap_or_default();
+            errors.push((key, format!("{} {}", code, message).trim().to_string()));
+        }
+        
+        rest = &after_start[end..];
+    }
+    
+    errors
+}
+
+pub fn parse_list_objects(xml_content: &str) -> Vec<S3Object> {
+    let mut objects = Vec::new();
+    let mut rest = xml_content;
+    
+    while let Some(start) = rest.find("<Contents>") {
+        let after_start = &rest[start + "<Contents>".len()..];
+        let end = match after_start.find("</Contents>") {
+            Some(end) => end,
+            None => break,
+        };
+        let entry = &after_start[..end];
+        
+        if let Some(key) = xml_tag(entry, "Key") {
+            objects.push(S3Object {
+                key,
+                size: xml_tag(entry, "Size").and_then(|s| s.parse().ok()).unwrap_or(0),
+                etag: xml_tag(entry, "ETag").map(|e| e.trim_matches('"').to_string()),
+                last_modified: xml_tag(entry, "LastModified"),
+            });
+        }
+        
+        rest = &after_start[end..];
+    }
+    
+    objects
+}
+
+/// Text content of the first `<tag>` element, with XML entities decoded
+fn xml_tag(xml_content: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml_content.find(&open)? + open.len();
+    let end = xml_content[start..].find(&close)? + start;
+    Some(xml_unescape(&xml_content[start..end]))
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
 fn hmac_sha256(key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
     let mut mac = HmacSha256::new_from_slice(key)
         .map_err(|e| format!("HMAC error: {}", e))?;
@@ -108,36 +738,107 @@ fn hmac_sha256(key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
     Ok(mac.finalize().into_bytes().to_vec())
 }
 
+/// Check that the credentials can do everything a collection needs: HEAD the bucket, list a
+/// prefix, write a small probe object and delete it again. Each step is timed and reported,
+/// later steps are skipped once one fails, and the addressing style that worked and the
+/// region the bucket reports are returned so the storage location can be fixed up front.
+/// Saved locations are tested by `storage_location_id` with their keychain credentials, so
+/// the secrets never go back to the frontend; `config` is for a location still being set up.
 #[tauri::command]
-pub async fn test_s3_connection(config: S3ConnectionConfig) -> Result<S3ConnectionResult, String> {
-    println!("Testing S3 connection to: {}", config.endpoint);
+pub async fn test_s3_connection(
+    storage_location_id: Option<String>,
+    config: Option<S3ConnectionConfig>,
+    app_handle: tauri::AppHandle,
+) -> Result<S3ConnectionResult, CollectorError> {
+    let mut config = match (storage_location_id, config) {
+        (Some(location_id), None) => S3ConnectionConfig::from_storage_location(&credentials::saved_location(&app_handle, &location_id)?)?,
+        (None, Some(config)) => config.apply_profile()?,
+        _ => return Err(CollectorError::new(ErrorKind::InvalidInput, "Pass either a saved storage location id or an S3 configuration")),
+    };
+    log::info!("Testing S3 connection to: {}", config.endpoint);
     
-    let client = reqwest::Client::new();
-    let region = config.region.as_deref().unwrap_or("us-east-1");
+    // A configured style is tested as-is; otherwise the detected style is tried first, then the other
+    let styles = match config.addressing_style {
+        Some(style) => vec![style],
+        None => vec![config.addressing(), config.addressing().other()],
+    };
     
-    // Create the URL for bucket HEAD request
-    let url = if config.endpoint.starts_with("http") {
-        format!("{}/{}", config.endpoint, config.bucket_name)
-    } else {
-        format!("https://{}/{}", config.endpoint, config.bucket_name)
+    let mut first_failure = None;
+    for style in styles {
+        config.addressing_style = Some(style);
+        let started = Instant::now();
+        let mut result = head_bucket(&config).await?;
+        result.steps.push(DiagnosticStep::timed("head_bucket", result.success, &result.message, started));
+        if result.success {
+            result.addressing_style = Some(style);
+            probe_access(&config, &mut result).await;
+            return Ok(result);
+        }
+        first_failure.get_or_insert(result);
+    }
+    Ok(first_failure.expect("at least one addressing style is tested"))
+}
+
+/// List, write and delete under `PROBE_PREFIX` once the bucket answered, stopping at the
+/// first step that fails
+async fn probe_access(config: &S3ConnectionConfig, result: &mut S3ConnectionResult) {
+    let key = format!("{}{}-{}.txt", PROBE_PREFIX, Utc::now().format("%Y%m%dT%H%M%S"), hex::encode(rand::random::<[u8; 4]>()));
+    
+    let started = Instant::now();
+    let url = format!("{}?list-type=2&max-keys=1&prefix={}", config.bucket_url(), aws_uri_encode(PROBE_PREFIX, true));
+    let listed = signed_get(config, &url).await
+        .map(|_| "Objects can be listed".to_string())
+        .map_err(String::from);
+    if !result.record("list_objects", listed, started) {
+        return;
+    }
+    
+    let started = Instant::now();
+    let content = b"bids-collector connection test, safe to delete\n";
+    let written = crate::upload_to_s3_compatible(config, &key, content, &SourceMetadata::default(), &storage::no_progress()).await
+        .map(|_| format!("Wrote probe object {}", key))
+        .map_err(String::from);
+    if !result.record("put_object", written, started) {
+        return;
+    }
+    
+    let started = Instant::now();
+    let deleted = match delete_objects(config, std::slice::from_ref(&key)).await {
+        Ok(errors) if errors.is_empty() => Ok(format!("Deleted probe object {}", key)),
+        Ok(errors) => Err(errors.into_iter().map(|(_, error)| error).collect::<Vec<_>>().join("; ")),
+        Err(e) => Err(e),
     };
+    let deleted = deleted.map_err(|e| format!("{} (the probe object {} was left in the bucket)", e, key));
+    if !result.record("delete_objects", deleted, started) {
+        return;
+    }
+    
+    let latencies: Vec<String> = result.steps.iter()
+        .map(|step| format!("{} {} ms", step.name, step.latency_ms))
+        .collect();
+    result.message = format!("Connected: the bucket can be listed, written to and deleted from ({})", latencies.join(", "));
+}
+
+/// HEAD the bucket in the config's addressing style
+async fn head_bucket(config: &S3ConnectionConfig) -> Result<S3ConnectionResult, String> {
+    let client = crate::network::client();
+    let region = config.region_or_default();
+    
+    // Create the URL for bucket HEAD request
+    let url = config.bucket_url();
     
-    println!("Testing URL: {}", url);
+    log::info!("Testing URL: {}", url);
     
     let now = Utc::now();
     let timestamp_str = now.format("%Y%m%dT%H%M%SZ").to_string();
-    let _date_str = now.format("%Y%m%d").to_string();
     
     // Create headers for AWS signature
     let mut headers = HashMap::new();
     
-    // Parse host from URL
-    let parsed_url = Url::parse(&url).map_err(|e| format!("Invalid URL: {}", e))?;
-    let host = parsed_url.host_str().ok_or("No host in URL")?;
-    
-    headers.insert("host".to_string(), host.to_string());
+    headers.insert("host".to_string(), host_header(&url)?);
     headers.insert("x-amz-date".to_string(), timestamp_str.clone());
     headers.insert("x-amz-content-sha256".to_string(), "UNSIGNED-PAYLOAD".to_string());
+    config.add_session_header(&mut headers);
     
     // Generate AWS signature
     let authorization = generate_aws_signature_v4(
@@ -159,45 +860,36 @@ pub async fn test_s3_connection(config: S3ConnectionConfig) -> Result<S3Connecti
     
     request_builder = request_builder.header("Authorization", authorization);
     
-    match request_builder.send().await {
+    let (success, message, bucket_region) = match request_builder.send().await {
         Ok(response) => {
             let status = response.status();
-            println!("Response status: {}", status);
+            log::info!("Response status: {}", status);
+            // AWS names the bucket's region even when it refuses the request
+            let bucket_region = response.headers()
+                .get("x-amz-bucket-region")
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string());
+            let wrong_region = bucket_region.as_deref().filter(|actual| *actual != region);
             
-            if status.is_success() {
-                Ok(S3ConnectionResult {
-                    success: true,
-                    message: "Successfully connected to S3-compatible service!".to_string(),
-                })
+            let (success, message) = if status.is_success() {
+                (true, "Successfully connected to S3-compatible service!".to_string())
+            } else if let (Some(actual), 301 | 400) = (wrong_region, status.as_u16()) {
+                (false, format!("The bucket is in region {}, not {}. Set the region to {}.", actual, region, actual))
             } else if status == 401 {
-                Ok(S3ConnectionResult {
-                    success: false,
-                    message: "Authentication failed (401 Unauthorized). Please check your access key ID and secret access key.".to_string(),
-                })
+                (false, "Authentication failed (401 Unauthorized). Please check your access key ID and secret access key.".to_string())
             } else if status == 403 {
-                Ok(S3ConnectionResult {
-                    success: false,
-                    message: "Access denied (403 Forbidden). The credentials are valid but do not have permission to access this bucket.".to_string(),
-                })
+                (false, "Access denied (403 Forbidden). The credentials are valid but do not have permission to access this bucket.".to_string())
             } else if status == 404 {
-                Ok(S3ConnectionResult {
-                    success: false,
-                    message: "Bucket not found (404). Please verify the bucket name and endpoint URL.".to_string(),
-                })
+                (false, "Bucket not found (404). Please verify the bucket name and endpoint URL.".to_string())
             } else if status == 412 {
-                Ok(S3ConnectionResult {
-                    success: false,
-                    message: "Precondition Failed (412). This usually indicates the S3 service doesn't support the required headers or authentication method. Try checking if your endpoint URL is correct and if the service supports AWS Signature V4.".to_string(),
-                })
+                (false, "Precondition Failed (412). This usually indicates the S3 service doesn't support the required headers or authentication method. Try checking if your endpoint URL is correct and if the service supports AWS Signature V4.".to_string())
             } else {
-                Ok(S3ConnectionResult {
-                    success: false,
-                    message: format!("Connection failed with status: {}", status),
-                })
-            }
+                (false, format!("Connection failed with status: {}", status))
+            };
+            (success, message, bucket_region)
         }
         Err(e) => {
-            println!("Connection error: {}", e);
+            log::warn!("Connection error: {}", e);
             
             let error_msg = if e.is_connect() {
                 "Cannot reach the S3-compatible service endpoint. Check your endpoint URL and network connectivity.".to_string()
@@ -206,11 +898,48 @@ pub async fn test_s3_connection(config: S3ConnectionConfig) -> Result<S3Connecti
             } else {
                 format!("Connection failed: {}", e)
             };
-            
-            Ok(S3ConnectionResult {
-                success: false,
-                message: error_msg,
-            })
+            (false, error_msg, None)
         }
+    };
+    
+    Ok(S3ConnectionResult {
+        success,
+        message,
+        addressing_style: None,
+        region: bucket_region,
+        steps: Vec::new(),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresignedUrl {
+    pub url: String,
+    pub expires_at: String,
+}
+
+/// Time-limited download link for an object in a saved S3-compatible storage location,
+/// for sharing a single file without sharing the location's credentials
+#[tauri::command]
+pub async fn generate_presigned_url(
+    storage_location_id: String,
+    key: String,
+    expiry_secs: u64,
+    app_handle: tauri::AppHandle,
+) -> Result<PresignedUrl, CollectorError> {
+    let location = credentials::saved_location(&app_handle, &storage_location_id)?;
+    let location_type = location.get("type").and_then(|v| v.as_str()).unwrap_or_default();
+    if location_type != "s3-compatible" {
+        return Err(format!("Presigned URLs need an S3-compatible storage location, {} is {}", storage_location_id, location_type).into());
     }
+    let config = S3ConnectionConfig::from_storage_location(&location)?;
+    
+    let now = Utc::now();
+    let url = presigned_get_url(&config, key.trim_start_matches('/'), expiry_secs, &now)?;
+    log::info!("Generated presigned URL for s3://{}/{} valid for {}s", config.bucket_name, key, expiry_secs);
+    
+    Ok(PresignedUrl {
+        url,
+        expires_at: (now + chrono::Duration::seconds(expiry_secs as i64)).to_rfc3339(),
+    })
 }
@@ -0,0 +1,80 @@
+/// Device names Windows reserves in every directory, with or without an extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Characters Windows does not allow in file names
+const INVALID_CHARS: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+
+/// Length from which Windows paths need the `\\?\` prefix; MAX_PATH (260) less room for a
+/// file name, as directories are limited to 248 characters
+const LONG_PATH: usize = 248;
+
+/// Turn a key from a provider listing or a storage location into a relative path that stays
+/// inside the destination: separators become `/`, `.` and empty segments are dropped, and
+/// absolute keys or keys with `..` are refused. On Windows, segments that name a reserved
+/// device or use characters it forbids are renamed.
+pub fn relative(key: &str) -> Result<String, String> {
+    let normalized = key.replace('\\', "/");
+    let drive = normalized.as_bytes().get(1) == Some(&b':') && normalized.as_bytes()[0].is_ascii_alphabetic();
+    if normalized.starts_with('/') || drive {
+        return Err(format!("Refusing absolute path {:?}", key));
+    }
+
+    let mut segments = Vec::new();
+    for segment in normalized.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return Err(format!("Refusing path {:?}: it leads out of the destination", key)),
+            segment if segment.chars().any(char::is_control) => {
+                return Err(format!("Refusing path {:?} with control characters", key));
+            }
+            segment if cfg!(windows) => segments.push(windows_segment(segment)),
+            segment => segments.push(segment.to_string()),
+        }
+    }
+    if segments.is_empty() {
+        return Err(format!("Refusing empty path {:?}", key));
+    }
+    Ok(segments.join("/"))
+}
+
+/// A path segment Windows can create: forbidden characters and trailing dots or spaces become
+/// `_`, and reserved device names get a `_` after their stem (`CON.json` -> `CON_.json`)
+fn windows_segment(segment: &str) -> String {
+    let mut name: String = segment.chars()
+        .map(|c| if INVALID_CHARS.contains(&c) { '_' } else { c })
+        .collect();
+    let trimmed = name.trim_end_matches(['.', ' ']).len();
+    if trimmed < name.len() {
+        name.replace_range(trimmed.., &"_".repeat(name.len() - trimmed));
+    }
+
+    let stem = name.split('.').next().unwrap_or("");
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        name.insert(stem.len(), '_');
+    }
+    name
+}
+
+/// Local path of `key` under `dest_dir`, see `relative`
+pub fn join(dest_dir: &str, key: &str) -> Result<String, String> {
+    let relative = relative(key)?;
+    // A directory that is already in the `\\?\` form takes backslashes only
+    if dest_dir.starts_with(r"\\?\") {
+        return Ok(format!(r"{}\{}", dest_dir.trim_end_matches(['/', '\\']), relative.replace('/', "\\")));
+    }
+    Ok(long_path(&format!("{}/{}", dest_dir.trim_end_matches(['/', '\\']), relative)))
+}
+
+/// On Windows, the `\\?\` form of an absolute path that is too long for the regular API, so
+/// deep `sub-xx/ses-yy/...` trees can be written. Other paths are returned unchanged.
+pub fn long_path(path: &str) -> String {
+    if !cfg!(windows) || path.len() < LONG_PATH || path.starts_with(r"\\") || !std::path::Path::new(path).is_absolute() {
+        return path.to_string();
+    }
+    // Verbatim paths skip all normalization, so they must use backslashes throughout
+    format!(r"\\?\{}", path.replace('/', "\\"))
+}
@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Default bandwidth left to background tasks while a foreground task is transferring
+pub const BACKGROUND_BYTES_PER_SEC: u64 = 512 * 1024;
+
+/// Background tasks that may transfer at the same time while a foreground task is active
+const BACKGROUND_SLOTS: u32 = 1;
+
+/// The same in performance mode
+const PERFORMANCE_BACKGROUND_SLOTS: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskPriority {
+    /// Started by the user and expected to finish quickly
+    Foreground,
+    /// Bulk collection that yields to foreground tasks
+    Background,
+}
+
+impl TaskPriority {
+    /// Task field `priority: "background"` (or `lowPriority: true`); foreground otherwise
+    pub fn from_task(task: &serde_json::Value) -> TaskPriority {
+        let background = task.get("priority").and_then(|v| v.as_str()) == Some("background")
+            || task.get("lowPriority").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        if background {
+            TaskPriority::Background
+        } else {
+            TaskPriority::Foreground
+        }
+    }
+}
+
+/// What a task is waiting for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitKind {
+    /// The global download window is closed
+    DownloadWindow,
+    /// All tasks were paused by the user
+    Paused,
+    /// The task's own time window is closed
+    TaskWindow,
+    AcPower,
+    UnmeteredNetwork,
+    /// Every concurrent task slot is taken
+    ConcurrencyLimit,
+    /// Other queued tasks start first
+    QueuePosition,
+    /// A background task waits for a transfer slot while a foreground task runs
+    ForegroundPriority,
+}
+
+/// Why a task is not transferring, for the UI to explain instead of showing "queued"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaitReason {
+    pub kind: WaitKind,
+    pub message: String,
+}
+
+impl WaitReason {
+    pub fn new(kind: WaitKind, message: impl Into<String>) -> WaitReason {
+        WaitReason {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+/// A running task as the scheduler sees it
+#[derive(Debug, Clone, Copy)]
+struct Registered {
+    priority: TaskPriority,
+    waiting_for_slot: bool,
+}
+
+/// How the scheduler is sharing capacity right now
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulerUsage {
+    pub foreground_active: bool,
+    pub performance_mode: bool,
+    /// Background files transferring next to foreground tasks, and how many may
+    pub background_slots_used: u32,
+    pub background_slots: u32,
+    /// Pace of background tasks while a foreground task runs; None when they are not throttled
+    pub background_bytes_per_sec: Option<f64>,
+    /// Combined pace of all transfers; None when unlimited
+    pub bandwidth_limit: Option<f64>,
+}
+
+/// Paces all transfers together when a bandwidth limit is set
+struct Throttle {
+    bytes_per_sec: Option<f64>,
+    /// When the bytes handed out so far have been sent at the limit
+    next_free: Instant,
+}
+
+/// Shares transfer capacity between foreground and background tasks.
+///
+/// Background tasks run at full speed while nothing else is active. As soon as a
+/// foreground task is running, they transfer one file at a time (across all
+/// background tasks) and are paced to the background bandwidth. In performance mode up to
+/// `PERFORMANCE_BACKGROUND_SLOTS` background files transfer at once. A bandwidth limit
+/// applies to all tasks on top of that.
+pub struct Scheduler {
+    /// Running tasks by ID
+    tasks: Mutex<HashMap<String, Registered>>,
+    /// Sized for performance mode; outside it each background file takes several permits
+    background_slots: Semaphore,
+    performance_mode: AtomicBool,
+    background_bytes_per_sec: Mutex<f64>,
+    throttle: Mutex<Throttle>,
+}
+
+pub type SchedulerState = Arc<Scheduler>;
+
+impl Scheduler {
+    pub fn new() -> Scheduler {
+        Scheduler {
+            tasks: Mutex::new(HashMap::new()),
+            background_slots: Semaphore::new(PERFORMANCE_BACKGROUND_SLOTS as usize),
+            performance_mode: AtomicBool::new(false),
+            background_bytes_per_sec: Mutex::new(BACKGROUND_BYTES_PER_SEC as f64),
+            throttle: Mutex::new(Throttle { bytes_per_sec: None, next_free: Instant::now() }),
+        }
+    }
+
+    /// Count a running task; the returned guard releases it when the task ends
+    pub fn register(self: &Arc<Self>, task_id: &str, priority: TaskPriority) -> PriorityGuard {
+        self.tasks.lock().unwrap().insert(task_id.to_string(), Registered {
+            priority,
+            waiting_for_slot: false,
+        });
+        PriorityGuard {
+            scheduler: self.clone(),
+            task_id: task_id.to_string(),
+        }
+    }
+
+    pub fn foreground_active(&self) -> bool {
+        self.tasks.lock().unwrap().values().any(|task| task.priority == TaskPriority::Foreground)
+    }
+
+    pub fn priority(&self, task_id: &str) -> Option<TaskPriority> {
+        self.tasks.lock().unwrap().get(task_id).map(|task| task.priority)
+    }
+
+    /// Why a running task is held back by the scheduler, if it is
+    pub fn waiting(&self, task_id: &str) -> Option<WaitReason> {
+        let waiting = self.tasks.lock().unwrap().get(task_id).is_some_and(|task| task.waiting_for_slot);
+        waiting.then(|| WaitReason::new(
+            WaitKind::ForegroundPriority,
+            "Waiting for a background transfer slot while a foreground task runs",
+        ))
+    }
+
+    fn permits_per_file(&self) -> u32 {
+        if self.performance_mode.load(Ordering::SeqCst) {
+            1
+        } else {
+            PERFORMANCE_BACKGROUND_SLOTS / BACKGROUND_SLOTS
+        }
+    }
+
+    pub fn usage(&self) -> SchedulerUsage {
+        let foreground_active = self.foreground_active();
+        let permits_per_file = self.permits_per_file();
+        let used_permits = PERFORMANCE_BACKGROUND_SLOTS as usize - self.background_slots.available_permits();
+        SchedulerUsage {
+            foreground_active,
+            performance_mode: self.performance_mode.load(Ordering::SeqCst),
+            background_slots_used: (used_permits as u32).div_ceil(permits_per_file),
+            background_slots: PERFORMANCE_BACKGROUND_SLOTS / permits_per_file,
+            background_bytes_per_sec: foreground_active.then(|| *self.background_bytes_per_sec.lock().unwrap()),
+            bandwidth_limit: self.throttle.lock().unwrap().bytes_per_sec,
+        }
+    }
+
+    fn set_waiting_for_slot(&self, task_id: &str, waiting: bool) {
+        if let Some(task) = self.tasks.lock().unwrap().get_mut(task_id) {
+            task.waiting_for_slot = waiting;
+        }
+    }
+
+    /// Let more background files transfer next to a foreground task; applies to the next file
+    pub fn set_performance_mode(&self, enabled: bool) {
+        self.performance_mode.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Cap all transfers at `limit` bytes per second (None for unlimited) and pace background
+    /// tasks to `background` next to foreground ones; applies to the next chunk
+    pub fn set_bandwidth(&self, limit: Option<u64>, background: u64) {
+        *self.background_bytes_per_sec.lock().unwrap() = background as f64;
+        let mut throttle = self.throttle.lock().unwrap();
+        throttle.bytes_per_sec = limit.map(|limit| limit as f64);
+        throttle.next_free = Instant::now();
+    }
+
+    /// Wait for a transfer slot before a background task starts its next file.
+    /// Returns immediately for foreground tasks or when no foreground task is running.
+    pub async fn acquire_slot(&self, task_id: &str, priority: TaskPriority) -> Option<SemaphorePermit<'_>> {
+        if priority == TaskPriority::Foreground || !self.foreground_active() {
+            return None;
+        }
+        self.set_waiting_for_slot(task_id, true);
+        let permit = self.background_slots.acquire_many(self.permits_per_file()).await.ok();
+        self.set_waiting_for_slot(task_id, false);
+        permit
+    }
+
+    /// Slow a task down after it transferred `bytes`: background tasks while a foreground task
+    /// is running, and every task while a bandwidth limit is set
+    pub async fn pace(&self, priority: TaskPriority, bytes: usize) {
+        if priority == TaskPriority::Background && self.foreground_active() {
+            let budget = *self.background_bytes_per_sec.lock().unwrap();
+            tokio::time::sleep(Duration::from_secs_f64(bytes as f64 / budget)).await;
+        }
+
+        // Each chunk books its share of the limit after the ones before it, across all tasks
+        let wait = {
+            let mut throttle = self.throttle.lock().unwrap();
+            let Some(limit) = throttle.bytes_per_sec else {
+                return;
+            };
+            let now = Instant::now();
+            throttle.next_free = throttle.next_free.max(now) + Duration::from_secs_f64(bytes as f64 / limit);
+            throttle.next_free - now
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Scheduler::new()
+    }
+}
+
+pub struct PriorityGuard {
+    scheduler: SchedulerState,
+    task_id: String,
+}
+
+impl Drop for PriorityGuard {
+    fn drop(&mut self) {
+        self.scheduler.tasks.lock().unwrap().remove(&self.task_id);
+    }
+}
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::CollectorError;
+use crate::rate_limit::{ProviderUsage, RateLimitState};
+use crate::scheduler::{SchedulerState, SchedulerUsage, TaskPriority, WaitKind, WaitReason};
+use crate::task_queue::{QueuePriority, QueueSnapshot, QueuedTask, TaskQueueState};
+use crate::work_queue::{self, LaneUsage, QueueState};
+use crate::DownloadState;
+
+/// A task waiting in the download queue and what keeps it there
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedTaskState {
+    pub task_id: String,
+    pub priority: QueuePriority,
+    pub queued_at: String,
+    /// 1-based place in the order tasks start in
+    pub position: usize,
+    pub waiting_for: WaitReason,
+}
+
+/// A task holding a concurrent slot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningTaskState {
+    pub task_id: String,
+    pub status: String,
+    /// None until the task has resolved its options
+    pub priority: Option<TaskPriority>,
+    pub speed: f64,
+    pub lanes: Vec<LaneUsage>,
+    /// Set while the task holds its slot without transferring
+    pub waiting_for: Option<WaitReason>,
+}
+
+/// Bandwidth the running tasks use, against what background tasks are allowed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthUsage {
+    pub bytes_per_sec: f64,
+    pub background_bytes_per_sec: f64,
+    /// Pace background tasks are held to; None while they are not throttled
+    pub background_budget: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulerSnapshot {
+    pub max_concurrent: usize,
+    pub window_open: bool,
+    pub paused: bool,
+    pub running: Vec<RunningTaskState>,
+    /// In the order the tasks will start
+    pub queued: Vec<QueuedTaskState>,
+    pub usage: SchedulerUsage,
+    pub bandwidth: BandwidthUsage,
+    pub providers: Vec<ProviderUsage>,
+}
+
+/// Why a queued task has not started, following the checks `TaskQueue` makes before
+/// handing it a slot. `ready_index` counts the unblocked tasks ahead of it.
+fn queued_reason(queue: &QueueSnapshot, task: &QueuedTask, ready_index: usize) -> WaitReason {
+    if queue.paused {
+        return WaitReason::new(WaitKind::Paused, "All tasks are paused");
+    }
+    if !queue.window_open {
+        return WaitReason::new(WaitKind::DownloadWindow, "The download window is closed");
+    }
+    if let Some(blocked) = &task.blocked {
+        return WaitReason::new(task.blocked_kind.unwrap_or(WaitKind::TaskWindow), blocked.clone());
+    }
+    let free_slots = queue.max_concurrent.saturating_sub(queue.running.len());
+    if free_slots == 0 {
+        WaitReason::new(
+            WaitKind::ConcurrencyLimit,
+            format!("All {} task slots are in use", queue.max_concurrent),
+        )
+    } else if ready_index < free_slots {
+        WaitReason::new(WaitKind::QueuePosition, "Starting")
+    } else {
+        WaitReason::new(
+            WaitKind::QueuePosition,
+            format!("{} tasks ahead take the free slots", ready_index),
+        )
+    }
+}
+
+/// The download queue, running tasks and the capacity they share. Tasks have no
+/// dependencies on each other and quotas are checked when a task starts, so neither keeps
+/// a queued task waiting; a task that exceeds its quota fails instead.
+#[tauri::command]
+pub async fn get_scheduler_state(
+    queue: tauri::State<'_, TaskQueueState>,
+    scheduler: tauri::State<'_, SchedulerState>,
+    file_queues: tauri::State<'_, QueueState>,
+    rate_limiter: tauri::State<'_, RateLimitState>,
+    downloads: tauri::State<'_, DownloadState>,
+) -> Result<SchedulerSnapshot, CollectorError> {
+    let snapshot = queue.snapshot();
+
+    let mut ready_index = 0;
+    let queued = snapshot.queued.iter()
+        .enumerate()
+        .map(|(index, task)| {
+            let waiting_for = queued_reason(&snapshot, task, ready_index);
+            if task.blocked.is_none() {
+                ready_index += 1;
+            }
+            QueuedTaskState {
+                task_id: task.task_id.clone(),
+                priority: task.priority,
+                queued_at: task.queued_at.clone(),
+                position: index + 1,
+                waiting_for,
+            }
+        })
+        .collect();
+
+    let progress = downloads.lock().unwrap().clone();
+    let running: Vec<RunningTaskState> = snapshot.running.iter()
+        .map(|task_id| {
+            let task = progress.get(task_id);
+            let paused = task.and_then(|task| {
+                let message = task.pause_reason.clone()?;
+                Some(WaitReason::new(task.pause_kind.unwrap_or(WaitKind::DownloadWindow), message))
+            });
+            RunningTaskState {
+                task_id: task_id.clone(),
+                status: task.map(|task| task.status.clone()).unwrap_or_else(|| "starting".to_string()),
+                priority: scheduler.priority(task_id),
+                speed: task.map(|task| task.speed).unwrap_or_default(),
+                lanes: work_queue::lane_usage(&file_queues, task_id),
+                waiting_for: paused.or_else(|| scheduler.waiting(task_id)),
+            }
+        })
+        .collect();
+
+    let usage = scheduler.usage();
+    let bandwidth = BandwidthUsage {
+        bytes_per_sec: running.iter().map(|task| task.speed).sum(),
+        background_bytes_per_sec: running.iter()
+            .filter(|task| task.priority == Some(TaskPriority::Background))
+            .map(|task| task.speed)
+            .sum(),
+        background_budget: usage.background_bytes_per_sec,
+    };
+
+    Ok(SchedulerSnapshot {
+        max_concurrent: snapshot.max_concurrent,
+        window_open: snapshot.window_open,
+        paused: snapshot.paused,
+        running,
+        queued,
+        usage,
+        bandwidth,
+        providers: rate_limiter.usage(),
+    })
+}
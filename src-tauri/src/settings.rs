@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+use crate::error::CollectorError;
+use crate::notifications::{NotificationSettings, NotificationState};
+use crate::scheduler::{self, SchedulerState};
+use crate::task_queue::{self, TaskQueueState};
+use crate::{gzip, range_support};
+
+/// File in the app data directory holding the application settings
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Slowest bandwidth accepted as a limit, so a typo cannot stall every transfer
+const MIN_BANDWIDTH: u64 = 16 * 1024;
+
+/// Upper bound for each retry count
+const MAX_RETRIES: u32 = 20;
+
+/// How often a file is tried again before its task fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// Range resumes of a broken stream before the file is downloaded again from the start
+    pub max_resumes: u32,
+    /// Whole-file downloads after broken streams that could not be resumed
+    pub max_full_attempts: u32,
+    /// Downloads of a gzip file that fails validation
+    pub max_gzip_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_resumes: range_support::MAX_RESUMES,
+            max_full_attempts: range_support::MAX_FULL_ATTEMPTS,
+            max_gzip_attempts: gzip::MAX_ATTEMPTS,
+        }
+    }
+}
+
+/// Application-wide defaults the download engine reads at runtime. Per-task options in the
+/// task payload still take precedence.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppSettings {
+    /// Local directory used when a local storage location has no path of its own
+    pub default_download_directory: Option<String>,
+    pub max_concurrent_tasks: usize,
+    /// Combined transfer speed of all tasks in bytes per second; None for unlimited
+    pub bandwidth_limit: Option<u64>,
+    /// Bytes per second left to background tasks while a foreground task is transferring
+    pub background_bandwidth: u64,
+    pub retry: RetryPolicy,
+    /// Folders scanned for task files to queue, see `watch_folders`
+    pub watch_folders: Vec<String>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            default_download_directory: None,
+            max_concurrent_tasks: task_queue::DEFAULT_MAX_CONCURRENT,
+            bandwidth_limit: None,
+            background_bandwidth: scheduler::BACKGROUND_BYTES_PER_SEC,
+            retry: RetryPolicy::default(),
+            watch_folders: Vec::new(),
+        }
+    }
+}
+
+impl AppSettings {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(dir) = &self.default_download_directory {
+            if !PathBuf::from(dir).is_absolute() {
+                return Err(format!("Default download directory must be an absolute path, got {}", dir));
+            }
+        }
+        if let Some(folder) = self.watch_folders.iter().find(|folder| !PathBuf::from(folder).is_absolute()) {
+            return Err(format!("Watch folders must be absolute paths, got {}", folder));
+        }
+        if !(1..=task_queue::MAX_CONCURRENT_LIMIT).contains(&self.max_concurrent_tasks) {
+            return Err(format!("Concurrent tasks must be between 1 and {}, got {}", task_queue::MAX_CONCURRENT_LIMIT, self.max_concurrent_tasks));
+        }
+        if self.bandwidth_limit.is_some_and(|limit| limit < MIN_BANDWIDTH) || self.background_bandwidth < MIN_BANDWIDTH {
+            return Err(format!("Bandwidth limits must be at least {} bytes per second", MIN_BANDWIDTH));
+        }
+        let retry = self.retry;
+        if retry.max_full_attempts == 0 || retry.max_gzip_attempts == 0 {
+            return Err("Files need at least one download attempt".to_string());
+        }
+        if [retry.max_resumes, retry.max_full_attempts, retry.max_gzip_attempts].iter().any(|n| *n > MAX_RETRIES) {
+            return Err(format!("Retry counts must be at most {}", MAX_RETRIES));
+        }
+        Ok(())
+    }
+}
+
+/// Everything `get_settings` reports: the application settings and the notification
+/// preferences, which keep their own file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsSnapshot {
+    #[serde(flatten)]
+    pub settings: AppSettings,
+    pub notifications: NotificationSettings,
+}
+
+/// The application settings, persisted in the app data directory
+pub struct Settings {
+    settings_path: PathBuf,
+    settings: Mutex<AppSettings>,
+}
+
+pub type SettingsState = Arc<Settings>;
+
+impl Settings {
+    /// Load the settings from the app data directory, falling back to the defaults
+    pub fn open(app_handle: &tauri::AppHandle) -> Result<Settings, String> {
+        let settings_path = app_handle.path().app_data_dir()
+            .map(|dir| dir.join(SETTINGS_FILE))
+            .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+
+        let settings = match std::fs::read(&settings_path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", settings_path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => AppSettings::default(),
+            Err(e) => return Err(format!("Failed to read {}: {}", settings_path.display(), e)),
+        };
+
+        Ok(Settings {
+            settings_path,
+            settings: Mutex::new(settings),
+        })
+    }
+
+    pub fn settings(&self) -> AppSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.settings.lock().unwrap().retry
+    }
+
+    pub fn default_download_directory(&self) -> Option<String> {
+        self.settings.lock().unwrap().default_download_directory.clone()
+    }
+
+    /// Hand the concurrency and bandwidth limits to the queue and the scheduler
+    pub fn apply(&self, queue: &TaskQueueState, scheduler: &SchedulerState) -> Result<(), String> {
+        let settings = self.settings();
+        queue.set_max_concurrent(settings.max_concurrent_tasks)?;
+        scheduler.set_bandwidth(settings.bandwidth_limit, settings.background_bandwidth);
+        Ok(())
+    }
+
+    /// Validate, save and apply new settings
+    pub fn update(&self, settings: AppSettings, queue: &TaskQueueState, scheduler: &SchedulerState) -> Result<(), String> {
+        settings.validate()?;
+        if let Some(dir) = &settings.default_download_directory {
+            crate::path_guard::add_root(dir)?;
+        }
+        // Task files are moved into subfolders once they were picked up
+        for folder in &settings.watch_folders {
+            crate::path_guard::add_root(folder)?;
+        }
+
+        crate::path_guard::check(&self.settings_path)?;
+        if let Some(parent) = self.settings_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        let content = serde_json::to_vec_pretty(&settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        std::fs::write(&self.settings_path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.settings_path.display(), e))?;
+
+        *self.settings.lock().unwrap() = settings;
+        self.apply(queue, scheduler)
+    }
+}
+
+#[tauri::command]
+pub async fn get_settings(
+    state: tauri::State<'_, SettingsState>,
+    notifications: tauri::State<'_, NotificationState>,
+) -> Result<SettingsSnapshot, CollectorError> {
+    Ok(SettingsSnapshot {
+        settings: state.settings(),
+        notifications: notifications.settings(),
+    })
+}
+
+/// Replace the application settings, and the notification preferences when given. Limits
+/// take effect right away; a lower concurrency lets running tasks finish.
+#[tauri::command]
+pub async fn update_settings(
+    settings: AppSettings,
+    notifications: Option<NotificationSettings>,
+    state: tauri::State<'_, SettingsState>,
+    dispatcher: tauri::State<'_, NotificationState>,
+    queue: tauri::State<'_, TaskQueueState>,
+    scheduler: tauri::State<'_, SchedulerState>,
+) -> Result<SettingsSnapshot, CollectorError> {
+    state.update(settings, &queue, &scheduler)?;
+    if let Some(notifications) = notifications {
+        dispatcher.update(notifications)?;
+    }
+
+    log::info!("Settings updated: {:?}", state.settings());
+    Ok(SettingsSnapshot {
+        settings: state.settings(),
+        notifications: dispatcher.settings(),
+    })
+}
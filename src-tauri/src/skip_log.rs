@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use tokio::fs;
+
+use crate::path_guard;
+
+/// Directory (relative to the dataset root) that holds one skip log per task
+const SKIP_LOG_DIR: &str = ".bids-collector/skipped";
+
+/// How skipped files are checked before they are trusted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyPolicy {
+    /// Trust size matches; only files without a previous hash are hashed
+    None,
+    /// Hash this many skipped files, chosen pseudo-randomly per task
+    Sample(usize),
+    All,
+    /// Hash the listed files, e.g. those `verify_dataset` found corrupt
+    Paths(HashSet<String>),
+}
+
+impl VerifyPolicy {
+    /// Task field `verifySkipped`: `"all"`, a sample size, a list of paths, or absent/false for none
+    pub fn from_task(task: &serde_json::Value) -> Result<VerifyPolicy, String> {
+        match task.get("verifySkipped") {
+            None | Some(serde_json::Value::Null) | Some(serde_json::Value::Bool(false)) => Ok(VerifyPolicy::None),
+            Some(serde_json::Value::Bool(true)) => Ok(VerifyPolicy::All),
+            Some(serde_json::Value::String(s)) if s == "all" => Ok(VerifyPolicy::All),
+            Some(serde_json::Value::Number(n)) => n.as_u64()
+                .map(|n| VerifyPolicy::Sample(n as usize))
+                .ok_or_else(|| format!("Invalid verifySkipped sample size: {}", n)),
+            Some(serde_json::Value::Array(paths)) => paths.iter()
+                .map(|path| path.as_str().map(|s| s.to_string()).ok_or_else(|| format!("Invalid path in verifySkipped: {}", path)))
+                .collect::<Result<_, _>>()
+                .map(VerifyPolicy::Paths),
+            Some(other) => Err(format!("Invalid verifySkipped value: {}", other)),
+        }
+    }
+
+    /// Pick which of the candidate paths get hashed. The sample is derived from
+    /// the task ID, so it differs between runs but is reproducible from the log.
+    pub fn select(&self, candidates: &[&str], task_id: &str) -> HashSet<String> {
+        match self {
+            VerifyPolicy::None => HashSet::new(),
+            VerifyPolicy::All => candidates.iter().map(|p| p.to_string()).collect(),
+            VerifyPolicy::Paths(paths) => candidates.iter().filter(|p| paths.contains(**p)).map(|p| p.to_string()).collect(),
+            VerifyPolicy::Sample(count) => {
+                let mut ranked: Vec<(String, &str)> = candidates
+                    .iter()
+                    .map(|path| (hex::encode(Sha256::digest(format!("{}:{}", task_id, path))), *path))
+                    .collect();
+                ranked.sort();
+                ranked.into_iter().take(*count).map(|(_, path)| path.to_string()).collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipVerification {
+    /// Size matched and the previous manifest hash was reused
+    NotChecked,
+    /// Hash matched the previous manifest or the provider's checksum
+    Verified,
+    /// Hash differed; the file was downloaded again
+    Mismatch,
+    /// Hashed, but there was nothing to compare against
+    Unverifiable,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub size: u64,
+    pub verification: SkipVerification,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DroppedFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// A file whose signed URL expired before it was transferred
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiredFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// A file moved locally because the provider renamed it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenamedFile {
+    pub from: String,
+    pub to: String,
+    pub size: u64,
+}
+
+/// Every file a task left alone because it already existed at the destination,
+/// the files the user dropped from it while it was running, and those whose signed URL
+/// expired before their turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkipLog {
+    pub task_id: String,
+    pub policy: String,
+    pub started_at: String,
+    pub files: Vec<SkippedFile>,
+    #[serde(default)]
+    pub dropped: Vec<DroppedFile>,
+    #[serde(default)]
+    pub renamed: Vec<RenamedFile>,
+    #[serde(default)]
+    pub expired: Vec<ExpiredFile>,
+}
+
+impl SkipLog {
+    pub fn new(task_id: &str, policy: &VerifyPolicy) -> SkipLog {
+        let policy = match policy {
+            VerifyPolicy::Paths(paths) => format!("Paths({})", paths.len()),
+            other => format!("{:?}", other),
+        };
+        SkipLog {
+            task_id: task_id.to_string(),
+            policy,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            files: Vec::new(),
+            dropped: Vec::new(),
+            renamed: Vec::new(),
+            expired: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, path: &str, size: u64, verification: SkipVerification) {
+        self.files.push(SkippedFile {
+            path: path.to_string(),
+            size,
+            verification,
+        });
+    }
+
+    pub fn record_dropped(&mut self, path: &str, size: u64) {
+        self.dropped.push(DroppedFile {
+            path: path.to_string(),
+            size,
+        });
+    }
+
+    pub fn record_renamed(&mut self, from: &str, to: &str, size: u64) {
+        self.renamed.push(RenamedFile {
+            from: from.to_string(),
+            to: to.to_string(),
+            size,
+        });
+    }
+
+    pub fn record_expired(&mut self, path: &str, size: u64) {
+        self.expired.push(ExpiredFile {
+            path: path.to_string(),
+            size,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.dropped.is_empty() && self.renamed.is_empty() && self.expired.is_empty()
+    }
+
+    pub fn count(&self, verification: SkipVerification) -> usize {
+        self.files.iter().filter(|f| f.verification == verification).count()
+    }
+
+    /// Write the log next to the dataset, returning its path
+    pub async fn write_local(&self, dest_dir: &str) -> Result<String, String> {
+        let dir = format!("{}/{}", dest_dir, SKIP_LOG_DIR);
+        path_guard::check(&dir)?;
+        fs::create_dir_all(&dir).await
+            .map_err(|e| format!("Failed to create directory {}: {}", dir, e))?;
+
+        let path = format!("{}/{}.json", dir, self.task_id);
+        let content = serde_json::to_vec_pretty(self)
+            .map_err(|e| format!("Failed to serialize skip log: {}", e))?;
+        fs::write(&path, content).await
+            .map_err(|e| format!("Failed to write skip log {}: {}", path, e))?;
+
+        log::info!(
+            "Skip log: {} files ({} verified, {} re-downloaded after mismatch), {} dropped, {} renamed, {} expired, written to {}",
+            self.files.len(),
+            self.count(SkipVerification::Verified),
+            self.count(SkipVerification::Mismatch),
+            self.dropped.len(),
+            self.renamed.len(),
+            self.expired.len(),
+            path
+        );
+        Ok(path)
+    }
+}
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::error::CollectorError;
+use crate::filters::FileFilter;
+use crate::providers::{DatasetListing, RemoteFile};
+
+/// One listed file of a staged task and whether the current selection includes it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedFile {
+    pub path: String,
+    pub size: u64,
+    pub selected: bool,
+}
+
+/// A task that was created but not started: its listing is kept so filters and the
+/// destination can be edited against it before `commit_task` queues the transfer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedTask {
+    pub task_id: String,
+    /// Payload `commit_task` starts the task with, as for `start_download_task`
+    pub task_data: Value,
+    pub identifier: String,
+    pub version: Option<String>,
+    pub listed_files: usize,
+    pub listed_size: u64,
+    pub selected_files: usize,
+    pub selected_size: u64,
+    pub files: Vec<StagedFile>,
+    pub created_at: String,
+}
+
+struct Staged {
+    task_data: Value,
+    identifier: String,
+    version: Option<String>,
+    files: Vec<RemoteFile>,
+    created_at: String,
+}
+
+/// Tasks waiting to be committed, by task ID. Staged tasks live in memory only.
+#[derive(Default)]
+pub struct StagedTasks {
+    tasks: Mutex<HashMap<String, Staged>>,
+}
+
+pub type StagingState = Arc<StagedTasks>;
+
+/// Provider and download path the listing of a task depends on
+pub fn listing_source(task_data: &Value) -> Result<(String, String), String> {
+    let task = task_data.get("task").ok_or("No task data found")?;
+    let provider = task.get("datasetProvider")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown");
+    let download_path = task.get("downloadPath")
+        .and_then(|v| v.as_str())
+        .ok_or("No download path specified")?;
+    Ok((provider.to_string(), download_path.to_string()))
+}
+
+fn view(task_id: &str, staged: &Staged) -> Result<StagedTask, String> {
+    let task = staged.task_data.get("task").ok_or("No task data found")?;
+    let filter = FileFilter::from_task(task)?;
+
+    let files: Vec<StagedFile> = staged.files
+        .iter()
+        .map(|file| StagedFile {
+            path: file.path.clone(),
+            size: file.size,
+            selected: filter.matches(&file.path),
+        })
+        .collect();
+    let selected = files.iter().filter(|file| file.selected);
+
+    Ok(StagedTask {
+        task_id: task_id.to_string(),
+        task_data: staged.task_data.clone(),
+        identifier: staged.identifier.clone(),
+        version: staged.version.clone(),
+        listed_files: files.len(),
+        listed_size: files.iter().map(|file| file.size).sum(),
+        selected_files: selected.clone().count(),
+        selected_size: selected.map(|file| file.size).sum(),
+        files,
+        created_at: staged.created_at.clone(),
+    })
+}
+
+impl StagedTasks {
+    /// Stage a task with a fresh listing, replacing an earlier staging of the same task
+    pub fn stage(&self, task_id: &str, task_data: Value, listing: DatasetListing) -> Result<StagedTask, String> {
+        let staged = Staged {
+            task_data,
+            identifier: listing.identifier,
+            version: listing.version,
+            files: listing.files,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let task = view(task_id, &staged)?;
+        self.tasks.lock().unwrap().insert(task_id.to_string(), staged);
+        Ok(task)
+    }
+
+    /// Replace the payload of a staged task whose listing still applies
+    pub fn edit(&self, task_id: &str, task_data: Value) -> Result<StagedTask, String> {
+        let mut tasks = self.tasks.lock().unwrap();
+        let staged = tasks.get_mut(task_id).ok_or_else(|| format!("Task {} is not staged", task_id))?;
+
+        let previous = std::mem::replace(&mut staged.task_data, task_data);
+        view(task_id, staged).inspect_err(|_| staged.task_data = previous)
+    }
+
+    /// Payload the task was staged with, when it is staged
+    pub fn task_data(&self, task_id: &str) -> Option<Value> {
+        self.tasks.lock().unwrap().get(task_id).map(|staged| staged.task_data.clone())
+    }
+
+    pub fn get(&self, task_id: &str) -> Result<StagedTask, String> {
+        let tasks = self.tasks.lock().unwrap();
+        let staged = tasks.get(task_id).ok_or_else(|| format!("Task {} is not staged", task_id))?;
+        view(task_id, staged)
+    }
+
+    /// Remove a task from staging, returning the payload to start it with
+    pub fn take(&self, task_id: &str) -> Result<Value, String> {
+        self.tasks.lock().unwrap()
+            .remove(task_id)
+            .map(|staged| staged.task_data)
+            .ok_or_else(|| format!("Task {} is not staged", task_id))
+    }
+}
+
+#[tauri::command]
+pub async fn get_staged_task(task_id: String, staging: tauri::State<'_, StagingState>) -> Result<StagedTask, CollectorError> {
+    Ok(staging.get(&task_id)?)
+}
+
+/// Every staged task, oldest first
+#[tauri::command]
+pub async fn list_staged_tasks(staging: tauri::State<'_, StagingState>) -> Result<Vec<StagedTask>, CollectorError> {
+    let tasks = staging.tasks.lock().unwrap();
+    let mut staged = tasks
+        .iter()
+        .map(|(task_id, staged)| view(task_id, staged))
+        .collect::<Result<Vec<_>, String>>()?;
+    staged.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    Ok(staged)
+}
+
+/// Drop a staged task without starting it
+#[tauri::command]
+pub async fn discard_staged_task(task_id: String, staging: tauri::State<'_, StagingState>) -> Result<(), CollectorError> {
+    staging.take(&task_id)?;
+    log::info!("Discarded staged task {}", task_id);
+    Ok(())
+}
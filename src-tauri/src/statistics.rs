@@ -0,0 +1,195 @@
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::Manager;
+
+use crate::catalog::CatalogState;
+use crate::error::CollectorError;
+use crate::DownloadProgress;
+
+/// Where a queued task came from, for its notification and its statistics record
+#[derive(Debug, Clone)]
+pub struct TaskOrigin {
+    /// `download` for collections from a provider, `transfer` for copies between storage locations
+    pub kind: &'static str,
+    pub provider: Option<String>,
+    pub dataset: Option<String>,
+}
+
+/// What one finished task transferred, as kept in the catalog database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferRecord {
+    pub task_id: String,
+    pub kind: String,
+    pub provider: Option<String>,
+    pub dataset: Option<String>,
+    /// completed, failed or cancelled
+    pub status: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub bytes: u64,
+    pub files: u64,
+    pub duration_seconds: f64,
+    /// Bytes per second over the whole task
+    pub average_speed: f64,
+    /// Extra attempts files needed after broken or corrupt transfers
+    pub retries: u64,
+    pub error: Option<String>,
+}
+
+impl TransferRecord {
+    /// Record of a task that stopped, or None when it never started
+    fn of(progress: &DownloadProgress, origin: &TaskOrigin) -> Option<TransferRecord> {
+        let started_at = progress.started_at.clone()?;
+        let finished_at = progress.completed_at.clone().unwrap_or_else(|| Utc::now().to_rfc3339());
+        let duration_seconds = match (DateTime::parse_from_rfc3339(&started_at), DateTime::parse_from_rfc3339(&finished_at)) {
+            (Ok(start), Ok(end)) => (end - start).num_milliseconds().max(0) as f64 / 1000.0,
+            _ => 0.0,
+        };
+        Some(TransferRecord {
+            task_id: progress.task_id.clone(),
+            kind: origin.kind.to_string(),
+            provider: origin.provider.clone(),
+            dataset: origin.dataset.clone(),
+            status: progress.status.clone(),
+            started_at,
+            finished_at,
+            bytes: progress.downloaded_size,
+            files: progress.completed_files.unwrap_or(0) as u64,
+            duration_seconds,
+            average_speed: if duration_seconds > 0.0 { progress.downloaded_size as f64 / duration_seconds } else { 0.0 },
+            retries: progress.retries as u64,
+            error: progress.error_message.clone(),
+        })
+    }
+}
+
+/// Sums over a set of tasks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferTotals {
+    pub tasks: u64,
+    pub completed: u64,
+    pub failed: u64,
+    pub cancelled: u64,
+    pub bytes: u64,
+    pub files: u64,
+    pub duration_seconds: f64,
+    /// Bytes per second over the time the tasks were running
+    pub average_speed: f64,
+    pub retries: u64,
+}
+
+impl TransferTotals {
+    fn add(&mut self, record: &TransferRecord) {
+        self.tasks += 1;
+        match record.status.as_str() {
+            "completed" => self.completed += 1,
+            "failed" => self.failed += 1,
+            "cancelled" => self.cancelled += 1,
+            _ => {}
+        }
+        self.bytes += record.bytes;
+        self.files += record.files;
+        self.duration_seconds += record.duration_seconds;
+        self.retries += record.retries;
+        self.average_speed = if self.duration_seconds > 0.0 { self.bytes as f64 / self.duration_seconds } else { 0.0 };
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthlyStatistics {
+    /// `YYYY-MM`, by the time the tasks finished (UTC)
+    pub month: String,
+    #[serde(flatten)]
+    pub totals: TransferTotals,
+}
+
+/// Dates limiting `get_transfer_statistics`, both inclusive, as `YYYY-MM-DD` (UTC)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatisticsRange {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl StatisticsRange {
+    /// Lower and exclusive upper bound of `finished_at`, as RFC 3339 timestamps that
+    /// compare like the ones recorded
+    fn bounds(&self) -> Result<(String, String), String> {
+        let parse = |date: &str| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date {:?} (expected YYYY-MM-DD): {}", date, e));
+        let start_of = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap().and_utc().to_rfc3339();
+
+        let from = match &self.from {
+            Some(from) => start_of(parse(from)?),
+            None => String::new(),
+        };
+        let to = match &self.to {
+            Some(to) => start_of(parse(to)?.checked_add_days(Days::new(1)).ok_or("Invalid end date")?),
+            // Sorts after every timestamp
+            None => "~".to_string(),
+        };
+        Ok((from, to))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferStatistics {
+    pub range: StatisticsRange,
+    pub totals: TransferTotals,
+    /// Oldest month first; months without tasks are left out
+    pub months: Vec<MonthlyStatistics>,
+    pub by_provider: BTreeMap<String, TransferTotals>,
+    /// Every task in the range, oldest first
+    pub tasks: Vec<TransferRecord>,
+}
+
+impl TransferStatistics {
+    fn from_records(range: StatisticsRange, tasks: Vec<TransferRecord>) -> TransferStatistics {
+        let mut totals = TransferTotals::default();
+        let mut months: BTreeMap<String, TransferTotals> = BTreeMap::new();
+        let mut by_provider: BTreeMap<String, TransferTotals> = BTreeMap::new();
+        for record in &tasks {
+            totals.add(record);
+            let month = record.finished_at.get(..7).unwrap_or(&record.finished_at).to_string();
+            months.entry(month).or_default().add(record);
+            let provider = record.provider.clone().unwrap_or_else(|| record.kind.clone());
+            by_provider.entry(provider).or_default().add(record);
+        }
+        TransferStatistics {
+            range,
+            totals,
+            months: months.into_iter().map(|(month, totals)| MonthlyStatistics { month, totals }).collect(),
+            by_provider,
+            tasks,
+        }
+    }
+}
+
+/// Keep the outcome of a task that stopped; failing to do so only costs the statistics
+pub fn record(progress: &DownloadProgress, origin: &TaskOrigin, app_handle: &tauri::AppHandle) {
+    let Some(record) = TransferRecord::of(progress, origin) else {
+        return;
+    };
+    if let Err(e) = app_handle.state::<CatalogState>().lock().unwrap().record_transfer(&record) {
+        log::warn!("Failed to record statistics of task {}: {}", record.task_id, e);
+    }
+}
+
+/// Bytes, files, durations, speeds, retries and failures of the tasks that finished between
+/// `range.from` and `range.to`, in total, per month and per provider, for charting how much
+/// data was collected
+#[tauri::command]
+pub async fn get_transfer_statistics(
+    range: Option<StatisticsRange>,
+    catalog: tauri::State<'_, CatalogState>,
+) -> Result<TransferStatistics, CollectorError> {
+    let range = range.unwrap_or_default();
+    let (from, to) = range.bounds()?;
+    let tasks = catalog.lock().unwrap().transfers(&from, &to)?;
+    Ok(TransferStatistics::from_records(range, tasks))
+}
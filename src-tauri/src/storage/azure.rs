@@ -0,0 +1,450 @@
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::{RemoteStorage, UploadProgress, PATH_SEGMENT};
+use crate::credentials;
+use crate::error::{CollectorError, ErrorKind};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const API_VERSION: &str = "2021-08-06";
+
+/// Files above this size are uploaded as separate blocks and committed with a block list
+const DEFAULT_BLOCK_SIZE: u64 = 8 * 1024 * 1024;
+
+const MAX_BLOCK_SIZE: u64 = 4000 * 1024 * 1024;
+
+/// A block blob holds at most this many committed blocks
+const MAX_BLOCKS: usize = 50_000;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AzureBlobConfig {
+    pub account_name: String,
+    pub container_name: String,
+    /// Shared Key authorization; ignored when a SAS token is set
+    #[serde(default)]
+    pub account_key: Option<String>,
+    /// Container or account SAS, with or without the leading '?'
+    #[serde(default)]
+    pub sas_token: Option<String>,
+    /// Blob service URL for sovereign clouds or Azurite; https://<account>.blob.core.windows.net otherwise
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Virtual directory datasets are stored under
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub block_size: Option<u64>,
+}
+
+impl AzureBlobConfig {
+    /// Read the Azure settings of a storage location from the task payload.
+    /// The account key and SAS token may come from the keychain, see `credentials::secret_field`.
+    pub fn from_storage_location(location: &serde_json::Value) -> Result<AzureBlobConfig, String> {
+        let field = |name: &str| location.get(name).and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(|s| s.to_string());
+
+        let config = AzureBlobConfig {
+            account_name: field("accountName").ok_or("No account name in Azure storage location")?,
+            container_name: field("containerName").ok_or("No container name in Azure storage location")?,
+            account_key: credentials::secret_field(location, "accountKey")?,
+            sas_token: credentials::secret_field(location, "sasToken")?,
+            endpoint: field("endpoint"),
+            prefix: field("path").unwrap_or_default(),
+            block_size: location.get("blockSize").and_then(|v| v.as_u64()),
+        };
+
+        if config.account_key.is_none() && config.sas_token.is_none() {
+            return Err("Azure storage location needs an account key or a SAS token".to_string());
+        }
+        Ok(config)
+    }
+
+    fn endpoint(&self) -> String {
+        match &self.endpoint {
+            Some(endpoint) => endpoint.trim_end_matches('/').to_string(),
+            None => format!("https://{}.blob.core.windows.net", self.account_name),
+        }
+    }
+
+    fn block_size(&self) -> u64 {
+        self.block_size.unwrap_or(DEFAULT_BLOCK_SIZE).clamp(1024 * 1024, MAX_BLOCK_SIZE)
+    }
+
+    /// Blob name for `key`, below the configured prefix
+    fn blob_name(&self, key: &str) -> String {
+        let prefix = self.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", prefix, key)
+        }
+    }
+
+    /// Path of the endpoint URL, which holds the account name on Azurite
+    fn endpoint_path(&self) -> String {
+        url::Url::parse(&self.endpoint())
+            .map(|u| u.path().trim_end_matches('/').to_string())
+            .unwrap_or_default()
+    }
+
+    /// Path of the container, or of a blob in it, below the endpoint with each segment encoded
+    fn resource_path(&self, blob: Option<&str>) -> String {
+        let mut path = format!("/{}", utf8_percent_encode(&self.container_name, PATH_SEGMENT));
+        for segment in blob.into_iter().flat_map(|b| b.split('/')).filter(|s| !s.is_empty()) {
+            path.push('/');
+            path.extend(utf8_percent_encode(segment, PATH_SEGMENT));
+        }
+        path
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AzureConnectionResult {
+    pub success: bool,
+    pub message: String,
+}
+
+/// Headers and body of one Blob service request besides authorization
+#[derive(Default)]
+struct BlobRequest<'a> {
+    blob: Option<&'a str>,
+    query: Vec<(&'static str, String)>,
+    /// `x-ms-*` headers, signed under Shared Key
+    ms_headers: Vec<(&'static str, String)>,
+    content_type: Option<&'static str>,
+    body: Option<Vec<u8>>,
+}
+
+pub struct AzureBlobStorage {
+    config: AzureBlobConfig,
+    client: reqwest::Client,
+}
+
+impl AzureBlobStorage {
+    pub fn new(config: AzureBlobConfig) -> Result<AzureBlobStorage, String> {
+        let client = crate::network::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to create Azure client: {}", e))?;
+        Ok(AzureBlobStorage { config, client })
+    }
+
+    fn build(&self, method: Method, request: BlobRequest) -> Result<RequestBuilder, String> {
+        let path = self.config.resource_path(request.blob);
+        let mut query: Vec<String> = request.query
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, utf8_percent_encode(value, NON_ALPHANUMERIC)))
+            .collect();
+
+        let mut ms_headers = request.ms_headers.clone();
+        ms_headers.push(("x-ms-version", API_VERSION.to_string()));
+
+        let authorization = match (&self.config.sas_token, &self.config.account_key) {
+            (Some(sas), _) => {
+                query.push(sas.trim_start_matches('?').to_string());
+                None
+            }
+            (None, Some(key)) => {
+                ms_headers.push(("x-ms-date", Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string()));
+                Some(self.shared_key(&method, &path, &request, &ms_headers, key)?)
+            }
+            (None, None) => return Err("No Azure account key or SAS token configured".to_string()),
+        };
+
+        let mut url = format!("{}{}", self.config.endpoint(), path);
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query.join("&"));
+        }
+
+        let mut builder = self.client.request(method, url);
+        for (name, value) in &ms_headers {
+            builder = builder.header(*name, value);
+        }
+        if let Some(content_type) = request.content_type {
+            builder = builder.header("Content-Type", content_type);
+        }
+        if let Some(authorization) = authorization {
+            builder = builder.header("Authorization", authorization);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        Ok(builder)
+    }
+
+    /// `Authorization: SharedKey` value for a request, see "Authorize with Shared Key" in the Azure docs.
+    /// `ms_headers` are all `x-ms-*` headers sent, including version and date.
+    fn shared_key(
+        &self,
+        method: &Method,
+        path: &str,
+        request: &BlobRequest,
+        ms_headers: &[(&'static str, String)],
+        key: &str,
+    ) -> Result<String, String> {
+        let mut headers: Vec<(String, &str)> = ms_headers.iter().map(|(n, v)| (n.to_lowercase(), v.as_str())).collect();
+        headers.sort();
+        let canonical_headers: String = headers.iter().map(|(n, v)| format!("{}:{}\n", n, v.trim())).collect();
+
+        let mut canonical_resource = format!("/{}{}{}", self.config.account_name, self.config.endpoint_path(), path);
+        let mut params: Vec<(String, &str)> = request.query.iter().map(|(n, v)| (n.to_lowercase(), v.as_str())).collect();
+        params.sort();
+        for (name, value) in params {
+            canonical_resource.push_str(&format!("\n{}:{}", name, value));
+        }
+
+        let content_length = match request.body.as_ref().map(|b| b.len()) {
+            Some(length) if length > 0 => length.to_string(),
+            _ => String::new(),
+        };
+        // Content-Encoding, -Language, -Length, -MD5, -Type, Date, If-* and Range, then the canonical parts
+        let string_to_sign = format!(
+            "{}\n\n\n{}\n\n{}\n\n\n\n\n\n\n{}{}",
+            method.as_str(),
+            content_length,
+            request.content_type.unwrap_or_default(),
+            canonical_headers,
+            canonical_resource
+        );
+
+        let key = BASE64.decode(key.trim())
+            .map_err(|e| format!("Azure account key is not valid base64: {}", e))?;
+        let mut mac = HmacSha256::new_from_slice(&key)
+            .map_err(|e| format!("HMAC error: {}", e))?;
+        mac.update(string_to_sign.as_bytes());
+        let signature = BASE64.encode(mac.finalize().into_bytes());
+
+        Ok(format!("SharedKey {}:{}", self.config.account_name, signature))
+    }
+
+    async fn send(&self, method: Method, request: BlobRequest<'_>, what: &str) -> Result<Response, CollectorError> {
+        let response = self.build(method, request)?.send().await
+            .map_err(|e| CollectorError::from_request(format!("Azure {} failed", what), &e))?;
+        if response.status() == StatusCode::FORBIDDEN {
+            let body = response.text().await.unwrap_or_default();
+            let code = xml_tag(&body, "Code").unwrap_or_else(|| "AuthorizationFailure".to_string());
+            return Err(CollectorError::new(
+                ErrorKind::PermissionDenied,
+                format!("Azure {} was refused ({}): check the account key or SAS token permissions", what, code),
+            ));
+        }
+        Ok(response)
+    }
+
+    async fn expect_success(response: Response, what: &str) -> Result<(), CollectorError> {
+        if response.status().is_success() {
+            return Ok(());
+        }
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        let code = xml_tag(&body, "Code").unwrap_or_default();
+        Err(CollectorError::new(ErrorKind::of_status(status), format!("Azure {} failed: HTTP {} {}", what, status, code)))
+    }
+
+    async fn put_blob(&self, blob: &str, content: &[u8]) -> Result<(), CollectorError> {
+        let response = self.send(Method::PUT, BlobRequest {
+            blob: Some(blob),
+            ms_headers: vec![("x-ms-blob-type", "BlockBlob".to_string())],
+            content_type: Some("application/octet-stream"),
+            body: Some(content.to_vec()),
+            ..Default::default()
+        }, &format!("upload of {}", blob)).await?;
+        Self::expect_success(response, &format!("upload of {}", blob)).await
+    }
+
+    /// Stage the content as blocks of `block_size`, then commit them in order
+    async fn put_blocks(&self, blob: &str, content: &[u8], progress: &UploadProgress) -> Result<(), CollectorError> {
+        let block_size = self.config.block_size() as usize;
+        if content.len().div_ceil(block_size) > MAX_BLOCKS {
+            return Err(CollectorError::new(
+                ErrorKind::InvalidInput,
+                format!("{} needs more than {} blocks; increase the block size", blob, MAX_BLOCKS),
+            ));
+        }
+
+        let mut block_ids = Vec::new();
+        let mut staged = 0;
+        for (index, block) in content.chunks(block_size).enumerate() {
+            // Block ids must all have the same length before encoding
+            let block_id = BASE64.encode(format!("block-{:06}", index));
+            let what = format!("upload of block {} of {}", index + 1, blob);
+            let response = self.send(Method::PUT, BlobRequest {
+                blob: Some(blob),
+                query: vec![("comp", "block".to_string()), ("blockid", block_id.clone())],
+                content_type: Some("application/octet-stream"),
+                body: Some(block.to_vec()),
+                ..Default::default()
+            }, &what).await?;
+            Self::expect_success(response, &what).await?;
+            block_ids.push(block_id);
+            staged += block.len() as u64;
+            progress(staged);
+        }
+
+        let block_list: String = block_ids.iter().map(|id| format!("<Latest>{}</Latest>", id)).collect();
+        let what = format!("commit of {}", blob);
+        let response = self.send(Method::PUT, BlobRequest {
+            blob: Some(blob),
+            query: vec![("comp", "blocklist".to_string())],
+            content_type: Some("application/xml"),
+            body: Some(format!("<?xml version=\"1.0\" encoding=\"utf-8\"?><BlockList>{}</BlockList>", block_list).into_bytes()),
+            ..Default::default()
+        }, &what).await?;
+        Self::expect_success(response, &what).await
+    }
+
+    /// One page of List Blobs; returns the names and sizes and the marker of the next page
+    async fn list_page(&self, prefix: &str, marker: Option<&str>, max_results: Option<u32>) -> Result<(Vec<(String, u64)>, Option<String>), CollectorError> {
+        let mut query = vec![
+            ("restype", "container".to_string()),
+            ("comp", "list".to_string()),
+            ("prefix", prefix.to_string()),
+        ];
+        if let Some(marker) = marker {
+            query.push(("marker", marker.to_string()));
+        }
+        if let Some(max_results) = max_results {
+            query.push(("maxresults", max_results.to_string()));
+        }
+
+        let response = self.send(Method::GET, BlobRequest { query, ..Default::default() }, "listing").await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(CollectorError::new(ErrorKind::NotFound, format!("Container {} does not exist", self.config.container_name)));
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(CollectorError::new(ErrorKind::of_status(status), format!("Azure listing failed: HTTP {}", status)));
+        }
+
+        let body = response.text().await
+            .map_err(|e| CollectorError::from_request("Failed to read Azure listing", &e))?;
+        Ok(parse_blob_list(&body))
+    }
+}
+
+fn xml_tag(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&close)? + start;
+    Some(
+        xml[start..end]
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// Blob names and sizes of one List Blobs page, and the marker of the next page
+pub fn parse_blob_list(xml: &str) -> (Vec<(String, u64)>, Option<String>) {
+    let mut blobs = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find("<Blob>") {
+        let after_start = &rest[start..];
+        let Some(end) = after_start.find("</Blob>") else { break };
+        let entry = &after_start[..end];
+        if let Some(name) = xml_tag(entry, "Name") {
+            let size = xml_tag(entry, "Content-Length").and_then(|s| s.parse().ok()).unwrap_or(0);
+            blobs.push((name, size));
+        }
+        rest = &after_start[end..];
+    }
+
+    let next_marker = xml_tag(xml, "NextMarker").filter(|m| !m.is_empty());
+    (blobs, next_marker)
+}
+
+#[async_trait]
+impl RemoteStorage for AzureBlobStorage {
+    fn display_name(&self) -> &'static str {
+        "Azure Blob Storage"
+    }
+
+    fn location(&self, prefix: &str) -> String {
+        format!("{}{}/", self.config.endpoint(), self.config.resource_path(Some(&self.config.blob_name(prefix))))
+    }
+
+    async fn put_with_progress(&self, key: &str, content: &[u8], progress: &UploadProgress) -> Result<(), CollectorError> {
+        let blob = self.config.blob_name(key);
+        if content.len() as u64 > self.config.block_size() {
+            self.put_blocks(&blob, content, progress).await
+        } else {
+            self.put_blob(&blob, content).await?;
+            progress(content.len() as u64);
+            Ok(())
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CollectorError> {
+        let blob = self.config.blob_name(key);
+        let what = format!("download of {}", blob);
+        let response = self.send(Method::GET, BlobRequest { blob: Some(&blob), ..Default::default() }, &what).await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let content = response.bytes().await
+                    .map_err(|e| CollectorError::from_request(format!("Failed to read {}", blob), &e))?;
+                Ok(Some(content.to_vec()))
+            }
+            status => Err(CollectorError::new(ErrorKind::of_status(status), format!("Azure {} failed: HTTP {}", what, status))),
+        }
+    }
+
+    async fn list_sizes(&self, prefix: &str) -> Result<HashMap<String, u64>, CollectorError> {
+        let full_prefix = self.config.blob_name(prefix);
+        let mut sizes = HashMap::new();
+        let mut marker = None;
+
+        loop {
+            let (blobs, next_marker) = self.list_page(&full_prefix, marker.as_deref(), None).await?;
+            for (name, size) in blobs {
+                if let Some(relative) = name.strip_prefix(&full_prefix) {
+                    sizes.insert(relative.to_string(), size);
+                }
+            }
+            match next_marker {
+                Some(next) => marker = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(sizes)
+    }
+}
+
+#[tauri::command]
+pub async fn test_azure_connection(config: AzureBlobConfig) -> Result<AzureConnectionResult, CollectorError> {
+    log::info!("Testing Azure connection to: {}/{}", config.account_name, config.container_name);
+
+    if config.account_key.is_none() && config.sas_token.is_none() {
+        return Ok(AzureConnectionResult {
+            success: false,
+            message: "An account key or a SAS token is required".to_string(),
+        });
+    }
+
+    let container = config.container_name.clone();
+    let storage = AzureBlobStorage::new(config)?;
+    let (success, message) = match storage.list_page("", None, Some(1)).await {
+        Ok(_) => (true, format!("Successfully connected to container {}!", container)),
+        Err(e) => (false, e.message),
+    };
+
+    log::info!("Azure connection test: {}", message);
+    Ok(AzureConnectionResult { success, message })
+}
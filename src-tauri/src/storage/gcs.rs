@@ -0,0 +1,481 @@
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL;
+use base64::Engine;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::signature::{SignatureEncoding, Signer};
+use rsa::RsaPrivateKey;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::{RemoteStorage, UploadProgress};
+use crate::credentials;
+use crate::error::{CollectorError, ErrorKind};
+
+const STORAGE_API: &str = "https://storage.googleapis.com/storage/v1";
+const UPLOAD_API: &str = "https://storage.googleapis.com/upload/storage/v1";
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_write";
+
+/// Object names are sent as a single path segment, so '/' is escaped too
+const OBJECT_NAME: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// Resumable upload chunks must be a multiple of 256 KiB
+const CHUNK_GRANULARITY: u64 = 256 * 1024;
+const DEFAULT_CHUNK_SIZE: u64 = 32 * CHUNK_GRANULARITY;
+
+/// Attempts per chunk before the upload is given up
+const CHUNK_ATTEMPTS: u32 = 3;
+
+/// Wait before retrying a throttled chunk that came without a Retry-After
+const THROTTLED_WAIT: Duration = Duration::from_secs(2);
+
+/// Access tokens are refreshed this long before they expire
+const TOKEN_MARGIN: Duration = Duration::from_secs(60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The fields of a service account key file that are needed to sign token requests
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ServiceAccountKey {
+    pub client_email: String,
+    pub private_key: String,
+    #[serde(default)]
+    pub token_uri: Option<String>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+}
+
+impl ServiceAccountKey {
+    pub fn parse(json: &str) -> Result<ServiceAccountKey, String> {
+        let key: ServiceAccountKey = serde_json::from_str(json)
+            .map_err(|e| format!("Not a service account key file: {}", e))?;
+        if key.client_email.is_empty() || key.private_key.is_empty() {
+            return Err("Service account key file has no client_email or private_key".to_string());
+        }
+        Ok(key)
+    }
+
+    /// Contents of the key file given inline (`serviceAccountJson`) or by path (`serviceAccountPath`)
+    fn load(json: Option<&str>, path: Option<&str>) -> Result<ServiceAccountKey, String> {
+        match (json, path) {
+            (Some(json), _) => ServiceAccountKey::parse(json),
+            (None, Some(path)) => {
+                let json = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read service account key {}: {}", path, e))?;
+                ServiceAccountKey::parse(&json)
+            }
+            (None, None) => Err("Google Cloud Storage needs a service account key".to_string()),
+        }
+    }
+
+    fn token_uri(&self) -> &str {
+        self.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI)
+    }
+
+    /// Signed JWT for the OAuth 2.0 service account flow
+    fn assertion(&self) -> Result<String, String> {
+        let now = chrono::Utc::now().timestamp();
+        let header = BASE64_URL.encode(json!({ "alg": "RS256", "typ": "JWT" }).to_string());
+        let claims = BASE64_URL.encode(json!({
+            "iss": self.client_email,
+            "scope": SCOPE,
+            "aud": self.token_uri(),
+            "iat": now,
+            "exp": now + 3600,
+        }).to_string());
+        let signing_input = format!("{}.{}", header, claims);
+
+        let private_key = RsaPrivateKey::from_pkcs8_pem(&self.private_key)
+            .map_err(|e| format!("Invalid private key in service account file: {}", e))?;
+        let signature = SigningKey::<Sha256>::new(private_key).sign(signing_input.as_bytes());
+
+        Ok(format!("{}.{}", signing_input, BASE64_URL.encode(signature.to_bytes())))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GcsConfig {
+    pub bucket_name: String,
+    /// Service account key file contents; takes precedence over the path
+    #[serde(default)]
+    pub service_account_json: Option<String>,
+    #[serde(default)]
+    pub service_account_path: Option<String>,
+    /// Prefix datasets are stored under
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub chunk_size: Option<u64>,
+}
+
+impl GcsConfig {
+    /// Read the GCS settings of a storage location from the task payload.
+    /// The key file contents may come from the keychain, see `credentials::secret_field`.
+    pub fn from_storage_location(location: &Value) -> Result<GcsConfig, String> {
+        let field = |name: &str| location.get(name).and_then(|v| v.as_str()).filter(|s| !s.is_empty()).map(|s| s.to_string());
+
+        Ok(GcsConfig {
+            bucket_name: field("bucketName").ok_or("No bucket name in Google Cloud Storage location")?,
+            service_account_json: credentials::secret_field(location, "serviceAccountJson")?,
+            service_account_path: field("serviceAccountPath"),
+            prefix: field("path").unwrap_or_default(),
+            chunk_size: location.get("chunkSize").and_then(|v| v.as_u64()),
+        })
+    }
+
+    fn object_name(&self, key: &str) -> String {
+        let prefix = self.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", prefix, key)
+        }
+    }
+
+    /// Chunk size rounded down to the 256 KiB granularity resumable uploads require
+    fn chunk_size(&self) -> u64 {
+        let size = self.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+        (size / CHUNK_GRANULARITY).max(1) * CHUNK_GRANULARITY
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcsConnectionResult {
+    pub success: bool,
+    pub message: String,
+    /// Service account the connection authenticated as
+    pub client_email: Option<String>,
+}
+
+pub struct GcsStorage {
+    config: GcsConfig,
+    key: ServiceAccountKey,
+    client: reqwest::Client,
+    /// Access token and when it expires
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl GcsStorage {
+    pub fn new(config: GcsConfig) -> Result<GcsStorage, String> {
+        let key = ServiceAccountKey::load(config.service_account_json.as_deref(), config.service_account_path.as_deref())?;
+        let client = crate::network::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to create Google Cloud Storage client: {}", e))?;
+
+        Ok(GcsStorage {
+            config,
+            key,
+            client,
+            token: Mutex::new(None),
+        })
+    }
+
+    /// A valid access token, exchanged for a fresh signed assertion when the cached one is about to expire
+    async fn access_token(&self) -> Result<String, CollectorError> {
+        if let Some((token, expires_at)) = self.token.lock().unwrap().as_ref() {
+            if Instant::now() + TOKEN_MARGIN < *expires_at {
+                return Ok(token.clone());
+            }
+        }
+
+        let response = self.client
+            .post(self.key.token_uri())
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", &self.key.assertion()?),
+            ])
+            .send()
+            .await
+            .map_err(|e| CollectorError::from_request("Token request failed", &e))?;
+
+        let status = response.status();
+        let body: Value = response.json().await
+            .map_err(|e| CollectorError::from_request("Failed to read token response", &e))?;
+        if !status.is_success() {
+            let reason = body.get("error_description").and_then(|v| v.as_str()).unwrap_or("unknown error");
+            return Err(CollectorError::new(
+                ErrorKind::Authentication,
+                format!("Service account {} was refused a token: {}", self.key.client_email, reason),
+            ));
+        }
+
+        let token = body.get("access_token").and_then(|v| v.as_str())
+            .ok_or("Token response has no access_token")?
+            .to_string();
+        let expires_in = body.get("expires_in").and_then(|v| v.as_u64()).unwrap_or(3600);
+        *self.token.lock().unwrap() = Some((token.clone(), Instant::now() + Duration::from_secs(expires_in)));
+        Ok(token)
+    }
+
+    async fn send(&self, request: RequestBuilder, what: &str) -> Result<Response, CollectorError> {
+        let response = request
+            .bearer_auth(self.access_token().await?)
+            .send()
+            .await
+            .map_err(|e| CollectorError::from_request(format!("Google Cloud Storage {} failed", what), &e))?;
+
+        let status = response.status();
+        if status == StatusCode::FORBIDDEN || status == StatusCode::UNAUTHORIZED {
+            return Err(CollectorError::new(ErrorKind::of_status(status), format!(
+                "Google Cloud Storage {} was refused: {} lacks access to bucket {}",
+                what, self.key.client_email, self.config.bucket_name
+            )));
+        }
+        Ok(response)
+    }
+
+    async fn error(response: Response, what: &str) -> CollectorError {
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or_default();
+        let message = body.pointer("/error/message").and_then(|v| v.as_str()).unwrap_or_default();
+        CollectorError::new(
+            ErrorKind::of_status(status),
+            format!("Google Cloud Storage {} failed: HTTP {} {}", what, status, message),
+        )
+    }
+
+    /// Open a resumable upload session for `name`, returning its session URI
+    async fn start_upload(&self, name: &str, length: usize) -> Result<String, CollectorError> {
+        let url = format!(
+            "{}/b/{}/o?uploadType=resumable&name={}",
+            UPLOAD_API,
+            self.config.bucket_name,
+            utf8_percent_encode(name, OBJECT_NAME)
+        );
+        let what = format!("upload of {}", name);
+        let response = self.send(
+            self.client.post(&url)
+                .header("X-Upload-Content-Type", "application/octet-stream")
+                .header("X-Upload-Content-Length", length.to_string())
+                .header("Content-Length", "0"),
+            &what,
+        ).await?;
+
+        if !response.status().is_success() {
+            return Err(Self::error(response, &what).await);
+        }
+        response.headers()
+            .get("Location")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string())
+            .ok_or_else(|| format!("Upload session for {} has no Location", name).into())
+    }
+
+    /// Bytes the session has persisted, after a chunk failed part way
+    async fn committed_bytes(&self, session: &str, length: usize) -> Result<Option<usize>, CollectorError> {
+        let response = self.send(
+            self.client.put(session)
+                .header("Content-Range", format!("bytes */{}", length))
+                .header("Content-Length", "0"),
+            "upload status query",
+        ).await?;
+
+        match response.status().as_u16() {
+            200 | 201 => Ok(None),
+            308 => Ok(Some(Self::persisted(&response))),
+            _ => Err(Self::error(response, "upload status query").await),
+        }
+    }
+
+    /// Bytes a 308 answer says are persisted. "Range: bytes=0-<last byte>" is missing when
+    /// nothing was persisted yet.
+    fn persisted(response: &Response) -> usize {
+        response.headers()
+            .get("Range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|r| r.rsplit('-').next())
+            .and_then(|last| last.parse::<usize>().ok())
+            .map(|last| last + 1)
+            .unwrap_or(0)
+    }
+
+    /// Send `content` through a resumable session chunk by chunk, resuming from the
+    /// persisted offset when a chunk fails
+    async fn upload(&self, name: &str, content: &[u8], progress: &UploadProgress) -> Result<(), CollectorError> {
+        let length = content.len();
+        let session = self.start_upload(name, length).await?;
+        let chunk_size = self.config.chunk_size() as usize;
+        let mut offset = 0;
+        let mut failures = 0;
+
+        loop {
+            let end = (offset + chunk_size).min(length);
+            let content_range = if length == 0 {
+                "bytes */0".to_string()
+            } else {
+                format!("bytes {}-{}/{}", offset, end - 1, length)
+            };
+
+            let result = self.send(
+                self.client.put(&session)
+                    .header("Content-Range", content_range)
+                    .body(content[offset..end].to_vec()),
+                &format!("upload of {}", name),
+            ).await;
+
+            match result {
+                Ok(response) if response.status().is_success() => {
+                    progress(length as u64);
+                    return Ok(());
+                }
+                // The server may persist less than the chunk, so continue from what it kept
+                Ok(response) if response.status().as_u16() == 308 => {
+                    let persisted = Self::persisted(&response);
+                    if persisted > offset {
+                        offset = persisted;
+                        failures = 0;
+                        progress(offset as u64);
+                        continue;
+                    }
+                    log::warn!("Chunk of {} at byte {} was not persisted", name, offset);
+                }
+                Ok(response) if !response.status().is_server_error() && response.status() != StatusCode::TOO_MANY_REQUESTS => {
+                    return Err(Self::error(response, &format!("upload of {}", name)).await);
+                }
+                Ok(response) => {
+                    log::warn!("Chunk of {} failed with HTTP {}", name, response.status());
+                    if let Some(throttled) = crate::rate_limit::throttled_error(&response) {
+                        tokio::time::sleep(throttled.retry_after.unwrap_or(THROTTLED_WAIT)).await;
+                    }
+                }
+                Err(e) => log::warn!("Chunk of {} failed: {}", name, e),
+            }
+
+            failures += 1;
+            if failures >= CHUNK_ATTEMPTS {
+                return Err(CollectorError::new(
+                    ErrorKind::Network,
+                    format!("Upload of {} failed after {} attempts at byte {}", name, failures, offset),
+                ));
+            }
+            match self.committed_bytes(&session, length).await? {
+                Some(committed) => offset = committed,
+                None => {
+                    progress(length as u64);
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl RemoteStorage for GcsStorage {
+    fn display_name(&self) -> &'static str {
+        "Google Cloud Storage"
+    }
+
+    fn location(&self, prefix: &str) -> String {
+        format!("gs://{}/{}", self.config.bucket_name, self.config.object_name(prefix))
+    }
+
+    async fn put_with_progress(&self, key: &str, content: &[u8], progress: &UploadProgress) -> Result<(), CollectorError> {
+        self.upload(&self.config.object_name(key), content, progress).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CollectorError> {
+        let name = self.config.object_name(key);
+        let url = format!(
+            "{}/b/{}/o/{}?alt=media",
+            STORAGE_API,
+            self.config.bucket_name,
+            utf8_percent_encode(&name, OBJECT_NAME)
+        );
+        let what = format!("download of {}", name);
+        let response = self.send(self.client.get(&url), &what).await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let content = response.bytes().await
+                    .map_err(|e| CollectorError::from_request(format!("Failed to read {}", name), &e))?;
+                Ok(Some(content.to_vec()))
+            }
+            _ => Err(Self::error(response, &what).await),
+        }
+    }
+
+    async fn list_sizes(&self, prefix: &str) -> Result<HashMap<String, u64>, CollectorError> {
+        let full_prefix = self.config.object_name(prefix);
+        let mut sizes = HashMap::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut request = self.client
+                .get(format!("{}/b/{}/o", STORAGE_API, self.config.bucket_name))
+                .query(&[("prefix", full_prefix.as_str()), ("fields", "items(name,size),nextPageToken")]);
+            if let Some(token) = &page_token {
+                request = request.query(&[("pageToken", token)]);
+            }
+
+            let response = self.send(request, "listing").await?;
+            if !response.status().is_success() {
+                return Err(Self::error(response, "listing").await);
+            }
+            let page: Value = response.json().await
+                .map_err(|e| CollectorError::from_request("Failed to read Google Cloud Storage listing", &e))?;
+
+            for item in page.get("items").and_then(|v| v.as_array()).into_iter().flatten() {
+                let name = item.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+                // Sizes are uint64 values encoded as strings
+                let size = item.get("size").and_then(|v| v.as_str()).and_then(|s| s.parse().ok()).unwrap_or(0);
+                if let Some(relative) = name.strip_prefix(&full_prefix) {
+                    sizes.insert(relative.to_string(), size);
+                }
+            }
+
+            page_token = page.get("nextPageToken").and_then(|v| v.as_str()).map(|s| s.to_string());
+            if page_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(sizes)
+    }
+}
+
+#[tauri::command]
+pub async fn test_gcs_connection(config: GcsConfig) -> Result<GcsConnectionResult, CollectorError> {
+    log::info!("Testing Google Cloud Storage connection to bucket: {}", config.bucket_name);
+
+    let storage = match GcsStorage::new(config) {
+        Ok(storage) => storage,
+        Err(e) => {
+            return Ok(GcsConnectionResult {
+                success: false,
+                message: e,
+                client_email: None,
+            })
+        }
+    };
+    let client_email = Some(storage.key.client_email.clone());
+
+    let request = storage.client
+        .get(format!("{}/b/{}/o", STORAGE_API, storage.config.bucket_name))
+        .query(&[("maxResults", "1"), ("fields", "nextPageToken")]);
+    let (success, message) = match storage.send(request, "listing").await {
+        Ok(response) if response.status().is_success() => {
+            (true, format!("Successfully connected to bucket {}!", storage.config.bucket_name))
+        }
+        Ok(response) if response.status() == StatusCode::NOT_FOUND => {
+            (false, format!("Bucket {} does not exist", storage.config.bucket_name))
+        }
+        Ok(response) => (false, GcsStorage::error(response, "listing").await.message),
+        Err(e) => (false, e.message),
+    };
+
+    log::info!("Google Cloud Storage connection test: {}", message);
+    Ok(GcsConnectionResult {
+        success,
+        message,
+        client_email,
+    })
+}
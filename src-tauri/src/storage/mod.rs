@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use percent_encoding::{AsciiSet, CONTROLS};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::CollectorError;
+use crate::providers::RemoteFile;
+use crate::s3_client::S3ConnectionConfig;
+
+pub mod azure;
+pub mod gcs;
+pub mod s3;
+pub mod sftp;
+pub mod webdav;
+
+/// Characters escaped in a single path segment of a storage URL
+pub(crate) const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ').add(b'"').add(b'#').add(b'%').add(b'/').add(b'<').add(b'>')
+    .add(b'?').add(b'[').add(b']').add(b'\\').add(b'^').add(b'`').add(b'{').add(b'|').add(b'}');
+
+/// Pieces a streamed request body is handed to the connection in
+const BODY_PIECE_SIZE: usize = 1024 * 1024;
+
+/// Called with the bytes of an upload sent so far, after each chunk, block or part
+pub type UploadProgress = Arc<dyn Fn(u64) + Send + Sync>;
+
+/// Progress callback for uploads nobody follows
+pub fn no_progress() -> UploadProgress {
+    Arc::new(|_| {})
+}
+
+/// Request body streaming `content` in pieces, reporting the bytes handed to the connection
+/// for destinations that store a file with a single request
+pub(crate) fn counting_body(content: Vec<u8>, progress: &UploadProgress) -> reqwest::Body {
+    let progress = progress.clone();
+    let content = Arc::new(content);
+    let pieces = futures_util::stream::iter((0..content.len()).step_by(BODY_PIECE_SIZE)).map(move |start| {
+        let end = (start + BODY_PIECE_SIZE).min(content.len());
+        progress(end as u64);
+        Ok::<_, std::io::Error>(content[start..end].to_vec())
+    });
+    reqwest::Body::wrap_stream(pieces)
+}
+
+/// Where an uploaded file was collected from, kept with it where the destination supports
+/// object metadata
+#[derive(Debug, Clone, Default)]
+pub struct SourceMetadata {
+    /// Source URL without its query, so signed URLs do not leave their tokens behind
+    pub url: Option<String>,
+    pub etag: Option<String>,
+}
+
+impl SourceMetadata {
+    pub fn of(file: &RemoteFile) -> SourceMetadata {
+        SourceMetadata {
+            url: file.url.split(['?', '#']).next().map(|url| url.to_string()),
+            etag: file.etag.clone(),
+        }
+    }
+}
+
+/// A file at a remote destination
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub size: u64,
+    /// Only for destinations that report one
+    pub etag: Option<String>,
+}
+
+/// A remote destination that collected files are uploaded to one by one.
+///
+/// The upload loop only talks to destinations through this trait, so a new storage
+/// location type needs an implementation here and an arm in `from_storage_location`.
+#[async_trait]
+pub trait RemoteStorage: Send + Sync {
+    /// Name used in log messages
+    fn display_name(&self) -> &'static str;
+
+    /// URI of the data stored under `prefix`, as recorded in the catalog
+    fn location(&self, prefix: &str) -> String;
+
+    /// Store `content` at `key`, a path relative to the storage root, reporting the bytes
+    /// sent as the upload goes
+    async fn put_with_progress(&self, key: &str, content: &[u8], progress: &UploadProgress) -> Result<(), CollectorError>;
+
+    /// Store `content` at `key`
+    async fn put(&self, key: &str, content: &[u8]) -> Result<(), CollectorError> {
+        self.put_with_progress(key, content, &no_progress()).await
+    }
+
+    /// Store `content` at `key` along with where it was collected from; destinations without
+    /// object metadata store only the content
+    async fn put_with_source(
+        &self,
+        key: &str,
+        content: &[u8],
+        _source: &SourceMetadata,
+        progress: &UploadProgress,
+    ) -> Result<(), CollectorError> {
+        self.put_with_progress(key, content, progress).await
+    }
+
+    /// Content stored at `key`, or None when there is nothing there
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CollectorError>;
+
+    /// Sizes of all files under `prefix`, keyed by their path relative to it
+    async fn list_sizes(&self, prefix: &str) -> Result<HashMap<String, u64>, CollectorError>;
+
+    /// All files under `prefix` with their sizes and ETags, keyed like `list_sizes`
+    async fn list_objects(&self, prefix: &str) -> Result<HashMap<String, StoredObject>, CollectorError> {
+        Ok(self.list_sizes(prefix).await?
+            .into_iter()
+            .map(|(path, size)| (path, StoredObject { size, etag: None }))
+            .collect())
+    }
+}
+
+/// Storage location types handled through `RemoteStorage`
+pub const REMOTE_TYPES: &[&str] = &["s3-compatible", "sftp", "webdav", "azure-blob", "gcs"];
+
+/// Open the remote storage described by a storage location from the task payload
+pub fn from_storage_location(location: &serde_json::Value) -> Result<Box<dyn RemoteStorage>, String> {
+    let storage_type = location.get("type")
+        .and_then(|t| t.as_str())
+        .ok_or("No storage type specified")?;
+
+    match storage_type {
+        "s3-compatible" => Ok(Box::new(S3ConnectionConfig::from_storage_location(location)?)),
+        "sftp" => Ok(Box::new(sftp::SftpStorage::new(sftp::SftpConfig::from_storage_location(location)?))),
+        "webdav" => Ok(Box::new(webdav::WebDavStorage::new(webdav::WebDavConfig::from_storage_location(location)?)?)),
+        "azure-blob" => Ok(Box::new(azure::AzureBlobStorage::new(azure::AzureBlobConfig::from_storage_location(location)?)?)),
+        "gcs" => Ok(Box::new(gcs::GcsStorage::new(gcs::GcsConfig::from_storage_location(location)?)?)),
+        other => Err(format!("Unsupported storage type: {}", other)),
+    }
+}
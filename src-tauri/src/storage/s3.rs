@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+use super::{RemoteStorage, SourceMetadata, StoredObject, UploadProgress};
+use crate::error::CollectorError;
+use crate::s3_client::{self, S3ConnectionConfig};
+
+#[async_trait]
+impl RemoteStorage for S3ConnectionConfig {
+    fn display_name(&self) -> &'static str {
+        "S3-compatible storage"
+    }
+
+    fn location(&self, prefix: &str) -> String {
+        format!("s3://{}/{}", self.bucket_name, prefix)
+    }
+
+    async fn put_with_progress(&self, key: &str, content: &[u8], progress: &UploadProgress) -> Result<(), CollectorError> {
+        crate::upload_to_s3_compatible(self, key, content, &SourceMetadata::default(), progress).await
+    }
+
+    async fn put_with_source(
+        &self,
+        key: &str,
+        content: &[u8],
+        source: &SourceMetadata,
+        progress: &UploadProgress,
+    ) -> Result<(), CollectorError> {
+        crate::upload_to_s3_compatible(self, key, content, source, progress).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CollectorError> {
+        // GetObject errors are treated as a missing object, as the bucket may not be listable
+        let response = match s3_client::get_object(self, key).await {
+            Ok(response) => response,
+            Err(_) => return Ok(None),
+        };
+        let content = response.bytes().await
+            .map_err(|e| CollectorError::from_request(format!("Failed to read {}", key), &e))?;
+        Ok(Some(content.to_vec()))
+    }
+
+    async fn list_sizes(&self, prefix: &str) -> Result<HashMap<String, u64>, CollectorError> {
+        Ok(s3_client::list_objects(self, prefix).await?
+            .into_iter()
+            .filter_map(|object| object.key.strip_prefix(prefix).map(|path| (path.to_string(), object.size)))
+            .collect())
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<HashMap<String, StoredObject>, CollectorError> {
+        Ok(s3_client::list_objects(self, prefix).await?
+            .into_iter()
+            .filter_map(|object| {
+                let path = object.key.strip_prefix(prefix)?.to_string();
+                Some((path, StoredObject { size: object.size, etag: object.etag }))
+            })
+            .collect())
+    }
+}
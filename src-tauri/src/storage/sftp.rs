@@ -0,0 +1,361 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use ssh2::{CheckResult, HashType, KnownHostFileKind, RenameFlags, Session, Sftp};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{RemoteStorage, UploadProgress};
+use crate::credentials;
+use crate::error::{CollectorError, ErrorKind};
+
+const DEFAULT_PORT: u16 = 22;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Bytes written to the remote file between progress reports
+const WRITE_PIECE_SIZE: usize = 1024 * 1024;
+
+/// SFTP status codes for a missing file and a refused operation
+const SFTP_NO_SUCH_FILE: i32 = 2;
+const SFTP_PERMISSION_DENIED: i32 = 3;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SftpConfig {
+    pub host: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    pub username: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Private key file; tried before the password. The SSH agent is used when neither is set.
+    #[serde(default)]
+    pub private_key_path: Option<String>,
+    #[serde(default)]
+    pub passphrase: Option<String>,
+    /// Remote directory datasets are stored under; relative paths start at the login directory
+    #[serde(default)]
+    pub base_path: String,
+}
+
+impl SftpConfig {
+    /// Read the SFTP settings of a storage location from the task payload.
+    /// The password and key passphrase may come from the keychain, see `credentials::secret_field`.
+    pub fn from_storage_location(location: &serde_json::Value) -> Result<SftpConfig, String> {
+        let field = |name: &str| location.get(name).and_then(|v| v.as_str()).map(|s| s.to_string());
+
+        Ok(SftpConfig {
+            host: field("host").ok_or("No host in SFTP storage location")?,
+            port: location.get("port").and_then(|v| v.as_u64()).map(|p| p as u16),
+            username: field("username").ok_or("No username in SFTP storage location")?,
+            password: credentials::secret_field(location, "password")?,
+            private_key_path: field("privateKeyPath"),
+            passphrase: credentials::secret_field(location, "passphrase")?,
+            base_path: field("path").unwrap_or_default(),
+        })
+    }
+
+    fn port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_PORT)
+    }
+
+    fn remote_path(&self, key: &str) -> PathBuf {
+        let base = self.base_path.trim_end_matches('/');
+        if base.is_empty() {
+            PathBuf::from(key)
+        } else {
+            PathBuf::from(format!("{}/{}", base, key))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SftpConnectionResult {
+    pub success: bool,
+    pub message: String,
+    /// SHA-256 of the server's host key, hex encoded, to compare against the server's admins' records
+    pub host_key_fingerprint: Option<String>,
+}
+
+struct Connection {
+    // Kept alive for as long as the SFTP channel is in use
+    _session: Session,
+    sftp: Sftp,
+}
+
+/// Open a session, check the host key against `~/.ssh/known_hosts` and authenticate
+fn connect(config: &SftpConfig) -> Result<(Session, Option<String>), CollectorError> {
+    let address = (config.host.as_str(), config.port())
+        .to_socket_addrs()
+        .map_err(|e| CollectorError::new(ErrorKind::Network, format!("Cannot resolve {}: {}", config.host, e)))?
+        .next()
+        .ok_or_else(|| CollectorError::new(ErrorKind::Network, format!("Cannot resolve {}", config.host)))?;
+    let tcp = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+        .map_err(|e| CollectorError::new(
+            if e.kind() == std::io::ErrorKind::TimedOut { ErrorKind::Timeout } else { ErrorKind::Network },
+            format!("Cannot connect to {}:{}: {}", config.host, config.port(), e),
+        ))?;
+
+    let mut session = Session::new().map_err(|e| format!("Failed to create SSH session: {}", e))?;
+    session.set_tcp_stream(tcp);
+    session.handshake()
+        .map_err(|e| CollectorError::new(ErrorKind::Network, format!("SSH handshake with {} failed: {}", config.host, e)))?;
+
+    let fingerprint = session.host_key_hash(HashType::Sha256).map(hex::encode);
+    check_host_key(&session, config)?;
+
+    let authentication_failed = |method: &str, e: ssh2::Error| CollectorError::new(
+        ErrorKind::Authentication,
+        format!("{} authentication as {} failed: {}", method, config.username, e),
+    );
+    if let Some(key_path) = &config.private_key_path {
+        session.userauth_pubkey_file(&config.username, None, Path::new(key_path), config.passphrase.as_deref())
+            .map_err(|e| authentication_failed("Key", e))?;
+    } else if let Some(password) = &config.password {
+        session.userauth_password(&config.username, password)
+            .map_err(|e| authentication_failed("Password", e))?;
+    } else {
+        session.userauth_agent(&config.username)
+            .map_err(|e| authentication_failed("SSH agent", e))?;
+    }
+
+    if !session.authenticated() {
+        return Err(CollectorError::new(ErrorKind::Authentication, format!("Authentication as {} was rejected", config.username)));
+    }
+
+    Ok((session, fingerprint))
+}
+
+/// Refuse hosts whose key changed; unknown hosts are accepted with a warning
+fn check_host_key(session: &Session, config: &SftpConfig) -> Result<(), String> {
+    let (key, _) = session.host_key().ok_or("Server sent no host key")?;
+    let mut known_hosts = session.known_hosts()
+        .map_err(|e| format!("Failed to load known hosts: {}", e))?;
+
+    let known_hosts_file = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".ssh").join("known_hosts"));
+    if let Some(file) = known_hosts_file.filter(|f| f.exists()) {
+        if let Err(e) = known_hosts.read_file(&file, KnownHostFileKind::OpenSSH) {
+            log::warn!("Failed to read {}: {}", file.display(), e);
+        }
+    }
+
+    match known_hosts.check_port(&config.host, config.port(), key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(format!(
+            "Host key of {} does not match ~/.ssh/known_hosts; refusing to connect",
+            config.host
+        )),
+        CheckResult::NotFound | CheckResult::Failure => {
+            log::info!("Host {} is not in ~/.ssh/known_hosts; accepting its key", config.host);
+            Ok(())
+        }
+    }
+}
+
+fn is_missing(error: &ssh2::Error) -> bool {
+    matches!(error.code(), ssh2::ErrorCode::SFTP(SFTP_NO_SUCH_FILE))
+}
+
+/// Error of a failed SFTP operation, with the server's status as its kind
+fn sftp_error(context: impl std::fmt::Display, error: &ssh2::Error) -> CollectorError {
+    let kind = match error.code() {
+        ssh2::ErrorCode::SFTP(SFTP_NO_SUCH_FILE) => ErrorKind::NotFound,
+        ssh2::ErrorCode::SFTP(SFTP_PERMISSION_DENIED) => ErrorKind::PermissionDenied,
+        _ => ErrorKind::Network,
+    };
+    CollectorError::new(kind, format!("{}: {}", context, error))
+}
+
+/// Create `dir` and its missing parents
+fn create_dirs(sftp: &Sftp, dir: &Path) -> Result<(), CollectorError> {
+    let mut current = PathBuf::new();
+    for component in dir.components() {
+        current.push(component);
+        if sftp.stat(&current).is_err() {
+            sftp.mkdir(&current, 0o755)
+                .map_err(|e| sftp_error(format!("Failed to create remote directory {}", current.display()), &e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Upload into a temporary file and rename it into place, so readers never see a partial file
+fn write_file(sftp: &Sftp, path: &Path, content: &[u8], progress: &UploadProgress) -> Result<(), CollectorError> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        create_dirs(sftp, parent)?;
+    }
+
+    let partial = PathBuf::from(format!("{}.part", path.display()));
+    let mut file = sftp.create(&partial)
+        .map_err(|e| sftp_error(format!("Failed to create {}", partial.display()), &e))?;
+    let mut written = 0;
+    for piece in content.chunks(WRITE_PIECE_SIZE) {
+        file.write_all(piece)
+            .map_err(|e| CollectorError::from_io(format!("Failed to write {}", partial.display()), &e))?;
+        written += piece.len() as u64;
+        progress(written);
+    }
+    drop(file);
+
+    let flags = RenameFlags::OVERWRITE | RenameFlags::ATOMIC | RenameFlags::NATIVE;
+    if sftp.rename(&partial, path, Some(flags)).is_err() {
+        // Servers without overwrite support: replace by hand
+        let _ = sftp.unlink(path);
+        sftp.rename(&partial, path, None)
+            .map_err(|e| sftp_error(format!("Failed to move {} into place", path.display()), &e))?;
+    }
+    Ok(())
+}
+
+fn walk(sftp: &Sftp, root: &Path, dir: &Path, sizes: &mut HashMap<String, u64>) -> Result<(), CollectorError> {
+    let entries = match sftp.readdir(dir) {
+        Ok(entries) => entries,
+        Err(e) if is_missing(&e) => return Ok(()),
+        Err(e) => return Err(sftp_error(format!("Failed to list {}", dir.display()), &e)),
+    };
+
+    for (path, stat) in entries {
+        if stat.is_dir() {
+            walk(sftp, root, &path, sizes)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            sizes.insert(relative.to_string_lossy().replace('\\', "/"), stat.size.unwrap_or(0));
+        }
+    }
+    Ok(())
+}
+
+/// SFTP destination. One session is opened on first use and shared by all operations.
+pub struct SftpStorage {
+    config: SftpConfig,
+    connection: Arc<Mutex<Option<Connection>>>,
+}
+
+impl SftpStorage {
+    pub fn new(config: SftpConfig) -> SftpStorage {
+        SftpStorage {
+            config,
+            connection: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Run a blocking SFTP operation on the shared session, reconnecting after failures
+    async fn with_sftp<T, F>(&self, operation: F) -> Result<T, CollectorError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Sftp, &SftpConfig) -> Result<T, CollectorError> + Send + 'static,
+    {
+        let config = self.config.clone();
+        let connection = self.connection.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap();
+            if connection.is_none() {
+                let (session, _) = connect(&config)?;
+                let sftp = session.sftp().map_err(|e| sftp_error(format!("Failed to start SFTP on {}", config.host), &e))?;
+                *connection = Some(Connection { _session: session, sftp });
+            }
+
+            let result = operation(&connection.as_ref().unwrap().sftp, &config);
+            if result.is_err() {
+                // The session may be broken; the next operation starts a fresh one
+                *connection = None;
+            }
+            result
+        })
+        .await
+        .map_err(|e| format!("SFTP task failed: {}", e))?
+    }
+}
+
+#[async_trait]
+impl RemoteStorage for SftpStorage {
+    fn display_name(&self) -> &'static str {
+        "SFTP storage"
+    }
+
+    fn location(&self, prefix: &str) -> String {
+        format!("sftp://{}@{}:{}/{}", self.config.username, self.config.host, self.config.port(), self.config.remote_path(prefix).display())
+    }
+
+    async fn put_with_progress(&self, key: &str, content: &[u8], progress: &UploadProgress) -> Result<(), CollectorError> {
+        let key = key.to_string();
+        let content = content.to_vec();
+        let progress = progress.clone();
+        self.with_sftp(move |sftp, config| write_file(sftp, &config.remote_path(&key), &content, &progress)).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CollectorError> {
+        let key = key.to_string();
+        self.with_sftp(move |sftp, config| {
+            let path = config.remote_path(&key);
+            let mut file = match sftp.open(&path) {
+                Ok(file) => file,
+                Err(e) if is_missing(&e) => return Ok(None),
+                Err(e) => return Err(sftp_error(format!("Failed to open {}", path.display()), &e)),
+            };
+            let mut content = Vec::new();
+            file.read_to_end(&mut content)
+                .map_err(|e| CollectorError::from_io(format!("Failed to read {}", path.display()), &e))?;
+            Ok(Some(content))
+        })
+        .await
+    }
+
+    async fn list_sizes(&self, prefix: &str) -> Result<HashMap<String, u64>, CollectorError> {
+        let prefix = prefix.trim_end_matches('/').to_string();
+        self.with_sftp(move |sftp, config| {
+            let root = config.remote_path(&prefix);
+            let mut sizes = HashMap::new();
+            walk(sftp, &root, &root, &mut sizes)?;
+            Ok(sizes)
+        })
+        .await
+    }
+}
+
+#[tauri::command]
+pub async fn test_sftp_connection(config: SftpConfig) -> Result<SftpConnectionResult, CollectorError> {
+    log::info!("Testing SFTP connection to: {}@{}:{}", config.username, config.host, config.port());
+
+    let result = tokio::task::spawn_blocking(move || {
+        let (session, fingerprint) = match connect(&config) {
+            Ok(connected) => connected,
+            Err(e) => return (false, e.message, None),
+        };
+
+        let sftp = match session.sftp() {
+            Ok(sftp) => sftp,
+            Err(e) => return (false, format!("Connected, but the server does not offer SFTP: {}", e), fingerprint),
+        };
+
+        if config.base_path.is_empty() {
+            return (true, "Successfully connected to SFTP server!".to_string(), fingerprint);
+        }
+
+        match sftp.stat(Path::new(&config.base_path)) {
+            Ok(stat) if stat.is_dir() => (true, "Successfully connected to SFTP server!".to_string(), fingerprint),
+            Ok(_) => (false, format!("{} exists but is not a directory", config.base_path), fingerprint),
+            Err(e) if is_missing(&e) => (
+                true,
+                format!("Connected. {} does not exist yet and will be created on first upload.", config.base_path),
+                fingerprint,
+            ),
+            Err(e) => (false, format!("Connected, but {} is not accessible: {}", config.base_path, e), fingerprint),
+        }
+    })
+    .await
+    .map_err(|e| format!("SFTP test failed: {}", e))?;
+
+    let (success, message, host_key_fingerprint) = result;
+    log::info!("SFTP connection test: {}", message);
+    Ok(SftpConnectionResult {
+        success,
+        message,
+        host_key_fingerprint,
+    })
+}
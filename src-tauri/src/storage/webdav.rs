@@ -0,0 +1,431 @@
+use async_trait::async_trait;
+use percent_encoding::{percent_decode_str, utf8_percent_encode};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::{RemoteStorage, UploadProgress, PATH_SEGMENT};
+use crate::credentials;
+use crate::error::{CollectorError, ErrorKind};
+
+/// Nextcloud/ownCloud endpoints contain this, followed by the user name
+const NEXTCLOUD_FILES_PATH: &str = "/remote.php/dav/files/";
+
+const DEFAULT_CHUNK_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Nextcloud rejects chunks below 5 MiB, except for the last one
+const MIN_CHUNK_SIZE: u64 = 5 * 1024 * 1024;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getcontentlength/></d:prop></d:propfind>"#;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WebDavConfig {
+    /// WebDAV root, e.g. https://cloud.example.org/remote.php/dav/files/alice
+    pub endpoint: String,
+    pub username: String,
+    /// Account or app password
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Directory datasets are stored under, relative to the endpoint
+    #[serde(default)]
+    pub base_path: String,
+    /// Size of the pieces large files are uploaded in on Nextcloud/ownCloud
+    #[serde(default)]
+    pub chunk_size: Option<u64>,
+}
+
+impl WebDavConfig {
+    /// Read the WebDAV settings of a storage location from the task payload.
+    /// The password may come from the keychain, see `credentials::secret_field`.
+    pub fn from_storage_location(location: &serde_json::Value) -> Result<WebDavConfig, String> {
+        let field = |name: &str| location.get(name).and_then(|v| v.as_str()).map(|s| s.to_string());
+
+        Ok(WebDavConfig {
+            endpoint: field("endpoint").ok_or("No endpoint in WebDAV storage location")?,
+            username: field("username").ok_or("No username in WebDAV storage location")?,
+            password: credentials::secret_field(location, "password")?,
+            base_path: field("path").unwrap_or_default(),
+            chunk_size: location.get("chunkSize").and_then(|v| v.as_u64()),
+        })
+    }
+
+    fn chunk_size(&self) -> u64 {
+        self.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE).max(MIN_CHUNK_SIZE)
+    }
+
+    /// Path segments below the endpoint for `key`, starting with the base path
+    fn segments<'a>(&'a self, key: &'a str) -> Vec<&'a str> {
+        self.base_path.split('/')
+            .chain(key.split('/'))
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    fn url(&self, segments: &[&str]) -> String {
+        let mut url = self.endpoint.trim_end_matches('/').to_string();
+        for segment in segments {
+            url.push('/');
+            url.extend(utf8_percent_encode(segment, PATH_SEGMENT));
+        }
+        url
+    }
+
+    /// Upload collection of the Nextcloud chunking API, when the endpoint is a Nextcloud files root
+    fn uploads_url(&self) -> Option<String> {
+        let endpoint = self.endpoint.trim_end_matches('/');
+        let (server, user) = endpoint.split_once(NEXTCLOUD_FILES_PATH)?;
+        let user = user.split('/').next().filter(|u| !u.is_empty())?;
+        Some(format!("{}/remote.php/dav/uploads/{}", server, user))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebDavConnectionResult {
+    pub success: bool,
+    pub message: String,
+    /// Whether large files will be uploaded in chunks (Nextcloud/ownCloud)
+    pub chunked_uploads: bool,
+}
+
+/// One `<response>` of a PROPFIND multistatus
+struct DavEntry {
+    path: String,
+    is_collection: bool,
+    size: u64,
+}
+
+pub struct WebDavStorage {
+    config: WebDavConfig,
+    client: reqwest::Client,
+    /// Collections known to exist, so MKCOL is sent once per directory
+    collections: Mutex<HashSet<String>>,
+}
+
+impl WebDavStorage {
+    pub fn new(config: WebDavConfig) -> Result<WebDavStorage, String> {
+        let client = crate::network::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to create WebDAV client: {}", e))?;
+
+        Ok(WebDavStorage {
+            config,
+            client,
+            collections: Mutex::new(HashSet::new()),
+        })
+    }
+
+    fn request(&self, method: &str, url: &str) -> RequestBuilder {
+        let method = Method::from_bytes(method.as_bytes()).expect("valid WebDAV method");
+        self.client
+            .request(method, url)
+            .basic_auth(&self.config.username, self.config.password.as_ref())
+    }
+
+    async fn send(&self, request: RequestBuilder, what: &str) -> Result<Response, CollectorError> {
+        let response = request.send().await
+            .map_err(|e| CollectorError::from_request(format!("WebDAV {} failed", what), &e))?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return Err(CollectorError::new(
+                ErrorKind::Authentication,
+                format!("WebDAV {} was rejected: check the username and app password", what),
+            ));
+        }
+        Ok(response)
+    }
+
+    /// Create every missing collection on the way to `segments`
+    async fn ensure_collections(&self, segments: &[&str]) -> Result<(), CollectorError> {
+        for depth in 1..=segments.len() {
+            let url = self.config.url(&segments[..depth]);
+            if self.collections.lock().unwrap().contains(&url) {
+                continue;
+            }
+
+            let response = self.send(self.request("MKCOL", &url), &format!("MKCOL {}", url)).await?;
+            match response.status() {
+                // 405: the collection already exists
+                status if status.is_success() || status == StatusCode::METHOD_NOT_ALLOWED => {
+                    self.collections.lock().unwrap().insert(url);
+                }
+                status => return Err(status_error(status, format!("Failed to create directory {}", url))),
+            }
+        }
+        Ok(())
+    }
+
+    async fn put_whole(&self, url: &str, content: &[u8]) -> Result<(), CollectorError> {
+        let response = self.send(self.request("PUT", url).body(content.to_vec()), &format!("PUT {}", url)).await?;
+        if !response.status().is_success() {
+            return Err(status_error(response.status(), format!("Upload to {} failed", url)));
+        }
+        Ok(())
+    }
+
+    /// Nextcloud chunked upload (v2): numbered chunks in an upload collection, assembled by a MOVE
+    async fn put_chunked(&self, uploads_url: &str, url: &str, content: &[u8], progress: &UploadProgress) -> Result<(), CollectorError> {
+        let upload_url = format!("{}/bids-collector-{}", uploads_url, upload_id(url, content.len()));
+        let total_length = content.len().to_string();
+
+        let response = self.send(
+            self.request("MKCOL", &upload_url).header("Destination", url),
+            "starting a chunked upload",
+        ).await?;
+        if !response.status().is_success() && response.status() != StatusCode::METHOD_NOT_ALLOWED {
+            return Err(status_error(response.status(), format!("Failed to start chunked upload of {}", url)));
+        }
+
+        let mut sent = 0;
+        for (index, chunk) in content.chunks(self.config.chunk_size() as usize).enumerate() {
+            let chunk_url = format!("{}/{}", upload_url, index + 1);
+            let response = self.send(
+                self.request("PUT", &chunk_url)
+                    .header("Destination", url)
+                    .header("OC-Total-Length", &total_length)
+                    .body(chunk.to_vec()),
+                &format!("upload of chunk {}", index + 1),
+            ).await?;
+            if !response.status().is_success() {
+                // Leave nothing behind in the user's upload area
+                let _ = self.request("DELETE", &upload_url).send().await;
+                return Err(status_error(response.status(), format!("Upload of chunk {} of {} failed", index + 1, url)));
+            }
+            sent += chunk.len() as u64;
+            progress(sent);
+        }
+
+        let response = self.send(
+            self.request("MOVE", &format!("{}/.file", upload_url))
+                .header("Destination", url)
+                .header("OC-Total-Length", &total_length)
+                .header("Overwrite", "T"),
+            "assembling the chunked upload",
+        ).await?;
+        if !response.status().is_success() {
+            let _ = self.request("DELETE", &upload_url).send().await;
+            return Err(status_error(response.status(), format!("Assembling the chunked upload of {} failed", url)));
+        }
+        Ok(())
+    }
+
+    /// Direct children of a collection; None when it does not exist
+    async fn propfind(&self, url: &str, depth: &str) -> Result<Option<Vec<DavEntry>>, CollectorError> {
+        let response = self.send(
+            self.request("PROPFIND", url)
+                .header("Depth", depth)
+                .header("Content-Type", "application/xml; charset=utf-8")
+                .body(PROPFIND_BODY),
+            &format!("PROPFIND {}", url),
+        ).await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => return Ok(None),
+            StatusCode::MULTI_STATUS => {}
+            status => return Err(status_error(status, format!("Listing {} failed", url))),
+        }
+
+        let body = response.text().await
+            .map_err(|e| CollectorError::from_request(format!("Failed to read listing of {}", url), &e))?;
+        Ok(Some(parse_multistatus(&body)))
+    }
+}
+
+/// Error for a request the server answered with `status`
+fn status_error(status: StatusCode, message: String) -> CollectorError {
+    CollectorError::new(ErrorKind::of_status(status), format!("{}: HTTP {}", message, status))
+}
+
+/// Name of the upload collection, stable for a destination and length so a retry replaces it
+fn upload_id(url: &str, length: usize) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(url.as_bytes());
+    hasher.update(length.to_le_bytes());
+    hex::encode(&hasher.finalize()[..8])
+}
+
+/// Inner text of every element named `name`, whatever namespace prefix the server uses
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let Some(tag_end) = rest.find('>') else { break };
+        let tag = &rest[..tag_end];
+        let tag_name = tag.split_whitespace().next().unwrap_or_default().trim_end_matches('/');
+        let local = tag_name.rsplit(':').next().unwrap_or_default();
+
+        if local != name || tag.starts_with('/') {
+            continue;
+        }
+        if tag.ends_with('/') {
+            found.push("");
+            continue;
+        }
+
+        let content = &rest[tag_end + 1..];
+        let close = format!("</{}>", tag_name);
+        let Some(end) = content.find(&close) else { break };
+        found.push(&content[..end]);
+        rest = &content[end + close.len()..];
+    }
+
+    found
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn parse_multistatus(xml: &str) -> Vec<DavEntry> {
+    elements(xml, "response")
+        .into_iter()
+        .filter_map(|response| {
+            let href = xml_unescape(elements(response, "href").first()?.trim());
+            // The href may be an absolute URL or an absolute path
+            let path = match href.find("://") {
+                Some(scheme) => href[scheme + 3..].find('/').map(|p| href[scheme + 3 + p..].to_string())?,
+                None => href,
+            };
+            Some(DavEntry {
+                path: percent_decode_str(&path).decode_utf8_lossy().trim_end_matches('/').to_string(),
+                is_collection: elements(response, "resourcetype")
+                    .first()
+                    .is_some_and(|t| !elements(t, "collection").is_empty()),
+                size: elements(response, "getcontentlength")
+                    .first()
+                    .and_then(|l| l.trim().parse().ok())
+                    .unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+/// Decoded path component of a URL, as it appears in PROPFIND hrefs
+fn decoded_path(url: &str) -> String {
+    let path = url.split_once("://")
+        .and_then(|(_, rest)| rest.find('/').map(|p| rest[p..].to_string()))
+        .unwrap_or_default();
+    percent_decode_str(&path).decode_utf8_lossy().trim_end_matches('/').to_string()
+}
+
+#[async_trait]
+impl RemoteStorage for WebDavStorage {
+    fn display_name(&self) -> &'static str {
+        "WebDAV"
+    }
+
+    fn location(&self, prefix: &str) -> String {
+        format!("{}/", self.config.url(&self.config.segments(prefix)))
+    }
+
+    async fn put_with_progress(&self, key: &str, content: &[u8], progress: &UploadProgress) -> Result<(), CollectorError> {
+        let segments = self.config.segments(key);
+        let (_, parents) = segments.split_last().ok_or("Empty upload key")?;
+        self.ensure_collections(parents).await?;
+
+        let url = self.config.url(&segments);
+        match self.config.uploads_url() {
+            Some(uploads_url) if content.len() as u64 > self.config.chunk_size() => {
+                self.put_chunked(&uploads_url, &url, content, progress).await
+            }
+            _ => {
+                self.put_whole(&url, content).await?;
+                progress(content.len() as u64);
+                Ok(())
+            }
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CollectorError> {
+        let url = self.config.url(&self.config.segments(key));
+        let response = self.send(self.request("GET", &url), &format!("GET {}", url)).await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let content = response.bytes().await
+                    .map_err(|e| CollectorError::from_request(format!("Failed to read {}", url), &e))?;
+                Ok(Some(content.to_vec()))
+            }
+            status => Err(status_error(status, format!("Download of {} failed", url))),
+        }
+    }
+
+    async fn list_sizes(&self, prefix: &str) -> Result<HashMap<String, u64>, CollectorError> {
+        let root_url = self.config.url(&self.config.segments(prefix));
+        let root_path = decoded_path(&root_url);
+        let mut sizes = HashMap::new();
+
+        // Depth: infinity is disabled on most servers, so walk one level at a time
+        let mut pending = vec![root_url];
+        while let Some(url) = pending.pop() {
+            let Some(entries) = self.propfind(&url, "1").await? else {
+                continue;
+            };
+            let own_path = decoded_path(&url);
+
+            for entry in entries {
+                if entry.path == own_path {
+                    continue;
+                }
+                let Some(relative) = entry.path.strip_prefix(&root_path) else {
+                    continue;
+                };
+                let relative = relative.trim_start_matches('/');
+
+                if entry.is_collection {
+                    let segments: Vec<&str> = relative.split('/').collect();
+                    let mut child = self.config.segments(prefix);
+                    child.extend(segments);
+                    pending.push(self.config.url(&child));
+                } else {
+                    sizes.insert(relative.to_string(), entry.size);
+                }
+            }
+        }
+
+        Ok(sizes)
+    }
+}
+
+#[tauri::command]
+pub async fn test_webdav_connection(config: WebDavConfig) -> Result<WebDavConnectionResult, CollectorError> {
+    log::info!("Testing WebDAV connection to: {} as {}", config.endpoint, config.username);
+
+    let chunked_uploads = config.uploads_url().is_some();
+    let base_path = config.base_path.clone();
+    let storage = WebDavStorage::new(config)?;
+    let url = storage.config.url(&storage.config.segments(""));
+
+    let (success, message) = match storage.propfind(&url, "0").await {
+        Ok(Some(entries)) if entries.first().is_some_and(|e| !e.is_collection) => {
+            (false, format!("{} exists but is not a directory", base_path))
+        }
+        Ok(Some(_)) => (true, "Successfully connected to WebDAV server!".to_string()),
+        Ok(None) if base_path.is_empty() => (false, format!("{} was not found on the server", url)),
+        Ok(None) => (
+            true,
+            format!("Connected. {} does not exist yet and will be created on first upload.", base_path),
+        ),
+        Err(e) => (false, e.message),
+    };
+
+    log::info!("WebDAV connection test: {}", message);
+    Ok(WebDavConnectionResult {
+        success,
+        message,
+        chunked_uploads,
+    })
+}
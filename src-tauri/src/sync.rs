@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tokio::fs;
+
+use crate::checksum::ChecksumAlgorithm;
+use crate::manifest::{Manifest, ManifestEntry};
+use crate::path_guard;
+use crate::providers::RemoteFile;
+use crate::safe_path;
+
+/// Outcome of comparing a remote file with what the destination already holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncDecision {
+    /// Not at the destination yet
+    Missing,
+    /// Present but different from the remote file; the reason is logged
+    Changed(&'static str),
+    /// Same size and no newer version markers; safe to skip
+    Unchanged,
+}
+
+/// Compare a remote file against the destination copy.
+///
+/// `existing_size` is the size at the destination (None when absent) and `previous`
+/// the entry recorded when the copy was collected. ETags are compared first, then
+/// Last-Modified, then published SHA-256 checksums; without any of them the size
+/// match decides.
+pub fn compare(remote: &RemoteFile, existing_size: Option<u64>, previous: Option<&ManifestEntry>) -> SyncDecision {
+    let Some(existing_size) = existing_size else {
+        return SyncDecision::Missing;
+    };
+
+    if existing_size != remote.size {
+        return SyncDecision::Changed("size differs");
+    }
+
+    let Some(previous) = previous else {
+        return SyncDecision::Unchanged;
+    };
+
+    if let (Some(remote_etag), Some(previous_etag)) = (&remote.etag, &previous.etag) {
+        return if remote_etag == previous_etag {
+            SyncDecision::Unchanged
+        } else {
+            SyncDecision::Changed("ETag changed")
+        };
+    }
+
+    if let (Some(remote_modified), Some(previous_modified)) = (&remote.last_modified, &previous.last_modified) {
+        let newer = match (
+            chrono::DateTime::parse_from_rfc3339(remote_modified),
+            chrono::DateTime::parse_from_rfc3339(previous_modified),
+        ) {
+            (Ok(remote_time), Ok(previous_time)) => remote_time > previous_time,
+            _ => remote_modified != previous_modified,
+        };
+        return if newer {
+            SyncDecision::Changed("modified since last collection")
+        } else {
+            SyncDecision::Unchanged
+        };
+    }
+
+    if let Some(checksum) = remote.checksum.as_ref().filter(|c| c.algorithm == ChecksumAlgorithm::Sha256) {
+        if !checksum.value.eq_ignore_ascii_case(&previous.sha256) {
+            return SyncDecision::Changed("checksum changed");
+        }
+    }
+
+    SyncDecision::Unchanged
+}
+
+
+/// A collected file that the provider now lists under a different path
+#[derive(Debug, Clone)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+    pub size: u64,
+}
+
+/// Whether a listed file has the same content as a previously collected one: the provider's
+/// SHA-256 decides when it publishes one, otherwise a matching ETag
+fn same_content(remote: &RemoteFile, entry: &ManifestEntry) -> bool {
+    if remote.size != entry.size {
+        return false;
+    }
+    if let Some(checksum) = remote.checksum.as_ref().filter(|c| c.algorithm == ChecksumAlgorithm::Sha256) {
+        return checksum.value.eq_ignore_ascii_case(&entry.sha256);
+    }
+    matches!((&remote.etag, &entry.etag), (Some(remote_etag), Some(entry_etag)) if remote_etag == entry_etag)
+}
+
+/// Pair files missing at the destination with collected files the provider no longer lists
+/// at their old path. `listed` holds every path of the listing, including filtered-out ones,
+/// so files the task merely excludes are never moved.
+pub fn detect_renames(missing: &[&RemoteFile], listed: &HashSet<String>, previous: &Manifest) -> Vec<Rename> {
+    let mut orphans: Vec<&ManifestEntry> = previous.files
+        .iter()
+        .filter(|entry| !listed.contains(&entry.path))
+        .collect();
+    let mut renames = Vec::new();
+
+    for file in missing {
+        if let Some(position) = orphans.iter().position(|entry| same_content(file, entry)) {
+            let entry = orphans.swap_remove(position);
+            renames.push(Rename {
+                from: entry.path.clone(),
+                to: file.path.clone(),
+                size: file.size,
+            });
+        }
+    }
+
+    renames
+}
+
+/// Move renamed files into place under `dest_dir` and point their manifest entries at the
+/// new paths. Returns the renames that were applied; the others are downloaded as usual.
+pub async fn apply_renames(dest_dir: &str, renames: Vec<Rename>, previous: &mut Manifest) -> Vec<Rename> {
+    let mut applied = Vec::new();
+
+    for rename in renames {
+        let (Ok(from), Ok(to)) = (safe_path::join(dest_dir, &rename.from), safe_path::join(dest_dir, &rename.to)) else {
+            continue;
+        };
+        let (from, to) = (PathBuf::from(from), PathBuf::from(to));
+
+        // The old copy must still be there untouched
+        match fs::metadata(&from).await {
+            Ok(metadata) if metadata.is_file() && metadata.len() == rename.size => {}
+            _ => continue,
+        }
+        if let Err(e) = path_guard::check(&from).and_then(|_| path_guard::check(&to)) {
+            log::info!("Not renaming {} to {}: {}", rename.from, rename.to, e);
+            continue;
+        }
+        if let Some(parent) = to.parent() {
+            if let Err(e) = fs::create_dir_all(parent).await {
+                log::warn!("Failed to create directory {}: {}", parent.display(), e);
+                continue;
+            }
+        }
+        if let Err(e) = fs::rename(&from, &to).await {
+            log::warn!("Failed to move {} to {}: {}", rename.from, rename.to, e);
+            continue;
+        }
+
+        log::info!("Renamed {} to {} instead of downloading it again", rename.from, rename.to);
+        if let Some(entry) = previous.files.iter_mut().find(|e| e.path == rename.from) {
+            entry.path = rename.to.clone();
+        }
+        applied.push(rename);
+    }
+
+    applied
+}
@@ -0,0 +1,340 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+use crate::error::CollectorError;
+use crate::scheduler::{SchedulerState, WaitKind, WaitReason};
+use crate::settings::SettingsState;
+
+/// Tasks transferring at the same time unless configured otherwise
+pub const DEFAULT_MAX_CONCURRENT: usize = 2;
+
+pub const MAX_CONCURRENT_LIMIT: usize = 16;
+
+/// Where a task is placed in the download queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueuePriority {
+    Low,
+    Normal,
+    High,
+}
+
+impl QueuePriority {
+    /// Task field `priority`: "high", "low" (or "background" / `lowPriority: true`), normal otherwise
+    pub fn from_task(task: &serde_json::Value) -> QueuePriority {
+        let low_priority = task.get("lowPriority").and_then(|v| v.as_bool()).unwrap_or(false);
+        match task.get("priority").and_then(|v| v.as_str()) {
+            Some("high") => QueuePriority::High,
+            Some("low") | Some("background") => QueuePriority::Low,
+            _ if low_priority => QueuePriority::Low,
+            _ => QueuePriority::Normal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedTask {
+    pub task_id: String,
+    pub priority: QueuePriority,
+    pub queued_at: String,
+    /// Why the task's own constraints keep it from starting; blocked tasks do not hold up
+    /// the ones behind them
+    pub blocked: Option<String>,
+    pub blocked_kind: Option<WaitKind>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSnapshot {
+    pub max_concurrent: usize,
+    /// False outside the configured download window: queued tasks wait, running ones pause
+    pub window_open: bool,
+    /// Set by "Pause all": queued tasks wait and running ones pause like outside the window
+    pub paused: bool,
+    pub running: Vec<String>,
+    /// Waiting tasks in the order they will start
+    pub queued: Vec<QueuedTask>,
+}
+
+/// Limits how many download tasks transfer at once.
+///
+/// Tasks wait in `queued` ordered by priority, first come first served within a priority.
+/// Reordering and promoting change the order directly; later arrivals are still placed by
+/// priority behind everything of the same or higher priority.
+pub struct TaskQueue {
+    inner: Mutex<QueueSnapshot>,
+    changed: Notify,
+}
+
+pub type TaskQueueState = Arc<TaskQueue>;
+
+impl TaskQueue {
+    pub fn new() -> TaskQueue {
+        TaskQueue {
+            inner: Mutex::new(QueueSnapshot {
+                max_concurrent: DEFAULT_MAX_CONCURRENT,
+                window_open: true,
+                paused: false,
+                running: Vec::new(),
+                queued: Vec::new(),
+            }),
+            changed: Notify::new(),
+        }
+    }
+
+    pub fn snapshot(&self) -> QueueSnapshot {
+        self.inner.lock().unwrap().clone()
+    }
+
+    pub fn enqueue(&self, task_id: &str, priority: QueuePriority) {
+        let mut queue = self.inner.lock().unwrap();
+        let position = queue.queued.iter()
+            .position(|queued| queued.priority < priority)
+            .unwrap_or(queue.queued.len());
+        queue.queued.insert(position, QueuedTask {
+            task_id: task_id.to_string(),
+            priority,
+            queued_at: chrono::Utc::now().to_rfc3339(),
+            blocked: None,
+            blocked_kind: None,
+        });
+        log::info!("Queued task {} ({:?} priority, position {})", task_id, priority, position + 1);
+    }
+
+    /// Move the task to running if a slot is free and it is next in line for one
+    fn try_start(&self, task_id: &str) -> bool {
+        let mut queue = self.inner.lock().unwrap();
+        if !queue.window_open || queue.paused {
+            return false;
+        }
+        let free_slots = queue.max_concurrent.saturating_sub(queue.running.len());
+        let ready = queue.queued.iter()
+            .filter(|queued| queued.blocked.is_none())
+            .position(|queued| queued.task_id == task_id);
+        match ready {
+            Some(ready) if ready < free_slots => {
+                queue.queued.retain(|queued| queued.task_id != task_id);
+                queue.running.push(task_id.to_string());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Record why the task cannot start yet, waking the others when that changes
+    fn set_blocked(&self, task_id: &str, blocked: Option<WaitReason>) {
+        let mut queue = self.inner.lock().unwrap();
+        let Some(queued) = queue.queued.iter_mut().find(|queued| queued.task_id == task_id) else {
+            return;
+        };
+        if queued.blocked_kind == blocked.as_ref().map(|reason| reason.kind)
+            && queued.blocked.as_ref() == blocked.as_ref().map(|reason| &reason.message)
+        {
+            return;
+        }
+        if let Some(reason) = &blocked {
+            log::info!("Task {} stays queued: {}", task_id, reason.message);
+        }
+        queued.blocked_kind = blocked.as_ref().map(|reason| reason.kind);
+        queued.blocked = blocked.map(|reason| reason.message);
+        drop(queue);
+        self.changed.notify_waiters();
+    }
+
+    /// Wait until the task may start. `blocked` reports why the task's own constraints keep
+    /// it waiting, if they do. Returns a guard that frees the slot when dropped, or None once
+    /// `cancelled` reports true (the task is then removed from the queue).
+    pub async fn wait_turn(
+        self: &Arc<Self>,
+        task_id: &str,
+        cancelled: impl Fn() -> bool,
+        blocked: impl Fn() -> Option<WaitReason>,
+    ) -> Option<RunningTask> {
+        loop {
+            // Register for wake-ups before checking, so a change in between is not missed
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            if cancelled() {
+                self.remove(task_id);
+                return None;
+            }
+            self.set_blocked(task_id, blocked());
+            if self.try_start(task_id) {
+                log::info!("Task {} leaves the queue", task_id);
+                return Some(RunningTask {
+                    queue: self.clone(),
+                    task_id: task_id.to_string(),
+                });
+            }
+            changed.await;
+        }
+    }
+
+    pub fn is_window_open(&self) -> bool {
+        self.inner.lock().unwrap().window_open
+    }
+
+    /// Open or close the download window, waking the tasks waiting on it
+    pub fn set_window_open(&self, open: bool) {
+        let mut queue = self.inner.lock().unwrap();
+        if queue.window_open == open {
+            return;
+        }
+        queue.window_open = open;
+        drop(queue);
+
+        log::info!("Download window {}", if open { "opened, starting queued tasks" } else { "closed, pausing tasks" });
+        self.changed.notify_waiters();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.lock().unwrap().paused
+    }
+
+    /// Pause or resume every task at once, waking the tasks waiting on it
+    pub fn set_paused(&self, paused: bool) {
+        let mut queue = self.inner.lock().unwrap();
+        if queue.paused == paused {
+            return;
+        }
+        queue.paused = paused;
+        drop(queue);
+
+        log::info!("{} all tasks", if paused { "Pausing" } else { "Resuming" });
+        self.changed.notify_waiters();
+    }
+
+    /// Wait until the download window is open, the queue is not paused and `blocked` reports
+    /// nothing in the way.
+    /// Returns false once `cancelled` reports true.
+    pub async fn wait_until_allowed(&self, cancelled: impl Fn() -> bool, blocked: impl Fn() -> Option<WaitReason>) -> bool {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            if cancelled() {
+                return false;
+            }
+            if self.is_window_open() && !self.is_paused() && blocked().is_none() {
+                return true;
+            }
+            changed.await;
+        }
+    }
+
+    /// Drop a waiting task, e.g. when it was cancelled
+    pub fn remove(&self, task_id: &str) {
+        self.inner.lock().unwrap().queued.retain(|queued| queued.task_id != task_id);
+        self.changed.notify_waiters();
+    }
+
+    /// Wake waiting tasks so they re-check the queue, e.g. after a cancellation
+    pub fn wake(&self) {
+        self.changed.notify_waiters();
+    }
+
+    pub fn set_max_concurrent(&self, max_concurrent: usize) -> Result<(), String> {
+        if !(1..=MAX_CONCURRENT_LIMIT).contains(&max_concurrent) {
+            return Err(format!("Concurrent tasks must be between 1 and {}, got {}", MAX_CONCURRENT_LIMIT, max_concurrent));
+        }
+        self.inner.lock().unwrap().max_concurrent = max_concurrent;
+        self.changed.notify_waiters();
+        Ok(())
+    }
+
+    /// Put the listed queued tasks first, in the given order; the others follow as they were
+    fn reorder(&self, task_ids: &[String]) -> Result<(), String> {
+        let mut queue = self.inner.lock().unwrap();
+        if let Some(unknown) = task_ids.iter().find(|id| !queue.queued.iter().any(|q| &q.task_id == *id)) {
+            return Err(format!("Task {} is not waiting in the queue", unknown));
+        }
+
+        let mut reordered: Vec<QueuedTask> = task_ids.iter()
+            .filter_map(|id| queue.queued.iter().find(|q| &q.task_id == id).cloned())
+            .collect();
+        reordered.extend(queue.queued.iter().filter(|q| !task_ids.contains(&q.task_id)).cloned());
+        queue.queued = reordered;
+        drop(queue);
+
+        self.changed.notify_waiters();
+        Ok(())
+    }
+}
+
+impl Default for TaskQueue {
+    fn default() -> Self {
+        TaskQueue::new()
+    }
+}
+
+/// A task holding one of the concurrent slots
+pub struct RunningTask {
+    queue: TaskQueueState,
+    task_id: String,
+}
+
+impl Drop for RunningTask {
+    fn drop(&mut self) {
+        self.queue.inner.lock().unwrap().running.retain(|id| id != &self.task_id);
+        self.queue.changed.notify_waiters();
+    }
+}
+
+#[tauri::command]
+pub async fn get_download_queue(queue: tauri::State<'_, TaskQueueState>) -> Result<QueueSnapshot, CollectorError> {
+    Ok(queue.snapshot())
+}
+
+/// Takes effect immediately when raised; running tasks finish when it is lowered.
+/// Saved with the application settings.
+#[tauri::command]
+pub async fn set_max_concurrent_tasks(
+    max_concurrent: usize,
+    queue: tauri::State<'_, TaskQueueState>,
+    settings: tauri::State<'_, SettingsState>,
+    scheduler: tauri::State<'_, SchedulerState>,
+) -> Result<QueueSnapshot, CollectorError> {
+    let mut updated = settings.settings();
+    updated.max_concurrent_tasks = max_concurrent;
+    settings.update(updated, &queue, &scheduler)?;
+    log::info!("Download queue runs up to {} tasks at once", max_concurrent);
+    Ok(queue.snapshot())
+}
+
+#[tauri::command]
+pub async fn reorder_queued_tasks(
+    task_ids: Vec<String>,
+    queue: tauri::State<'_, TaskQueueState>,
+) -> Result<QueueSnapshot, CollectorError> {
+    queue.reorder(&task_ids)?;
+    Ok(queue.snapshot())
+}
+
+/// Hold every task: running ones pause before their next file and queued ones stay queued
+#[tauri::command]
+pub async fn pause_all_tasks(queue: tauri::State<'_, TaskQueueState>) -> Result<QueueSnapshot, CollectorError> {
+    queue.set_paused(true);
+    Ok(queue.snapshot())
+}
+
+#[tauri::command]
+pub async fn resume_all_tasks(queue: tauri::State<'_, TaskQueueState>) -> Result<QueueSnapshot, CollectorError> {
+    queue.set_paused(false);
+    Ok(queue.snapshot())
+}
+
+/// Move a queued task to the front so it starts as soon as a slot frees up
+#[tauri::command]
+pub async fn promote_queued_task(
+    task_id: String,
+    queue: tauri::State<'_, TaskQueueState>,
+) -> Result<QueueSnapshot, CollectorError> {
+    queue.reorder(std::slice::from_ref(&task_id))?;
+    log::info!("Promoted task {} to the front of the queue", task_id);
+    Ok(queue.snapshot())
+}
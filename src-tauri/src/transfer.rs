@@ -0,0 +1,324 @@
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{Emitter, Manager};
+use tokio::fs;
+
+use crate::archive;
+use crate::catalog::{CatalogEntry, CatalogState};
+use crate::constraints::TaskConstraints;
+use crate::error::{CollectorError, ErrorKind};
+use crate::formatting;
+use crate::manifest::{self, Manifest};
+use crate::path_guard;
+use crate::quota::{self, StorageQuota};
+use crate::safe_path;
+use crate::statistics::TaskOrigin;
+use crate::scheduler::{SchedulerState, TaskPriority};
+use crate::storage::{self, RemoteStorage};
+use crate::task_queue::{QueuePriority, TaskQueueState};
+use crate::transfer_rate::TransferRate;
+use crate::{DownloadProgress, DownloadState};
+
+/// One side of a transfer: a local storage root or a remote storage location
+enum Endpoint {
+    Local(String),
+    Remote(Box<dyn RemoteStorage>),
+}
+
+impl Endpoint {
+    fn open(location: &Value) -> Result<Endpoint, String> {
+        match location.get("type").and_then(|t| t.as_str()) {
+            Some("local") => location.get("path")
+                .and_then(|p| p.as_str())
+                .filter(|p| !p.is_empty())
+                .map(|p| Endpoint::Local(p.to_string()))
+                .ok_or_else(|| "No storage path specified".to_string()),
+            Some(_) => Ok(Endpoint::Remote(storage::from_storage_location(location)?)),
+            None => Err("No storage type specified".to_string()),
+        }
+    }
+
+    /// Where the dataset under `prefix` lives, as recorded in the catalog
+    fn location(&self, prefix: &str) -> String {
+        match self {
+            Endpoint::Local(root) => format!("{}/{}", root, prefix),
+            Endpoint::Remote(storage) => storage.location(prefix),
+        }
+    }
+
+    /// Sizes of the files under `prefix`, keyed by their path relative to it
+    async fn list(&self, prefix: &str) -> Result<HashMap<String, u64>, CollectorError> {
+        match self {
+            Endpoint::Local(root) => {
+                let dir = Path::new(root).join(prefix);
+                Ok(tokio::task::spawn_blocking(move || list_local(&dir))
+                    .await
+                    .map_err(|e| format!("Listing failed: {}", e))??)
+            }
+            Endpoint::Remote(storage) => storage.list_sizes(&format!("{}/", prefix)).await,
+        }
+    }
+
+    async fn read(&self, prefix: &str, path: &str) -> Result<Vec<u8>, CollectorError> {
+        match self {
+            Endpoint::Local(root) => {
+                let source = format!("{}/{}/{}", root, prefix, path);
+                fs::read(&source).await.map_err(|e| CollectorError::from_io(format!("Failed to read {}", source), &e))
+            }
+            Endpoint::Remote(storage) => storage.get(&format!("{}/{}", prefix, path)).await?
+                .ok_or_else(|| CollectorError::new(ErrorKind::NotFound, format!("{} disappeared from the source", path))),
+        }
+    }
+
+    async fn write(&self, prefix: &str, path: &str, content: &[u8]) -> Result<(), CollectorError> {
+        match self {
+            Endpoint::Local(root) => {
+                let dest = path_guard::check(safe_path::join(&format!("{}/{}", root, prefix), path)?)?;
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent).await
+                        .map_err(|e| CollectorError::from_io(format!("Failed to create directory {}", parent.display()), &e))?;
+                }
+                fs::write(&dest, content).await
+                    .map_err(|e| CollectorError::from_io(format!("Failed to write {}", dest.display()), &e))
+            }
+            Endpoint::Remote(storage) => storage.put(&format!("{}/{}", prefix, path), content).await
+                .map_err(|e| e.context(format!("Failed to upload {}", path))),
+        }
+    }
+}
+
+/// Files below `dir` with their paths relative to it; nothing when it does not exist
+fn list_local(dir: &Path) -> Result<HashMap<String, u64>, String> {
+    let mut files = HashMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = match std::fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && current == dir => return Ok(files),
+            Err(e) => return Err(format!("Failed to read {}: {}", current.display(), e)),
+        };
+        for entry in entries {
+            let path = entry.map_err(|e| format!("Failed to read {}: {}", current.display(), e))?.path();
+            // Symlinked directories are not followed, so a link back up cannot loop
+            let metadata = path.symlink_metadata()
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            if metadata.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let Some(size) = std::fs::metadata(&path).ok().filter(|m| m.is_file()).map(|m| m.len()) else {
+                continue;
+            };
+            let relative = path.strip_prefix(dir).map(PathBuf::from).unwrap_or_default();
+            files.insert(archive::to_slash_path(&relative), size);
+        }
+    }
+    Ok(files)
+}
+
+/// Catalog entry for the copy at the destination, when the source was cataloged
+fn catalog_copy(catalog: &CatalogState, source: &str, task_id: &str, dest_type: &str, dest: &str) -> Result<Option<CatalogEntry>, String> {
+    let original = catalog.lock().unwrap().list()?
+        .into_iter()
+        .filter(|entry| entry.location == source)
+        .max_by(|a, b| a.collected_at.cmp(&b.collected_at));
+    Ok(original.map(|entry| CatalogEntry {
+        id: task_id.to_string(),
+        storage_type: dest_type.to_string(),
+        location: dest.to_string(),
+        collected_at: chrono::Utc::now().to_rfc3339(),
+        ..entry
+    }))
+}
+
+fn update_progress(task_id: &str, state: &DownloadState, update: impl FnOnce(&mut DownloadProgress)) {
+    let mut downloads = state.lock().unwrap();
+    if let Some(progress) = downloads.get_mut(task_id) {
+        update(progress);
+    }
+}
+
+async fn run_transfer(
+    task_id: String,
+    source_location: Value,
+    dest_location: Value,
+    prefix: String,
+    state: DownloadState,
+    app_handle: tauri::AppHandle,
+) -> Result<(), CollectorError> {
+    let source = Endpoint::open(&source_location)?;
+    let dest = Endpoint::open(&dest_location)?;
+    let (source_uri, dest_uri) = (source.location(&prefix), dest.location(&prefix));
+    log::info!("Transferring {} to {} in task {}", source_uri, dest_uri, task_id);
+
+    let scheduler = app_handle.state::<SchedulerState>().inner().clone();
+    let _priority_guard = scheduler.register(&task_id, TaskPriority::Foreground);
+    update_progress(&task_id, &state, |progress| progress.status = "collecting".to_string());
+
+    let files = source.list(&prefix).await?;
+    if files.is_empty() {
+        return Err(CollectorError::new(ErrorKind::NotFound, format!("Nothing to transfer at {}", source_uri)));
+    }
+    // The manifest goes last, so the destination only claims the files once they are all there
+    let mut paths: Vec<&String> = files.keys().collect();
+    paths.sort_by_key(|path| (path.as_str() == manifest::MANIFEST_PATH, path.as_str()));
+
+    // Files that already made it in an earlier, interrupted transfer are kept
+    let existing = dest.list(&prefix).await?;
+    let total_files = files.len() as u32;
+    let total_size: u64 = files.values().sum();
+    let missing_size: u64 = files.iter()
+        .filter(|(path, size)| existing.get(*path) != Some(size))
+        .map(|(_, size)| size)
+        .sum();
+    if let Some(quota) = StorageQuota::from_location(&dest_location) {
+        quota::enforce(&quota, missing_size).await?;
+    }
+
+    let source_manifest = if files.contains_key(manifest::MANIFEST_PATH) {
+        Some(Manifest::from_json(&source.read(&prefix, manifest::MANIFEST_PATH).await?)?)
+    } else {
+        None
+    };
+    let recorded = source_manifest.as_ref().map(|m| m.by_path()).unwrap_or_default();
+
+    update_progress(&task_id, &state, |progress| {
+        progress.total_files = Some(total_files);
+        progress.total_size = total_size;
+    });
+
+    let mut transferred_files = 0u32;
+    let mut skipped_files = 0u32;
+    let mut transferred_size = 0u64;
+    let mut rate = TransferRate::new();
+    for path in paths {
+        if crate::is_cancelled(&task_id, &state) {
+            return Err(CollectorError::new(ErrorKind::Cancelled, "Transfer cancelled"));
+        }
+        crate::wait_until_allowed(&task_id, &TaskConstraints::default(), &state, &app_handle).await?;
+
+        let size = files[path];
+        if existing.get(path) == Some(&size) {
+            log::info!("Skipping {}, already at the destination", path);
+            skipped_files += 1;
+            transferred_size += size;
+            update_progress(&task_id, &state, |progress| {
+                progress.skipped_files = skipped_files;
+                progress.downloaded_size = transferred_size;
+            });
+            continue;
+        }
+
+        let content = source.read(&prefix, path).await?;
+        if let Some(entry) = recorded.get(path.as_str()) {
+            let sha256 = hex::encode(Sha256::digest(&content));
+            if !sha256.eq_ignore_ascii_case(&entry.sha256) {
+                return Err(CollectorError::new(ErrorKind::Integrity, format!("{} does not match its manifest checksum at the source", path)));
+            }
+        }
+        scheduler.pace(TaskPriority::Foreground, content.len()).await;
+        dest.write(&prefix, path, &content).await?;
+
+        transferred_files += 1;
+        transferred_size += size;
+        rate.record(content.len() as u64);
+        let progress_percent = (transferred_size as f64 / total_size as f64 * 100.0).min(100.0);
+        update_progress(&task_id, &state, |progress| {
+            progress.progress = progress_percent;
+            progress.downloaded_size = transferred_size;
+            progress.completed_files = Some(transferred_files);
+            progress.current_file = Some(path.clone());
+            progress.update_rate(&rate);
+        });
+
+        let _ = app_handle.emit("download_progress", json!({
+            "taskId": task_id,
+            "progress": progress_percent,
+            "uploadedSize": transferred_size,
+            "totalSize": total_size,
+            "currentFile": path,
+            "completedFiles": transferred_files,
+            "skippedFiles": skipped_files,
+            "totalFiles": total_files,
+            "speed": rate.instantaneous(),
+            "averageSpeed": rate.average(),
+            "etaSeconds": rate.eta_seconds(total_size.saturating_sub(transferred_size)),
+            "status": "transferring"
+        }));
+    }
+
+    let dest_type = dest_location.get("type").and_then(|t| t.as_str()).unwrap_or("local");
+    let catalog = app_handle.state::<CatalogState>();
+    match catalog_copy(&catalog, &source_uri, &task_id, dest_type, &dest_uri) {
+        Ok(Some(entry)) => {
+            if let Err(e) = catalog.lock().unwrap().record(&entry) {
+                log::warn!("Failed to record task {} in catalog: {}", task_id, e);
+            }
+        }
+        Ok(None) => log::info!("{} is not in the catalog, so neither is its copy", source_uri),
+        Err(e) => log::warn!("Failed to read catalog: {}", e),
+    }
+
+    update_progress(&task_id, &state, |progress| {
+        progress.status = "completed".to_string();
+        progress.speed = 0.0;
+        progress.eta_seconds = None;
+        progress.progress = 100.0;
+        progress.completed_at = Some(chrono::Utc::now().to_rfc3339());
+        progress.current_file = Some(format!(
+            "Completed - {}",
+            formatting::transfer_summary(transferred_files as usize, transferred_size, progress.started_at.as_deref())
+        ));
+    });
+    let _ = app_handle.emit("download_completed", json!({
+        "taskId": task_id,
+        "status": "completed",
+        "totalFiles": total_files,
+        "transferredFiles": transferred_files,
+        "skippedFiles": skipped_files,
+        "totalSize": total_size,
+        "summary": formatting::transfer_summary(transferred_files as usize, transferred_size, None)
+    }));
+
+    log::info!("Transferred {} files from {} to {}", transferred_files, source_uri, dest_uri);
+    Ok(())
+}
+
+/// Copy a collected dataset from one storage location to another, e.g. from local disk to an
+/// S3 bucket or between two buckets, without downloading it from the provider again. Runs as
+/// a queued task that reports progress and can be paused and cancelled like a download. Files
+/// already at the destination with the same size are kept, so a cancelled transfer can simply
+/// be started again; the source stays in place until removed with `delete_dataset`.
+#[tauri::command]
+pub async fn transfer_dataset(
+    source_location: Value,
+    dest_location: Value,
+    prefix: String,
+    state: tauri::State<'_, DownloadState>,
+    queue: tauri::State<'_, TaskQueueState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, CollectorError> {
+    // Transfers are not forwarded to the daemon; they only run where tasks run
+    crate::daemon::ensure_local_engine(&app_handle)?;
+    let prefix = safe_path::relative(&prefix)
+        .map_err(|_| format!("{:?} is not a dataset path inside the storage location", prefix))?;
+    let (source, dest) = (Endpoint::open(&source_location)?, Endpoint::open(&dest_location)?);
+    if source.location(&prefix) == dest.location(&prefix) {
+        return Err("Source and destination are the same location".into());
+    }
+
+    let task_id = format!("transfer-{}-{}", prefix.replace('/', "-"), chrono::Utc::now().format("%Y%m%dT%H%M%S"));
+    {
+        let mut progress = DownloadProgress::new(&task_id);
+        progress.status = "queued".to_string();
+        state.lock().unwrap().insert(task_id.clone(), progress);
+    }
+    queue.enqueue(&task_id, QueuePriority::Normal);
+
+    let work = run_transfer(task_id.clone(), source_location, dest_location, prefix.clone(), state.inner().clone(), app_handle.clone());
+    let origin = TaskOrigin { kind: "transfer", provider: None, dataset: Some(prefix) };
+    crate::run_when_queued(task_id.clone(), origin, TaskConstraints::default(), state.inner().clone(), queue.inner().clone(), app_handle, work);
+    Ok(task_id)
+}
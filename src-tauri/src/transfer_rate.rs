@@ -0,0 +1,75 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Span the instantaneous speed is averaged over
+const WINDOW: Duration = Duration::from_secs(10);
+
+/// Speed of a running transfer, from the bytes actually moved over the network.
+/// Skipped files count towards progress but not towards speed.
+#[derive(Debug, Clone)]
+pub struct TransferRate {
+    started: Instant,
+    transferred: u64,
+    /// Cumulative transferred bytes at recent ticks, oldest first
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl TransferRate {
+    pub fn new() -> TransferRate {
+        let now = Instant::now();
+        TransferRate {
+            started: now,
+            transferred: 0,
+            samples: VecDeque::from([(now, 0)]),
+        }
+    }
+
+    /// Count `bytes` more as transferred now
+    pub fn record(&mut self, bytes: u64) {
+        let now = Instant::now();
+        self.transferred += bytes;
+        self.samples.push_back((now, self.transferred));
+
+        // Keep one sample at or before the window start so the window stays fully covered
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].0) >= WINDOW {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Bytes per second over the last `WINDOW`
+    pub fn instantaneous(&self) -> f64 {
+        let Some(&(since, base)) = self.samples.front() else {
+            return 0.0;
+        };
+        let elapsed = since.elapsed().as_secs_f64();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        (self.transferred - base) as f64 / elapsed
+    }
+
+    /// Bytes per second since the transfer started
+    pub fn average(&self) -> f64 {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        self.transferred as f64 / elapsed
+    }
+
+    /// Seconds left for `remaining` bytes at the current speed (the average while the
+    /// window is empty); None until something was transferred
+    pub fn eta_seconds(&self, remaining: u64) -> Option<u64> {
+        let speed = match self.instantaneous() {
+            speed if speed > 0.0 => speed,
+            _ => self.average(),
+        };
+        (speed > 0.0).then(|| (remaining as f64 / speed).ceil() as u64)
+    }
+}
+
+impl Default for TransferRate {
+    fn default() -> Self {
+        TransferRate::new()
+    }
+}
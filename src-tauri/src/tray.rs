@@ -0,0 +1,163 @@
+use std::time::Duration;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{TrayIcon, TrayIconBuilder};
+use tauri::{Manager, WindowEvent};
+
+use crate::formatting;
+use crate::os_progress::FINISHED_STATUSES;
+use crate::task_queue::TaskQueueState;
+use crate::DownloadState;
+
+const TRAY_ID: &str = "main";
+
+/// How often the tray tooltip and status line are refreshed from the task manager
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+const PAUSE_ALL: &str = "pause-all";
+const RESUME_ALL: &str = "resume-all";
+const OPEN_APP: &str = "open-app";
+const QUIT: &str = "quit";
+
+/// Download state shown by the tray
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TrayStatus {
+    active_tasks: usize,
+    queued_tasks: usize,
+    /// Combined speed of the active tasks
+    bytes_per_sec: f64,
+    paused: bool,
+}
+
+impl TrayStatus {
+    fn collect(state: &DownloadState, queue: &TaskQueueState) -> TrayStatus {
+        let downloads = state.lock().unwrap();
+        let mut status = TrayStatus {
+            active_tasks: 0,
+            queued_tasks: 0,
+            bytes_per_sec: 0.0,
+            paused: queue.is_paused(),
+        };
+        for progress in downloads.values() {
+            if progress.status == "queued" {
+                status.queued_tasks += 1;
+            } else if !FINISHED_STATUSES.contains(&progress.status.as_str()) {
+                status.active_tasks += 1;
+                status.bytes_per_sec += progress.speed;
+            }
+        }
+        status
+    }
+
+    fn label(&self) -> String {
+        let format = formatting::current();
+        let tasks = format!(
+            "{} active, {} queued",
+            format.count(self.active_tasks as u64), format.count(self.queued_tasks as u64)
+        );
+        match self {
+            TrayStatus { paused: true, .. } => format!("Paused: {}", tasks),
+            TrayStatus { active_tasks: 0, queued_tasks: 0, .. } => "No active downloads".to_string(),
+            TrayStatus { active_tasks: 0, .. } => tasks,
+            _ => format!("{} at {}", tasks, format.rate(self.bytes_per_sec)),
+        }
+    }
+}
+
+fn show_main_window(app_handle: &tauri::AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+fn handle_menu_event(app_handle: &tauri::AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        PAUSE_ALL => app_handle.state::<TaskQueueState>().set_paused(true),
+        RESUME_ALL => app_handle.state::<TaskQueueState>().set_paused(false),
+        OPEN_APP => show_main_window(app_handle),
+        QUIT => app_handle.exit(0),
+        _ => {}
+    }
+}
+
+/// The tray icon and the menu items that change with the download state
+struct Tray {
+    icon: TrayIcon,
+    status: MenuItem<tauri::Wry>,
+    pause_all: MenuItem<tauri::Wry>,
+    resume_all: MenuItem<tauri::Wry>,
+}
+
+impl Tray {
+    fn build(app_handle: &tauri::AppHandle) -> tauri::Result<Tray> {
+        let status = MenuItem::with_id(app_handle, "status", "No active downloads", false, None::<&str>)?;
+        let pause_all = MenuItem::with_id(app_handle, PAUSE_ALL, "Pause all", true, None::<&str>)?;
+        let resume_all = MenuItem::with_id(app_handle, RESUME_ALL, "Resume all", false, None::<&str>)?;
+        let open_app = MenuItem::with_id(app_handle, OPEN_APP, "Open BIDS Collector", true, None::<&str>)?;
+        let quit = MenuItem::with_id(app_handle, QUIT, "Quit", true, None::<&str>)?;
+        let menu = Menu::with_items(app_handle, &[
+            &status,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &pause_all,
+            &resume_all,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &open_app,
+            &quit,
+        ])?;
+
+        let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+            .tooltip("BIDS Collector")
+            .menu(&menu)
+            .on_menu_event(handle_menu_event);
+        if let Some(icon) = app_handle.default_window_icon() {
+            builder = builder.icon(icon.clone());
+        }
+
+        Ok(Tray {
+            icon: builder.build(app_handle)?,
+            status,
+            pause_all,
+            resume_all,
+        })
+    }
+
+    fn show(&self, status: TrayStatus) -> tauri::Result<()> {
+        let label = status.label();
+        self.icon.set_tooltip(Some(format!("BIDS Collector: {}", label)))?;
+        self.status.set_text(label)?;
+        self.pause_all.set_enabled(!status.paused)?;
+        self.resume_all.set_enabled(status.paused)
+    }
+}
+
+/// Add the tray icon and keep it in sync with the running tasks for the lifetime of the app
+pub fn spawn(app_handle: &tauri::AppHandle, state: DownloadState, queue: TaskQueueState) -> Result<(), String> {
+    let tray = Tray::build(app_handle).map_err(|e| format!("Failed to create tray icon: {}", e))?;
+
+    tauri::async_runtime::spawn(async move {
+        let mut shown = None;
+        loop {
+            let status = TrayStatus::collect(&state, &queue);
+            if shown != Some(status) {
+                if let Err(e) = tray.show(status) {
+                    log::warn!("Failed to update tray icon: {}", e);
+                }
+                shown = Some(status);
+            }
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+        }
+    });
+    Ok(())
+}
+
+/// Closing the main window hides it to the tray, so transfers keep running until "Quit" is
+/// chosen there. Without a tray icon the window closes as usual.
+pub fn handle_window_event(window: &tauri::Window, event: &WindowEvent) {
+    if let WindowEvent::CloseRequested { api, .. } = event {
+        if window.label() == "main" && window.app_handle().tray_by_id(TRAY_ID).is_some() {
+            let _ = window.hide();
+            api.prevent_close();
+        }
+    }
+}
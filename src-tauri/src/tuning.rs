@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::error::CollectorError;
+
+const KIB: usize = 1024;
+const MIB: usize = 1024 * 1024;
+
+/// When buffered download data is flushed to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "mode", content = "bytes")]
+pub enum FlushStrategy {
+    /// Flush after every network chunk (lowest memory, most syscalls)
+    EveryChunk,
+    /// Flush whenever this many bytes were written since the last flush
+    Interval(u64),
+    /// Only flush once the file is complete
+    AtEnd,
+}
+
+/// Stream and buffer sizes used by transfers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferTuning {
+    /// Preset name, or "custom" for user-edited values
+    pub profile: String,
+    /// Read size when streaming local files (hashing, uploads from disk)
+    pub chunk_size: usize,
+    /// Capacity of the write buffer in front of downloaded files
+    pub write_buffer_size: usize,
+    pub flush_strategy: FlushStrategy,
+}
+
+pub type TuningState = Arc<Mutex<TransferTuning>>;
+
+impl TransferTuning {
+    /// Campus network / 10 GbE: large buffers, few syscalls
+    pub fn lan() -> TransferTuning {
+        TransferTuning {
+            profile: "lan".to_string(),
+            chunk_size: 8 * MIB,
+            write_buffer_size: 16 * MIB,
+            flush_strategy: FlushStrategy::AtEnd,
+        }
+    }
+
+    /// Typical home or office connection
+    pub fn broadband() -> TransferTuning {
+        TransferTuning {
+            profile: "broadband".to_string(),
+            chunk_size: MIB,
+            write_buffer_size: 4 * MIB,
+            flush_strategy: FlushStrategy::Interval(64 * MIB as u64),
+        }
+    }
+
+    /// DSL, VPN or intercontinental links: small buffers flushed often
+    pub fn high_latency() -> TransferTuning {
+        TransferTuning {
+            profile: "high-latency".to_string(),
+            chunk_size: 256 * KIB,
+            write_buffer_size: MIB,
+            flush_strategy: FlushStrategy::Interval(8 * MIB as u64),
+        }
+    }
+
+    /// Performance mode on machines with many cores and plenty of free memory; not a network profile
+    pub fn performance() -> TransferTuning {
+        TransferTuning {
+            profile: "performance".to_string(),
+            chunk_size: 16 * MIB,
+            write_buffer_size: 64 * MIB,
+            flush_strategy: FlushStrategy::AtEnd,
+        }
+    }
+
+    pub fn presets() -> Vec<TransferTuning> {
+        vec![TransferTuning::lan(), TransferTuning::broadband(), TransferTuning::high_latency()]
+    }
+
+    pub fn preset(name: &str) -> Option<TransferTuning> {
+        TransferTuning::presets().into_iter().find(|p| p.profile == name)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let range = 4 * KIB..=256 * MIB;
+        if !range.contains(&self.chunk_size) {
+            return Err(format!("Chunk size must be between 4 KiB and 256 MiB, got {} bytes", self.chunk_size));
+        }
+        if !range.contains(&self.write_buffer_size) {
+            return Err(format!("Write buffer size must be between 4 KiB and 256 MiB, got {} bytes", self.write_buffer_size));
+        }
+        if let FlushStrategy::Interval(0) = self.flush_strategy {
+            return Err("Flush interval must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether the writer should flush after `unflushed` bytes were written
+    pub fn should_flush(&self, unflushed: u64) -> bool {
+        match self.flush_strategy {
+            FlushStrategy::EveryChunk => unflushed > 0,
+            FlushStrategy::Interval(bytes) => unflushed >= bytes,
+            FlushStrategy::AtEnd => false,
+        }
+    }
+}
+
+impl Default for TransferTuning {
+    fn default() -> Self {
+        TransferTuning::broadband()
+    }
+}
+
+#[tauri::command]
+pub async fn list_network_profiles() -> Result<Vec<TransferTuning>, CollectorError> {
+    Ok(TransferTuning::presets())
+}
+
+#[tauri::command]
+pub async fn get_transfer_tuning(state: tauri::State<'_, TuningState>) -> Result<TransferTuning, CollectorError> {
+    Ok(state.lock().unwrap().clone())
+}
+
+/// Switch to a preset by name, or store custom values when `tuning` is given
+#[tauri::command]
+pub async fn set_transfer_tuning(
+    profile: Option<String>,
+    tuning: Option<TransferTuning>,
+    state: tauri::State<'_, TuningState>,
+) -> Result<TransferTuning, CollectorError> {
+    let new_tuning = match (profile, tuning) {
+        (_, Some(mut custom)) => {
+            custom.profile = "custom".to_string();
+            custom
+        }
+        (Some(name), None) => TransferTuning::preset(&name)
+            .ok_or_else(|| format!("Unknown network profile: {}", name))?,
+        (None, None) => return Err("Either a profile name or custom tuning values are required".into()),
+    };
+
+    new_tuning.validate()?;
+    log::info!("Transfer tuning set to {:?}", new_tuning);
+
+    *state.lock().unwrap() = new_tuning.clone();
+    Ok(new_tuning)
+}
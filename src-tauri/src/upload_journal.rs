@@ -0,0 +1,130 @@
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use tauri::Manager;
+
+use crate::manifest::ManifestEntry;
+use crate::path_guard;
+use crate::providers::RemoteFile;
+use crate::storage::{RemoteStorage, StoredObject};
+
+/// Directory in the app data directory holding one journal per dataset being uploaded
+const JOURNAL_DIR: &str = "upload-journals";
+
+/// An object an upload stored, appended to the journal as soon as the destination accepted it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletedUpload {
+    /// Size and ETag the provider listed, so a file that changed since is uploaded again
+    pub source_size: u64,
+    pub source_etag: Option<String>,
+    /// MD5 of the stored content, which S3 reports as the ETag of a plain PutObject
+    pub md5: String,
+    pub entry: ManifestEntry,
+}
+
+impl CompletedUpload {
+    /// Whether the provider still lists the file that was uploaded
+    pub fn matches(&self, file: &RemoteFile) -> bool {
+        self.source_size == file.size
+            && (self.source_etag.is_none() || file.etag.is_none() || self.source_etag == file.etag)
+    }
+
+    fn md5_matches(&self, object: &StoredObject) -> bool {
+        object.etag.as_deref().is_some_and(|etag| etag.trim_matches('"').eq_ignore_ascii_case(&self.md5))
+    }
+}
+
+/// Objects uploaded for one dataset at one destination, kept until the upload finishes, so a
+/// run that was interrupted, failed or cancelled can be started again without uploading
+/// everything a second time
+pub struct UploadJournal {
+    path: PathBuf,
+    completed: HashMap<String, CompletedUpload>,
+}
+
+impl UploadJournal {
+    /// Journal of uploads to `location`, the URI of the dataset at the destination, with what
+    /// an earlier run that did not finish recorded
+    pub fn open(app_handle: &tauri::AppHandle, location: &str) -> Result<UploadJournal, String> {
+        let file_name = format!("{}.jsonl", &hex::encode(Sha256::digest(location.as_bytes()))[..16]);
+        let path = app_handle.path().app_data_dir()
+            .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+            .join(JOURNAL_DIR)
+            .join(file_name);
+
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        // A line cut off by a crash is ignored; that object is simply uploaded again
+        let completed = content.lines()
+            .filter_map(|line| serde_json::from_str::<CompletedUpload>(line).ok())
+            .map(|upload| (upload.entry.path.clone(), upload))
+            .collect();
+        Ok(UploadJournal { path, completed })
+    }
+
+    /// Uploads of the earlier run that the destination still holds with the recorded size.
+    /// ETags are compared with the recorded MD5 only where the destination reports MD5
+    /// ETags at all, which multipart, KMS-encrypted and many non-AWS objects do not.
+    pub async fn confirmed(&self, storage: &dyn RemoteStorage, prefix: &str) -> Result<HashMap<String, CompletedUpload>, String> {
+        if self.completed.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let stored = storage.list_objects(&format!("{}/", prefix)).await?;
+        let md5_etags = self.completed.values()
+            .any(|upload| stored.get(&upload.entry.path).is_some_and(|object| upload.md5_matches(object)));
+
+        let confirmed: HashMap<String, CompletedUpload> = self.completed.iter()
+            .filter(|(path, upload)| stored.get(*path).is_some_and(|object| {
+                object.size == upload.entry.size && (!md5_etags || upload.md5_matches(object))
+            }))
+            .map(|(path, upload)| (path.clone(), upload.clone()))
+            .collect();
+        log::info!(
+            "{} of {} objects recorded by an earlier upload to {} are still there",
+            confirmed.len(), self.completed.len(), storage.location(prefix)
+        );
+        Ok(confirmed)
+    }
+
+    /// Note an object the destination accepted
+    pub fn record(&self, file: &RemoteFile, entry: &ManifestEntry, content: &[u8]) -> Result<(), String> {
+        let upload = CompletedUpload {
+            source_size: file.size,
+            source_etag: file.etag.clone(),
+            md5: hex::encode(Md5::digest(content)),
+            entry: entry.clone(),
+        };
+        let mut line = serde_json::to_string(&upload)
+            .map_err(|e| format!("Failed to serialize upload of {}: {}", entry.path, e))?;
+        line.push('\n');
+
+        path_guard::check(&self.path)?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+
+    /// Forget the uploads once the dataset is complete at the destination
+    pub fn finish(&self) -> Result<(), String> {
+        path_guard::check(&self.path)?;
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to remove {}: {}", self.path.display(), e)),
+        }
+    }
+}
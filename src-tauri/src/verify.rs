@@ -0,0 +1,302 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tokio::fs;
+
+use crate::catalog::{Catalog, CatalogEntry, CatalogState};
+use crate::checksum::ChecksumAlgorithm;
+use crate::error::CollectorError;
+use crate::manifest::{self, Manifest, ManifestEntry};
+use crate::providers::RemoteFile;
+use crate::safe_path;
+use crate::storage;
+use crate::task_queue::TaskQueueState;
+use crate::{DownloadOptions, DownloadState};
+
+/// Minimum time between two `dataset-verify-progress` events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    Missing,
+    SizeMismatch,
+    ChecksumMismatch,
+}
+
+/// A file whose copy at the destination is missing or differs from what was collected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileProblem {
+    pub path: String,
+    pub kind: ProblemKind,
+    pub expected_size: u64,
+    /// None when the file is missing
+    pub actual_size: Option<u64>,
+    pub expected_sha256: Option<String>,
+    pub actual_sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationReport {
+    pub dataset_id: String,
+    pub location: String,
+    pub checked_at: String,
+    pub files_checked: usize,
+    pub files_ok: usize,
+    /// Present with the expected size, but without a checksum to compare against; always
+    /// the case for remote destinations, whose content is not downloaded to be hashed
+    pub files_unverified: usize,
+    pub problems: Vec<FileProblem>,
+    /// Task re-collecting the broken files, when a repair was requested
+    pub repair_task_id: Option<String>,
+}
+
+enum Outcome {
+    Ok,
+    Unverified,
+    Problem(FileProblem),
+}
+
+/// What a file at the destination should be: the manifest entry recorded when it was
+/// collected, or the provider listing for files the manifest does not know
+struct Expected<'a> {
+    path: &'a str,
+    size: u64,
+    sha256: Option<String>,
+}
+
+impl<'a> Expected<'a> {
+    fn of(file: &'a RemoteFile, recorded: Option<&ManifestEntry>) -> Expected<'a> {
+        match recorded {
+            Some(entry) => Expected { path: &file.path, size: entry.size, sha256: Some(entry.sha256.clone()) },
+            None => Expected {
+                path: &file.path,
+                size: file.size,
+                sha256: file.checksum.as_ref()
+                    .filter(|c| c.algorithm == ChecksumAlgorithm::Sha256)
+                    .map(|c| c.value.clone()),
+            },
+        }
+    }
+
+    fn recorded(entry: &'a ManifestEntry) -> Expected<'a> {
+        Expected { path: &entry.path, size: entry.size, sha256: Some(entry.sha256.clone()) }
+    }
+
+    fn problem(&self, kind: ProblemKind, actual_size: Option<u64>, actual_sha256: Option<String>) -> Outcome {
+        Outcome::Problem(FileProblem {
+            path: self.path.to_string(),
+            kind,
+            expected_size: self.size,
+            actual_size,
+            expected_sha256: self.sha256.clone(),
+            actual_sha256,
+        })
+    }
+}
+
+/// The catalog entry named by ID, or the latest collection into a local directory
+fn find_entry(catalog: &Catalog, target: &str) -> Result<CatalogEntry, String> {
+    if let Some(entry) = catalog.get(target)? {
+        return Ok(entry);
+    }
+    catalog.list()?
+        .into_iter()
+        .filter(|entry| entry.storage_type == "local" && Path::new(&entry.location) == Path::new(target))
+        .max_by(|a, b| a.collected_at.cmp(&b.collected_at))
+        .ok_or_else(|| format!("{} is neither a cataloged dataset nor the directory of one", target))
+}
+
+/// Storage location the dataset was collected into, in the shape tasks carry
+fn storage_location_of(entry: &CatalogEntry, given: Option<Value>) -> Result<Value, String> {
+    if entry.storage_type != "local" {
+        return given.ok_or_else(|| format!("The storage location is needed to verify a dataset on {} storage", entry.storage_type));
+    }
+    let root = entry.location
+        .strip_suffix(&entry.identifier)
+        .map(|root| root.trim_end_matches(['/', '\\']))
+        .filter(|root| !root.is_empty())
+        .ok_or_else(|| format!("Cannot tell the storage root of {}", entry.location))?;
+    Ok(json!({ "id": "verify", "type": "local", "path": root }))
+}
+
+/// Task payload re-collecting the dataset with its original selection
+fn task_of(entry: &CatalogEntry) -> Value {
+    let mut task = match &entry.selection {
+        Value::Object(selection) => Value::Object(selection.clone()),
+        _ => json!({}),
+    };
+    task["datasetProvider"] = json!(entry.provider);
+    task["downloadPath"] = json!(entry.identifier);
+    task
+}
+
+async fn check_local(dest_dir: &str, expected: &Expected<'_>, chunk_size: usize) -> Result<Outcome, String> {
+    let path = safe_path::join(dest_dir, expected.path)?;
+    let Some(size) = fs::metadata(&path).await.ok().filter(|m| m.is_file()).map(|m| m.len()) else {
+        return Ok(expected.problem(ProblemKind::Missing, None, None));
+    };
+    if size != expected.size {
+        return Ok(expected.problem(ProblemKind::SizeMismatch, Some(size), None));
+    }
+    let Some(expected_sha256) = &expected.sha256 else {
+        return Ok(Outcome::Unverified);
+    };
+
+    let (_, sha256) = manifest::hash_file(&path, chunk_size).await?;
+    if sha256.eq_ignore_ascii_case(expected_sha256) {
+        Ok(Outcome::Ok)
+    } else {
+        Ok(expected.problem(ProblemKind::ChecksumMismatch, Some(size), Some(sha256)))
+    }
+}
+
+fn check_remote(sizes: &HashMap<String, u64>, expected: &Expected<'_>) -> Outcome {
+    match sizes.get(expected.path) {
+        None => expected.problem(ProblemKind::Missing, None, None),
+        Some(size) if *size != expected.size => expected.problem(ProblemKind::SizeMismatch, Some(*size), None),
+        Some(_) => Outcome::Unverified,
+    }
+}
+
+/// Files to check: every listed file, except archives that were unpacked at the destination,
+/// whose extracted files are checked against the manifest instead
+fn expected_files<'a>(listed: &'a [RemoteFile], manifest: Option<&'a Manifest>) -> Vec<Expected<'a>> {
+    let recorded: HashMap<&str, &ManifestEntry> = manifest.map(|m| m.by_path()).unwrap_or_default();
+    let extracted_from: HashSet<&str> = manifest
+        .map(|m| m.files.iter()
+            .filter_map(|entry| entry.source_url.as_deref())
+            .collect())
+        .unwrap_or_default();
+
+    let mut expected = Vec::new();
+    let mut covered = HashSet::new();
+    for file in listed {
+        covered.insert(file.path.as_str());
+        let entry = recorded.get(file.path.as_str()).copied();
+        if entry.is_none() && extracted_from.contains(file.url.as_str()) {
+            continue;
+        }
+        expected.push(Expected::of(file, entry));
+    }
+    if let Some(manifest) = manifest {
+        let listed_urls: HashSet<&str> = listed.iter().map(|f| f.url.as_str()).collect();
+        expected.extend(manifest.files.iter()
+            .filter(|entry| !covered.contains(entry.path.as_str()))
+            .filter(|entry| entry.source_url.as_deref().is_some_and(|url| listed_urls.contains(url)))
+            .map(Expected::recorded));
+    }
+    expected
+}
+
+fn report_progress(app_handle: &tauri::AppHandle, dataset_id: &str, checked: usize, total: usize, current: &str) {
+    let _ = app_handle.emit("dataset-verify-progress", json!({
+        "datasetId": dataset_id,
+        "checkedFiles": checked,
+        "totalFiles": total,
+        "currentFile": current,
+    }));
+}
+
+/// Check a collected dataset against a fresh listing from its provider: every selected file
+/// must be at the destination with the size and SHA-256 recorded when it was collected
+/// (or published by the provider). `target` is a catalog ID or the local directory of a
+/// collection; remote destinations need their `storage_location`. With `repair`, a sync
+/// task re-collects the missing and broken files and leaves the intact ones in place.
+#[tauri::command]
+pub async fn verify_dataset(
+    target: String,
+    storage_location: Option<Value>,
+    repair: Option<bool>,
+    catalog: tauri::State<'_, CatalogState>,
+    state: tauri::State<'_, DownloadState>,
+    queue: tauri::State<'_, TaskQueueState>,
+    app_handle: tauri::AppHandle,
+) -> Result<VerificationReport, CollectorError> {
+    let entry = find_entry(&catalog.lock().unwrap(), &target)?;
+    let location = storage_location_of(&entry, storage_location)?;
+    let task = task_of(&entry);
+    let options = DownloadOptions::from_task(&task, &location, &entry.provider, &entry.identifier, &app_handle)?;
+    log::info!("Verifying {} at {}", entry.id, entry.location);
+
+    let listing = crate::list_with_limits(&options, &entry.identifier).await?;
+    let listed: Vec<RemoteFile> = listing.files
+        .into_iter()
+        .filter(|f| options.filter.is_empty() || options.filter.matches(&f.path))
+        .collect();
+
+    let remote = match entry.storage_type.as_str() {
+        "local" => None,
+        _ => {
+            let storage = storage::from_storage_location(&location)?;
+            Some(crate::load_remote_sync_state(storage.as_ref(), &entry.identifier).await?)
+        }
+    };
+    let manifest = match &remote {
+        None => manifest::read_local(&entry.location).await,
+        Some((_, manifest)) => manifest.clone(),
+    };
+    let expected = expected_files(&listed, manifest.as_ref());
+
+    let mut report = VerificationReport {
+        dataset_id: entry.id.clone(),
+        location: entry.location.clone(),
+        checked_at: chrono::Utc::now().to_rfc3339(),
+        files_checked: 0,
+        files_ok: 0,
+        files_unverified: 0,
+        problems: Vec::new(),
+        repair_task_id: None,
+    };
+    let mut last_report: Option<Instant> = None;
+    for file in &expected {
+        let outcome = match &remote {
+            None => check_local(&entry.location, file, options.tuning.chunk_size).await?,
+            Some((sizes, _)) => check_remote(sizes, file),
+        };
+        match outcome {
+            Outcome::Ok => report.files_ok += 1,
+            Outcome::Unverified => report.files_unverified += 1,
+            Outcome::Problem(problem) => report.problems.push(problem),
+        }
+        report.files_checked += 1;
+
+        if last_report.map_or(true, |at| at.elapsed() >= PROGRESS_INTERVAL) {
+            report_progress(&app_handle, &entry.id, report.files_checked, expected.len(), file.path);
+            last_report = Some(Instant::now());
+        }
+    }
+    log::info!(
+        "Verified {}: {} files ok, {} unverified, {} missing or broken",
+        entry.id, report.files_ok, report.files_unverified, report.problems.len()
+    );
+
+    if repair.unwrap_or(false) && !report.problems.is_empty() {
+        // Same-size files only fail the hash check, so sync mode would keep them without this list
+        let corrupt: Vec<&str> = report.problems.iter()
+            .filter(|problem| problem.kind == ProblemKind::ChecksumMismatch)
+            .map(|problem| problem.path.as_str())
+            .collect();
+        let mut repair_task = task;
+        repair_task["mode"] = json!("sync");
+        repair_task["verifySkipped"] = json!(corrupt);
+
+        let task_id = format!("repair-{}-{}", entry.id, chrono::Utc::now().format("%Y%m%dT%H%M%S"));
+        let task_data = json!({ "task": repair_task, "storageLocations": [location] });
+        match crate::daemon::daemon_client(&app_handle) {
+            Some(daemon) => daemon.enqueue(&task_id, &task_data).await?,
+            None => {
+                crate::queue_download_task(task_id.clone(), task_data, state.inner().clone(), queue.inner().clone(), app_handle.clone())?;
+            }
+        }
+        log::info!("Repairing {} files of {} in task {}", report.problems.len(), entry.id, task_id);
+        report.repair_task_id = Some(task_id);
+    }
+
+    Ok(report)
+}
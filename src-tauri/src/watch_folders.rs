@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::Manager;
+
+use crate::automation;
+use crate::path_guard;
+use crate::settings::SettingsState;
+
+/// How often the watch folders are scanned for task files
+const SCAN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Files modified more recently may still be being written
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// Subfolders task files are moved to once they were queued, or could not be
+const PROCESSED_DIR: &str = "processed";
+const FAILED_DIR: &str = "failed";
+
+/// Queue the task files dropped into the watch folders of the app settings, so pipelines and
+/// other users can hand the background service work by copying a file. Each `*.json` file
+/// holds `{"taskId"?, "taskData"}` like `POST /v1/tasks` of the automation API. It is moved to
+/// `processed/` before it is queued, and on to `failed/` with a `.error.txt` when it cannot be.
+pub fn spawn(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SCAN_INTERVAL).await;
+            let folders = app_handle.state::<SettingsState>().settings().watch_folders;
+            for folder in folders {
+                if let Err(e) = scan(&app_handle, Path::new(&folder)) {
+                    log::warn!("Failed to scan watch folder {}: {}", folder, e);
+                }
+            }
+        }
+    });
+}
+
+fn scan(app_handle: &tauri::AppHandle, folder: &Path) -> Result<(), String> {
+    let entries = std::fs::read_dir(folder)
+        .map_err(|e| format!("Failed to read {}: {}", folder.display(), e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_task_file = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+            && !entry.file_name().to_string_lossy().starts_with('.');
+        let settled = entry.metadata().ok()
+            .filter(|metadata| metadata.is_file())
+            .and_then(|metadata| metadata.modified().ok())
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age >= SETTLE_TIME);
+        if !is_task_file || !settled {
+            continue;
+        }
+
+        // Claimed first, so a file is never queued twice even when moving it later fails; the
+        // time in front keeps earlier files of the same name
+        let file_name = format!("{}-{}", chrono::Utc::now().format("%Y%m%dT%H%M%S"), entry.file_name().to_string_lossy());
+        let claimed = move_into(&path, &folder.join(PROCESSED_DIR), &file_name)?;
+        let queued = std::fs::read(&claimed)
+            .map_err(|e| format!("Failed to read {}: {}", claimed.display(), e))
+            .and_then(|body| automation::enqueue(app_handle, &body).map_err(|e| e.message));
+        match queued {
+            Ok(task_id) => log::info!("Queued task {} from {}", task_id, path.display()),
+            Err(e) => {
+                log::warn!("Rejected task file {}: {}", path.display(), e);
+                let failed = move_into(&claimed, &folder.join(FAILED_DIR), &file_name)?;
+                let mut report = failed.into_os_string();
+                report.push(".error.txt");
+                std::fs::write(&report, format!("{}\n", e))
+                    .map_err(|e| format!("Failed to write {}: {}", Path::new(&report).display(), e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Move a task file into a subfolder of its watch folder
+fn move_into(path: &Path, folder: &Path, file_name: &str) -> Result<PathBuf, String> {
+    path_guard::check(folder)?;
+    std::fs::create_dir_all(folder)
+        .map_err(|e| format!("Failed to create directory {}: {}", folder.display(), e))?;
+    let target = folder.join(file_name);
+    path_guard::check(path)?;
+    std::fs::rename(path, &target)
+        .map_err(|e| format!("Failed to move {} to {}: {}", path.display(), target.display(), e))?;
+    Ok(target)
+}
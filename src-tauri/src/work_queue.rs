@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::error::CollectorError;
+use crate::lanes::{self, TransferLane};
+use crate::providers::RemoteFile;
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileState {
+    Pending,
+    Transferring,
+    Done,
+    /// Already at the destination and left alone
+    Skipped,
+    /// Excluded by the user while the task was running
+    Dropped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedFile {
+    pub path: String,
+    pub size: u64,
+    pub state: FileState,
+    /// Position in the task's file list
+    #[serde(skip)]
+    index: usize,
+    /// Raised when the file is dropped, so an in-flight transfer stops
+    #[serde(skip)]
+    dropped: Arc<AtomicBool>,
+}
+
+/// Progress of one transfer lane of a task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaneUsage {
+    pub lane: TransferLane,
+    pub files: usize,
+    pub pending: usize,
+    pub transferring: usize,
+    /// Transferred or skipped
+    pub finished: usize,
+    pub dropped: usize,
+    pub bytes: u64,
+    pub finished_bytes: u64,
+}
+
+/// A file taken from the queue for transfer
+pub struct NextFile {
+    pub index: usize,
+    pub dropped: Arc<AtomicBool>,
+}
+
+/// Transfer order and per-file state of one task
+#[derive(Debug, Default)]
+pub struct TaskQueue {
+    files: Vec<QueuedFile>,
+}
+
+/// Task ID -> queue of the task's files
+pub type QueueState = Arc<Mutex<HashMap<String, TaskQueue>>>;
+
+impl TaskQueue {
+    /// Queue files in the order given (the lane order)
+    pub fn new(file_list: &[RemoteFile]) -> TaskQueue {
+        TaskQueue {
+            files: file_list
+                .iter()
+                .enumerate()
+                .map(|(index, file)| QueuedFile {
+                    path: file.path.clone(),
+                    size: file.size,
+                    state: FileState::Pending,
+                    index,
+                    dropped: Arc::new(AtomicBool::new(false)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Take the next pending file
+    pub fn next(&mut self) -> Option<NextFile> {
+        let file = self.files.iter_mut().find(|f| f.state == FileState::Pending)?;
+        file.state = FileState::Transferring;
+        Some(NextFile {
+            index: file.index,
+            dropped: file.dropped.clone(),
+        })
+    }
+
+    pub fn finish(&mut self, index: usize, state: FileState) {
+        if let Some(file) = self.files.iter_mut().find(|f| f.index == index && f.state != FileState::Dropped) {
+            file.state = state;
+        }
+    }
+
+    /// Drop files that have not finished yet; transfers in flight are stopped.
+    /// Returns the files that were dropped.
+    pub fn drop_files(&mut self, paths: &[String]) -> Vec<QueuedFile> {
+        let mut dropped = Vec::new();
+        for file in self.files.iter_mut() {
+            if matches!(file.state, FileState::Pending | FileState::Transferring) && paths.contains(&file.path) {
+                file.state = FileState::Dropped;
+                file.dropped.store(true, Ordering::SeqCst);
+                dropped.push(file.clone());
+            }
+        }
+        dropped
+    }
+
+    /// Whether the first `count` files of the original order are all transferred, skipped or dropped
+    pub fn settled(&self, count: usize) -> bool {
+        self.files
+            .iter()
+            .filter(|f| f.index < count)
+            .all(|f| matches!(f.state, FileState::Done | FileState::Skipped | FileState::Dropped))
+    }
+
+    /// Move pending files to the end of the queue, keeping their relative order
+    pub fn deprioritize(&mut self, paths: &[String]) -> usize {
+        let (moved, kept): (Vec<QueuedFile>, Vec<QueuedFile>) = self.files
+            .drain(..)
+            .partition(|f| f.state == FileState::Pending && paths.contains(&f.path));
+        let count = moved.len();
+        self.files = kept;
+        self.files.extend(moved);
+        count
+    }
+
+    /// Per-lane counts, metadata lane first; lanes without files are left out
+    pub fn lane_usage(&self) -> Vec<LaneUsage> {
+        [TransferLane::Metadata, TransferLane::Bulk]
+            .into_iter()
+            .map(|lane| {
+                let files: Vec<&QueuedFile> = self.files.iter().filter(|f| lanes::classify(&f.path, f.size) == lane).collect();
+                let finished = files.iter().filter(|f| matches!(f.state, FileState::Done | FileState::Skipped));
+                LaneUsage {
+                    lane,
+                    files: files.len(),
+                    pending: files.iter().filter(|f| f.state == FileState::Pending).count(),
+                    transferring: files.iter().filter(|f| f.state == FileState::Transferring).count(),
+                    finished: finished.clone().count(),
+                    dropped: files.iter().filter(|f| f.state == FileState::Dropped).count(),
+                    bytes: files.iter().map(|f| f.size).sum(),
+                    finished_bytes: finished.map(|f| f.size).sum(),
+                }
+            })
+            .filter(|usage| usage.files > 0)
+            .collect()
+    }
+
+    fn remaining(&self) -> impl Iterator<Item = &QueuedFile> {
+        self.files
+            .iter()
+            .filter(|f| matches!(f.state, FileState::Pending | FileState::Transferring))
+    }
+}
+
+/// One page of the files a task has not transferred yet, in transfer order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemainingPage {
+    pub task_id: String,
+    pub offset: usize,
+    pub total_remaining: usize,
+    pub remaining_bytes: u64,
+    pub files: Vec<QueuedFile>,
+}
+
+pub fn register(queues: &QueueState, task_id: &str, file_list: &[RemoteFile]) {
+    queues.lock().unwrap().insert(task_id.to_string(), TaskQueue::new(file_list));
+}
+
+pub fn next(queues: &QueueState, task_id: &str) -> Option<NextFile> {
+    queues.lock().unwrap().get_mut(task_id)?.next()
+}
+
+pub fn finish(queues: &QueueState, task_id: &str, index: usize, state: FileState) {
+    if let Some(queue) = queues.lock().unwrap().get_mut(task_id) {
+        queue.finish(index, state);
+    }
+}
+
+/// Whether the metadata lane (the first `metadata_count` files) has finished
+pub fn lane_settled(queues: &QueueState, task_id: &str, metadata_count: usize) -> bool {
+    metadata_count > 0
+        && queues.lock().unwrap().get(task_id).map(|q| q.settled(metadata_count)).unwrap_or(false)
+}
+
+/// Files the user dropped from the task
+pub fn dropped(queues: &QueueState, task_id: &str) -> Vec<QueuedFile> {
+    queues.lock().unwrap()
+        .get(task_id)
+        .map(|q| q.files.iter().filter(|f| f.state == FileState::Dropped).cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Per-lane progress of a task, empty before it listed its files
+pub fn lane_usage(queues: &QueueState, task_id: &str) -> Vec<LaneUsage> {
+    queues.lock().unwrap().get(task_id).map(|q| q.lane_usage()).unwrap_or_default()
+}
+
+pub fn remove(queues: &QueueState, task_id: &str) {
+    queues.lock().unwrap().remove(task_id);
+}
+
+#[tauri::command]
+pub async fn get_task_remaining(
+    task_id: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    queues: tauri::State<'_, QueueState>,
+) -> Result<RemainingPage, CollectorError> {
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+
+    let queues = queues.lock().unwrap();
+    let queue = queues.get(&task_id)
+        .ok_or_else(|| format!("No file list for task {}; it has not started listing yet", task_id))?;
+
+    Ok(RemainingPage {
+        task_id,
+        offset,
+        total_remaining: queue.remaining().count(),
+        remaining_bytes: queue.remaining().map(|f| f.size).sum(),
+        files: queue.remaining().skip(offset).take(limit).cloned().collect(),
+    })
+}
+
+/// Let a running task transfer everything else before these files
+#[tauri::command]
+pub async fn deprioritize_files(
+    task_id: String,
+    paths: Vec<String>,
+    queues: tauri::State<'_, QueueState>,
+) -> Result<usize, CollectorError> {
+    let mut queues = queues.lock().unwrap();
+    let queue = queues.get_mut(&task_id)
+        .ok_or_else(|| format!("Task {} is not running", task_id))?;
+
+    let moved = queue.deprioritize(&paths);
+    log::info!("Task {}: moved {} of {} files to the end of the queue", task_id, moved, paths.len());
+    Ok(moved)
+}
+
+/// Exclude files from a running task without cancelling it. Files already
+/// transferring are stopped and removed; dropped files are listed in the task's skip log.
+#[tauri::command]
+pub async fn skip_files(
+    task_id: String,
+    paths: Vec<String>,
+    queues: tauri::State<'_, QueueState>,
+) -> Result<Vec<QueuedFile>, CollectorError> {
+    let mut queues = queues.lock().unwrap();
+    let queue = queues.get_mut(&task_id)
+        .ok_or_else(|| format!("Task {} is not running", task_id))?;
+
+    let dropped = queue.drop_files(&paths);
+    log::info!("Task {}: dropped {} of {} requested files", task_id, dropped.len(), paths.len());
+    Ok(dropped)
+}